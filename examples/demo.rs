@@ -3,7 +3,7 @@
 use std::collections::HashMap;
 
 use eframe::{App, CreationContext};
-use egui::{Color32, Id, Modifiers, PointerButton, Ui};
+use egui::{Color32, Id, Key, KeyboardShortcut, Modifiers, PointerButton, Ui};
 use egui_snarl::{
     InPin, InPinId, NodeId, OutPin, OutPinId, Snarl,
     ui::{
@@ -982,10 +982,9 @@ const fn default_config() -> SnarlConfig {
     SnarlConfig {
         // Mouse and keyboard buttons for interaction with the graph
         // can be configured here, like so:
-        rect_select: ModifierClick {
-            modifiers: Modifiers::SHIFT,
-            mouse_button: PointerButton::Primary,
-        },
+        rect_select: ModifierClick::new(Modifiers::SHIFT, PointerButton::Primary),
+        delete_selection: Some(KeyboardShortcut::new(Modifiers::NONE, Key::Delete)),
+        drag_subtree_modifier: Some(Modifiers::ALT),
         ..SnarlConfig::new()
     }
 }
@@ -0,0 +1,961 @@
+//! Compact binary serialization of the [`Snarl`] graph.
+//!
+//! Values are encoded with a one byte tag followed by the payload.
+//! Integers are written as variable length integers, small ones in the tag itself,
+//! floats as little-endian bytes and names of struct fields and enum variants
+//! are written once and referenced by index afterwards.
+//!
+//! Format is self-describing, so fields omitted with `skip_serializing_if`
+//! read back with their defaults, same as with JSON.
+
+use std::fmt;
+
+use egui::ahash::HashMap;
+use serde::{
+    Deserialize, Serialize,
+    de::{self, DeserializeOwned, DeserializeSeed, Visitor},
+    ser,
+};
+
+use crate::Snarl;
+
+/// Header of the encoded data: magic bytes and format version.
+const HEADER: [u8; 5] = *b"SNRL\x01";
+
+/// Maximum nesting of sequences, maps, options and enums accepted by decoder.
+const MAX_DEPTH: usize = 128;
+
+const TAG_UNIT: u8 = 0;
+const TAG_NONE: u8 = 1;
+const TAG_SOME: u8 = 2;
+const TAG_FALSE: u8 = 3;
+const TAG_TRUE: u8 = 4;
+const TAG_UINT: u8 = 5;
+const TAG_NEG_INT: u8 = 6;
+const TAG_F32: u8 = 7;
+const TAG_F64: u8 = 8;
+const TAG_STR: u8 = 9;
+const TAG_BYTES: u8 = 10;
+const TAG_SEQ: u8 = 11;
+const TAG_MAP: u8 = 12;
+const TAG_END: u8 = 13;
+const TAG_ENUM: u8 = 14;
+const TAG_NAME: u8 = 15;
+const TAG_NAME_REF: u8 = 16;
+
+/// Tags from this one up hold unsigned integers below 128 in the tag itself.
+const TAG_SMALL_UINT: u8 = 0x80;
+
+/// Error of [`Snarl::to_bytes`] and [`Snarl::from_bytes`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BinaryError {
+    /// Data does not start with the header of the format or has unsupported version.
+    InvalidHeader,
+
+    /// Data ended before the value was complete.
+    UnexpectedEnd,
+
+    /// Unknown or misplaced tag of the value.
+    InvalidTag(u8),
+
+    /// String is not valid UTF-8.
+    InvalidUtf8,
+
+    /// Integer or length does not fit into its type.
+    IntegerOverflow,
+
+    /// Values are nested deeper than supported.
+    TooDeep,
+
+    /// Data continues after the graph.
+    TrailingBytes,
+
+    /// Error reported by the serialized or deserialized type.
+    Custom(String),
+}
+
+impl fmt::Display for BinaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryError::InvalidHeader => f.write_str("invalid header"),
+            BinaryError::UnexpectedEnd => f.write_str("unexpected end of data"),
+            BinaryError::InvalidTag(tag) => write!(f, "invalid tag {tag}"),
+            BinaryError::InvalidUtf8 => f.write_str("string is not valid UTF-8"),
+            BinaryError::IntegerOverflow => f.write_str("integer overflow"),
+            BinaryError::TooDeep => f.write_str("values are nested too deep"),
+            BinaryError::TrailingBytes => f.write_str("trailing bytes after the value"),
+            BinaryError::Custom(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for BinaryError {}
+
+impl ser::Error for BinaryError {
+    fn custom<M: fmt::Display>(msg: M) -> Self {
+        BinaryError::Custom(msg.to_string())
+    }
+}
+
+impl de::Error for BinaryError {
+    fn custom<M: fmt::Display>(msg: M) -> Self {
+        BinaryError::Custom(msg.to_string())
+    }
+}
+
+impl<T> Snarl<T> {
+    /// Serializes the graph into compact binary form.
+    ///
+    /// Output is usually several times smaller than JSON
+    /// and can be read back with [`Snarl::from_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if node value fails to serialize.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::{InPinId, OutPinId, Snarl};
+    /// let mut snarl = Snarl::<String>::new();
+    /// let a = snarl.insert_node(egui::pos2(0.0, 0.0), "a".to_owned());
+    /// let b = snarl.insert_node(egui::pos2(100.0, 0.0), "b".to_owned());
+    /// snarl.connect(OutPinId { node: a, output: 0 }, InPinId { node: b, input: 0 });
+    ///
+    /// let bytes = snarl.to_bytes().unwrap();
+    /// let restored = Snarl::<String>::from_bytes(&bytes).unwrap();
+    /// assert_eq!(restored[b], "b");
+    /// assert_eq!(restored.wires().count(), 1);
+    /// ```
+    pub fn to_bytes(&self) -> Result<Vec<u8>, BinaryError>
+    where
+        T: Serialize,
+    {
+        encode(self)
+    }
+
+    /// Deserializes the graph from data produced by [`Snarl::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if data is malformed or node value fails to deserialize.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BinaryError>
+    where
+        T: DeserializeOwned,
+    {
+        decode(bytes)
+    }
+}
+
+fn encode<V>(value: &V) -> Result<Vec<u8>, BinaryError>
+where
+    V: Serialize + ?Sized,
+{
+    let mut encoder = Encoder {
+        out: HEADER.to_vec(),
+        names: HashMap::default(),
+    };
+    value.serialize(&mut encoder)?;
+    Ok(encoder.out)
+}
+
+fn decode<V>(bytes: &[u8]) -> Result<V, BinaryError>
+where
+    V: DeserializeOwned,
+{
+    let input = bytes
+        .strip_prefix(&HEADER)
+        .ok_or(BinaryError::InvalidHeader)?;
+
+    let mut decoder = Decoder {
+        input,
+        names: Vec::new(),
+        depth: 0,
+    };
+    let value = V::deserialize(&mut decoder)?;
+    if !decoder.input.is_empty() {
+        return Err(BinaryError::TrailingBytes);
+    }
+    Ok(value)
+}
+
+struct Encoder {
+    out: Vec<u8>,
+
+    /// Indices of names already written.
+    names: HashMap<&'static str, u64>,
+}
+
+impl Encoder {
+    fn varint(&mut self, mut value: u64) {
+        #![allow(clippy::cast_possible_truncation)]
+
+        while value >= 0x80 {
+            self.out.push((value as u8 & 0x7f) | 0x80);
+            value >>= 7;
+        }
+        self.out.push(value as u8);
+    }
+
+    fn uint(&mut self, value: u64) {
+        #![allow(clippy::cast_possible_truncation)]
+
+        if value < u64::from(TAG_SMALL_UINT) {
+            self.out.push(TAG_SMALL_UINT | value as u8);
+        } else {
+            self.out.push(TAG_UINT);
+            self.varint(value);
+        }
+    }
+
+    fn bytes(&mut self, tag: u8, bytes: &[u8]) {
+        self.out.push(tag);
+        self.varint(bytes.len() as u64);
+        self.out.extend_from_slice(bytes);
+    }
+
+    fn name(&mut self, name: &'static str) {
+        if let Some(&idx) = self.names.get(name) {
+            self.out.push(TAG_NAME_REF);
+            self.varint(idx);
+        } else {
+            self.names.insert(name, self.names.len() as u64);
+            self.bytes(TAG_NAME, name.as_bytes());
+        }
+    }
+}
+
+impl ser::Serializer for &mut Encoder {
+    type Ok = ();
+    type Error = BinaryError;
+
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<(), BinaryError> {
+        self.out.push(if v { TAG_TRUE } else { TAG_FALSE });
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), BinaryError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), BinaryError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), BinaryError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), BinaryError> {
+        #![allow(clippy::cast_sign_loss)]
+
+        if v >= 0 {
+            self.uint(v as u64);
+        } else {
+            // Bitwise negation maps negative values to non-negative ones without overflow.
+            self.out.push(TAG_NEG_INT);
+            self.varint(!v as u64);
+        }
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), BinaryError> {
+        self.serialize_u64(u64::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), BinaryError> {
+        self.serialize_u64(u64::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), BinaryError> {
+        self.serialize_u64(u64::from(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), BinaryError> {
+        self.uint(v);
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), BinaryError> {
+        self.out.push(TAG_F32);
+        self.out.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), BinaryError> {
+        #![allow(clippy::cast_possible_truncation)]
+
+        // Doubles that are exactly representable as single floats take half the space.
+        let single = v as f32;
+        if f64::from(single).to_bits() == v.to_bits() {
+            return self.serialize_f32(single);
+        }
+        self.out.push(TAG_F64);
+        self.out.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), BinaryError> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), BinaryError> {
+        self.bytes(TAG_STR, v.as_bytes());
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), BinaryError> {
+        self.bytes(TAG_BYTES, v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), BinaryError> {
+        self.out.push(TAG_NONE);
+        Ok(())
+    }
+
+    fn serialize_some<V>(self, value: &V) -> Result<(), BinaryError>
+    where
+        V: Serialize + ?Sized,
+    {
+        self.out.push(TAG_SOME);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), BinaryError> {
+        self.out.push(TAG_UNIT);
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), BinaryError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), BinaryError> {
+        self.out.push(TAG_ENUM);
+        self.name(variant);
+        self.serialize_unit()
+    }
+
+    fn serialize_newtype_struct<V>(self, _name: &'static str, value: &V) -> Result<(), BinaryError>
+    where
+        V: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<V>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &V,
+    ) -> Result<(), BinaryError>
+    where
+        V: Serialize + ?Sized,
+    {
+        self.out.push(TAG_ENUM);
+        self.name(variant);
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self, BinaryError> {
+        self.out.push(TAG_SEQ);
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self, BinaryError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Self, BinaryError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self, BinaryError> {
+        self.out.push(TAG_ENUM);
+        self.name(variant);
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self, BinaryError> {
+        self.out.push(TAG_MAP);
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self, BinaryError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self, BinaryError> {
+        self.out.push(TAG_ENUM);
+        self.name(variant);
+        self.serialize_map(Some(len))
+    }
+}
+
+/// Compound values are written as their elements followed by the end tag.
+impl Encoder {
+    fn close(&mut self) {
+        self.out.push(TAG_END);
+    }
+}
+
+impl ser::SerializeSeq for &mut Encoder {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_element<V>(&mut self, value: &V) -> Result<(), BinaryError>
+    where
+        V: Serialize + ?Sized,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), BinaryError> {
+        self.close();
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut Encoder {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_element<V>(&mut self, value: &V) -> Result<(), BinaryError>
+    where
+        V: Serialize + ?Sized,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), BinaryError> {
+        self.close();
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut Encoder {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_field<V>(&mut self, value: &V) -> Result<(), BinaryError>
+    where
+        V: Serialize + ?Sized,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), BinaryError> {
+        self.close();
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut Encoder {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_field<V>(&mut self, value: &V) -> Result<(), BinaryError>
+    where
+        V: Serialize + ?Sized,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), BinaryError> {
+        self.close();
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for &mut Encoder {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_key<V>(&mut self, key: &V) -> Result<(), BinaryError>
+    where
+        V: Serialize + ?Sized,
+    {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<V>(&mut self, value: &V) -> Result<(), BinaryError>
+    where
+        V: Serialize + ?Sized,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), BinaryError> {
+        self.close();
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut Encoder {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_field<V>(&mut self, key: &'static str, value: &V) -> Result<(), BinaryError>
+    where
+        V: Serialize + ?Sized,
+    {
+        self.name(key);
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), BinaryError> {
+        self.close();
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut Encoder {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_field<V>(&mut self, key: &'static str, value: &V) -> Result<(), BinaryError>
+    where
+        V: Serialize + ?Sized,
+    {
+        self.name(key);
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), BinaryError> {
+        self.close();
+        Ok(())
+    }
+}
+
+struct Decoder<'de> {
+    input: &'de [u8],
+
+    /// Names read so far, referenced by index.
+    names: Vec<&'de str>,
+
+    /// Current nesting of compound values.
+    depth: usize,
+}
+
+impl<'de> Decoder<'de> {
+    fn peek(&self) -> Result<u8, BinaryError> {
+        self.input
+            .first()
+            .copied()
+            .ok_or(BinaryError::UnexpectedEnd)
+    }
+
+    fn byte(&mut self) -> Result<u8, BinaryError> {
+        let byte = self.peek()?;
+        self.input = &self.input[1..];
+        Ok(byte)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'de [u8], BinaryError> {
+        if self.input.len() < len {
+            return Err(BinaryError::UnexpectedEnd);
+        }
+        let (head, tail) = self.input.split_at(len);
+        self.input = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], BinaryError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn varint(&mut self) -> Result<u64, BinaryError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            let bits = u64::from(byte & 0x7f);
+            if bits << shift >> shift != bits {
+                return Err(BinaryError::IntegerOverflow);
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(BinaryError::IntegerOverflow)
+    }
+
+    fn len(&mut self) -> Result<usize, BinaryError> {
+        usize::try_from(self.varint()?).map_err(|_| BinaryError::IntegerOverflow)
+    }
+
+    fn str(&mut self) -> Result<&'de str, BinaryError> {
+        let len = self.len()?;
+        std::str::from_utf8(self.take(len)?).map_err(|_| BinaryError::InvalidUtf8)
+    }
+
+    fn name(&mut self, tag: u8) -> Result<&'de str, BinaryError> {
+        if tag == TAG_NAME {
+            let name = self.str()?;
+            self.names.push(name);
+            Ok(name)
+        } else {
+            let idx = self.len()?;
+            self.names
+                .get(idx)
+                .copied()
+                .ok_or(BinaryError::InvalidTag(tag))
+        }
+    }
+
+    /// Consumes end tag if it is next.
+    fn at_end(&mut self) -> Result<bool, BinaryError> {
+        if self.peek()? == TAG_END {
+            self.input = &self.input[1..];
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Runs `f` one nesting level deeper.
+    fn nested<R>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<R, BinaryError>,
+    ) -> Result<R, BinaryError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(BinaryError::TooDeep);
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn compound<V>(&mut self, visitor: V, map: bool) -> Result<V::Value, BinaryError>
+    where
+        V: Visitor<'de>,
+    {
+        self.nested(|de| {
+            let mut access = Compound { de, done: false };
+            let value = if map {
+                visitor.visit_map(&mut access)?
+            } else {
+                visitor.visit_seq(&mut access)?
+            };
+
+            // Visitor may stop reading before the end, like for tuples.
+            if !access.done && !access.de.at_end()? {
+                return Err(de::Error::invalid_length(0, &"end of the sequence or map"));
+            }
+            Ok(value)
+        })
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Decoder<'de> {
+    type Error = BinaryError;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.byte()? {
+            tag @ TAG_SMALL_UINT.. => visitor.visit_u64(u64::from(tag - TAG_SMALL_UINT)),
+            TAG_UNIT => visitor.visit_unit(),
+            TAG_NONE => visitor.visit_none(),
+            TAG_SOME => self.nested(|de| visitor.visit_some(de)),
+            TAG_FALSE => visitor.visit_bool(false),
+            TAG_TRUE => visitor.visit_bool(true),
+            TAG_UINT => visitor.visit_u64(self.varint()?),
+            TAG_NEG_INT => {
+                let value =
+                    i64::try_from(self.varint()?).map_err(|_| BinaryError::IntegerOverflow)?;
+                visitor.visit_i64(!value)
+            }
+            TAG_F32 => visitor.visit_f32(f32::from_le_bytes(self.array()?)),
+            TAG_F64 => visitor.visit_f64(f64::from_le_bytes(self.array()?)),
+            TAG_STR => visitor.visit_borrowed_str(self.str()?),
+            tag @ (TAG_NAME | TAG_NAME_REF) => visitor.visit_borrowed_str(self.name(tag)?),
+            TAG_BYTES => {
+                let len = self.len()?;
+                visitor.visit_borrowed_bytes(self.take(len)?)
+            }
+            TAG_SEQ => self.compound(visitor, false),
+            TAG_MAP => self.compound(visitor, true),
+            // Enums read without type hint look like maps with single entry.
+            TAG_ENUM => self.nested(|de| visitor.visit_map(EnumEntry { de, key: true })),
+            tag => Err(BinaryError::InvalidTag(tag)),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, BinaryError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.peek()? {
+            TAG_NONE => {
+                self.byte()?;
+                visitor.visit_none()
+            }
+            TAG_SOME => {
+                self.byte()?;
+                self.nested(|de| visitor.visit_some(de))
+            }
+            tag => Err(BinaryError::InvalidTag(tag)),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, BinaryError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, BinaryError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.byte()? {
+            TAG_ENUM => self.nested(|de| visitor.visit_enum(de)),
+            tag => Err(BinaryError::InvalidTag(tag)),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+/// Access to elements of sequence or entries of map.
+struct Compound<'a, 'de> {
+    de: &'a mut Decoder<'de>,
+    done: bool,
+}
+
+impl<'de> de::SeqAccess<'de> for Compound<'_, 'de> {
+    type Error = BinaryError;
+
+    fn next_element_seed<S>(&mut self, seed: S) -> Result<Option<S::Value>, BinaryError>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        if self.done || self.de.at_end()? {
+            self.done = true;
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Compound<'_, 'de> {
+    type Error = BinaryError;
+
+    fn next_key_seed<S>(&mut self, seed: S) -> Result<Option<S::Value>, BinaryError>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        if self.done || self.de.at_end()? {
+            self.done = true;
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<S>(&mut self, seed: S) -> Result<S::Value, BinaryError>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+/// Enum variant exposed as map entry from variant name to its content.
+struct EnumEntry<'a, 'de> {
+    de: &'a mut Decoder<'de>,
+    key: bool,
+}
+
+impl<'de> de::MapAccess<'de> for EnumEntry<'_, 'de> {
+    type Error = BinaryError;
+
+    fn next_key_seed<S>(&mut self, seed: S) -> Result<Option<S::Value>, BinaryError>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        if !self.key {
+            return Ok(None);
+        }
+        self.key = false;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<S>(&mut self, seed: S) -> Result<S::Value, BinaryError>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for &mut Decoder<'de> {
+    type Error = BinaryError;
+    type Variant = Self;
+
+    fn variant_seed<S>(self, seed: S) -> Result<(S::Value, Self), BinaryError>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(&mut *self)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for &mut Decoder<'de> {
+    type Error = BinaryError;
+
+    fn unit_variant(self) -> Result<(), BinaryError> {
+        <()>::deserialize(self)
+    }
+
+    fn newtype_variant_seed<S>(self, seed: S) -> Result<S::Value, BinaryError>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, BinaryError>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, BinaryError>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+}
+
+#[test]
+fn graph_round_trips() {
+    use crate::{InPinId, OutPinId};
+
+    let mut snarl = Snarl::<String>::new();
+    let a = snarl.insert_node(egui::pos2(-10.5, 0.0), "a".to_owned());
+    let b = snarl.insert_node_collapsed(egui::pos2(100.0, 1e9), "b".to_owned());
+    let c = snarl.insert_node(egui::pos2(0.1, -300.0), String::new());
+    snarl.remove_node(a);
+    snarl.connect(
+        OutPinId { node: b, output: 3 },
+        InPinId { node: c, input: 0 },
+    );
+    snarl.connect(
+        OutPinId { node: c, output: 0 },
+        InPinId {
+            node: b,
+            input: 200,
+        },
+    );
+
+    let bytes = snarl.to_bytes().unwrap();
+    let restored = Snarl::<String>::from_bytes(&bytes).unwrap();
+
+    assert_eq!(
+        serde_json::to_value(&restored).unwrap(),
+        serde_json::to_value(&snarl).unwrap()
+    );
+    assert!(bytes.len() < serde_json::to_vec(&snarl).unwrap().len());
+}
+
+#[test]
+fn self_describing_values_round_trip() {
+    let value = serde_json::json!({
+        "int": -1_234_567,
+        "big": u64::MAX,
+        "float": 0.1,
+        "text": "snarl",
+        "list": [null, true, false, 1, -1, 127, 128, [], {}],
+        "nested": {"a": {"b": {"c": [1.5, "x"]}}},
+    });
+
+    let bytes = encode(&value).unwrap();
+    assert_eq!(decode::<serde_json::Value>(&bytes).unwrap(), value);
+}
+
+#[test]
+fn malformed_data_is_rejected() {
+    let mut snarl = Snarl::<Option<u32>>::new();
+    snarl.insert_node(egui::pos2(0.0, 0.0), Some(7));
+    snarl.insert_node(egui::pos2(10.0, 0.0), None);
+    let bytes = snarl.to_bytes().unwrap();
+
+    for len in 0..bytes.len() {
+        assert!(Snarl::<Option<u32>>::from_bytes(&bytes[..len]).is_err());
+    }
+
+    let mut trailing = bytes.clone();
+    trailing.push(TAG_UNIT);
+    assert_eq!(
+        Snarl::<Option<u32>>::from_bytes(&trailing).unwrap_err(),
+        BinaryError::TrailingBytes
+    );
+
+    let mut deep = HEADER.to_vec();
+    deep.extend(std::iter::repeat_n(TAG_SEQ, MAX_DEPTH + 1));
+    assert_eq!(
+        decode::<serde_json::Value>(&deep).unwrap_err(),
+        BinaryError::TooDeep
+    );
+}
@@ -0,0 +1,415 @@
+//! Structural comparison of two [`Snarl`] graphs.
+
+use egui::{Pos2, ahash::HashSet};
+
+use crate::{InPinId, Node, NodeId, OutPinId, Snarl, Wire, WireAttach, WireData};
+
+/// Difference between two states of the [`Snarl`] graph.
+///
+/// Produced by [`Snarl::diff`].
+/// Nodes are matched by [`NodeId`], so both graphs are expected
+/// to originate from the same graph, like two snapshots of it.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SnarlDiff<T> {
+    /// Nodes present only in the new graph.
+    pub added_nodes: Vec<(NodeId, Node<T>)>,
+
+    /// Nodes present only in the old graph.
+    pub removed_nodes: Vec<NodeId>,
+
+    /// Nodes with changed position, with old and new positions.
+    pub moved_nodes: Vec<(NodeId, Pos2, Pos2)>,
+
+    /// Nodes with changed value, with the new value.
+    pub changed_nodes: Vec<(NodeId, T)>,
+
+    /// Nodes opened or collapsed, with the new open state.
+    pub toggled_nodes: Vec<(NodeId, bool)>,
+
+    /// Wires present only in the new graph.
+    pub added_wires: Vec<(OutPinId, InPinId)>,
+
+    /// Wires present only in the old graph.
+    pub removed_wires: Vec<(OutPinId, InPinId)>,
+
+    /// Wires of the new graph with attach positions or data
+    /// that differ from the old graph, with the new values.
+    /// Added wires are listed here unless they are attached to pin centers
+    /// and have no data.
+    pub changed_wires: Vec<(OutPinId, InPinId, WireAttach, WireData)>,
+}
+
+/// Conflict between [`SnarlDiff`] and the graph it is applied to.
+///
+/// Returned by [`Snarl::apply`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DiffConflict {
+    /// Node to remove, move, change or connect does not exist.
+    MissingNode(NodeId),
+
+    /// Node to add already exists.
+    NodeExists(NodeId),
+
+    /// Wire to remove does not exist.
+    MissingWire(OutPinId, InPinId),
+}
+
+impl std::fmt::Display for DiffConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiffConflict::MissingNode(node) => write!(f, "node {} does not exist", node.0),
+            DiffConflict::NodeExists(node) => write!(f, "node {} already exists", node.0),
+            DiffConflict::MissingWire(from, to) => write!(
+                f,
+                "wire from {}:{} to {}:{} does not exist",
+                from.node.0, from.output, to.node.0, to.input
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DiffConflict {}
+
+impl<T> SnarlDiff<T> {
+    /// Returns true if graphs are equal.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.moved_nodes.is_empty()
+            && self.changed_nodes.is_empty()
+            && self.toggled_nodes.is_empty()
+            && self.added_wires.is_empty()
+            && self.removed_wires.is_empty()
+            && self.changed_wires.is_empty()
+    }
+}
+
+impl<T> Snarl<T> {
+    /// Computes difference from this graph to the `other` graph.
+    ///
+    /// See [`Snarl::diff_with_tolerance`] to ignore tiny position changes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::{InPinId, OutPinId, Snarl};
+    /// let mut old = Snarl::<u32>::new();
+    /// let a = old.insert_node(egui::pos2(0.0, 0.0), 1);
+    ///
+    /// let mut new = old.clone();
+    /// let b = new.insert_node(egui::pos2(100.0, 0.0), 2);
+    /// new.connect(OutPinId { node: a, output: 0 }, InPinId { node: b, input: 0 });
+    ///
+    /// let diff = old.diff(&new);
+    /// assert_eq!(diff.added_nodes.len(), 1);
+    /// assert_eq!(diff.added_wires.len(), 1);
+    /// assert!(diff.removed_nodes.is_empty());
+    /// ```
+    #[must_use]
+    pub fn diff(&self, other: &Snarl<T>) -> SnarlDiff<T>
+    where
+        T: PartialEq + Clone,
+    {
+        self.diff_with_tolerance(other, 0.0)
+    }
+
+    /// Computes difference from this graph to the `other` graph.
+    ///
+    /// Nodes moved by no more than `epsilon` are not reported as moved.
+    #[must_use]
+    pub fn diff_with_tolerance(&self, other: &Snarl<T>, epsilon: f32) -> SnarlDiff<T>
+    where
+        T: PartialEq + Clone,
+    {
+        let mut diff = SnarlDiff {
+            added_nodes: Vec::new(),
+            removed_nodes: Vec::new(),
+            moved_nodes: Vec::new(),
+            changed_nodes: Vec::new(),
+            toggled_nodes: Vec::new(),
+            added_wires: Vec::new(),
+            removed_wires: Vec::new(),
+            changed_wires: Vec::new(),
+        };
+
+        for (idx, node) in &self.nodes {
+            let id = NodeId(idx);
+            match other.nodes.get(idx) {
+                None => diff.removed_nodes.push(id),
+                Some(other_node) => {
+                    if node.pos.distance(other_node.pos) > epsilon {
+                        diff.moved_nodes.push((id, node.pos, other_node.pos));
+                    }
+                    if node.value != other_node.value {
+                        diff.changed_nodes.push((id, other_node.value.clone()));
+                    }
+                    if node.open != other_node.open {
+                        diff.toggled_nodes.push((id, other_node.open));
+                    }
+                }
+            }
+        }
+
+        for (idx, node) in &other.nodes {
+            if !self.nodes.contains(idx) {
+                diff.added_nodes.push((NodeId(idx), node.clone()));
+            }
+        }
+
+        diff.removed_wires = self
+            .wires
+            .wires
+            .difference(&other.wires.wires)
+            .map(|wire| (wire.out_pin, wire.in_pin))
+            .collect();
+        diff.added_wires = other
+            .wires
+            .wires
+            .difference(&self.wires.wires)
+            .map(|wire| (wire.out_pin, wire.in_pin))
+            .collect();
+
+        for wire in other.wires.iter() {
+            let attach = other.wires.attach(&wire);
+            let data = other.wires.data(&wire);
+            let (old_attach, old_data) = if self.wires.contains(&wire) {
+                (self.wires.attach(&wire), self.wires.data(&wire))
+            } else {
+                (WireAttach::default(), None)
+            };
+            if attach != old_attach || data != old_data {
+                diff.changed_wires.push((
+                    wire.out_pin,
+                    wire.in_pin,
+                    attach,
+                    data.cloned().unwrap_or_default(),
+                ));
+            }
+        }
+
+        // Keep output stable regardless of hash set order.
+        diff.removed_wires.sort_unstable();
+        diff.added_wires.sort_unstable();
+        diff.changed_wires
+            .sort_unstable_by_key(|&(out_pin, in_pin, _, _)| (out_pin, in_pin));
+
+        diff
+    }
+
+    /// Applies `diff` produced by [`Snarl::diff`] to this graph.
+    ///
+    /// Wires are removed first, then nodes are removed, added, moved, changed
+    /// and opened or collapsed, and new wires are connected last
+    /// with their attach positions and data.
+    /// Added nodes keep their ids from the diff.
+    ///
+    /// With `skip_conflicts` entries that conflict with the graph are skipped
+    /// and the rest of the diff is applied.
+    /// Otherwise the diff is validated before any change is made.
+    ///
+    /// # Errors
+    ///
+    /// Returns first [`DiffConflict`] found if `skip_conflicts` is false.
+    /// The graph is left untouched in this case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::{DiffConflict, InPinId, OutPinId, Snarl};
+    /// let mut old = Snarl::<u32>::new();
+    /// let a = old.insert_node(egui::pos2(0.0, 0.0), 1);
+    ///
+    /// let mut new = old.clone();
+    /// let b = new.insert_node(egui::pos2(100.0, 0.0), 2);
+    /// new.connect(OutPinId { node: a, output: 0 }, InPinId { node: b, input: 0 });
+    ///
+    /// let diff = old.diff(&new);
+    /// let mut patched = old.clone();
+    /// patched.apply(&diff, false).unwrap();
+    /// assert!(patched.diff(&new).is_empty());
+    ///
+    /// // Applying same diff again conflicts with already added node.
+    /// assert_eq!(patched.apply(&diff, false), Err(DiffConflict::NodeExists(b)));
+    /// ```
+    pub fn apply(&mut self, diff: &SnarlDiff<T>, skip_conflicts: bool) -> Result<(), DiffConflict>
+    where
+        T: Clone,
+    {
+        if !skip_conflicts && let Some(conflict) = self.diff_conflict(diff) {
+            return Err(conflict);
+        }
+
+        for &(out_pin, in_pin) in &diff.removed_wires {
+            self.wires.remove(&Wire { out_pin, in_pin });
+        }
+
+        for &node in &diff.removed_nodes {
+            if self.nodes.contains(node.0) {
+                self.remove_node(node);
+            }
+        }
+
+        self.insert_nodes_at(
+            diff.added_nodes
+                .iter()
+                .map(|(node, data)| (*node, data.clone())),
+        );
+
+        for &(node, _, pos) in &diff.moved_nodes {
+            if let Some(node) = self.nodes.get_mut(node.0) {
+                node.pos = pos;
+            }
+        }
+
+        for (node, value) in &diff.changed_nodes {
+            if let Some(node) = self.nodes.get_mut(node.0) {
+                node.value = value.clone();
+            }
+        }
+
+        for &(node, open) in &diff.toggled_nodes {
+            if let Some(node) = self.nodes.get_mut(node.0) {
+                node.open = open;
+            }
+        }
+
+        for &(out_pin, in_pin) in &diff.added_wires {
+            if self.nodes.contains(out_pin.node.0) && self.nodes.contains(in_pin.node.0) {
+                self.wires.insert(Wire { out_pin, in_pin });
+            }
+        }
+
+        for (out_pin, in_pin, attach, data) in &diff.changed_wires {
+            let wire = Wire {
+                out_pin: *out_pin,
+                in_pin: *in_pin,
+            };
+            if self.wires.contains(&wire) {
+                self.wires.insert_attached(wire, *attach);
+                self.wires.set_data(wire, data.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns first entry of the `diff` that conflicts with this graph.
+    fn diff_conflict(&self, diff: &SnarlDiff<T>) -> Option<DiffConflict> {
+        for &(out_pin, in_pin) in &diff.removed_wires {
+            if !self.wires.contains(&Wire { out_pin, in_pin }) {
+                return Some(DiffConflict::MissingWire(out_pin, in_pin));
+            }
+        }
+
+        let mut removed = HashSet::default();
+        for &node in &diff.removed_nodes {
+            if !self.nodes.contains(node.0) || !removed.insert(node) {
+                return Some(DiffConflict::MissingNode(node));
+            }
+        }
+
+        let mut added = HashSet::default();
+        for &(node, _) in &diff.added_nodes {
+            let occupied = self.nodes.contains(node.0) && !removed.contains(&node);
+            if occupied || !added.insert(node) {
+                return Some(DiffConflict::NodeExists(node));
+            }
+        }
+
+        let exists = |node: NodeId| {
+            added.contains(&node) || (self.nodes.contains(node.0) && !removed.contains(&node))
+        };
+
+        let touched = diff
+            .moved_nodes
+            .iter()
+            .map(|&(node, _, _)| node)
+            .chain(diff.changed_nodes.iter().map(|&(node, _)| node))
+            .chain(diff.toggled_nodes.iter().map(|&(node, _)| node))
+            .chain(
+                diff.added_wires
+                    .iter()
+                    .flat_map(|&(out_pin, in_pin)| [out_pin.node, in_pin.node]),
+            );
+
+        for node in touched {
+            if !exists(node) {
+                return Some(DiffConflict::MissingNode(node));
+            }
+        }
+
+        let removed_wires = diff.removed_wires.iter().collect::<HashSet<_>>();
+        let added_wires = diff.added_wires.iter().collect::<HashSet<_>>();
+        for &(out_pin, in_pin, _, _) in &diff.changed_wires {
+            let kept = self.wires.contains(&Wire { out_pin, in_pin })
+                && !removed_wires.contains(&(out_pin, in_pin))
+                && exists(out_pin.node)
+                && exists(in_pin.node);
+            if !kept && !added_wires.contains(&(out_pin, in_pin)) {
+                return Some(DiffConflict::MissingWire(out_pin, in_pin));
+            }
+        }
+
+        None
+    }
+}
+
+#[test]
+fn diff_restores_open_state_and_wire_extras() {
+    let mut old = Snarl::<u32>::new();
+    let a = old.insert_node(Pos2::ZERO, 1);
+    let b = old.insert_node(Pos2::ZERO, 2);
+    let kept = (
+        OutPinId { node: a, output: 0 },
+        InPinId { node: b, input: 0 },
+    );
+    old.connect(kept.0, kept.1);
+
+    let mut new = old.clone();
+    new.open_node(a, false);
+    new.set_wire_data(
+        kept.0,
+        kept.1,
+        WireData {
+            label: Some("kept".to_owned()),
+            ..WireData::default()
+        },
+    );
+    let added = (
+        OutPinId { node: b, output: 0 },
+        InPinId { node: a, input: 1 },
+    );
+    new.connect_at(
+        added.0,
+        added.1,
+        WireAttach {
+            out_pin: Some(0.25),
+            in_pin: None,
+        },
+    );
+
+    let diff = old.diff(&new);
+    assert_eq!(diff.toggled_nodes, [(a, false)]);
+    assert_eq!(diff.added_wires, [added]);
+    assert_eq!(diff.changed_wires.len(), 2);
+
+    let mut patched = old.clone();
+    patched.apply(&diff, false).unwrap();
+    assert!(patched.diff(&new).is_empty());
+    assert!(!patched.node_info(a).unwrap().open);
+    assert_eq!(patched.wire_attach(added.0, added.1).out_pin, Some(0.25));
+    assert_eq!(
+        patched.wire_data(kept.0, kept.1).unwrap().label.as_deref(),
+        Some("kept")
+    );
+
+    // Changed wire must exist after the diff is applied.
+    let mut other = old.clone();
+    other.disconnect(kept.0, kept.1);
+    assert_eq!(
+        other.apply(&diff, false),
+        Err(DiffConflict::MissingWire(kept.0, kept.1))
+    );
+}
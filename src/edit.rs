@@ -0,0 +1,378 @@
+//! Recording of structural edits of the [`Snarl`] graph.
+
+use egui::Pos2;
+
+use crate::{InPinId, Node, NodeId, OutPinId, Snarl, Wire, WireAttach, WireData};
+
+/// Single recorded mutation of the graph.
+#[derive(Clone, Debug)]
+enum EditOp<T> {
+    Insert {
+        node: NodeId,
+        data: Node<T>,
+    },
+    Remove {
+        node: NodeId,
+        data: Node<T>,
+        wires: Vec<(Wire, WireAttach, WireData)>,
+    },
+    Connect(Wire),
+    Disconnect(Wire, WireAttach, WireData),
+    Move {
+        node: NodeId,
+        from: Pos2,
+        to: Pos2,
+    },
+}
+
+/// Guard that applies mutations to the [`Snarl`] and records them.
+///
+/// Created with [`Snarl::begin_edit`].
+/// Call [`EditGuard::finish`] to get recorded [`Edit`].
+/// Dropping the guard keeps the mutations applied but discards the record.
+///
+/// Mutations are applied to the graph directly,
+/// so [`SnarlViewer`](crate::ui::SnarlViewer) callbacks like
+/// [`SnarlViewer::node_moved`](crate::ui::SnarlViewer::node_moved) are not called.
+/// Only methods of the guard are recorded,
+/// nodes merged with [`Snarl::merge_deserialized`] or changes from [`Snarl::apply`]
+/// can't be recorded and should be kept out of the recorded range of the history.
+pub struct EditGuard<'a, T> {
+    snarl: &'a mut Snarl<T>,
+    ops: Vec<EditOp<T>>,
+}
+
+/// Recorded group of mutations of the [`Snarl`].
+///
+/// Produced by [`EditGuard::finish`].
+/// Can be reverted and reapplied any number of times
+/// as long as the graph is in the state right after or right before the edit respectively,
+/// like it is when edits are kept in undo history.
+/// Nodes are restored at their recorded ids,
+/// which only requires the ids to be free.
+#[derive(Clone, Debug)]
+pub struct Edit<T> {
+    ops: Vec<EditOp<T>>,
+}
+
+impl<T> Snarl<T> {
+    /// Starts recording structural edit of the graph.
+    ///
+    /// Mutations made through returned [`EditGuard`]
+    /// can be reverted and reapplied later with produced [`Edit`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::{InPinId, OutPinId, Snarl};
+    /// let mut snarl = Snarl::<()>::new();
+    ///
+    /// let mut guard = snarl.begin_edit();
+    /// let a = guard.insert_node(egui::pos2(0.0, 0.0), ());
+    /// let b = guard.insert_node(egui::pos2(100.0, 0.0), ());
+    /// guard.connect(OutPinId { node: a, output: 0 }, InPinId { node: b, input: 0 });
+    /// let edit = guard.finish();
+    ///
+    /// edit.revert(&mut snarl);
+    /// assert_eq!(snarl.node_count(), 0);
+    ///
+    /// edit.reapply(&mut snarl);
+    /// assert_eq!(snarl.node_count(), 2);
+    /// assert_eq!(snarl.wire_count(), 1);
+    /// ```
+    pub fn begin_edit(&mut self) -> EditGuard<'_, T>
+    where
+        T: Clone,
+    {
+        EditGuard {
+            snarl: self,
+            ops: Vec::new(),
+        }
+    }
+
+    /// Inserts node at exactly `node` id.
+    #[track_caller]
+    fn restore_node(&mut self, node: NodeId, data: Node<T>) {
+        assert!(
+            !self.nodes.contains(node.0),
+            "graph was modified since the edit was recorded"
+        );
+        self.insert_nodes_at([(node, data)]);
+    }
+
+    #[track_caller]
+    fn move_node_to(&mut self, node: NodeId, pos: Pos2) {
+        self.nodes[node.0].pos = pos;
+    }
+}
+
+impl<T> EditGuard<'_, T>
+where
+    T: Clone,
+{
+    /// Returns the graph being edited.
+    #[must_use]
+    pub fn snarl(&self) -> &Snarl<T> {
+        self.snarl
+    }
+
+    /// Adds a node to the Snarl.
+    /// Returns the index of the node.
+    ///
+    /// See [`Snarl::insert_node`].
+    pub fn insert_node(&mut self, pos: Pos2, node: T) -> NodeId {
+        let id = self.snarl.insert_node(pos, node);
+        self.record_insert(id);
+        id
+    }
+
+    /// Adds a node to the Snarl in collapsed state.
+    /// Returns the index of the node.
+    ///
+    /// See [`Snarl::insert_node_collapsed`].
+    pub fn insert_node_collapsed(&mut self, pos: Pos2, node: T) -> NodeId {
+        let id = self.snarl.insert_node_collapsed(pos, node);
+        self.record_insert(id);
+        id
+    }
+
+    /// Removes a node from the Snarl together with its wires.
+    /// Returns the node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node does not exist.
+    #[track_caller]
+    pub fn remove_node(&mut self, node: NodeId) -> T {
+        let wires = self
+            .snarl
+            .wires
+            .iter()
+            .filter(|wire| wire.out_pin.node == node || wire.in_pin.node == node)
+            .map(|wire| (wire, self.snarl.wires.attach(&wire), self.wire_data(wire)))
+            .collect();
+
+        let data = self.snarl.nodes[node.0].clone();
+        let value = self.snarl.remove_node(node);
+
+        self.ops.push(EditOp::Remove { node, data, wires });
+        value
+    }
+
+    /// Connects two nodes.
+    /// Returns true if the connection was established.
+    /// Returns false if the connection already exists.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either of the nodes does not exist.
+    #[track_caller]
+    pub fn connect(&mut self, from: OutPinId, to: InPinId) -> bool {
+        let connected = self.snarl.connect(from, to);
+        if connected {
+            self.ops.push(EditOp::Connect(Wire {
+                out_pin: from,
+                in_pin: to,
+            }));
+        }
+        connected
+    }
+
+    /// Disconnects two nodes.
+    /// Returns true if the connection was removed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either of the nodes does not exist.
+    #[track_caller]
+    pub fn disconnect(&mut self, from: OutPinId, to: InPinId) -> bool {
+        let attach = self.snarl.wire_attach(from, to);
+        let data = self.snarl.wire_data(from, to).cloned().unwrap_or_default();
+        let disconnected = self.snarl.disconnect(from, to);
+        if disconnected {
+            self.ops.push(EditOp::Disconnect(
+                Wire {
+                    out_pin: from,
+                    in_pin: to,
+                },
+                attach,
+                data,
+            ));
+        }
+        disconnected
+    }
+
+    /// Removes all connections to the node's pin.
+    /// Returns number of removed connections.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node does not exist.
+    #[track_caller]
+    pub fn drop_inputs(&mut self, pin: InPinId) -> usize {
+        let remotes = self.snarl.wires.wired_outputs(pin).collect::<Vec<_>>();
+        let count = remotes.len();
+        for out_pin in remotes {
+            self.disconnect(out_pin, pin);
+        }
+        count
+    }
+
+    /// Removes all connections from the node's pin.
+    /// Returns number of removed connections.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node does not exist.
+    #[track_caller]
+    pub fn drop_outputs(&mut self, pin: OutPinId) -> usize {
+        let remotes = self.snarl.wires.wired_inputs(pin).collect::<Vec<_>>();
+        let count = remotes.len();
+        for in_pin in remotes {
+            self.disconnect(pin, in_pin);
+        }
+        count
+    }
+
+    /// Moves the node to `pos`.
+    ///
+    /// Unlike dragging the node in the UI, it doesn't call
+    /// [`SnarlViewer::node_moved`](crate::ui::SnarlViewer::node_moved).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node does not exist.
+    #[track_caller]
+    pub fn move_node(&mut self, node: NodeId, pos: Pos2) {
+        let from = self.snarl.nodes[node.0].pos;
+        if from != pos {
+            self.snarl.move_node_to(node, pos);
+            self.ops.push(EditOp::Move {
+                node,
+                from,
+                to: pos,
+            });
+        }
+    }
+
+    /// Finishes recording and returns the recorded edit.
+    #[must_use]
+    pub fn finish(self) -> Edit<T> {
+        Edit { ops: self.ops }
+    }
+
+    fn wire_data(&self, wire: Wire) -> WireData {
+        self.snarl.wires.data(&wire).cloned().unwrap_or_default()
+    }
+
+    fn record_insert(&mut self, node: NodeId) {
+        let data = self.snarl.nodes[node.0].clone();
+        self.ops.push(EditOp::Insert { node, data });
+    }
+}
+
+impl<T> Edit<T>
+where
+    T: Clone,
+{
+    /// Returns true if the edit has no recorded mutations.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Undoes recorded mutations in reverse order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the graph is not in the state right after the edit.
+    #[track_caller]
+    pub fn revert(&self, snarl: &mut Snarl<T>) {
+        for op in self.ops.iter().rev() {
+            match op {
+                EditOp::Insert { node, .. } => {
+                    snarl.remove_node(*node);
+                }
+                EditOp::Remove { node, data, wires } => {
+                    snarl.restore_node(*node, data.clone());
+                    for (wire, attach, data) in wires {
+                        snarl.wires.insert_attached(*wire, *attach);
+                        snarl.wires.set_data(*wire, data.clone());
+                    }
+                }
+                EditOp::Connect(wire) => {
+                    snarl.wires.remove(wire);
+                }
+                EditOp::Disconnect(wire, attach, data) => {
+                    snarl.wires.insert_attached(*wire, *attach);
+                    snarl.wires.set_data(*wire, data.clone());
+                }
+                EditOp::Move { node, from, .. } => snarl.move_node_to(*node, *from),
+            }
+        }
+    }
+
+    /// Applies recorded mutations again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the graph is not in the state right before the edit.
+    #[track_caller]
+    pub fn reapply(&self, snarl: &mut Snarl<T>) {
+        for op in &self.ops {
+            match op {
+                EditOp::Insert { node, data } => snarl.restore_node(*node, data.clone()),
+                EditOp::Remove { node, .. } => {
+                    snarl.remove_node(*node);
+                }
+                EditOp::Connect(wire) => {
+                    snarl.wires.insert(*wire);
+                }
+                EditOp::Disconnect(wire, ..) => {
+                    snarl.wires.remove(wire);
+                }
+                EditOp::Move { node, to, .. } => snarl.move_node_to(*node, *to),
+            }
+        }
+    }
+}
+
+#[test]
+fn nodes_are_restored_at_recorded_ids() {
+    let mut snarl = Snarl::<u32>::new();
+    let a = snarl.insert_node(Pos2::ZERO, 1);
+    let b = snarl.insert_node(Pos2::ZERO, 2);
+    let c = snarl.insert_node(Pos2::ZERO, 3);
+    snarl.connect(
+        OutPinId { node: a, output: 0 },
+        InPinId { node: b, input: 0 },
+    );
+
+    let mut guard = snarl.begin_edit();
+    guard.remove_node(a);
+    guard.remove_node(c);
+    let edit = guard.finish();
+
+    // Rebuild the slab with node at unrelated id, so free slots are reordered.
+    let mut other = snarl.clone();
+    let far = other.insert_nodes_at([(
+        NodeId(7),
+        Node {
+            value: 4,
+            pos: Pos2::ZERO,
+            open: true,
+        },
+    )]);
+    assert_eq!(far, [NodeId(7)]);
+    snarl.apply(&snarl.diff(&other), false).unwrap();
+
+    edit.revert(&mut snarl);
+    assert_eq!(snarl[a], 1);
+    assert_eq!(snarl[c], 3);
+    assert_eq!(snarl[NodeId(7)], 4);
+    assert_eq!(snarl.wire_count(), 1);
+
+    edit.reapply(&mut snarl);
+    assert_eq!(snarl.node_count(), 2);
+    assert_eq!(snarl.wire_count(), 0);
+}
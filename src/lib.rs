@@ -16,12 +16,31 @@
 #![warn(clippy::pedantic, clippy::dbg_macro, clippy::must_use_candidate)]
 #![allow(clippy::range_plus_one, clippy::inline_always)]
 
+#[cfg(feature = "binary")]
+mod binary;
+mod diff;
+mod edit;
 pub mod ui;
 
-use std::ops::{Index, IndexMut};
+use std::{
+    hash::{Hash, Hasher},
+    ops::{Index, IndexMut},
+};
 
-use egui::{Pos2, ahash::HashSet};
+use egui::{
+    Color32, Pos2,
+    ahash::{HashMap, HashSet},
+};
 use slab::Slab;
+use smallvec::SmallVec;
+
+pub use self::{
+    diff::{DiffConflict, SnarlDiff},
+    edit::{Edit, EditGuard},
+};
+
+#[cfg(feature = "binary")]
+pub use self::binary::BinaryError;
 
 impl<T> Default for Snarl<T> {
     fn default() -> Self {
@@ -56,9 +75,16 @@ pub struct Node<T> {
     pub pos: egui::Pos2,
 
     /// Flag indicating that the node is open - not collapsed.
+    /// Defaults to `true` when missing in deserialized data.
+    #[cfg_attr(feature = "serde", serde(default = "default_open"))]
     pub open: bool,
 }
 
+#[cfg(feature = "serde")]
+const fn default_open() -> bool {
+    true
+}
+
 /// Output pin identifier.
 /// Cosists of node id and pin index.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -84,12 +110,120 @@ pub struct InPinId {
     pub input: usize,
 }
 
+/// Identifier of either input or output pin.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "facet", derive(facet::Facet), repr(u8))]
+pub enum AnyPinId {
+    /// Output pin.
+    Out(OutPinId),
+
+    /// Input pin.
+    In(InPinId),
+}
+
+impl AnyPinId {
+    /// Returns id of the node the pin belongs to.
+    #[must_use]
+    #[inline]
+    pub const fn node(self) -> NodeId {
+        match self {
+            AnyPinId::Out(pin) => pin.node,
+            AnyPinId::In(pin) => pin.node,
+        }
+    }
+}
+
+impl From<OutPinId> for AnyPinId {
+    #[inline]
+    fn from(pin: OutPinId) -> Self {
+        AnyPinId::Out(pin)
+    }
+}
+
+impl From<InPinId> for AnyPinId {
+    #[inline]
+    fn from(pin: InPinId) -> Self {
+        AnyPinId::In(pin)
+    }
+}
+
+/// Positions along rail pins where a wire is attached.
+///
+/// Rail pins are continuous edges instead of points,
+/// see [`PinInfo::rail`](crate::ui::PinInfo::rail).
+/// Positions are in `0.0..=1.0` range from the start to the end of the rail.
+/// `None` means the wire is attached to the pin's center.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "facet", derive(facet::Facet))]
+pub struct WireAttach {
+    /// Position along the output rail pin.
+    pub out_pin: Option<f32>,
+
+    /// Position along the input rail pin.
+    pub in_pin: Option<f32>,
+}
+
+impl WireAttach {
+    /// Returns `true` if the wire is attached to centers of both pins.
+    #[must_use]
+    #[inline]
+    pub const fn is_center(&self) -> bool {
+        self.out_pin.is_none() && self.in_pin.is_none()
+    }
+}
+
+/// Optional attributes of the wire stored in the graph.
+///
+/// Set with [`Snarl::connect_with_data`] or [`Snarl::set_wire_data`]
+/// and read back with [`Snarl::wire_data`],
+/// e.g. from [`SnarlViewer::show_wire_widget`](crate::ui::SnarlViewer::show_wire_widget).
+/// Serialized with the graph.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WireData {
+    /// Label of the wire.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub label: Option<String>,
+
+    /// Color of the wire.
+    /// Overrides color derived from pins when set.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub color: Option<Color32>,
+
+    /// Points for the wire to route through.
+    ///
+    /// Returned by default from
+    /// [`SnarlViewer::wire_waypoints`](crate::ui::SnarlViewer::wire_waypoints).
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub waypoints: Vec<Pos2>,
+}
+
+impl WireData {
+    /// Returns `true` if no attribute is set.
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.label.is_none() && self.color.is_none() && self.waypoints.is_empty()
+    }
+}
+
 /// Connection between two nodes.
 ///
 /// Nodes may support multiple connections to the same input or output.
 /// But duplicate connections between same input and the same output are not allowed.
 /// Attempt to insert existing connection will be ignored.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct Wire {
     out_pin: OutPinId,
@@ -97,10 +231,38 @@ struct Wire {
 }
 
 #[derive(Clone, Debug)]
+#[allow(clippy::struct_field_names)]
 struct Wires {
     wires: HashSet<Wire>,
+
+    /// Attach positions of wires connected to rail pins.
+    attach: HashMap<Wire, WireAttach>,
+
+    /// Attributes of wires that have any.
+    data: HashMap<Wire, WireData>,
+}
+
+/// Serialized form of the wire.
+/// Attach positions are omitted for wires attached to pin centers,
+/// so the format matches plain wires.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct WireRepr {
+    out_pin: OutPinId,
+    in_pin: InPinId,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    out_attach: Option<f32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    in_attach: Option<f32>,
+
+    #[serde(default, skip_serializing_if = "WireData::is_empty")]
+    data: WireData,
 }
 
+/// Wires are serialized sorted by output and then input pin ids,
+/// so identical graphs produce identical output regardless of hash set order.
 #[cfg(feature = "serde")]
 impl serde::Serialize for Wires {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -109,9 +271,19 @@ impl serde::Serialize for Wires {
     {
         use serde::ser::SerializeSeq;
 
-        let mut seq = serializer.serialize_seq(Some(self.wires.len()))?;
-        for wire in &self.wires {
-            seq.serialize_element(&wire)?;
+        let mut sorted = self.wires.iter().copied().collect::<Vec<_>>();
+        sorted.sort_unstable();
+
+        let mut seq = serializer.serialize_seq(Some(sorted.len()))?;
+        for wire in &sorted {
+            let attach = self.attach(wire);
+            seq.serialize_element(&WireRepr {
+                out_pin: wire.out_pin,
+                in_pin: wire.in_pin,
+                out_attach: attach.out_pin,
+                in_attach: attach.in_pin,
+                data: self.data.get(wire).cloned().unwrap_or_default(),
+            })?;
         }
         seq.end()
     }
@@ -126,7 +298,7 @@ impl<'de> serde::Deserialize<'de> for Wires {
         struct Visitor;
 
         impl<'de> serde::de::Visitor<'de> for Visitor {
-            type Value = HashSet<Wire>;
+            type Value = Wires;
 
             fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
                 formatter.write_str("a sequence of wires")
@@ -136,16 +308,26 @@ impl<'de> serde::Deserialize<'de> for Wires {
             where
                 A: serde::de::SeqAccess<'de>,
             {
-                let mut wires = HashSet::with_hasher(egui::ahash::RandomState::new());
-                while let Some(wire) = seq.next_element()? {
-                    wires.insert(wire);
+                let mut wires = Wires::new();
+                while let Some(repr) = seq.next_element::<WireRepr>()? {
+                    let wire = Wire {
+                        out_pin: repr.out_pin,
+                        in_pin: repr.in_pin,
+                    };
+                    wires.insert_attached(
+                        wire,
+                        WireAttach {
+                            out_pin: repr.out_attach,
+                            in_pin: repr.in_attach,
+                        },
+                    );
+                    wires.set_data(wire, repr.data);
                 }
                 Ok(wires)
             }
         }
 
-        let wires = deserializer.deserialize_seq(Visitor)?;
-        Ok(Wires { wires })
+        deserializer.deserialize_seq(Visitor)
     }
 }
 
@@ -153,6 +335,8 @@ impl Wires {
     fn new() -> Self {
         Wires {
             wires: HashSet::with_hasher(egui::ahash::RandomState::new()),
+            attach: HashMap::with_hasher(egui::ahash::RandomState::new()),
+            data: HashMap::with_hasher(egui::ahash::RandomState::new()),
         }
     }
 
@@ -160,27 +344,69 @@ impl Wires {
         self.wires.insert(wire)
     }
 
+    fn reserve(&mut self, additional: usize) {
+        self.wires.reserve(additional);
+    }
+
+    /// Inserts the wire or updates attach positions of existing one.
+    fn insert_attached(&mut self, wire: Wire, attach: WireAttach) -> bool {
+        if attach.is_center() {
+            self.attach.remove(&wire);
+        } else {
+            self.attach.insert(wire, attach);
+        }
+        self.wires.insert(wire)
+    }
+
+    fn attach(&self, wire: &Wire) -> WireAttach {
+        self.attach.get(wire).copied().unwrap_or_default()
+    }
+
+    /// Sets attributes of existing wire.
+    /// Empty data removes stored attributes.
+    fn set_data(&mut self, wire: Wire, data: WireData) -> bool {
+        if !self.wires.contains(&wire) {
+            return false;
+        }
+        if data.is_empty() {
+            self.data.remove(&wire);
+        } else {
+            self.data.insert(wire, data);
+        }
+        true
+    }
+
+    fn data(&self, wire: &Wire) -> Option<&WireData> {
+        self.data.get(wire)
+    }
+
     fn remove(&mut self, wire: &Wire) -> bool {
+        self.attach.remove(wire);
+        self.data.remove(wire);
         self.wires.remove(wire)
     }
 
-    fn drop_node(&mut self, node: NodeId) -> usize {
+    fn retain(&mut self, mut f: impl FnMut(&Wire) -> bool) -> usize {
         let count = self.wires.len();
-        self.wires
-            .retain(|wire| wire.out_pin.node != node && wire.in_pin.node != node);
+        self.wires.retain(|wire| f(wire));
+        if count != self.wires.len() {
+            let wires = &self.wires;
+            self.attach.retain(|wire, _| wires.contains(wire));
+            self.data.retain(|wire, _| wires.contains(wire));
+        }
         count - self.wires.len()
     }
 
+    fn drop_node(&mut self, node: NodeId) -> usize {
+        self.retain(|wire| wire.out_pin.node != node && wire.in_pin.node != node)
+    }
+
     fn drop_inputs(&mut self, pin: InPinId) -> usize {
-        let count = self.wires.len();
-        self.wires.retain(|wire| wire.in_pin != pin);
-        count - self.wires.len()
+        self.retain(|wire| wire.in_pin != pin)
     }
 
     fn drop_outputs(&mut self, pin: OutPinId) -> usize {
-        let count = self.wires.len();
-        self.wires.retain(|wire| wire.out_pin != pin);
-        count - self.wires.len()
+        self.retain(|wire| wire.out_pin != pin)
     }
 
     fn wired_inputs(&self, out_pin: OutPinId) -> impl Iterator<Item = InPinId> + '_ {
@@ -200,18 +426,74 @@ impl Wires {
     fn iter(&self) -> impl Iterator<Item = Wire> + '_ {
         self.wires.iter().copied()
     }
+
+    fn len(&self) -> usize {
+        self.wires.len()
+    }
+
+    /// Removes wires attached to nodes for which `has_node` returns false
+    /// and attributes left without wire.
+    /// Returns number of removed wires.
+    fn prune(&mut self, has_node: impl Fn(NodeId) -> bool) -> usize {
+        let removed = self.retain(|wire| has_node(wire.out_pin.node) && has_node(wire.in_pin.node));
+        let wires = &self.wires;
+        self.attach.retain(|wire, _| wires.contains(wire));
+        self.data.retain(|wire, _| wires.contains(wire));
+        removed
+    }
+
+    fn contains(&self, wire: &Wire) -> bool {
+        self.wires.contains(wire)
+    }
+}
+
+fn hash_pos(pos: Pos2, hasher: &mut impl Hasher) {
+    pos.x.to_bits().hash(hasher);
+    pos.y.to_bits().hash(hasher);
+}
+
+/// Sorts and deduplicates node ids.
+fn unique_nodes(nodes: impl Iterator<Item = NodeId>) -> smallvec::IntoIter<[NodeId; 8]> {
+    let mut nodes = nodes.collect::<SmallVec<[NodeId; 8]>>();
+    nodes.sort_unstable();
+    nodes.dedup();
+    nodes.into_iter()
+}
+
+/// Version of [`SnarlUiState`] format.
+const SNARL_UI_STATE_VERSION: u32 = 1;
+
+/// UI state of nodes stored alongside the graph,
+/// so visual state survives serialization.
+///
+/// Missing in data serialized by older versions and then defaults to empty.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct SnarlUiState {
+    /// Version of the format, `0` if loaded from data without UI state.
+    #[cfg_attr(feature = "serde", serde(default))]
+    version: u32,
+
+    /// Order in which nodes are drawn, from bottom to top.
+    #[cfg_attr(feature = "serde", serde(default))]
+    draw_order: Vec<NodeId>,
 }
 
 /// Snarl is generic node-graph container.
 ///
 /// It holds graph state - positioned nodes and wires between their pins.
 /// It can be rendered using [`Snarl::show`].
+///
+/// With `serde` feature, nodes are serialized in order of their [`NodeId`]
+/// and wires in order of their pin ids, so equal graphs serialize identically.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Snarl<T> {
     // #[cfg_attr(feature = "serde", serde(with = "serde_nodes"))]
     nodes: Slab<Node<T>>,
     wires: Wires,
+    #[cfg_attr(feature = "serde", serde(default))]
+    ui: SnarlUiState,
 }
 
 impl<T> Snarl<T> {
@@ -228,9 +510,38 @@ impl<T> Snarl<T> {
         Snarl {
             nodes: Slab::new(),
             wires: Wires::new(),
+            ui: SnarlUiState::default(),
         }
     }
 
+    /// Reserves capacity for at least `additional` more nodes.
+    ///
+    /// Use it before inserting many nodes at once,
+    /// like when graph is loaded progressively,
+    /// to avoid repeated reallocation of node storage.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::Snarl;
+    /// let mut snarl = Snarl::<usize>::new();
+    /// snarl.reserve(10_000);
+    /// for i in 0..10_000 {
+    ///     snarl.insert_node(egui::pos2(i as f32, 0.0), i);
+    /// }
+    /// assert_eq!(snarl.node_count(), 10_000);
+    /// ```
+    pub fn reserve(&mut self, additional: usize) {
+        self.nodes.reserve(additional);
+    }
+
+    /// Reserves capacity for at least `additional` more wires.
+    ///
+    /// See [`Snarl::reserve`].
+    pub fn reserve_wires(&mut self, additional: usize) {
+        self.wires.reserve(additional);
+    }
+
     /// Adds a node to the Snarl.
     /// Returns the index of the node.
     ///
@@ -251,6 +562,66 @@ impl<T> Snarl<T> {
         NodeId(idx)
     }
 
+    /// Adds a node to the Snarl unless it already has `max_nodes` nodes.
+    /// Returns the index of the node,
+    /// or gives the node back if the capacity is reached.
+    ///
+    /// Pass [`SnarlConfig::max_nodes`](crate::ui::SnarlConfig::max_nodes)
+    /// to respect the same cap as the UI does.
+    /// `None` means unbounded.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(node)` if the Snarl already has `max_nodes` nodes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::Snarl;
+    /// let mut snarl = Snarl::<()>::new();
+    /// assert!(snarl.try_insert_node(egui::pos2(0.0, 0.0), (), Some(1)).is_ok());
+    /// assert!(snarl.try_insert_node(egui::pos2(0.0, 0.0), (), Some(1)).is_err());
+    /// ```
+    pub fn try_insert_node(
+        &mut self,
+        pos: egui::Pos2,
+        node: T,
+        max_nodes: Option<usize>,
+    ) -> Result<NodeId, T> {
+        if max_nodes.is_some_and(|max| self.nodes.len() >= max) {
+            return Err(node);
+        }
+        Ok(self.insert_node(pos, node))
+    }
+
+    /// Adds a node to the Snarl at `pos` snapped to the `grid`.
+    /// Returns the index of the node.
+    ///
+    /// Pass [`SnarlConfig::grid_snap`](crate::ui::SnarlConfig::grid_snap)
+    /// to align the node with the same grid dragged nodes snap to.
+    /// `None` inserts the node at `pos` as is.
+    ///
+    /// Node size is not known until it is shown,
+    /// so node position is snapped regardless of [`SnapGrid::anchor`](crate::ui::SnapGrid::anchor).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::{Snarl, ui::SnapGrid};
+    /// let mut snarl = Snarl::<()>::new();
+    /// let node = snarl.insert_node_snapped(egui::pos2(18.0, 33.0), (), Some(SnapGrid::quad(10.0)));
+    /// assert_eq!(snarl.node_info(node).unwrap().pos, egui::pos2(20.0, 30.0));
+    /// ```
+    pub fn insert_node_snapped(
+        &mut self,
+        pos: egui::Pos2,
+        node: T,
+        grid: Option<ui::SnapGrid>,
+    ) -> NodeId {
+        let pos = grid.map_or(pos, |grid| grid.snap(pos));
+        self.insert_node(pos, node)
+    }
+
     /// Adds a node to the Snarl in collapsed state.
     /// Returns the index of the node.
     ///
@@ -303,6 +674,145 @@ impl<T> Snarl<T> {
         value
     }
 
+    /// Replaces value of the node keeping its id, position and wires.
+    /// Returns the previous value, or `None` if the node does not exist.
+    ///
+    /// Caller is responsible for the new value having compatible pins,
+    /// use [`Snarl::replace_node_pruned`] to drop wires of removed pins.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::Snarl;
+    /// let mut snarl = Snarl::<u32>::new();
+    /// let node = snarl.insert_node(egui::pos2(0.0, 0.0), 1);
+    /// assert_eq!(snarl.replace_node(node, 2), Some(1));
+    /// assert_eq!(snarl[node], 2);
+    /// ```
+    pub fn replace_node(&mut self, idx: NodeId, value: T) -> Option<T> {
+        let node = self.nodes.get_mut(idx.0)?;
+        Some(std::mem::replace(&mut node.value, value))
+    }
+
+    /// Replaces value of the node keeping its id and position.
+    /// Returns the previous value, or `None` if the node does not exist.
+    ///
+    /// Wires connected to input pins with index `inputs` or higher
+    /// and output pins with index `outputs` or higher are removed.
+    pub fn replace_node_pruned(
+        &mut self,
+        idx: NodeId,
+        value: T,
+        inputs: usize,
+        outputs: usize,
+    ) -> Option<T> {
+        let old = self.replace_node(idx, value)?;
+        self.wires.retain(|wire| {
+            !(wire.in_pin.node == idx && wire.in_pin.input >= inputs
+                || wire.out_pin.node == idx && wire.out_pin.output >= outputs)
+        });
+        Some(old)
+    }
+
+    /// Removes multiple nodes from the Snarl at once.
+    /// Returns removed nodes with their ids.
+    ///
+    /// Wires of all removed nodes are dropped in a single pass.
+    /// Ids of nodes that don't exist are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::Snarl;
+    /// let mut snarl = Snarl::<()>::new();
+    /// let a = snarl.insert_node(egui::pos2(0.0, 0.0), ());
+    /// let b = snarl.insert_node(egui::pos2(0.0, 0.0), ());
+    /// assert_eq!(snarl.remove_nodes([a, b]).len(), 2);
+    /// ```
+    pub fn remove_nodes(&mut self, nodes: impl IntoIterator<Item = NodeId>) -> Vec<(NodeId, T)> {
+        let mut removed = Vec::new();
+        for node in nodes {
+            if let Some(data) = self.nodes.try_remove(node.0) {
+                removed.push((node, data.value));
+            }
+        }
+
+        if !removed.is_empty() {
+            let ids = removed.iter().map(|(id, _)| *id).collect::<HashSet<_>>();
+            self.wires.retain(|wire| {
+                !ids.contains(&wire.out_pin.node) && !ids.contains(&wire.in_pin.node)
+            });
+        }
+
+        removed
+    }
+
+    /// Retains only the nodes specified by the predicate.
+    /// Returns removed nodes with their ids.
+    ///
+    /// Removes all nodes for which `f` returns `false`,
+    /// and drops all their wires in a single pass.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::Snarl;
+    /// let mut snarl = Snarl::<i32>::new();
+    /// snarl.insert_node(egui::pos2(0.0, 0.0), 1);
+    /// snarl.insert_node(egui::pos2(0.0, 0.0), 2);
+    /// let removed = snarl.retain_nodes(|_, value| *value > 1);
+    /// assert_eq!(removed.len(), 1);
+    /// assert_eq!(snarl.node_count(), 1);
+    /// ```
+    pub fn retain_nodes(&mut self, mut f: impl FnMut(NodeId, &T) -> bool) -> Vec<(NodeId, T)> {
+        let remove = self
+            .nodes
+            .iter()
+            .filter(|&(idx, node)| !f(NodeId(idx), &node.value))
+            .map(|(idx, _)| NodeId(idx))
+            .collect::<Vec<_>>();
+
+        self.remove_nodes(remove)
+    }
+
+    /// Inserts nodes at given ids.
+    /// Nodes with ids that are already taken are skipped.
+    /// Returns ids of inserted nodes.
+    ///
+    /// Slab can't insert at given key,
+    /// so nodes are inserted directly while their id is next vacant key,
+    /// e.g. for ids freed last or appended after all other nodes.
+    /// Otherwise the slab is rebuilt once with remaining nodes.
+    fn insert_nodes_at(
+        &mut self,
+        nodes: impl IntoIterator<Item = (NodeId, Node<T>)>,
+    ) -> Vec<NodeId> {
+        let mut inserted = Vec::new();
+        let mut taken = HashSet::default();
+        let mut rest = Vec::new();
+        for (id, node) in nodes {
+            if self.nodes.contains(id.0) || !taken.insert(id) {
+                continue;
+            }
+            if rest.is_empty() && self.nodes.vacant_key() == id.0 {
+                self.nodes.insert(node);
+            } else {
+                rest.push((id.0, node));
+            }
+            inserted.push(id);
+        }
+
+        if !rest.is_empty() {
+            let mut entries = std::mem::take(&mut self.nodes)
+                .into_iter()
+                .collect::<Vec<_>>();
+            entries.append(&mut rest);
+            self.nodes = entries.into_iter().collect();
+        }
+
+        inserted
+    }
+
     /// Connects two nodes.
     /// Returns true if the connection was successful.
     /// Returns false if the connection already exists.
@@ -322,6 +832,205 @@ impl<T> Snarl<T> {
         self.wires.insert(wire)
     }
 
+    /// Connects many pairs of pins at once.
+    /// Returns whether each pair was connected, in order of `pairs`.
+    ///
+    /// Pairs with missing nodes and already existing connections are skipped
+    /// and reported as `false` instead of panicking like [`Snarl::connect`] does.
+    /// Use it to wire imported graphs in bulk.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::{InPinId, NodeId, OutPinId, Snarl};
+    /// let mut snarl = Snarl::<()>::new();
+    /// let a = snarl.insert_node(egui::pos2(0.0, 0.0), ());
+    /// let b = snarl.insert_node(egui::pos2(100.0, 0.0), ());
+    ///
+    /// let connected = snarl.connect_many(&[
+    ///     (OutPinId { node: a, output: 0 }, InPinId { node: b, input: 0 }),
+    ///     (OutPinId { node: a, output: 0 }, InPinId { node: b, input: 0 }),
+    ///     (OutPinId { node: a, output: 0 }, InPinId { node: NodeId(42), input: 0 }),
+    /// ]);
+    /// assert_eq!(connected, [true, false, false]);
+    /// ```
+    pub fn connect_many(&mut self, pairs: &[(OutPinId, InPinId)]) -> Vec<bool> {
+        self.wires.reserve(pairs.len());
+
+        pairs
+            .iter()
+            .map(|&(from, to)| {
+                self.nodes.contains(from.node.0)
+                    && self.nodes.contains(to.node.0)
+                    && self.wires.insert(Wire {
+                        out_pin: from,
+                        in_pin: to,
+                    })
+            })
+            .collect()
+    }
+
+    /// Connects two nodes attaching the wire at given positions along rail pins.
+    /// Returns true if the connection was successful.
+    /// Returns false if the connection already exists,
+    /// in which case its attach positions are updated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either node does not exist.
+    #[track_caller]
+    pub fn connect_at(&mut self, from: OutPinId, to: InPinId, attach: WireAttach) -> bool {
+        assert!(self.nodes.contains(from.node.0));
+        assert!(self.nodes.contains(to.node.0));
+
+        let wire = Wire {
+            out_pin: from,
+            in_pin: to,
+        };
+        self.wires.insert_attached(wire, attach)
+    }
+
+    /// Returns positions along rail pins where the wire is attached.
+    ///
+    /// Returns default [`WireAttach`] for wires attached to pin centers
+    /// and for pins that are not connected.
+    #[must_use]
+    pub fn wire_attach(&self, from: OutPinId, to: InPinId) -> WireAttach {
+        self.wires.attach(&Wire {
+            out_pin: from,
+            in_pin: to,
+        })
+    }
+
+    /// Connects two nodes and stores `data` with the wire.
+    /// Returns true if the connection was successful.
+    /// Returns false if the connection already exists,
+    /// in which case its data is replaced.
+    ///
+    /// Call it from [`SnarlViewer::connect`](crate::ui::SnarlViewer::connect)
+    /// to attach label, color or waypoints to wires created in the UI.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either node does not exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::{InPinId, OutPinId, Snarl, WireData};
+    /// let mut snarl = Snarl::<()>::new();
+    /// let a = snarl.insert_node(egui::pos2(0.0, 0.0), ());
+    /// let b = snarl.insert_node(egui::pos2(100.0, 0.0), ());
+    ///
+    /// let from = OutPinId { node: a, output: 0 };
+    /// let to = InPinId { node: b, input: 0 };
+    /// let data = WireData {
+    ///     label: Some("value".to_owned()),
+    ///     ..WireData::default()
+    /// };
+    /// assert!(snarl.connect_with_data(from, to, data.clone()));
+    /// assert_eq!(snarl.wire_data(from, to), Some(&data));
+    ///
+    /// snarl.disconnect(from, to);
+    /// assert_eq!(snarl.wire_data(from, to), None);
+    /// ```
+    #[track_caller]
+    pub fn connect_with_data(&mut self, from: OutPinId, to: InPinId, data: WireData) -> bool {
+        let connected = self.connect(from, to);
+        self.wires.set_data(
+            Wire {
+                out_pin: from,
+                in_pin: to,
+            },
+            data,
+        );
+        connected
+    }
+
+    /// Sets attributes of the wire between two pins.
+    /// Empty `data` clears them.
+    /// Returns false if there is no such wire.
+    pub fn set_wire_data(&mut self, from: OutPinId, to: InPinId, data: WireData) -> bool {
+        self.wires.set_data(
+            Wire {
+                out_pin: from,
+                in_pin: to,
+            },
+            data,
+        )
+    }
+
+    /// Returns attributes of the wire between two pins.
+    ///
+    /// Returns `None` if there is no such wire or it has no attributes.
+    #[must_use]
+    pub fn wire_data(&self, from: OutPinId, to: InPinId) -> Option<&WireData> {
+        self.wires.data(&Wire {
+            out_pin: from,
+            in_pin: to,
+        })
+    }
+
+    /// Connects two nodes unless the Snarl already has `max_wires` wires.
+    /// Returns `Some(true)` if the connection was successful,
+    /// `Some(false)` if the connection already exists
+    /// and `None` if the capacity is reached.
+    ///
+    /// Pass [`SnarlConfig::max_wires`](crate::ui::SnarlConfig::max_wires)
+    /// to respect the same cap as the UI does.
+    /// `None` means unbounded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either node does not exist.
+    #[track_caller]
+    pub fn try_connect(
+        &mut self,
+        from: OutPinId,
+        to: InPinId,
+        max_wires: Option<usize>,
+    ) -> Option<bool> {
+        let wire = Wire {
+            out_pin: from,
+            in_pin: to,
+        };
+
+        if max_wires.is_some_and(|max| self.wires.len() >= max) && !self.wires.contains(&wire) {
+            return None;
+        }
+        Some(self.connect(from, to))
+    }
+
+    /// Removes wires that can't be drawn and stale wire attributes.
+    /// Returns number of removed wires.
+    ///
+    /// Wires are stored as a set, so [`Snarl::connect`] never creates duplicates
+    /// and duplicate entries in deserialized data are collapsed while loading.
+    /// Deserialized data may still contain wires attached to nodes missing from it,
+    /// those are removed here, along with attach positions and [`WireData`]
+    /// of wires that no longer exist.
+    ///
+    /// Such wires are also reported by [`Snarl::validate`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::{InPinId, OutPinId, Snarl};
+    /// let mut snarl = Snarl::<()>::new();
+    /// let a = snarl.insert_node(egui::pos2(0.0, 0.0), ());
+    /// let b = snarl.insert_node(egui::pos2(0.0, 0.0), ());
+    /// let from = OutPinId { node: a, output: 0 };
+    /// let to = InPinId { node: b, input: 0 };
+    /// assert!(snarl.connect(from, to));
+    /// assert!(!snarl.connect(from, to));
+    /// assert_eq!(snarl.dedup_wires(), 0);
+    /// assert_eq!(snarl.wire_count(), 1);
+    /// ```
+    pub fn dedup_wires(&mut self) -> usize {
+        let nodes = &self.nodes;
+        self.wires.prune(|node| nodes.contains(node.0))
+    }
+
     /// Disconnects two nodes.
     /// Returns true if the connection was removed.
     ///
@@ -378,6 +1087,87 @@ impl<T> Snarl<T> {
         self.wires.drop_node(node)
     }
 
+    /// Returns number of nodes in the Snarl.
+    #[must_use]
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns number of wires in the Snarl.
+    #[must_use]
+    pub fn wire_count(&self) -> usize {
+        self.wires.len()
+    }
+
+    /// Returns hash of the graph contents for change detection.
+    ///
+    /// Hashes node ids, values, positions and open state,
+    /// and wires with their attach positions and data.
+    /// Wires are hashed sorted by pin ids, so the result does not depend on storage order.
+    /// Draw order and other UI state are not included.
+    ///
+    /// Hash is stable within one build of the program,
+    /// compare it with values computed by the same program, e.g. to show unsaved changes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::{InPinId, OutPinId, Snarl};
+    /// let mut snarl = Snarl::<u32>::new();
+    /// let a = snarl.insert_node(egui::pos2(0.0, 0.0), 1);
+    /// let b = snarl.insert_node(egui::pos2(100.0, 0.0), 2);
+    /// let saved = snarl.content_hash();
+    ///
+    /// snarl.connect(OutPinId { node: a, output: 0 }, InPinId { node: b, input: 0 });
+    /// assert_ne!(snarl.content_hash(), saved);
+    ///
+    /// snarl.disconnect(OutPinId { node: a, output: 0 }, InPinId { node: b, input: 0 });
+    /// assert_eq!(snarl.content_hash(), saved);
+    /// ```
+    #[must_use]
+    pub fn content_hash(&self) -> u64
+    where
+        T: Hash,
+    {
+        let mut hasher = std::hash::DefaultHasher::new();
+
+        self.nodes.len().hash(&mut hasher);
+        for (idx, node) in &self.nodes {
+            idx.hash(&mut hasher);
+            node.value.hash(&mut hasher);
+            hash_pos(node.pos, &mut hasher);
+            node.open.hash(&mut hasher);
+        }
+
+        let mut wires = self.wires.iter().collect::<Vec<_>>();
+        wires.sort_unstable();
+
+        wires.len().hash(&mut hasher);
+        for wire in &wires {
+            wire.hash(&mut hasher);
+
+            let attach = self.wires.attach(wire);
+            attach.out_pin.map(f32::to_bits).hash(&mut hasher);
+            attach.in_pin.map(f32::to_bits).hash(&mut hasher);
+
+            // Presence and length tags keep adjacent wires from running together.
+            match self.wires.data(wire) {
+                None => false.hash(&mut hasher),
+                Some(data) => {
+                    true.hash(&mut hasher);
+                    data.label.hash(&mut hasher);
+                    data.color.hash(&mut hasher);
+                    data.waypoints.len().hash(&mut hasher);
+                    for &point in &data.waypoints {
+                        hash_pos(point, &mut hasher);
+                    }
+                }
+            }
+        }
+
+        hasher.finish()
+    }
+
     /// Returns reference to the node.
     #[must_use]
     pub fn node(&self, idx: NodeId) -> Option<&T> {
@@ -403,6 +1193,12 @@ impl<T> Snarl<T> {
         self.nodes.get_mut(idx.0)
     }
 
+    /// Returns position of the node and reference to it.
+    #[must_use]
+    pub fn node_pos(&self, idx: NodeId) -> Option<(Pos2, &T)> {
+        self.nodes.get(idx.0).map(|node| (node.pos, &node.value))
+    }
+
     /// Deprecated: Use [`node`](Self::node) instead.
     #[deprecated(since = "0.8.0", note = "renamed to `node` per Rust API guidelines")]
     #[must_use]
@@ -487,6 +1283,14 @@ impl<T> Snarl<T> {
         }
     }
 
+    /// Iterates over identifier, position and shared reference to each node.
+    ///
+    /// Same as [`Snarl::nodes_pos_ids`].
+    /// Useful for inspecting the graph without a viewer or `Ui`.
+    pub fn iter_nodes(&self) -> NodesPosIdsIter<'_, T> {
+        self.nodes_pos_ids()
+    }
+
     /// Iterates over mutable references to each node, its position and its identifier.
     pub fn nodes_pos_ids_mut(&mut self) -> NodesPosIdsIterMut<'_, T> {
         NodesPosIdsIterMut {
@@ -527,6 +1331,67 @@ impl<T> Snarl<T> {
         self.wires.iter().map(|wire| (wire.out_pin, wire.in_pin))
     }
 
+    /// Iterates over nodes connected to the `node` with wires in either direction.
+    ///
+    /// Each neighbor is yielded once in order of [`NodeId`],
+    /// even if it is connected with multiple wires.
+    /// The node itself is yielded if it is wired to itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::{InPinId, OutPinId, Snarl};
+    /// let mut snarl = Snarl::<()>::new();
+    /// let a = snarl.insert_node(egui::pos2(0.0, 0.0), ());
+    /// let b = snarl.insert_node(egui::pos2(100.0, 0.0), ());
+    /// let c = snarl.insert_node(egui::pos2(200.0, 0.0), ());
+    /// snarl.connect(OutPinId { node: a, output: 0 }, InPinId { node: b, input: 0 });
+    /// snarl.connect(OutPinId { node: a, output: 1 }, InPinId { node: b, input: 1 });
+    /// snarl.connect(OutPinId { node: b, output: 0 }, InPinId { node: c, input: 0 });
+    ///
+    /// assert_eq!(snarl.neighbors(b).collect::<Vec<_>>(), [a, c]);
+    /// assert_eq!(snarl.predecessors(b).collect::<Vec<_>>(), [a]);
+    /// assert_eq!(snarl.successors(a).collect::<Vec<_>>(), [b]);
+    /// ```
+    pub fn neighbors(&self, node: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        let nodes = self.wires.iter().filter_map(|wire| {
+            if wire.out_pin.node == node {
+                Some(wire.in_pin.node)
+            } else if wire.in_pin.node == node {
+                Some(wire.out_pin.node)
+            } else {
+                None
+            }
+        });
+        unique_nodes(nodes)
+    }
+
+    /// Iterates over nodes with outputs wired to inputs of the `node`.
+    ///
+    /// Each node is yielded once in order of [`NodeId`].
+    /// See [`Snarl::neighbors`].
+    pub fn predecessors(&self, node: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        let nodes = self
+            .wires
+            .iter()
+            .filter(|wire| wire.in_pin.node == node)
+            .map(|wire| wire.out_pin.node);
+        unique_nodes(nodes)
+    }
+
+    /// Iterates over nodes with inputs wired to outputs of the `node`.
+    ///
+    /// Each node is yielded once in order of [`NodeId`].
+    /// See [`Snarl::neighbors`].
+    pub fn successors(&self, node: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        let nodes = self
+            .wires
+            .iter()
+            .filter(|wire| wire.out_pin.node == node)
+            .map(|wire| wire.in_pin.node);
+        unique_nodes(nodes)
+    }
+
     /// Returns input pin of the node.
     #[must_use]
     pub fn in_pin(&self, pin: InPinId) -> InPin {
@@ -538,6 +1403,280 @@ impl<T> Snarl<T> {
     pub fn out_pin(&self, pin: OutPinId) -> OutPin {
         OutPin::new(self, pin)
     }
+
+    /// Returns order in which nodes were drawn last time the graph was shown,
+    /// from bottom to top.
+    ///
+    /// It is serialized with the graph and restores z-order of nodes when loaded.
+    #[must_use]
+    pub fn draw_order(&self) -> &[NodeId] {
+        &self.ui.draw_order
+    }
+
+    /// Updates stored draw order of nodes.
+    pub(crate) fn set_draw_order(&mut self, draw_order: &[NodeId]) {
+        if self.ui.draw_order != draw_order {
+            self.ui.draw_order = draw_order.to_vec();
+        }
+        self.ui.version = SNARL_UI_STATE_VERSION;
+    }
+
+    /// Converts node values into another type.
+    ///
+    /// Node ids, positions, open state and wires are preserved,
+    /// so the resulting Snarl has identical structure.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::Snarl;
+    /// let mut snarl = Snarl::<i32>::new();
+    /// let node = snarl.insert_node(egui::pos2(0.0, 0.0), 42);
+    /// let snarl = snarl.map(|_, value| value.to_string());
+    /// assert_eq!(snarl[node], "42");
+    /// ```
+    pub fn map<U>(self, mut f: impl FnMut(NodeId, T) -> U) -> Snarl<U> {
+        let nodes = self
+            .nodes
+            .into_iter()
+            .map(|(idx, node)| {
+                let node = Node {
+                    value: f(NodeId(idx), node.value),
+                    pos: node.pos,
+                    open: node.open,
+                };
+                (idx, node)
+            })
+            .collect();
+
+        Snarl {
+            nodes,
+            wires: self.wires,
+            ui: self.ui,
+        }
+    }
+
+    /// Deserializes graph fragment and merges it into this graph.
+    ///
+    /// Each decoded node is inserted at the id `remap` returns for its id in the fragment,
+    /// and wires between decoded nodes are reconnected to the remapped ids.
+    /// Positions, open state, wire attach positions and wire data are kept,
+    /// so the fragment keeps its layout and internal wiring.
+    ///
+    /// Returns ids of inserted nodes in order of their ids in the fragment.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if data can't be deserialized
+    /// or `remap` maps a node to an id that is already taken.
+    /// The graph is left untouched in this case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::{NodeId, Snarl};
+    /// let mut fragment = Snarl::<u32>::new();
+    /// fragment.insert_node(egui::pos2(0.0, 0.0), 1);
+    /// let data = serde_json::to_string(&fragment).unwrap();
+    ///
+    /// let mut snarl = Snarl::<u32>::new();
+    /// snarl.insert_node(egui::pos2(0.0, 0.0), 0);
+    ///
+    /// let mut de = serde_json::Deserializer::from_str(&data);
+    /// let inserted = snarl
+    ///     .merge_deserialized(&mut de, |node| NodeId(node.0 + 10))
+    ///     .unwrap();
+    /// assert_eq!(inserted, [NodeId(10)]);
+    /// assert_eq!(snarl[NodeId(10)], 1);
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn merge_deserialized<'de, D>(
+        &mut self,
+        deserializer: D,
+        remap: impl FnMut(NodeId) -> NodeId,
+    ) -> Result<Vec<NodeId>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+        T: serde::Deserialize<'de>,
+    {
+        use serde::{Deserialize, de::Error};
+
+        let fragment = Snarl::<T>::deserialize(deserializer)?;
+        self.merge_fragment(fragment, remap, |_, _| true)
+            .map_err(D::Error::custom)
+    }
+
+    /// Deserializes graph fragment and merges it into this graph
+    /// after the viewer inspects it.
+    ///
+    /// Same as [`Snarl::merge_deserialized`], but decoded nodes and wires with remapped ids
+    /// are passed to [`SnarlViewer::on_paste`](crate::ui::SnarlViewer::on_paste) first.
+    /// Changes the viewer makes to them are applied,
+    /// and nothing is inserted if it returns false.
+    ///
+    /// Returns ids of inserted nodes in order the viewer left them.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if data can't be deserialized,
+    /// `remap` maps a node to an id that is already taken
+    /// or the viewer leaves a node with id that is not in the remapped fragment.
+    /// The graph is left untouched in this case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::{InPin, InPinId, NodeId, OutPin, OutPinId, Snarl};
+    /// # use egui_snarl::ui::{PinContext, PinInfo, SnarlViewer};
+    /// struct Viewer;
+    ///
+    /// impl SnarlViewer<u32> for Viewer {
+    ///     fn title(&mut self, _: &u32) -> String { String::new() }
+    ///     fn inputs(&mut self, _: &u32) -> usize { 1 }
+    ///     fn outputs(&mut self, _: &u32) -> usize { 1 }
+    ///     fn show_input(&mut self, _: &InPin, _: &mut egui::Ui, _: PinContext, _: &mut Snarl<u32>) -> PinInfo {
+    ///         PinInfo::circle()
+    ///     }
+    ///     fn show_output(&mut self, _: &OutPin, _: &mut egui::Ui, _: PinContext, _: &mut Snarl<u32>) -> PinInfo {
+    ///         PinInfo::circle()
+    ///     }
+    ///     fn on_paste(&mut self, nodes: &mut Vec<(NodeId, u32)>, _: &mut Vec<(OutPinId, InPinId)>) -> bool {
+    ///         // Refuse fragments with zeros and double the rest.
+    ///         if nodes.iter().any(|&(_, value)| value == 0) {
+    ///             return false;
+    ///         }
+    ///         nodes.iter_mut().for_each(|(_, value)| *value *= 2);
+    ///         true
+    ///     }
+    /// }
+    ///
+    /// let mut fragment = Snarl::<u32>::new();
+    /// fragment.insert_node(egui::pos2(0.0, 0.0), 1);
+    /// let data = serde_json::to_string(&fragment).unwrap();
+    ///
+    /// let mut snarl = Snarl::<u32>::new();
+    /// let mut de = serde_json::Deserializer::from_str(&data);
+    /// let inserted = snarl
+    ///     .merge_deserialized_with(&mut de, |node| NodeId(node.0 + 10), &mut Viewer)
+    ///     .unwrap();
+    /// assert_eq!(snarl[inserted[0]], 2);
+    ///
+    /// fragment.insert_node(egui::pos2(100.0, 0.0), 0);
+    /// let data = serde_json::to_string(&fragment).unwrap();
+    /// let mut de = serde_json::Deserializer::from_str(&data);
+    /// let inserted = snarl
+    ///     .merge_deserialized_with(&mut de, |node| NodeId(node.0 + 20), &mut Viewer)
+    ///     .unwrap();
+    /// assert!(inserted.is_empty());
+    /// assert_eq!(snarl.node_count(), 1);
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn merge_deserialized_with<'de, D, V>(
+        &mut self,
+        deserializer: D,
+        remap: impl FnMut(NodeId) -> NodeId,
+        viewer: &mut V,
+    ) -> Result<Vec<NodeId>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+        T: serde::Deserialize<'de>,
+        V: ui::SnarlViewer<T>,
+    {
+        use serde::{Deserialize, de::Error};
+
+        let fragment = Snarl::<T>::deserialize(deserializer)?;
+        self.merge_fragment(fragment, remap, |nodes, wires| {
+            viewer.on_paste(nodes, wires)
+        })
+        .map_err(D::Error::custom)
+    }
+
+    /// Merges `fragment` into this graph at ids returned by `remap`.
+    ///
+    /// `on_paste` may change nodes and wires before they are inserted
+    /// or return false to cancel the merge.
+    #[cfg(feature = "serde")]
+    fn merge_fragment(
+        &mut self,
+        fragment: Snarl<T>,
+        mut remap: impl FnMut(NodeId) -> NodeId,
+        on_paste: impl FnOnce(&mut Vec<(NodeId, T)>, &mut Vec<(OutPinId, InPinId)>) -> bool,
+    ) -> Result<Vec<NodeId>, DiffConflict> {
+        let mut ids = HashMap::default();
+        let mut taken = HashSet::default();
+        for (idx, _) in &fragment.nodes {
+            let id = remap(NodeId(idx));
+            if self.nodes.contains(id.0) || !taken.insert(id) {
+                return Err(DiffConflict::NodeExists(id));
+            }
+            ids.insert(NodeId(idx), id);
+        }
+
+        // Attach positions and data of wires are restored for wires kept by `on_paste`.
+        let mut wire_extras = HashMap::default();
+        let mut wires = Vec::with_capacity(fragment.wires.len());
+        for wire in fragment.wires.iter() {
+            let (Some(&from), Some(&to)) =
+                (ids.get(&wire.out_pin.node), ids.get(&wire.in_pin.node))
+            else {
+                continue;
+            };
+            let remapped = Wire {
+                out_pin: OutPinId {
+                    node: from,
+                    output: wire.out_pin.output,
+                },
+                in_pin: InPinId {
+                    node: to,
+                    input: wire.in_pin.input,
+                },
+            };
+            let data = fragment.wires.data(&wire).cloned().unwrap_or_default();
+            wire_extras.insert(remapped, (fragment.wires.attach(&wire), data));
+            wires.push((remapped.out_pin, remapped.in_pin));
+        }
+
+        let mut layout = HashMap::default();
+        let mut nodes = Vec::with_capacity(ids.len());
+        for (idx, node) in fragment.nodes {
+            let id = ids[&NodeId(idx)];
+            layout.insert(id, (node.pos, node.open));
+            nodes.push((id, node.value));
+        }
+
+        if !on_paste(&mut nodes, &mut wires) {
+            return Ok(Vec::new());
+        }
+
+        // Each node must keep an id from the fragment,
+        // otherwise it has no layout and could collide with existing nodes.
+        let mut entries = Vec::with_capacity(nodes.len());
+        for (id, value) in nodes {
+            let Some((pos, open)) = layout.remove(&id) else {
+                return Err(DiffConflict::MissingNode(id));
+            };
+            entries.push((id, Node { value, pos, open }));
+        }
+        let inserted = self.insert_nodes_at(entries);
+
+        for (out_pin, in_pin) in wires {
+            if !self.nodes.contains(out_pin.node.0) || !self.nodes.contains(in_pin.node.0) {
+                continue;
+            }
+            let wire = Wire { out_pin, in_pin };
+            match wire_extras.remove(&wire) {
+                Some((attach, data)) => {
+                    self.wires.insert_attached(wire, attach);
+                    self.wires.set_data(wire, data);
+                }
+                None => {
+                    self.wires.insert(wire);
+                }
+            }
+        }
+
+        Ok(inserted)
+    }
 }
 
 impl<T> Index<NodeId> for Snarl<T> {
@@ -854,6 +1993,11 @@ pub struct OutPin {
 
     /// List of input pins connected to this output pin.
     pub remotes: Vec<InPinId>,
+
+    /// Position along the rail pin where the wire is attached.
+    /// Set when the pin is passed to [`SnarlViewer::connect`](crate::ui::SnarlViewer::connect)
+    /// after a wire is dropped on a rail pin, `None` otherwise.
+    pub rail_pos: Option<f32>,
 }
 
 /// Node and its output pin.
@@ -864,6 +2008,11 @@ pub struct InPin {
 
     /// List of output pins connected to this input pin.
     pub remotes: Vec<OutPinId>,
+
+    /// Position along the rail pin where the wire is attached.
+    /// Set when the pin is passed to [`SnarlViewer::connect`](crate::ui::SnarlViewer::connect)
+    /// after a wire is dropped on a rail pin, `None` otherwise.
+    pub rail_pos: Option<f32>,
 }
 
 impl OutPin {
@@ -871,8 +2020,18 @@ impl OutPin {
         OutPin {
             id: pin,
             remotes: snarl.wires.wired_inputs(pin).collect(),
+            rail_pos: None,
         }
     }
+
+    /// Returns `true` if the pin is connected to at least one input pin.
+    ///
+    /// Useful to show inline editor for default value of unconnected pin.
+    #[must_use]
+    #[inline]
+    pub fn is_connected(&self) -> bool {
+        !self.remotes.is_empty()
+    }
 }
 
 impl InPin {
@@ -880,6 +2039,469 @@ impl InPin {
         InPin {
             id: pin,
             remotes: snarl.wires.wired_outputs(pin).collect(),
+            rail_pos: None,
         }
     }
+
+    /// Returns `true` if the pin is connected to at least one output pin.
+    ///
+    /// Useful to show inline editor for default value of unconnected pin.
+    #[must_use]
+    #[inline]
+    pub fn is_connected(&self) -> bool {
+        !self.remotes.is_empty()
+    }
+}
+
+#[test]
+fn dedup_removes_dangling_wires() {
+    let mut snarl = Snarl::<()>::new();
+    let a = snarl.insert_node(Pos2::ZERO, ());
+    let b = snarl.insert_node(Pos2::ZERO, ());
+    let kept = Wire {
+        out_pin: OutPinId { node: a, output: 0 },
+        in_pin: InPinId { node: b, input: 0 },
+    };
+    let dangling = Wire {
+        out_pin: OutPinId { node: b, output: 0 },
+        in_pin: InPinId {
+            node: NodeId(7),
+            input: 0,
+        },
+    };
+    snarl.connect(kept.out_pin, kept.in_pin);
+
+    // Simulate data loaded without the node at the other end.
+    snarl.wires.insert(dangling);
+    snarl.wires.data.insert(
+        dangling,
+        WireData {
+            label: Some("stale".to_owned()),
+            ..WireData::default()
+        },
+    );
+
+    assert_eq!(snarl.dedup_wires(), 1);
+    assert_eq!(
+        snarl.wires().collect::<Vec<_>>(),
+        [(kept.out_pin, kept.in_pin)]
+    );
+    assert!(snarl.wires.data.is_empty());
+    assert_eq!(snarl.dedup_wires(), 0);
+}
+
+#[test]
+fn nodes_are_inserted_at_given_ids() {
+    let mut snarl = Snarl::<u32>::new();
+    let a = snarl.insert_node(Pos2::ZERO, 0);
+    let b = snarl.insert_node(Pos2::ZERO, 1);
+    snarl.insert_node(Pos2::ZERO, 2);
+    snarl.remove_node(a);
+    snarl.remove_node(b);
+
+    let node = |value| Node {
+        value,
+        pos: Pos2::ZERO,
+        open: true,
+    };
+    let inserted = snarl.insert_nodes_at([
+        (b, node(10)),
+        (NodeId(9), node(11)),
+        (a, node(12)),
+        (b, node(13)),
+        (NodeId(2), node(14)),
+    ]);
+
+    assert_eq!(inserted, [b, NodeId(9), a]);
+    assert_eq!(snarl[a], 12);
+    assert_eq!(snarl[b], 10);
+    assert_eq!(snarl[NodeId(2)], 2);
+    assert_eq!(snarl[NodeId(9)], 11);
+    assert_eq!(snarl.node_count(), 4);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn merge_remaps_fragment_ids() {
+    let mut fragment = Snarl::<u32>::new();
+    let a = fragment.insert_node(egui::pos2(1.0, 2.0), 1);
+    let b = fragment.insert_node_collapsed(Pos2::ZERO, 2);
+    fragment.connect_with_data(
+        OutPinId { node: a, output: 0 },
+        InPinId { node: b, input: 1 },
+        WireData {
+            label: Some("label".to_owned()),
+            ..WireData::default()
+        },
+    );
+    let data = serde_json::to_string(&fragment).unwrap();
+
+    let mut snarl = Snarl::<u32>::new();
+    let existing = snarl.insert_node(Pos2::ZERO, 0);
+    let inserted = snarl
+        .merge_deserialized(&mut serde_json::Deserializer::from_str(&data), |node| {
+            NodeId(node.0 + 1)
+        })
+        .unwrap();
+
+    assert_eq!(inserted, [NodeId(1), NodeId(2)]);
+    assert_eq!(snarl[existing], 0);
+    assert_eq!(
+        snarl.node_info(NodeId(1)).unwrap().pos,
+        egui::pos2(1.0, 2.0)
+    );
+    assert!(!snarl.node_info(NodeId(2)).unwrap().open);
+    let from = OutPinId {
+        node: NodeId(1),
+        output: 0,
+    };
+    let to = InPinId {
+        node: NodeId(2),
+        input: 1,
+    };
+    assert_eq!(snarl.wires().collect::<Vec<_>>(), [(from, to)]);
+    assert_eq!(
+        snarl.wire_data(from, to).unwrap().label.as_deref(),
+        Some("label")
+    );
+
+    // Taken ids are rejected without touching the graph.
+    let result =
+        snarl.merge_deserialized(&mut serde_json::Deserializer::from_str(&data), |node| node);
+    assert_eq!(
+        result.map_err(|err| err.to_string()),
+        Err("node 0 already exists".to_owned())
+    );
+    assert_eq!(snarl.node_count(), 3);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn merge_rejects_ids_changed_by_viewer() {
+    let mut fragment = Snarl::<u32>::new();
+    fragment.insert_node(Pos2::ZERO, 1);
+
+    let mut snarl = Snarl::<u32>::new();
+    let result = snarl.merge_fragment(
+        fragment,
+        |node| node,
+        |nodes, _| {
+            nodes[0].0 = NodeId(5);
+            true
+        },
+    );
+
+    assert_eq!(result, Err(DiffConflict::MissingNode(NodeId(5))));
+    assert_eq!(snarl.node_count(), 0);
+}
+
+#[test]
+fn capacity_limits_reject_only_new_items() {
+    let mut snarl = Snarl::<u32>::new();
+    let a = snarl.try_insert_node(Pos2::ZERO, 1, Some(2)).unwrap();
+    let b = snarl.try_insert_node(Pos2::ZERO, 2, Some(2)).unwrap();
+    assert_eq!(snarl.try_insert_node(Pos2::ZERO, 3, Some(2)), Err(3));
+    assert!(snarl.try_insert_node(Pos2::ZERO, 3, None).is_ok());
+
+    let from = OutPinId { node: a, output: 0 };
+    let to = InPinId { node: b, input: 0 };
+    assert_eq!(snarl.try_connect(from, to, Some(1)), Some(true));
+
+    // Existing wire doesn't count against the limit.
+    assert_eq!(snarl.try_connect(from, to, Some(1)), Some(false));
+    assert_eq!(
+        snarl.try_connect(from, InPinId { node: b, input: 1 }, Some(1)),
+        None
+    );
+    assert_eq!(snarl.wire_count(), 1);
+}
+
+#[test]
+fn map_keeps_ids_and_wires() {
+    let mut snarl = Snarl::<u32>::new();
+    let removed = snarl.insert_node(Pos2::ZERO, 0);
+    let a = snarl.insert_node(egui::pos2(1.0, 2.0), 1);
+    let b = snarl.insert_node_collapsed(Pos2::ZERO, 2);
+    snarl.remove_node(removed);
+
+    let from = OutPinId { node: a, output: 1 };
+    let to = InPinId { node: b, input: 0 };
+    snarl.connect_at(
+        from,
+        to,
+        WireAttach {
+            out_pin: None,
+            in_pin: Some(0.25),
+        },
+    );
+    snarl.set_wire_data(
+        from,
+        to,
+        WireData {
+            label: Some("label".to_owned()),
+            ..WireData::default()
+        },
+    );
+
+    let mapped = snarl.map(|id, value| (id, value * 10));
+
+    assert_eq!(mapped.node_count(), 2);
+    assert!(mapped.node(removed).is_none());
+    assert_eq!(mapped[a], (a, 10));
+    assert_eq!(mapped[b], (b, 20));
+    assert_eq!(mapped.node_info(a).unwrap().pos, egui::pos2(1.0, 2.0));
+    assert!(!mapped.node_info(b).unwrap().open);
+    assert_eq!(mapped.wires().collect::<Vec<_>>(), [(from, to)]);
+    assert_eq!(mapped.wire_attach(from, to).in_pin, Some(0.25));
+    assert_eq!(
+        mapped.wire_data(from, to).unwrap().label.as_deref(),
+        Some("label")
+    );
+
+    // Freed ids are reused after mapping like before.
+    let mut mapped = mapped;
+    assert_eq!(mapped.insert_node(Pos2::ZERO, (removed, 0)), removed);
+}
+
+#[test]
+fn retain_nodes_drops_wires_of_removed_nodes() {
+    let mut snarl = Snarl::<u32>::new();
+    let a = snarl.insert_node(Pos2::ZERO, 1);
+    let b = snarl.insert_node(Pos2::ZERO, 2);
+    let c = snarl.insert_node(Pos2::ZERO, 3);
+    snarl.connect(
+        OutPinId { node: a, output: 0 },
+        InPinId { node: b, input: 0 },
+    );
+    snarl.connect(
+        OutPinId { node: b, output: 0 },
+        InPinId { node: c, input: 0 },
+    );
+    snarl.connect(
+        OutPinId { node: a, output: 1 },
+        InPinId { node: c, input: 1 },
+    );
+
+    let removed = snarl.retain_nodes(|_, value| *value != 2);
+
+    assert_eq!(removed, [(b, 2)]);
+    assert_eq!(
+        snarl.node_ids().map(|(id, _)| id).collect::<Vec<_>>(),
+        [a, c]
+    );
+    assert_eq!(
+        snarl.wires().collect::<Vec<_>>(),
+        [(
+            OutPinId { node: a, output: 1 },
+            InPinId { node: c, input: 1 }
+        )]
+    );
+    assert!(snarl.retain_nodes(|_, _| true).is_empty());
+}
+
+#[test]
+fn replaced_node_drops_wires_of_removed_pins() {
+    let mut snarl = Snarl::<u32>::new();
+    let a = snarl.insert_node(Pos2::ZERO, 1);
+    let b = snarl.insert_node(Pos2::ZERO, 2);
+    let kept_in = (
+        OutPinId { node: a, output: 0 },
+        InPinId { node: b, input: 1 },
+    );
+    let dropped_in = (
+        OutPinId { node: a, output: 1 },
+        InPinId { node: b, input: 2 },
+    );
+    let kept_out = (
+        OutPinId { node: b, output: 0 },
+        InPinId { node: a, input: 3 },
+    );
+    let dropped_out = (
+        OutPinId { node: b, output: 1 },
+        InPinId { node: a, input: 0 },
+    );
+    for (from, to) in [kept_in, dropped_in, kept_out, dropped_out] {
+        snarl.connect(from, to);
+    }
+
+    assert_eq!(snarl.replace_node_pruned(b, 20, 2, 1), Some(2));
+
+    assert_eq!(snarl[b], 20);
+    let mut wires = snarl.wires().collect::<Vec<_>>();
+    wires.sort_unstable();
+    assert_eq!(wires, [kept_in, kept_out]);
+    assert_eq!(snarl.replace_node_pruned(NodeId(9), 0, 0, 0), None);
+}
+
+#[test]
+fn neighbors_include_self_loops_once() {
+    let mut snarl = Snarl::<()>::new();
+    let a = snarl.insert_node(Pos2::ZERO, ());
+    let b = snarl.insert_node(Pos2::ZERO, ());
+    let c = snarl.insert_node(Pos2::ZERO, ());
+    snarl.connect(
+        OutPinId { node: c, output: 0 },
+        InPinId { node: b, input: 0 },
+    );
+    snarl.connect(
+        OutPinId { node: a, output: 0 },
+        InPinId { node: b, input: 1 },
+    );
+    snarl.connect(
+        OutPinId { node: b, output: 0 },
+        InPinId { node: b, input: 2 },
+    );
+    snarl.connect(
+        OutPinId { node: b, output: 1 },
+        InPinId { node: b, input: 3 },
+    );
+
+    assert_eq!(snarl.neighbors(b).collect::<Vec<_>>(), [a, b, c]);
+    assert_eq!(snarl.predecessors(b).collect::<Vec<_>>(), [a, b, c]);
+    assert_eq!(snarl.successors(b).collect::<Vec<_>>(), [b]);
+    assert_eq!(snarl.successors(a).collect::<Vec<_>>(), [b]);
+    assert!(snarl.predecessors(a).next().is_none());
+
+    snarl.remove_node(c);
+    assert_eq!(snarl.neighbors(b).collect::<Vec<_>>(), [a, b]);
+}
+
+#[test]
+fn content_hash_tracks_contents_not_order() {
+    let build = |reversed: bool| {
+        let mut snarl = Snarl::<u32>::new();
+        let a = snarl.insert_node(Pos2::ZERO, 1);
+        let b = snarl.insert_node(egui::pos2(100.0, 0.0), 2);
+        let mut wires = [
+            (
+                OutPinId { node: a, output: 0 },
+                InPinId { node: b, input: 0 },
+            ),
+            (
+                OutPinId { node: a, output: 1 },
+                InPinId { node: b, input: 1 },
+            ),
+            (
+                OutPinId { node: b, output: 0 },
+                InPinId { node: a, input: 0 },
+            ),
+        ];
+        if reversed {
+            wires.reverse();
+        }
+        for (from, to) in wires {
+            snarl.connect(from, to);
+        }
+        let draw_order = if reversed { [b, a] } else { [a, b] };
+        snarl.set_draw_order(&draw_order);
+        (snarl, a, b)
+    };
+
+    let (mut snarl, a, b) = build(false);
+    let hash = snarl.content_hash();
+    assert_eq!(build(true).0.content_hash(), hash);
+
+    let from = OutPinId { node: a, output: 0 };
+    let to = InPinId { node: b, input: 0 };
+    let assert_changed = |label: &str, change: &dyn Fn(&mut Snarl<u32>)| {
+        let mut other = build(false).0;
+        change(&mut other);
+        assert_ne!(other.content_hash(), hash, "{label}");
+    };
+    assert_changed("value", &|snarl| snarl[a] = 10);
+    assert_changed("position", &|snarl| {
+        snarl.node_info_mut(b).unwrap().pos.x += 1.0;
+    });
+    assert_changed("open state", &|snarl| snarl.open_node(a, false));
+    assert_changed("attach", &|snarl| {
+        snarl.connect_at(
+            from,
+            to,
+            WireAttach {
+                out_pin: Some(0.5),
+                in_pin: None,
+            },
+        );
+    });
+    assert_changed("wire data", &|snarl| {
+        snarl.set_wire_data(
+            from,
+            to,
+            WireData {
+                label: Some("label".to_owned()),
+                ..WireData::default()
+            },
+        );
+    });
+
+    // Same waypoints routed through another wire.
+    let waypoints = vec![Pos2::ZERO, egui::pos2(50.0, 50.0)];
+    let route = |wire: usize| {
+        let mut other = build(false).0;
+        let (from, to) = other.wires().nth(wire).unwrap();
+        other.set_wire_data(
+            from,
+            to,
+            WireData {
+                waypoints: waypoints.clone(),
+                ..WireData::default()
+            },
+        );
+        other.content_hash()
+    };
+    assert_ne!(route(0), hash);
+    assert_ne!(route(0), route(1));
+
+    snarl.disconnect(from, to);
+    snarl.connect(from, to);
+    assert_eq!(snarl.content_hash(), hash);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn wires_serialize_in_pin_order() {
+    let build = |reversed: bool| {
+        let mut snarl = Snarl::<()>::new();
+        let a = snarl.insert_node(Pos2::ZERO, ());
+        let b = snarl.insert_node(Pos2::ZERO, ());
+        let mut wires = (0..16)
+            .map(|idx| {
+                (
+                    OutPinId {
+                        node: if idx % 2 == 0 { a } else { b },
+                        output: idx % 3,
+                    },
+                    InPinId {
+                        node: if idx % 2 == 0 { b } else { a },
+                        input: idx,
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+        if reversed {
+            wires.reverse();
+        }
+        for (from, to) in wires {
+            snarl.connect(from, to);
+        }
+        serde_json::to_value(&snarl).unwrap()
+    };
+
+    let value = build(false);
+    assert_eq!(build(true), value);
+
+    let wires = value["wires"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|wire| {
+            serde_json::from_value::<(OutPinId, InPinId)>(serde_json::json!([
+                wire["out_pin"],
+                wire["in_pin"]
+            ]))
+            .unwrap()
+        })
+        .collect::<Vec<_>>();
+    assert!(wires.is_sorted());
+    assert_eq!(wires.len(), 16);
 }
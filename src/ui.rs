@@ -1,12 +1,15 @@
 //! This module provides functionality for showing [`Snarl`] graph in [`Ui`].
 
-use std::{collections::HashMap, hash::Hash};
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
 
 use egui::{
-    Align, Align2, Color32, CornerRadius, Frame, Id, Key, LayerId, Layout, Margin, Modifiers,
-    PointerButton, Pos2, Rect, Scene, Sense, Shape, Stroke, StrokeKind, Style, Ui, UiBuilder,
-    UiKind, UiStackInfo, Vec2,
-    collapsing_header::paint_default_icon,
+    Align, Align2, Color32, CornerRadius, CursorIcon, Frame, Id, Key, KeyboardShortcut, LayerId,
+    Layout, Margin, Modifiers, PointerButton, Pos2, Rect, RichText, Scene, Sense, Shape, Stroke,
+    StrokeKind, Style, TextEdit, Ui, UiBuilder, UiKind, UiStackInfo, Vec2,
+    collapsing_header::{CollapsingState, paint_default_icon},
     emath::{GuiRounding, RectAlign, TSTransform},
     epaint::Shadow,
     pos2,
@@ -16,29 +19,55 @@ use egui::{
 use egui_scale::EguiScale;
 use smallvec::SmallVec;
 
-use crate::{InPin, InPinId, Node, NodeId, OutPin, OutPinId, Snarl, ui::wire::WireId};
+use crate::{
+    AnyPinId, InPin, InPinId, Node, NodeId, OutPin, OutPinId, Snarl, Wire, ui::wire::WireId,
+};
 
 mod background_pattern;
 mod config;
+mod cut;
+mod export;
+mod hit;
+mod hover_expand;
+mod node_menu;
 mod pin;
+mod pull;
 mod scale;
+mod select;
+mod snapshot;
 mod state;
+mod svg;
+mod validate;
 mod viewer;
+mod viewport;
 mod wire;
 
 use self::{
-    pin::AnyPin,
-    state::{NewWires, NodeState, RowHeights, SnarlState},
-    wire::{draw_wire, hit_wire, pick_wire_style},
+    hit::{HitGeometry, HitWire, Hovered},
+    state::{NewWires, NodeState, RowHeights, SnarlState, ViewportRequest},
+    viewport::pan_to_contain,
+    wire::{draw_wire, gradient_wire, hit_wire, pick_wire_style},
 };
 
 pub use self::{
-    background_pattern::{BackgroundPattern, Grid},
-    config::{ModifierClick, SnapGrid, SnapGridType, SnarlConfig},
-    pin::{AnyPins, PinContext, PinInfo, PinShape, PinWireInfo, SnarlPin},
-    state::selected_nodes,
+    background_pattern::{BackgroundPattern, BackgroundStyle, Grid},
+    config::{
+        CapacityLimit, DragHandle, EditSource, FitMode, FitOptions, ModifierClick, ModifierMatch,
+        NodeAnchor, NodeHitShape, ScrollMode, SnapAnchor, SnapGrid, SnapGridType, SnarlConfig,
+        WireDropAction,
+    },
+    hit::{HitTarget, WhichEnd},
+    node_menu::SnarlNodeMenu,
+    pin::{
+        AnyPins, PinConnectionIndicator, PinContext, PinInfo, PinLayout, PinShape, PinWireInfo,
+        SnarlPin,
+    },
+    snapshot::SnapshotOptions,
+    state::{InProgressWire, Interaction, selected_nodes},
+    svg::SvgOptions,
+    validate::{Diagnostic, DiagnosticTarget, Severity},
     viewer::SnarlViewer,
-    wire::{WireLayer, WireStyle},
+    wire::{WireLayer, WirePath, WireStyle},
 };
 
 /// Controls how header, pins, body and footer are placed in the node.
@@ -471,6 +500,28 @@ pub struct SelectionStyle {
     pub stroke: Stroke,
 }
 
+/// Controls how selected nodes are highlighted.
+///
+/// Colors and margin are taken from [`SelectionStyle`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "egui-probe", derive(egui_probe::EguiProbe))]
+#[cfg_attr(feature = "facet", derive(facet::Facet), repr(u8))]
+pub enum SelectionVisual {
+    /// Filled rect with outline (default, current behavior).
+    #[default]
+    Highlight,
+
+    /// Outline only.
+    Outline,
+
+    /// Fill only, painted over the node.
+    Fill,
+
+    /// Short strokes at the corners of the selection rect.
+    CornerHandles,
+}
+
 /// Controls when pin labels are visible.
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -514,6 +565,38 @@ pub enum PinPlacement {
     },
 }
 
+/// Controls where wires attach to their pins.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "egui-probe", derive(egui_probe::EguiProbe))]
+#[cfg_attr(feature = "facet", derive(facet::Facet), repr(u8))]
+pub enum WireEndpoint {
+    /// Wires attach at the center of the pin.
+    #[default]
+    PinCenter,
+
+    /// Wires attach at the node frame edge closest to the pin,
+    /// aligned with the pin.
+    NodeEdge,
+}
+
+/// Controls on which sides of the node pins are placed.
+/// See [`SnarlViewer::pin_sides`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "egui-probe", derive(egui_probe::EguiProbe))]
+#[cfg_attr(feature = "facet", derive(facet::Facet), repr(u8))]
+pub enum PinSides {
+    /// Inputs are on the left side and outputs are on the right side,
+    /// unless [`SnarlViewer::node_layout`] changes that.
+    #[default]
+    Sides,
+
+    /// Inputs are on the top edge and outputs are on the bottom edge.
+    /// Wires leave and enter these pins vertically.
+    TopBottom,
+}
+
 /// Style for rendering Snarl.
 #[derive(Clone, Copy, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -783,6 +866,289 @@ pub struct SnarlStyle {
     )]
     pub pin_label_visibility: Option<PinLabelVisibility>,
 
+    /// Color used to highlight the pin under dragged wire
+    /// if connection would be accepted.
+    /// Defaults to `ui.visuals().selection.stroke.color`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub valid_connection_color: Option<Color32>,
+
+    /// Color used to highlight the pin under dragged wire
+    /// if connection would be rejected by [`SnarlViewer::validate_connection`].
+    /// Defaults to `ui.visuals().error_fg_color`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub invalid_connection_color: Option<Color32>,
+
+    /// Fan out parallel wires running between the same pair of nodes.
+    /// If set to true, endpoints of such wires are offset perpendicular
+    /// to the average wire direction, so they form a readable bundle.
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub bundle_parallel_wires: Option<bool>,
+
+    /// Duration in seconds of animated viewport transitions
+    /// requested with [`SnarlWidget::fit_to_view`], [`SnarlWidget::ensure_node_visible`]
+    /// and [`SnarlWidget::focus_node`].
+    /// User pan and zoom interrupts the animation.
+    /// Defaults to `None`, which makes the view jump immediately.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub viewport_animation: Option<f32>,
+
+    /// Controls curvature of bezier wires.
+    /// Multiplies the offset of bezier control points from the pins.
+    /// Values below 1.0 make wires straighter, values above make them loopier.
+    /// Clamped to `0.0..=4.0`.
+    /// Defaults to 1.0.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    #[cfg_attr(feature = "egui-probe", egui_probe(range = 0.0..=4.0))]
+    pub bezier_tension: Option<f32>,
+
+    /// Show translucent ghost of the dragged node at the snapped position
+    /// when [`SnarlConfig::grid_snap`] is set.
+    /// If set to true, dragged node follows the cursor smoothly
+    /// and snaps to the grid when released.
+    /// Defaults to `false`, which snaps the node while it is dragged.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub show_snap_ghost: Option<bool>,
+
+    /// Vertical spacing between pin rows of a node.
+    ///
+    /// Default is `ui.spacing().item_spacing.y`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub pin_row_spacing: Option<f32>,
+
+    /// Padding between the node frame and its header and body contents.
+    /// Overrides inner margin of the node and header frames.
+    ///
+    /// Default is inner margin of the frames.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub node_padding: Option<Margin>,
+
+    /// Friction of the kinetic panning.
+    ///
+    /// If set, the view keeps gliding after fast pan is released
+    /// and decelerates with this rate per second.
+    /// Larger values stop the view faster, `5.0` is a good start.
+    ///
+    /// Default is `None` which stops panning immediately.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub pan_inertia: Option<f32>,
+
+    /// Fill of the rect selection band.
+    ///
+    /// Default is [`SnarlStyle::select_fill`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub select_rect_fill: Option<Color32>,
+
+    /// Stroke of the rect selection band.
+    ///
+    /// Default is [`SnarlStyle::select_stoke`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub select_rect_stroke: Option<Stroke>,
+
+    /// Length of dashes and gaps of the rect selection band stroke.
+    ///
+    /// Default is `None` which draws solid stroke.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub select_rect_dash: Option<f32>,
+
+    /// Decorative background fill drawn beneath the background pattern.
+    /// Defaults to [`BackgroundStyle::Plain`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub bg_style: Option<BackgroundStyle>,
+
+    /// Merge reciprocal wires between two nodes into one double-headed wire.
+    /// If set to true, for each wire from node A to node B paired with a wire from B to A
+    /// only the first one is drawn, with arrowheads on both ends.
+    /// Hit-testing resolves to the drawn wire.
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub merge_bidirectional: Option<bool>,
+
+    /// Dim pins incompatible with the wire being dragged.
+    /// If set to true, while new wire is dragged, pins of visible nodes for which
+    /// [`SnarlViewer::pin_compatible`] returns `false` for every dragged wire are drawn dimmed.
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub highlight_compatible_pins: Option<bool>,
+
+    /// Length of the wire above which its middle part fades out.
+    /// If set, wires longer than this many points keep full opacity
+    /// within half of the length from each end and fade towards the middle beyond that,
+    /// making long wires spanning the graph less distracting.
+    /// Defaults to `None`, wires are drawn with uniform color.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub long_wire_fade: Option<f32>,
+
+    /// Shadow drawn beneath the frame of the node under the pointer.
+    /// Painted right before the node, so it overlaps frames of the nodes beneath.
+    /// Defaults to `None`, node is drawn with shadow of its frame.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub hover_shadow: Option<Shadow>,
+
+    /// Shadow drawn beneath the frame and selection highlight of selected nodes.
+    /// Takes precedence over [`SnarlStyle::hover_shadow`].
+    /// Defaults to `None`, node is drawn with shadow of its frame.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub selection_shadow: Option<Shadow>,
+
+    /// Delay in seconds before collapsed node under the pointer is shown expanded.
+    /// If set, hovering collapsed node for this long shows it fully expanded above other nodes
+    /// until the pointer leaves it, without changing its collapsed state.
+    /// Defaults to `None`, collapsed nodes stay collapsed on hover.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub hover_expand_delay: Option<f32>,
+
+    /// Style of wires being dragged or pending a click to connect.
+    /// Makes wires that are not yet connected stand out.
+    /// Defaults to `None`, new wires use style of the pin they start from.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub preview_wire_style: Option<WireStyle>,
+
+    /// Radius of the pin's interactive area.
+    /// May exceed half of [`SnarlStyle::pin_size`] to make small pins easier to grab,
+    /// while pins are still drawn at their visual size.
+    /// Defaults to half of [`SnarlStyle::pin_size`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    #[cfg_attr(feature = "egui-probe", egui_probe(range = 0.0..))]
+    pub pin_hit_radius: Option<f32>,
+
+    /// Whether to show a popup to pick the pin when wires are dropped
+    /// onto several equally close pins.
+    ///
+    /// Otherwise the pin nearest to the pointer is picked
+    /// and ties go to the pin of the topmost node.
+    ///
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub disambiguate_pin_drop: Option<bool>,
+
+    /// Controls where wires attach to pins.
+    ///
+    /// Defaults to [`WireEndpoint::PinCenter`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub wire_attach: Option<WireEndpoint>,
+
+    /// Zoom level below which nodes are drawn as frames with titles,
+    /// without pins, body and footer.
+    ///
+    /// Defaults to `None` - nodes are always drawn fully.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub lod_title_zoom: Option<f32>,
+
+    /// Zoom level below which nodes are drawn as plain frames.
+    ///
+    /// Defaults to `None` - node titles are always drawn.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub lod_plain_zoom: Option<f32>,
+
+    /// Whether to show buttons that zoom the view in and out
+    /// at the top-right corner of the graph.
+    ///
+    /// Buttons step the zoom by [`SnarlConfig::zoom_step`].
+    ///
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub show_zoom_buttons: Option<bool>,
+
+    /// How selected nodes are highlighted.
+    /// See [`SnarlStyle::select_style`] for colors and margin.
+    ///
+    /// Defaults to [`SelectionVisual::Highlight`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub selection_visual: Option<SelectionVisual>,
+
+    /// Blend wire color from the output pin color to the input pin color.
+    /// If set to true, wires between pins with different wire colors,
+    /// e.g. provided by [`SnarlViewer::pin_color`], are drawn with gradient along their length.
+    /// Wires with color set in [`WireData`] and selected wires stay solid.
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub gradient_wires: Option<bool>,
+
     #[doc(hidden)]
     #[cfg_attr(feature = "egui-probe", egui_probe(skip))]
     #[cfg_attr(feature = "serde", serde(skip_serializing, default))]
@@ -874,12 +1240,21 @@ impl SnarlStyle {
     }
 
     fn node_frame(&self, style: &Style) -> Frame {
-        self.node_frame.unwrap_or_else(|| Frame::window(style))
+        let frame = self.node_frame.unwrap_or_else(|| Frame::window(style));
+        match self.node_padding {
+            None => frame,
+            Some(padding) => frame.inner_margin(padding),
+        }
     }
 
     fn header_frame(&self, style: &Style) -> Frame {
-        self.header_frame
-            .unwrap_or_else(|| self.node_frame(style).shadow(Shadow::NONE))
+        let frame = self
+            .header_frame
+            .unwrap_or_else(|| self.node_frame(style).shadow(Shadow::NONE));
+        match self.node_padding {
+            None => frame,
+            Some(padding) => frame.inner_margin(padding),
+        }
     }
 
     fn centering(&self) -> bool {
@@ -928,6 +1303,119 @@ impl SnarlStyle {
     fn pin_label_visibility(&self) -> PinLabelVisibility {
         self.pin_label_visibility.unwrap_or_default()
     }
+
+    fn valid_connection_color(&self, style: &Style) -> Color32 {
+        self.valid_connection_color
+            .unwrap_or(style.visuals.selection.stroke.color)
+    }
+
+    fn invalid_connection_color(&self, style: &Style) -> Color32 {
+        self.invalid_connection_color
+            .unwrap_or(style.visuals.error_fg_color)
+    }
+
+    fn bundle_parallel_wires(&self) -> bool {
+        self.bundle_parallel_wires.unwrap_or(false)
+    }
+
+    fn viewport_animation(&self) -> Option<f32> {
+        self.viewport_animation.filter(|duration| *duration > 0.0)
+    }
+
+    pub(crate) fn bezier_tension(&self) -> f32 {
+        self.bezier_tension.unwrap_or(1.0).clamp(0.0, 4.0)
+    }
+
+    fn show_snap_ghost(&self) -> bool {
+        self.show_snap_ghost.unwrap_or(false)
+    }
+
+    fn pin_row_spacing(&self, style: &Style) -> f32 {
+        self.pin_row_spacing.unwrap_or(style.spacing.item_spacing.y)
+    }
+
+    fn pan_inertia(&self) -> Option<f32> {
+        self.pan_inertia
+    }
+
+    fn select_rect_fill(&self, style: &Style) -> Color32 {
+        self.select_rect_fill
+            .unwrap_or_else(|| self.select_fill(style))
+    }
+
+    fn select_rect_stroke(&self, style: &Style) -> Stroke {
+        self.select_rect_stroke
+            .unwrap_or_else(|| self.select_stroke(style))
+    }
+
+    fn select_rect_dash(&self) -> Option<f32> {
+        self.select_rect_dash
+    }
+
+    fn bg_style(&self) -> BackgroundStyle {
+        self.bg_style.unwrap_or_default()
+    }
+
+    fn merge_bidirectional(&self) -> bool {
+        self.merge_bidirectional.unwrap_or(false)
+    }
+
+    fn highlight_compatible_pins(&self) -> bool {
+        self.highlight_compatible_pins.unwrap_or(false)
+    }
+
+    fn long_wire_fade(&self) -> Option<f32> {
+        self.long_wire_fade
+    }
+
+    fn hover_shadow(&self) -> Option<Shadow> {
+        self.hover_shadow
+    }
+
+    fn selection_shadow(&self) -> Option<Shadow> {
+        self.selection_shadow
+    }
+
+    fn hover_expand_delay(&self) -> Option<f32> {
+        self.hover_expand_delay
+    }
+
+    fn preview_wire_style(&self) -> Option<WireStyle> {
+        self.preview_wire_style
+    }
+
+    fn pin_hit_radius(&self, style: &Style) -> f32 {
+        self.pin_hit_radius
+            .unwrap_or_else(|| self.pin_size(style) * 0.5)
+    }
+
+    fn disambiguate_pin_drop(&self) -> bool {
+        self.disambiguate_pin_drop.unwrap_or(false)
+    }
+
+    fn wire_attach(&self) -> WireEndpoint {
+        self.wire_attach.unwrap_or_default()
+    }
+
+    fn lod_title_zoom(&self) -> Option<f32> {
+        self.lod_title_zoom
+    }
+
+    fn lod_plain_zoom(&self) -> Option<f32> {
+        self.lod_plain_zoom
+    }
+
+    fn show_zoom_buttons(&self) -> bool {
+        self.show_zoom_buttons.unwrap_or(false)
+    }
+
+    fn selection_visual(&self) -> SelectionVisual {
+        self.selection_visual.unwrap_or_default()
+    }
+
+    fn gradient_wires(&self) -> bool {
+        self.gradient_wires.unwrap_or(false)
+    }
 }
 
 #[cfg(feature = "serde")]
@@ -981,6 +1469,19 @@ mod serde_frame_option {
 
 impl SnarlStyle {
     /// Creates new [`SnarlStyle`] filled with default values.
+    ///
+    /// Use `with_*` methods to override them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::ui::{SnarlStyle, WireStyle};
+    /// const STYLE: SnarlStyle = SnarlStyle::new()
+    ///     .with_wire_style(WireStyle::Bezier3)
+    ///     .with_wire_width(2.0)
+    ///     .with_collapsible(false);
+    /// assert_eq!(STYLE.wire_width, Some(2.0));
+    /// ```
     #[must_use]
     pub const fn new() -> Self {
         SnarlStyle {
@@ -1018,1592 +1519,4843 @@ impl SnarlStyle {
             wire_widget_align: None,
             pin_hover_scale: None,
             pin_label_visibility: None,
-
+            valid_connection_color: None,
+            invalid_connection_color: None,
+
+            bundle_parallel_wires: None,
+            viewport_animation: None,
+            bezier_tension: None,
+            show_snap_ghost: None,
+            pin_row_spacing: None,
+            node_padding: None,
+            pan_inertia: None,
+            select_rect_fill: None,
+            select_rect_stroke: None,
+            select_rect_dash: None,
+            bg_style: None,
+            merge_bidirectional: None,
+            highlight_compatible_pins: None,
+            long_wire_fade: None,
+            hover_shadow: None,
+            selection_shadow: None,
+            hover_expand_delay: None,
+            preview_wire_style: None,
+            pin_hit_radius: None,
+            disambiguate_pin_drop: None,
+            wire_attach: None,
+            lod_title_zoom: None,
+            lod_plain_zoom: None,
+            show_zoom_buttons: None,
+            selection_visual: None,
+            gradient_wires: None,
             _non_exhaustive: (),
         }
     }
-}
 
-impl Default for SnarlStyle {
-    #[inline]
-    fn default() -> Self {
-        Self::new()
+    /// Set how nodes are laid out.
+    #[must_use]
+    pub const fn with_node_layout(mut self, node_layout: NodeLayout) -> Self {
+        self.node_layout = Some(node_layout);
+        self
     }
-}
 
-#[allow(dead_code)]
-struct Input {
-    hover_pos: Option<Pos2>,
-    interact_pos: Option<Pos2>,
-    zoom_delta: f32,
-    // primary_pressed: bool,
-    secondary_pressed: bool,
-    modifiers: Modifiers,
-    escape_pressed: bool,
-}
-
-struct DrawNodeResponse {
-    node_moved: Option<(NodeId, Vec2)>,
-    node_to_top: Option<NodeId>,
-    drag_released: bool,
-    pin_hovered: Option<AnyPin>,
-    final_rect: Rect,
-    in_pins: Vec<InPin>,
-    out_pins: Vec<OutPin>,
-}
+    /// Set the frame used to draw nodes.
+    #[must_use]
+    pub const fn with_node_frame(mut self, node_frame: Frame) -> Self {
+        self.node_frame = Some(node_frame);
+        self
+    }
 
-struct DrawPinsResponse {
-    drag_released: bool,
-    pin_hovered: Option<AnyPin>,
-    final_rect: Rect,
-    new_heights: RowHeights,
-}
+    /// Set the frame used to draw node headers.
+    #[must_use]
+    pub const fn with_header_frame(mut self, header_frame: Frame) -> Self {
+        self.header_frame = Some(header_frame);
+        self
+    }
 
-struct DrawPinsHorizontalResponse {
-    drag_released: bool,
-    pin_hovered: Option<AnyPin>,
-    final_rect: Rect,
-    new_widths: RowHeights, // Reusing RowHeights type for widths
-}
+    /// Set the blank space for dragging node by its header.
+    #[must_use]
+    pub const fn with_header_drag_space(mut self, header_drag_space: Vec2) -> Self {
+        self.header_drag_space = Some(header_drag_space);
+        self
+    }
 
-struct DrawBodyResponse {
-    final_rect: Rect,
-}
+    /// Set whether nodes can be collapsed.
+    #[must_use]
+    pub const fn with_collapsible(mut self, collapsible: bool) -> Self {
+        self.collapsible = Some(collapsible);
+        self
+    }
 
-struct PinResponse {
-    pos: Pos2,
-    wire_color: Color32,
-    wire_style: WireStyle,
-}
+    /// Set the size of pins.
+    #[must_use]
+    pub const fn with_pin_size(mut self, pin_size: f32) -> Self {
+        self.pin_size = Some(pin_size);
+        self
+    }
 
-/// Widget to display [`Snarl`] graph in [`Ui`].
-#[derive(Clone, Copy, Debug)]
-pub struct SnarlWidget {
-    id_salt: Id,
-    id: Option<Id>,
-    style: SnarlStyle,
-    config: SnarlConfig,
-    min_size: Vec2,
-    max_size: Vec2,
-}
+    /// Set the default fill color for pins.
+    #[must_use]
+    pub const fn with_pin_fill(mut self, pin_fill: Color32) -> Self {
+        self.pin_fill = Some(pin_fill);
+        self
+    }
 
-impl Default for SnarlWidget {
-    #[inline]
-    fn default() -> Self {
-        Self::new()
+    /// Set the default stroke for pins.
+    #[must_use]
+    pub const fn with_pin_stroke(mut self, pin_stroke: Stroke) -> Self {
+        self.pin_stroke = Some(pin_stroke);
+        self
     }
-}
 
-impl SnarlWidget {
-    /// Returns new [`SnarlWidget`] with default parameters.
-    #[inline]
+    /// Set the shape of pins.
     #[must_use]
-    pub fn new() -> Self {
-        SnarlWidget {
-            id_salt: Id::new(":snarl:"),
-            id: None,
-            style: SnarlStyle::new(),
-            config: SnarlConfig::new(),
-            min_size: Vec2::ZERO,
-            max_size: Vec2::INFINITY,
-        }
+    pub const fn with_pin_shape(mut self, pin_shape: PinShape) -> Self {
+        self.pin_shape = Some(pin_shape);
+        self
     }
 
-    /// Assign an explicit and globally unique [`Id`].
-    ///
-    /// Use this if you want to persist the state of the widget
-    /// when it changes position in the widget hierarchy.
-    ///
-    /// Prefer using [`SnarlWidget::id_salt`] otherwise.
-    #[inline]
+    /// Set the placement of pins.
     #[must_use]
-    pub fn id(mut self, id: Id) -> Self {
-        self.id = Some(id);
+    pub const fn with_pin_placement(mut self, pin_placement: PinPlacement) -> Self {
+        self.pin_placement = Some(pin_placement);
         self
     }
 
-    /// Assign a source for the unique [`Id`]
-    ///
-    /// It must be locally unique for the current [`Ui`] hierarchy position.
-    ///
-    /// Ignored if [`SnarlWidget::id`] was set.
-    #[inline]
+    /// Set the width of wires.
     #[must_use]
-    pub fn id_salt(mut self, id_salt: impl Hash) -> Self {
-        self.id_salt = Id::new(id_salt);
+    pub const fn with_wire_width(mut self, wire_width: f32) -> Self {
+        self.wire_width = Some(wire_width);
         self
     }
 
-    /// Set style parameters for the [`Snarl`] widget.
-    #[inline]
+    /// Set the size of wire frame which controls curvature of wires.
     #[must_use]
-    pub fn style(mut self, style: SnarlStyle) -> Self {
-        self.style = style;
+    pub const fn with_wire_frame_size(mut self, wire_frame_size: f32) -> Self {
+        self.wire_frame_size = Some(wire_frame_size);
         self
     }
 
-    /// Set config parameters for the [`Snarl`] widget.
-    #[inline]
+    /// Set whether to downscale wire frame when nodes are close.
     #[must_use]
-    pub fn config(mut self, config: SnarlConfig) -> Self {
-        self.config = config;
+    pub const fn with_downscale_wire_frame(mut self, downscale_wire_frame: bool) -> Self {
+        self.downscale_wire_frame = Some(downscale_wire_frame);
         self
     }
 
-    /// Set minimum size of the [`Snarl`] widget.
-    #[inline]
+    /// Set whether to upscale wire frame when nodes are far.
     #[must_use]
-    pub fn min_size(mut self, min_size: Vec2) -> Self {
-        self.min_size = min_size;
+    pub const fn with_upscale_wire_frame(mut self, upscale_wire_frame: bool) -> Self {
+        self.upscale_wire_frame = Some(upscale_wire_frame);
         self
     }
 
-    /// Set maximum size of the [`Snarl`] widget.
-    #[inline]
+    /// Set the default style of wires.
     #[must_use]
-    pub fn max_size(mut self, max_size: Vec2) -> Self {
-        self.max_size = max_size;
+    pub const fn with_wire_style(mut self, wire_style: WireStyle) -> Self {
+        self.wire_style = Some(wire_style);
         self
     }
 
-    #[inline]
-    fn get_id(&self, ui_id: Id) -> Id {
-        self.id.unwrap_or_else(|| ui_id.with(self.id_salt))
+    /// Set the layer where wires are rendered.
+    #[must_use]
+    pub const fn with_wire_layer(mut self, wire_layer: WireLayer) -> Self {
+        self.wire_layer = Some(wire_layer);
+        self
     }
 
-    /// Render [`Snarl`] using given viewer and style into the [`Ui`].
-    #[inline]
-    pub fn show<T, V>(&self, snarl: &mut Snarl<T>, viewer: &mut V, ui: &mut Ui) -> egui::Response
-    where
-        V: SnarlViewer<T>,
-    {
-        let snarl_id = self.get_id(ui.id());
+    /// Set the frame used to draw background.
+    #[must_use]
+    pub const fn with_bg_frame(mut self, bg_frame: Frame) -> Self {
+        self.bg_frame = Some(bg_frame);
+        self
+    }
 
-        show_snarl(
-            snarl_id,
-            self.style,
-            self.config,
-            self.min_size,
-            self.max_size,
-            snarl,
-            viewer,
-            ui,
-        )
+    /// Set the background pattern.
+    #[must_use]
+    pub const fn with_bg_pattern(mut self, bg_pattern: BackgroundPattern) -> Self {
+        self.bg_pattern = Some(bg_pattern);
+        self
     }
-}
 
-#[inline(never)]
-#[allow(clippy::too_many_arguments)]
-fn show_snarl<T, V>(
-    snarl_id: Id,
-    mut style: SnarlStyle,
-    mut config: SnarlConfig,
-    min_size: Vec2,
-    max_size: Vec2,
-    snarl: &mut Snarl<T>,
-    viewer: &mut V,
-    ui: &mut Ui,
-) -> egui::Response
-where
-    V: SnarlViewer<T>,
-{
-    #![allow(clippy::too_many_lines)]
+    /// Set the stroke for background pattern.
+    #[must_use]
+    pub const fn with_bg_pattern_stroke(mut self, bg_pattern_stroke: Stroke) -> Self {
+        self.bg_pattern_stroke = Some(bg_pattern_stroke);
+        self
+    }
 
-    let (mut latest_pos, input) = ui.ctx().input(|i| {
-        (
-            i.pointer.latest_pos(),
-            Input {
-                zoom_delta: i.zoom_delta(),
-                hover_pos: i.pointer.hover_pos(),
-                interact_pos: i.pointer.interact_pos(),
-                modifiers: i.modifiers,
-                // primary_pressed: i.pointer.primary_pressed(),
-                secondary_pressed: i.pointer.secondary_pressed(),
-                escape_pressed: i.key_pressed(Key::Escape),
-            },
-        )
-    });
+    /// Set the minimum viewport scale.
+    #[must_use]
+    pub const fn with_min_scale(mut self, min_scale: f32) -> Self {
+        self.min_scale = Some(min_scale);
+        self
+    }
 
-    let bg_frame = style.bg_frame(ui.style());
+    /// Set the maximum viewport scale.
+    #[must_use]
+    pub const fn with_max_scale(mut self, max_scale: f32) -> Self {
+        self.max_scale = Some(max_scale);
+        self
+    }
 
-    let outer_size_bounds = ui.available_size_before_wrap().max(min_size).min(max_size);
+    /// Set whether double click on background centers the view.
+    #[must_use]
+    pub const fn with_centering(mut self, centering: bool) -> Self {
+        self.centering = Some(centering);
+        self
+    }
 
-    let outer_resp = ui.allocate_response(outer_size_bounds, Sense::hover());
+    /// Set the stroke for selection.
+    #[must_use]
+    pub const fn with_select_stoke(mut self, select_stoke: Stroke) -> Self {
+        self.select_stoke = Some(select_stoke);
+        self
+    }
 
-    ui.painter().add(bg_frame.paint(outer_resp.rect));
+    /// Set the fill for selection.
+    #[must_use]
+    pub const fn with_select_fill(mut self, select_fill: Color32) -> Self {
+        self.select_fill = Some(select_fill);
+        self
+    }
 
-    let mut content_rect = outer_resp.rect - bg_frame.total_margin();
+    /// Set whether rect selection selects only fully contained nodes.
+    #[must_use]
+    pub const fn with_select_rect_contained(mut self, select_rect_contained: bool) -> Self {
+        self.select_rect_contained = Some(select_rect_contained);
+        self
+    }
 
-    // Make sure we don't shrink to the negative:
-    content_rect.max.x = content_rect.max.x.max(content_rect.min.x);
-    content_rect.max.y = content_rect.max.y.max(content_rect.min.y);
+    /// Set the style for node selection.
+    #[must_use]
+    pub const fn with_select_style(mut self, select_style: SelectionStyle) -> Self {
+        self.select_style = Some(select_style);
+        self
+    }
 
-    let snarl_layer_id = LayerId::new(ui.layer_id().order, snarl_id);
+    /// Set whether to show magnified text in crisp mode.
+    #[must_use]
+    pub const fn with_crisp_magnified_text(mut self, crisp_magnified_text: bool) -> Self {
+        self.crisp_magnified_text = Some(crisp_magnified_text);
+        self
+    }
 
-    ui.ctx().set_sublayer(ui.layer_id(), snarl_layer_id);
+    /// Set the smoothness of wire curves.
+    #[must_use]
+    pub const fn with_wire_smoothness(mut self, wire_smoothness: f32) -> Self {
+        self.wire_smoothness = Some(wire_smoothness);
+        self
+    }
 
-    let mut min_scale = style.min_scale();
-    let mut max_scale = style.max_scale();
+    /// Set the gap between wire widget and wire.
+    #[must_use]
+    pub const fn with_wire_widget_gap(mut self, wire_widget_gap: f32) -> Self {
+        self.wire_widget_gap = Some(wire_widget_gap);
+        self
+    }
 
-    let ui_rect = content_rect;
+    /// Set the alignment of wire widget with respect to the center point of wire.
+    #[must_use]
+    pub const fn with_wire_widget_align(mut self, wire_widget_align: Align2) -> Self {
+        self.wire_widget_align = Some(wire_widget_align);
+        self
+    }
 
-    let mut snarl_state =
-        SnarlState::load(ui.ctx(), snarl_id, snarl, ui_rect, min_scale, max_scale);
+    /// Set the scale factor for pin hover effect.
+    #[must_use]
+    pub const fn with_pin_hover_scale(mut self, pin_hover_scale: f32) -> Self {
+        self.pin_hover_scale = Some(pin_hover_scale);
+        self
+    }
 
-    // Allow viewer to update selection before rendering
-    if let Some(selection) = viewer.update_selection(snarl_state.selected_nodes()) {
-        snarl_state.select_many_nodes(true, selection.iter().copied());
+    /// Set when pin labels are visible.
+    #[must_use]
+    pub const fn with_pin_label_visibility(
+        mut self,
+        pin_label_visibility: PinLabelVisibility,
+    ) -> Self {
+        self.pin_label_visibility = Some(pin_label_visibility);
+        self
     }
 
-    // Apply automatic layout if requested
-    if viewer.apply_layout(snarl) {
-        let layout = viewer.compute_layout(snarl);
-        for (node_id, new_pos) in layout {
-            if let Some(node) = snarl.nodes.get_mut(node_id.0) {
-                node.pos = new_pos;
-            }
-        }
+    /// Set the color highlighting pin under dragged wire that can be connected.
+    #[must_use]
+    pub const fn with_valid_connection_color(mut self, valid_connection_color: Color32) -> Self {
+        self.valid_connection_color = Some(valid_connection_color);
+        self
     }
 
-    let mut to_global = snarl_state.to_global();
+    /// Set the color highlighting pin under dragged wire that can't be connected.
+    #[must_use]
+    pub const fn with_invalid_connection_color(
+        mut self,
+        invalid_connection_color: Color32,
+    ) -> Self {
+        self.invalid_connection_color = Some(invalid_connection_color);
+        self
+    }
 
-    let clip_rect = ui.clip_rect();
+    /// Set whether to fan out parallel wires between the same pair of nodes.
+    #[must_use]
+    pub const fn with_bundle_parallel_wires(mut self, bundle_parallel_wires: bool) -> Self {
+        self.bundle_parallel_wires = Some(bundle_parallel_wires);
+        self
+    }
 
-    let mut ui = ui.new_child(
-        UiBuilder::new()
-            .ui_stack_info(UiStackInfo::new(UiKind::Frame).with_frame(bg_frame))
-            .layer_id(snarl_layer_id)
-            .max_rect(Rect::EVERYTHING)
-            .sense(Sense::click_and_drag()),
-    );
+    /// Set the duration in seconds of animated viewport transitions.
+    #[must_use]
+    pub const fn with_viewport_animation(mut self, viewport_animation: f32) -> Self {
+        self.viewport_animation = Some(viewport_animation);
+        self
+    }
 
-    if style.crisp_magnified_text() {
-        style.scale(max_scale);
-        ui.style_mut().scale(max_scale);
+    /// Set the curvature of bezier wires.
+    #[must_use]
+    pub const fn with_bezier_tension(mut self, bezier_tension: f32) -> Self {
+        self.bezier_tension = Some(bezier_tension);
+        self
+    }
 
-        min_scale /= max_scale;
-        max_scale = 1.0;
+    /// Set whether to show ghost of the dragged node at the snapped position.
+    #[must_use]
+    pub const fn with_show_snap_ghost(mut self, show_snap_ghost: bool) -> Self {
+        self.show_snap_ghost = Some(show_snap_ghost);
+        self
     }
 
-    clamp_scale(&mut to_global, min_scale, max_scale, ui_rect);
+    /// Set the vertical spacing between pin rows of a node.
+    #[must_use]
+    pub const fn with_pin_row_spacing(mut self, pin_row_spacing: f32) -> Self {
+        self.pin_row_spacing = Some(pin_row_spacing);
+        self
+    }
 
-    let mut snarl_resp = ui.response();
-    Scene::new()
-        .zoom_range(min_scale..=max_scale)
-        .register_pan_and_zoom(&ui, &mut snarl_resp, &mut to_global);
+    /// Set the padding between the node frame and its contents.
+    #[must_use]
+    pub const fn with_node_padding(mut self, node_padding: Margin) -> Self {
+        self.node_padding = Some(node_padding);
+        self
+    }
 
-    if snarl_resp.changed() {
-        ui.ctx().request_repaint();
+    /// Set the friction of the kinetic panning.
+    #[must_use]
+    pub const fn with_pan_inertia(mut self, pan_inertia: f32) -> Self {
+        self.pan_inertia = Some(pan_inertia);
+        self
     }
 
-    // Inform viewer about current transform.
-    viewer.current_transform(&mut to_global, snarl);
+    /// Set the fill of the rect selection band.
+    #[must_use]
+    pub const fn with_select_rect_fill(mut self, select_rect_fill: Color32) -> Self {
+        self.select_rect_fill = Some(select_rect_fill);
+        self
+    }
 
-    snarl_state.set_to_global(to_global);
+    /// Set the stroke of the rect selection band.
+    #[must_use]
+    pub const fn with_select_rect_stroke(mut self, select_rect_stroke: Stroke) -> Self {
+        self.select_rect_stroke = Some(select_rect_stroke);
+        self
+    }
 
-    let to_global = to_global;
-    let from_global = to_global.inverse();
+    /// Set the length of dashes and gaps of the rect selection band stroke.
+    #[must_use]
+    pub const fn with_select_rect_dash(mut self, select_rect_dash: f32) -> Self {
+        self.select_rect_dash = Some(select_rect_dash);
+        self
+    }
 
-    // Graph viewport
-    let viewport = (from_global * ui_rect).round_ui();
-    let viewport_clip = from_global * clip_rect;
+    /// Set the decorative background fill drawn beneath the background pattern.
+    #[must_use]
+    pub const fn with_bg_style(mut self, bg_style: BackgroundStyle) -> Self {
+        self.bg_style = Some(bg_style);
+        self
+    }
 
-    ui.set_clip_rect(viewport.intersect(viewport_clip));
-    ui.expand_to_include_rect(viewport);
+    /// Set whether to merge reciprocal wires into one double-headed wire.
+    #[must_use]
+    pub const fn with_merge_bidirectional(mut self, merge_bidirectional: bool) -> Self {
+        self.merge_bidirectional = Some(merge_bidirectional);
+        self
+    }
 
-    // Set transform for snarl layer.
-    ui.ctx().set_transform_layer(snarl_layer_id, to_global);
+    /// Set whether to dim pins incompatible with the wire being dragged.
+    #[must_use]
+    pub const fn with_highlight_compatible_pins(mut self, highlight_compatible_pins: bool) -> Self {
+        self.highlight_compatible_pins = Some(highlight_compatible_pins);
+        self
+    }
 
-    // Map latest pointer position to graph space.
-    latest_pos = latest_pos.map(|pos| from_global * pos);
+    /// Set the length of the wire above which its middle part fades out.
+    #[must_use]
+    pub const fn with_long_wire_fade(mut self, long_wire_fade: f32) -> Self {
+        self.long_wire_fade = Some(long_wire_fade);
+        self
+    }
 
-    viewer.draw_background(
-        style.bg_pattern.as_ref(),
-        &viewport,
-        &style,
-        ui.style(),
-        ui.painter(),
-        snarl,
-    );
+    /// Set the shadow drawn beneath the node under the pointer.
+    #[must_use]
+    pub const fn with_hover_shadow(mut self, hover_shadow: Shadow) -> Self {
+        self.hover_shadow = Some(hover_shadow);
+        self
+    }
 
-    // Draw snap grid if visible
-    if let Some(ref grid) = config.grid_snap {
-        grid.draw(&viewport, ui.painter());
+    /// Set the shadow drawn beneath selected nodes.
+    #[must_use]
+    pub const fn with_selection_shadow(mut self, selection_shadow: Shadow) -> Self {
+        self.selection_shadow = Some(selection_shadow);
+        self
     }
 
-    let mut node_moved = None;
-    let mut node_to_top = None;
+    /// Set the delay before collapsed node under the pointer is shown expanded.
+    #[must_use]
+    pub const fn with_hover_expand_delay(mut self, hover_expand_delay: f32) -> Self {
+        self.hover_expand_delay = Some(hover_expand_delay);
+        self
+    }
 
-    // Process selection rect.
-    let mut rect_selection_ended = None;
-    if input.modifiers == config.rect_select.modifiers || snarl_state.is_rect_selection() {
-        let select_resp = ui.interact(snarl_resp.rect, snarl_id.with("select"), Sense::drag());
+    /// Set the style of wires being dragged or pending a click to connect.
+    #[must_use]
+    pub const fn with_preview_wire_style(mut self, preview_wire_style: WireStyle) -> Self {
+        self.preview_wire_style = Some(preview_wire_style);
+        self
+    }
 
-        if select_resp.dragged_by(config.rect_select.mouse_button)
-            && let Some(pos) = select_resp.interact_pointer_pos()
-        {
-            if snarl_state.is_rect_selection() {
-                snarl_state.update_rect_selection(pos);
-            } else {
-                snarl_state.start_rect_selection(pos);
-            }
-        }
+    /// Set radius of the pin's interactive area.
+    #[must_use]
+    pub const fn with_pin_hit_radius(mut self, pin_hit_radius: f32) -> Self {
+        self.pin_hit_radius = Some(pin_hit_radius);
+        self
+    }
 
-        if select_resp.drag_stopped_by(config.rect_select.mouse_button) {
-            if let Some(select_rect) = snarl_state.rect_selection() {
-                rect_selection_ended = Some(select_rect);
-            }
-            snarl_state.stop_rect_selection();
-        }
+    /// Set whether to pick the pin in a popup when wires are dropped
+    /// onto several equally close pins.
+    #[must_use]
+    pub const fn with_disambiguate_pin_drop(mut self, disambiguate_pin_drop: bool) -> Self {
+        self.disambiguate_pin_drop = Some(disambiguate_pin_drop);
+        self
     }
 
-    let wire_frame_size = style.wire_frame_size(ui.style());
-    let wire_width = style.wire_width(ui.style());
-    let wire_threshold = style.wire_smoothness();
+    /// Set where wires attach to pins.
+    #[must_use]
+    pub const fn with_wire_attach(mut self, wire_attach: WireEndpoint) -> Self {
+        self.wire_attach = Some(wire_attach);
+        self
+    }
 
-    let wire_shape_idx = match style.wire_layer() {
-        WireLayer::BehindNodes => Some(ui.painter().add(Shape::Noop)),
-        WireLayer::AboveNodes => None,
-    };
+    /// Set zoom level below which nodes are drawn as frames with titles.
+    #[must_use]
+    pub const fn with_lod_title_zoom(mut self, lod_title_zoom: f32) -> Self {
+        self.lod_title_zoom = Some(lod_title_zoom);
+        self
+    }
 
-    let mut input_info = HashMap::new();
-    let mut output_info = HashMap::new();
-    let mut input_pins = HashMap::new();
-    let mut output_pins = HashMap::new();
+    /// Set zoom level below which nodes are drawn as plain frames.
+    #[must_use]
+    pub const fn with_lod_plain_zoom(mut self, lod_plain_zoom: f32) -> Self {
+        self.lod_plain_zoom = Some(lod_plain_zoom);
+        self
+    }
 
-    let mut pin_hovered = None;
+    /// Set whether to show zoom buttons over the graph.
+    #[must_use]
+    pub const fn with_show_zoom_buttons(mut self, show_zoom_buttons: bool) -> Self {
+        self.show_zoom_buttons = Some(show_zoom_buttons);
+        self
+    }
 
-    let draw_order = snarl_state.update_draw_order(snarl);
-    let mut drag_released = false;
+    /// Sets how selected nodes are highlighted.
+    #[must_use]
+    pub const fn with_selection_visual(mut self, selection_visual: SelectionVisual) -> Self {
+        self.selection_visual = Some(selection_visual);
+        self
+    }
 
-    let mut nodes_bb = Rect::NOTHING;
-    let mut node_rects = Vec::new();
+    /// Sets whether wires blend from output pin color to input pin color.
+    #[must_use]
+    pub const fn with_gradient_wires(mut self, gradient_wires: bool) -> Self {
+        self.gradient_wires = Some(gradient_wires);
+        self
+    }
+}
 
-    for node_idx in draw_order {
-        if !snarl.nodes.contains(node_idx.0) {
-            continue;
-        }
+impl Default for SnarlStyle {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-        // show_node(node_idx);
-        let response = draw_node(
-            snarl,
-            &mut ui,
-            node_idx,
-            viewer,
-            &mut snarl_state,
-            &mut config,
-            &style,
-            snarl_id,
-            &mut input_info,
-            input.modifiers,
-            &mut output_info,
-        );
+#[allow(dead_code)]
+struct Input {
+    hover_pos: Option<Pos2>,
+    interact_pos: Option<Pos2>,
+    zoom_delta: f32,
+    // primary_pressed: bool,
+    secondary_pressed: bool,
+    modifiers: Modifiers,
+    escape_pressed: bool,
+}
 
-        if let Some(response) = response {
-            if let Some(v) = response.node_to_top {
-                node_to_top = Some(v);
-            }
-            if let Some(v) = response.node_moved {
-                node_moved = Some(v);
-            }
-            if let Some(v) = response.pin_hovered {
-                pin_hovered = Some(v);
-            }
-            drag_released |= response.drag_released;
+/// Pin under the pointer with squared distance from the pointer to it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct HoveredPin {
+    pin: AnyPinId,
+    dist_sq: f32,
+}
 
-            nodes_bb = nodes_bb.union(response.final_rect);
-            if rect_selection_ended.is_some() {
-                node_rects.push((node_idx, response.final_rect));
-            }
-            for pin in response.in_pins {
-                input_pins.insert(pin.id, pin);
-            }
-            for pin in response.out_pins {
-                output_pins.insert(pin.id, pin);
-            }
-        }
+/// Squared distance at which hovered pins are considered equally close.
+const PIN_TIE_DIST_SQ: f32 = 1e-3;
+
+/// Returns squared distance from `pos` to the center of the pin,
+/// or to the closest point of the rail pin.
+fn pin_distance_sq(pin_rect: Rect, rail: Option<Rail>, pos: Pos2) -> f32 {
+    let center = rail.map_or(pin_rect.center(), |rail| rail.at(rail.t(pos)));
+    center.distance_sq(pos)
+}
+
+/// Replaces `current` hovered pin with `candidate` if it is not farther.
+///
+/// Pins are offered in drawing order, so among equally close pins
+/// the one drawn last, i.e. of the topmost node, is picked.
+fn pick_hovered_pin(current: &mut Option<HoveredPin>, candidate: HoveredPin) {
+    if current.is_none_or(|current| candidate.dist_sq <= current.dist_sq) {
+        *current = Some(candidate);
     }
+}
 
-    let mut hovered_wire = None;
-    let mut hovered_wire_disconnect = false;
-    let mut wire_shapes = Vec::new();
-    let mut wire_widgets = Vec::new();
+/// Picks pin to drop wires onto from `candidates` in drawing order.
+///
+/// Nearest pin center wins, ties go to the topmost node.
+fn nearest_pin(candidates: &[HoveredPin]) -> Option<HoveredPin> {
+    let mut nearest = None;
+    for &candidate in candidates {
+        pick_hovered_pin(&mut nearest, candidate);
+    }
+    nearest
+}
 
-    // Draw and interact with wires
-    for wire in snarl.wires.iter() {
-        let Some(from_r) = output_info.get(&wire.out_pin) else {
-            continue;
-        };
-        let Some(to_r) = input_info.get(&wire.in_pin) else {
-            continue;
-        };
-        let Some(out_pin) = output_pins.get(&wire.out_pin) else {
-            continue;
-        };
-        let Some(in_pin) = input_pins.get(&wire.in_pin) else {
-            continue;
-        };
+/// Returns pins equally close to the nearest one
+/// that may accept `new_wires`, topmost first.
+///
+/// Pins of the same kind as wire sources are included if `same_kind` is true.
+fn pin_ties(candidates: &[HoveredPin], new_wires: &NewWires, same_kind: bool) -> Vec<AnyPinId> {
+    let Some(nearest) = nearest_pin(candidates) else {
+        return Vec::new();
+    };
 
-        if !snarl_state.has_new_wires() && snarl_resp.contains_pointer() && hovered_wire.is_none() {
-            // Try to find hovered wire
-            // If not dragging new wire
-            // And not hovering over item above.
+    candidates
+        .iter()
+        .rev()
+        .filter(|hovered| hovered.dist_sq - nearest.dist_sq <= PIN_TIE_DIST_SQ)
+        .filter(|hovered| {
+            let opposite = matches!(
+                (new_wires, hovered.pin),
+                (NewWires::In(_), AnyPinId::Out(_)) | (NewWires::Out(_), AnyPinId::In(_))
+            );
+            opposite || (same_kind && same_kind_sources(new_wires, hovered.pin).is_some())
+        })
+        .map(|hovered| hovered.pin)
+        .collect()
+}
 
-            if let Some(latest_pos) = latest_pos {
-                // Use vertical wire drawing when Y distance > X distance (Houdini-style)
-                let dx = (to_r.pos.x - from_r.pos.x).abs();
-                let dy = (to_r.pos.y - from_r.pos.y).abs();
-                let vertical_wire = dy > dx;
+/// Returns sources of `new_wires` as pins of the same kind as `target`,
+/// excluding `target` itself.
+///
+/// Returns `None` if wires are dragged from pins of the other kind
+/// or only from the `target`.
+fn same_kind_sources(new_wires: &NewWires, target: AnyPinId) -> Option<Vec<AnyPinId>> {
+    let sources: Vec<_> = match (new_wires, target) {
+        (NewWires::In(in_pins), AnyPinId::In(_)) => {
+            in_pins.iter().copied().map(AnyPinId::In).collect()
+        }
+        (NewWires::Out(out_pins), AnyPinId::Out(_)) => {
+            out_pins.iter().copied().map(AnyPinId::Out).collect()
+        }
+        _ => return None,
+    };
+    let sources: Vec<_> = sources.into_iter().filter(|&pin| pin != target).collect();
+    (!sources.is_empty()).then_some(sources)
+}
 
-                let wire_hit = hit_wire(
-                    ui.ctx(),
-                    WireId::Connected {
-                        snarl_id,
-                        out_pin: wire.out_pin,
-                        in_pin: wire.in_pin,
-                    },
-                    wire_frame_size,
-                    style.upscale_wire_frame(),
-                    style.downscale_wire_frame(),
-                    from_r.pos,
-                    to_r.pos,
-                    latest_pos,
-                    wire_width.max(2.0),
-                    pick_wire_style(from_r.wire_style, to_r.wire_style),
-                    vertical_wire,
-                );
+struct DrawNodeResponse {
+    /// Dragged node, drag delta and whether drag started this frame.
+    node_moved: Option<(NodeId, Vec2, bool)>,
+    node_drag_stopped: Option<NodeId>,
+    node_to_top: Option<NodeId>,
+    drag_released: bool,
+    pin_hovered: Option<HoveredPin>,
+    final_rect: Rect,
+    hit_shape: NodeHitShape,
+    in_pins: Vec<InPin>,
+    out_pins: Vec<OutPin>,
+    /// Node without context menu was right-clicked,
+    /// so graph menu should open instead.
+    menu_passthrough: bool,
+}
 
-                if wire_hit {
-                    hovered_wire = Some(wire);
+struct DrawPinsResponse {
+    drag_released: bool,
+    pin_hovered: Option<HoveredPin>,
+    final_rect: Rect,
+    new_heights: RowHeights,
+}
 
-                    let wire_r =
-                        ui.interact(snarl_resp.rect, ui.make_persistent_id(wire), Sense::click());
+struct DrawPinsHorizontalResponse {
+    drag_released: bool,
+    pin_hovered: Option<HoveredPin>,
+    final_rect: Rect,
+    new_widths: RowHeights, // Reusing RowHeights type for widths
+}
 
-                    //Remove hovered wire by second click
-                    hovered_wire_disconnect |=
-                        wire_r.clicked_by(config.remove_hovered_wire.mouse_button);
-                }
-            }
-        }
+struct DrawBodyResponse {
+    final_rect: Rect,
+}
 
-        let color = mix_colors(from_r.wire_color, to_r.wire_color);
+struct PinResponse {
+    pos: Pos2,
+    /// Interactive area of the pin.
+    hit_rect: Rect,
+    wire_color: Color32,
+    wire_style: WireStyle,
+    /// Pin is on top or bottom edge of the node.
+    vertical: bool,
+    /// Segment of the rail pin.
+    rail: Option<Rail>,
+}
 
-        let mut draw_width = wire_width;
-        if hovered_wire == Some(wire) {
-            draw_width *= 1.5;
+impl PinResponse {
+    /// Returns position where wire attaches to the pin.
+    fn attach_pos(&self, t: Option<f32>) -> Pos2 {
+        match (self.rail, t) {
+            (Some(rail), Some(t)) => rail.at(t),
+            _ => self.pos,
         }
+    }
 
-        // Use vertical wire drawing when Y distance > X distance (Houdini-style)
-        let dx = (to_r.pos.x - from_r.pos.x).abs();
-        let dy = (to_r.pos.y - from_r.pos.y).abs();
-        let vertical_wire = dy > dx;
+    /// Returns position along the rail pin closest to `pos`.
+    fn rail_t(&self, pos: Pos2) -> Option<f32> {
+        self.rail.map(|rail| rail.t(pos))
+    }
+}
 
-        draw_wire(
-            &ui,
-            WireId::Connected {
-                snarl_id,
-                out_pin: wire.out_pin,
-                in_pin: wire.in_pin,
-            },
-            &mut wire_shapes,
-            wire_frame_size,
-            style.upscale_wire_frame(),
-            style.downscale_wire_frame(),
-            from_r.pos,
-            to_r.pos,
-            Stroke::new(draw_width, color),
-            wire_threshold,
-            pick_wire_style(from_r.wire_style, to_r.wire_style),
-            vertical_wire,
-        );
-        if viewer.has_wire_widget(&wire.out_pin, &wire.in_pin, snarl) {
-            let center = Pos2::new(
-                f32::midpoint(from_r.pos.x, to_r.pos.x),
-                f32::midpoint(from_r.pos.y, to_r.pos.y),
-            );
-            let wire_x_length =
-                f32::max(from_r.pos.x, to_r.pos.x) - f32::min(from_r.pos.x, to_r.pos.x);
-            wire_widgets.push((out_pin, in_pin, center, wire_x_length));
-        }
+/// Projects position of the pin onto the closest edge of the node `frame`
+/// in the direction wires leave the pin.
+///
+/// Rail pins already lie along the edge and are kept as is.
+fn edge_attach_pos(frame: Rect, pin: &PinResponse) -> Pos2 {
+    let pos = pin.pos;
+    if pin.rail.is_some() {
+        return pos;
     }
 
-    // Remove hovered wire by second click
-    if hovered_wire_disconnect && let Some(wire) = hovered_wire {
-        let out_pin = OutPin::new(snarl, wire.out_pin);
-        let in_pin = InPin::new(snarl, wire.in_pin);
-        viewer.disconnect(&out_pin, &in_pin, snarl);
+    if pin.vertical {
+        let y = if (pos.y - frame.top()).abs() <= (pos.y - frame.bottom()).abs() {
+            frame.top()
+        } else {
+            frame.bottom()
+        };
+        pos2(pos.x, y)
+    } else {
+        let x = if (pos.x - frame.left()).abs() <= (pos.x - frame.right()).abs() {
+            frame.left()
+        } else {
+            frame.right()
+        };
+        pos2(x, pos.y)
     }
+}
 
-    if let Some(select_rect) = rect_selection_ended {
-        let mut select_nodes: Vec<NodeId> = node_rects
-            .into_iter()
-            .filter_map(|(id, rect)| {
-                let select = if style.select_rect_contained() {
-                    select_rect.contains_rect(rect)
-                } else {
-                    select_rect.intersects(rect)
-                };
+/// Level of detail nodes are drawn with, depending on zoom.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum NodeDetail {
+    /// Node frame only.
+    Plain,
 
-                if select { Some(id) } else { None }
-            })
-            .collect();
+    /// Node frame with the title, without pins, body and footer.
+    Title,
 
-        // In single_select mode, only select one node (the last one found)
-        if config.single_select && select_nodes.len() > 1 {
-            select_nodes = select_nodes.into_iter().last().into_iter().collect();
-        }
+    /// Fully shown node.
+    Full,
+}
 
-        if input
-            .modifiers
-            .contains(config.deselect_all_nodes.modifiers)
-        {
-            snarl_state.deselect_many_nodes(select_nodes.into_iter());
+/// Relative zoom change past a threshold required to show more detail again.
+const NODE_DETAIL_HYSTERESIS: f32 = 0.1;
+
+impl NodeDetail {
+    /// Returns detail for the zoom `scale` without hysteresis.
+    fn at_scale(style: &SnarlStyle, scale: f32) -> Self {
+        if style.lod_plain_zoom().is_some_and(|zoom| scale < zoom) {
+            NodeDetail::Plain
+        } else if style.lod_title_zoom().is_some_and(|zoom| scale < zoom) {
+            NodeDetail::Title
         } else {
-            // In single_select mode, always reset (clear previous selection)
-            let reset =
-                config.single_select || !input.modifiers.contains(config.rect_select.modifiers);
-            snarl_state.select_many_nodes(reset, select_nodes.into_iter());
+            NodeDetail::Full
         }
     }
 
-    if let Some(select_rect) = snarl_state.rect_selection() {
-        ui.painter().rect(
-            select_rect,
-            0.0,
-            style.select_fill(ui.style()),
-            style.select_stroke(ui.style()),
-            StrokeKind::Inside,
-        );
+    /// Returns detail for the zoom `scale` coming from `current` detail.
+    ///
+    /// Detail drops as soon as zoom crosses a threshold,
+    /// but is raised only when zoom exceeds it by [`NODE_DETAIL_HYSTERESIS`],
+    /// so zooming around the threshold does not flicker.
+    fn update(self, style: &SnarlStyle, scale: f32) -> Self {
+        let target = Self::at_scale(style, scale);
+        if target > self {
+            Self::at_scale(style, scale / (1.0 + NODE_DETAIL_HYSTERESIS)).max(self)
+        } else {
+            target
+        }
     }
 
-    // If right button is clicked while new wire is being dragged, cancel it.
-    // This is to provide way to 'not open' the link graph node menu, but just
-    // releasing the new wire to empty space.
-    //
-    // This uses `button_down` directly, instead of `clicked_by` to improve
-    // responsiveness of the cancel action.
-    if snarl_state.has_new_wires()
-        && ui.input(|x| x.pointer.button_down(config.cancel_wire_drag.mouse_button))
-    {
-        let _ = snarl_state.take_new_wires();
-        snarl_resp.flags.remove(Flags::CLICKED);
+    fn load(cx: &egui::Context, id: Id) -> Self {
+        cx.data(|d| d.get_temp(id.with("node-detail")))
+            .unwrap_or(NodeDetail::Full)
     }
 
-    // Do centering unless no nodes are present.
-    if style.centering() && snarl_resp.double_clicked() && nodes_bb.is_finite() {
-        let nodes_bb = nodes_bb.expand(100.0);
-        snarl_state.look_at(nodes_bb, ui_rect, min_scale, max_scale);
+    fn save(self, cx: &egui::Context, id: Id) {
+        cx.data_mut(|d| d.insert_temp(id.with("node-detail"), self));
     }
+}
 
-    if input.modifiers == config.deselect_all_nodes.modifiers
-        && snarl_resp.clicked_by(config.deselect_all_nodes.mouse_button)
-    {
-        snarl_state.deselect_all_nodes();
-    }
+/// Paints node with reduced detail: frame and optionally the title in the header.
+fn draw_simplified_node(
+    ui: &Ui,
+    node_frame: &Frame,
+    header_frame: &Frame,
+    frame_rect: Rect,
+    header_height: f32,
+    title: Option<String>,
+) {
+    let painter = ui.painter();
+    painter.rect(
+        frame_rect,
+        node_frame.corner_radius,
+        node_frame.fill,
+        node_frame.stroke,
+        StrokeKind::Inside,
+    );
 
-    // Wire end position will be overridden when link graph menu is opened.
-    let mut wire_end_pos = latest_pos.unwrap_or(snarl_resp.rect.center());
+    let Some(title) = title else {
+        return;
+    };
 
-    if drag_released {
-        let new_wires = snarl_state.take_new_wires();
-        if new_wires.is_some() {
-            ui.ctx().request_repaint();
+    let header_rect = Rect::from_min_size(
+        frame_rect.min,
+        vec2(frame_rect.width(), header_height.min(frame_rect.height())),
+    );
+    painter.rect_filled(
+        header_rect,
+        CornerRadius {
+            sw: 0,
+            se: 0,
+            ..node_frame.corner_radius
+        },
+        header_frame.fill,
+    );
+    painter
+        .with_clip_rect(header_rect.intersect(painter.clip_rect()))
+        .text(
+            header_rect.left_center() + vec2(header_frame.total_margin().left, 0.0),
+            Align2::LEFT_CENTER,
+            title,
+            egui::TextStyle::Body.resolve(ui.style()),
+            ui.visuals().text_color(),
+        );
+}
+
+/// Segment along which wires attach to the rail pin.
+#[derive(Clone, Copy, Debug)]
+struct Rail {
+    from: Pos2,
+    to: Pos2,
+}
+
+impl Rail {
+    /// Returns rect of the rail pin spanning `min..max` range of the pin row.
+    fn pin_rect(at: f32, min: f32, max: f32, size: f32, along_x: bool) -> Rect {
+        let across = at - size * 0.5..=at + size * 0.5;
+        let along = min..=max.max(min + size);
+        if along_x {
+            Rect::from_x_y_ranges(along, across)
+        } else {
+            Rect::from_x_y_ranges(across, along)
         }
-        match (new_wires, pin_hovered) {
-            (Some(NewWires::In(in_pins)), Some(AnyPin::Out(out_pin))) => {
-                for in_pin in in_pins {
-                    viewer.connect(
-                        &OutPin::new(snarl, out_pin),
-                        &InPin::new(snarl, in_pin),
-                        snarl,
-                    );
-                }
+    }
+
+    /// Creates rail segment inside rail pin rect.
+    fn new(rect: Rect, size: f32, along_x: bool) -> Self {
+        let inset = (size * 0.5).min(rect.size().max_elem() * 0.5);
+        if along_x {
+            Rail {
+                from: pos2(rect.min.x + inset, rect.center().y),
+                to: pos2(rect.max.x - inset, rect.center().y),
             }
-            (Some(NewWires::Out(out_pins)), Some(AnyPin::In(in_pin))) => {
-                for out_pin in out_pins {
-                    viewer.connect(
-                        &OutPin::new(snarl, out_pin),
-                        &InPin::new(snarl, in_pin),
-                        snarl,
-                    );
-                }
+        } else {
+            Rail {
+                from: pos2(rect.center().x, rect.min.y + inset),
+                to: pos2(rect.center().x, rect.max.y - inset),
             }
-            (Some(new_wires), None) if snarl_resp.hovered() => {
-                let pins = match &new_wires {
-                    NewWires::In(x) => AnyPins::In(x),
-                    NewWires::Out(x) => AnyPins::Out(x),
-                };
+        }
+    }
 
-                if viewer.has_dropped_wire_menu(pins, snarl) {
-                    // A wire is dropped without connecting to a pin.
-                    // Show context menu for the wire drop.
-                    snarl_state.set_new_wires_menu(new_wires);
+    /// Returns point at `t` along the rail.
+    fn at(self, t: f32) -> Pos2 {
+        self.from.lerp(self.to, t.clamp(0.0, 1.0))
+    }
 
-                    // Force open context menu.
-                    snarl_resp.flags.insert(Flags::LONG_TOUCHED);
-                }
-            }
-            _ => {}
+    /// Returns position along the rail of the point closest to `pos`.
+    fn t(self, pos: Pos2) -> f32 {
+        let dir = self.to - self.from;
+        let len_sq = dir.length_sq();
+        if len_sq <= f32::EPSILON {
+            return 0.5;
         }
+        ((pos - self.from).dot(dir) / len_sq).clamp(0.0, 1.0)
     }
+}
 
-    // If right button is clicked while new wire is being dragged, cancel it.
-    // This is to provide way to 'not open' the link graph node menu, but just
-    // releasing the new wire to empty space.
-    //
-    // This uses `button_down` directly, instead of `clicked_by` to improve
-    // responsiveness of the cancel action.
-    if snarl_state.has_new_wires() && ui.input(|x| x.pointer.button_down(PointerButton::Secondary))
-    {
-        let _ = snarl_state.take_new_wires();
+/// Widget to display [`Snarl`] graph in [`Ui`].
+#[derive(Clone, Copy, Debug)]
+pub struct SnarlWidget {
+    id_salt: Id,
+    id: Option<Id>,
+    style: SnarlStyle,
+    config: SnarlConfig,
+    min_size: Vec2,
+    max_size: Vec2,
+}
+
+impl Default for SnarlWidget {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    if input.modifiers.command || input.escape_pressed {
-        snarl_state.deselect_all_nodes();
+impl SnarlWidget {
+    /// Returns new [`SnarlWidget`] with default parameters.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        SnarlWidget {
+            id_salt: Id::new(":snarl:"),
+            id: None,
+            style: SnarlStyle::new(),
+            config: SnarlConfig::new(),
+            min_size: Vec2::ZERO,
+            max_size: Vec2::INFINITY,
+        }
     }
 
-    if let Some(interact_pos) = ui.ctx().input(|i| i.pointer.interact_pos()) {
-        if let Some(new_wires) = snarl_state.take_new_wires_menu() {
-            let pins = match &new_wires {
-                NewWires::In(x) => AnyPins::In(x),
-                NewWires::Out(x) => AnyPins::Out(x),
-            };
+    /// Assign an explicit and globally unique [`Id`].
+    ///
+    /// Use this if you want to persist the state of the widget
+    /// when it changes position in the widget hierarchy.
+    ///
+    /// Prefer using [`SnarlWidget::id_salt`] otherwise.
+    #[inline]
+    #[must_use]
+    pub fn id(mut self, id: Id) -> Self {
+        self.id = Some(id);
+        self
+    }
 
-            if viewer.has_dropped_wire_menu(pins, snarl) {
-                snarl_resp.context_menu(|ui| {
-                    let pins = match &new_wires {
-                        NewWires::In(x) => AnyPins::In(x),
-                        NewWires::Out(x) => AnyPins::Out(x),
-                    };
+    /// Assign a source for the unique [`Id`]
+    ///
+    /// It must be locally unique for the current [`Ui`] hierarchy position.
+    ///
+    /// Ignored if [`SnarlWidget::id`] was set.
+    #[inline]
+    #[must_use]
+    pub fn id_salt(mut self, id_salt: impl Hash) -> Self {
+        self.id_salt = Id::new(id_salt);
+        self
+    }
 
-                    let menu_pos = from_global * ui.cursor().min;
+    /// Set style parameters for the [`Snarl`] widget.
+    #[inline]
+    #[must_use]
+    pub fn style(mut self, style: SnarlStyle) -> Self {
+        self.style = style;
+        self
+    }
 
-                    // Override wire end position when the wire-drop context menu is opened.
-                    wire_end_pos = menu_pos;
+    /// Set config parameters for the [`Snarl`] widget.
+    #[inline]
+    #[must_use]
+    pub fn config(mut self, config: SnarlConfig) -> Self {
+        self.config = config;
+        self
+    }
 
-                    // The context menu is opened as *link* graph menu.
-                    viewer.show_dropped_wire_menu(menu_pos, ui, pins, snarl);
+    /// Set minimum size of the [`Snarl`] widget.
+    #[inline]
+    #[must_use]
+    pub fn min_size(mut self, min_size: Vec2) -> Self {
+        self.min_size = min_size;
+        self
+    }
 
-                    // Even though menu could be closed in `show_dropped_wire_menu`,
-                    // we need to revert the new wires here, because menu state is inaccessible.
-                    // Next frame context menu won't be shown and wires will be removed.
-                    snarl_state.set_new_wires_menu(new_wires);
-                });
-            }
-        } else if viewer.has_graph_menu(interact_pos, snarl) {
-            snarl_resp.context_menu(|ui| {
-                let menu_pos = from_global * ui.cursor().min;
+    /// Set maximum size of the [`Snarl`] widget.
+    #[inline]
+    #[must_use]
+    pub fn max_size(mut self, max_size: Vec2) -> Self {
+        self.max_size = max_size;
+        self
+    }
 
-                viewer.show_graph_menu(menu_pos, ui, snarl);
-            });
-        }
+    #[inline]
+    fn get_id(&self, ui_id: Id) -> Id {
+        self.id.unwrap_or_else(|| ui_id.with(self.id_salt))
     }
 
-    match snarl_state.new_wires() {
-        None => {}
-        Some(NewWires::In(in_pins)) => {
-            for &in_pin in in_pins {
-                let from_pos = wire_end_pos;
-                let to_r = &input_info[&in_pin];
+    /// Render [`Snarl`] using given viewer and style into the [`Ui`].
+    #[inline]
+    pub fn show<T, V>(&self, snarl: &mut Snarl<T>, viewer: &mut V, ui: &mut Ui) -> egui::Response
+    where
+        V: SnarlViewer<T>,
+    {
+        let snarl_id = self.get_id(ui.id());
 
-                // Use vertical wire drawing when Y distance > X distance
-                let dx = (to_r.pos.x - from_pos.x).abs();
-                let dy = (to_r.pos.y - from_pos.y).abs();
-                let vertical_wire = dy > dx;
+        show_snarl(
+            snarl_id,
+            self.style,
+            self.config,
+            self.min_size,
+            self.max_size,
+            snarl,
+            viewer,
+            ui,
+        )
+    }
+}
 
-                draw_wire(
-                    &ui,
-                    WireId::NewInput { snarl_id, in_pin },
-                    &mut wire_shapes,
-                    wire_frame_size,
-                    style.upscale_wire_frame(),
-                    style.downscale_wire_frame(),
-                    from_pos,
-                    to_r.pos,
-                    Stroke::new(wire_width, to_r.wire_color),
-                    wire_threshold,
-                    to_r.wire_style,
-                    vertical_wire,
-                );
-            }
-        }
-        Some(NewWires::Out(out_pins)) => {
-            for &out_pin in out_pins {
-                let from_r = &output_info[&out_pin];
-                let to_pos = wire_end_pos;
+#[inline(never)]
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
+fn show_snarl<T, V>(
+    snarl_id: Id,
+    mut style: SnarlStyle,
+    mut config: SnarlConfig,
+    min_size: Vec2,
+    max_size: Vec2,
+    snarl: &mut Snarl<T>,
+    viewer: &mut V,
+    ui: &mut Ui,
+) -> egui::Response
+where
+    V: SnarlViewer<T>,
+{
+    let (mut latest_pos, input) = ui.ctx().input(|i| {
+        (
+            i.pointer.latest_pos(),
+            Input {
+                zoom_delta: i.zoom_delta(),
+                hover_pos: i.pointer.hover_pos(),
+                interact_pos: i.pointer.interact_pos(),
+                modifiers: i.modifiers,
+                // primary_pressed: i.pointer.primary_pressed(),
+                secondary_pressed: i.pointer.secondary_pressed(),
+                escape_pressed: i.key_pressed(Key::Escape),
+            },
+        )
+    });
 
-                // Use vertical wire drawing when Y distance > X distance
-                let dx = (to_pos.x - from_r.pos.x).abs();
-                let dy = (to_pos.y - from_r.pos.y).abs();
-                let vertical_wire = dy > dx;
+    let bg_frame = style.bg_frame(ui.style());
 
-                draw_wire(
-                    &ui,
-                    WireId::NewOutput { snarl_id, out_pin },
-                    &mut wire_shapes,
-                    wire_frame_size,
-                    style.upscale_wire_frame(),
-                    style.downscale_wire_frame(),
-                    from_r.pos,
-                    to_pos,
-                    Stroke::new(wire_width, from_r.wire_color),
-                    wire_threshold,
-                    from_r.wire_style,
-                    vertical_wire,
-                );
-            }
-        }
-    }
+    let outer_size_bounds = ui.available_size_before_wrap().max(min_size).min(max_size);
 
-    match wire_shape_idx {
-        None => {
-            ui.painter().add(Shape::Vec(wire_shapes));
-        }
-        Some(idx) => {
-            ui.painter().set(idx, Shape::Vec(wire_shapes));
-        }
-    }
+    let outer_resp = ui.allocate_response(outer_size_bounds, Sense::hover());
 
-    for (out_pin, in_pin, center, wire_x_length) in wire_widgets {
-        let id = Id::new("wire-widget").with((out_pin.id, in_pin.id));
-        let cached = ui
-            .ctx()
-            .memory(|mem| mem.data.get_temp::<WireWidgetCache>(id));
-        let widget_rect = cached.map_or_else(
-            || {
-                RectAlign {
-                    parent: Align2::CENTER_CENTER,
-                    child: style.wire_widget_align.unwrap_or(Align2::CENTER_CENTER),
-                }
-                .align_rect(
-                    &Rect::from_center_size(center, [wire_x_length, 0.0].into()),
-                    Vec2::new(wire_x_length, 0.0),
-                    style.wire_widget_gap.unwrap_or(0.0),
-                )
-            },
-            |cached| {
-                #[allow(clippy::float_cmp)]
-                if cached.wire_len == wire_x_length {
-                    cached.widget_rect
-                } else {
-                    // calculate a new rect if the wire length has been changed due to user moving
-                    // the nodes connected by the wire
+    ui.painter().add(bg_frame.paint(outer_resp.rect));
 
-                    RectAlign {
-                        parent: Align2::CENTER_CENTER,
-                        child: style.wire_widget_align.unwrap_or(Align2::CENTER_CENTER),
-                    }
-                    .align_rect(
-                        &Rect::from_center_size(center, [wire_x_length, 0.0].into()),
-                        Vec2::new(wire_x_length, 0.0),
-                        style.wire_widget_gap.unwrap_or(0.0),
-                    )
-                }
-            },
-        );
-        let mut wire_ui = ui.new_child(
-            UiBuilder::new()
-                .max_rect(widget_rect)
-                .layout(Layout::default())
-                .id_salt(id),
-        );
-        viewer.show_wire_widget(out_pin, in_pin, &mut wire_ui, snarl);
-        ui.ctx().memory_mut(|mem| {
-            mem.data.insert_temp(
-                id,
-                WireWidgetCache {
-                    widget_rect: Rect::from_center_size(
-                        center,
-                        Vec2::new(wire_ui.min_rect().width(), wire_ui.min_rect().height()),
-                    ),
-                    wire_len: wire_x_length,
-                },
-            );
-        });
-    }
+    let mut content_rect = outer_resp.rect - bg_frame.total_margin();
 
-    ui.advance_cursor_after_rect(Rect::from_min_size(snarl_resp.rect.min, Vec2::ZERO));
+    // Make sure we don't shrink to the negative:
+    content_rect.max.x = content_rect.max.x.max(content_rect.min.x);
+    content_rect.max.y = content_rect.max.y.max(content_rect.min.y);
 
-    if let Some(node) = node_to_top
-        && snarl.nodes.contains(node.0)
-    {
-        snarl_state.node_to_top(node);
+    let snarl_layer_id = LayerId::new(ui.layer_id().order, snarl_id);
+
+    ui.ctx().set_sublayer(ui.layer_id(), snarl_layer_id);
+
+    let mut min_scale = style.min_scale();
+    let mut max_scale = style.max_scale();
+
+    let ui_rect = content_rect;
+
+    let mut snarl_state =
+        SnarlState::load(ui.ctx(), snarl_id, snarl, ui_rect, min_scale, max_scale);
+
+    // Allow viewer to update selection before rendering
+    if let Some(selection) = viewer.update_selection(snarl_state.selected_nodes()) {
+        snarl_state.select_many_nodes(true, selection.iter().copied());
     }
 
-    if let Some((node, delta)) = node_moved
-        && snarl.nodes.contains(node.0)
-    {
-        ui.ctx().request_repaint();
+    let to_global_before = snarl_state.to_global();
 
-        // Helper to snap position to grid if enabled
-        let snap_to_grid = |pos: Pos2| -> Pos2 {
-            if let Some(ref grid) = config.grid_snap {
-                grid.snap(pos)
-            } else {
-                pos
-            }
-        };
+    let selection_before = snarl_state
+        .selected_nodes()
+        .iter()
+        .copied()
+        .collect::<HashSet<_>>();
 
-        if snarl_state.selected_nodes().contains(&node) {
-            for node_id in snarl_state.selected_nodes() {
-                let node_data = &mut snarl.nodes[node_id.0];
-                node_data.pos += delta;
-                node_data.pos = snap_to_grid(node_data.pos);
-                let new_pos = node_data.pos;
-                viewer.node_moved(*node_id, new_pos, snarl);
+    // Apply automatic layout if requested
+    if viewer.apply_layout(snarl) {
+        let layout = viewer.compute_layout(snarl);
+        for (node_id, new_pos) in layout {
+            if let Some(node) = snarl.nodes.get_mut(node_id.0) {
+                node.pos = new_pos;
             }
-        } else {
-            let node_data = &mut snarl.nodes[node.0];
-            node_data.pos += delta;
-            node_data.pos = snap_to_grid(node_data.pos);
-            let new_pos = node_data.pos;
-            viewer.node_moved(node, new_pos, snarl);
         }
     }
 
-    // Draw foreground elements (comments, annotations, overlays)
-    viewer.draw_foreground(&viewport, &style, ui.style(), ui.painter(), snarl);
-
-    snarl_state.store(snarl, ui.ctx());
+    let time = ui.input(|i| i.time);
+    if snarl_state.step_animation(time) {
+        snarl_state.request_repaint();
+    }
 
-    snarl_resp
-}
+    let mut to_global = snarl_state.to_global();
 
-#[allow(clippy::too_many_arguments)]
-#[allow(clippy::too_many_lines)]
-fn draw_inputs<T, V>(
-    snarl: &mut Snarl<T>,
-    viewer: &mut V,
-    node: NodeId,
-    inputs: &[InPin],
-    pin_size: f32,
-    style: &SnarlStyle,
-    node_ui: &mut Ui,
-    inputs_rect: Rect,
-    payload_clip_rect: Rect,
-    input_x: f32,
-    min_pin_y_top: f32,
-    min_pin_y_bottom: f32,
-    input_spacing: Option<f32>,
-    snarl_state: &mut SnarlState,
-    config: &mut SnarlConfig,
-    modifiers: Modifiers,
-    input_positions: &mut HashMap<InPinId, PinResponse>,
-    heights: Heights,
-) -> DrawPinsResponse
-where
-    V: SnarlViewer<T>,
-{
-    let mut drag_released = false;
-    let mut pin_hovered = None;
+    let clip_rect = ui.clip_rect();
 
-    // Input pins on the left.
-    let mut inputs_ui = node_ui.new_child(
+    let mut ui = ui.new_child(
         UiBuilder::new()
-            .max_rect(inputs_rect.round_ui())
-            .layout(Layout::top_down(Align::Min))
-            .id_salt("inputs"),
+            .ui_stack_info(UiStackInfo::new(UiKind::Frame).with_frame(bg_frame))
+            .layer_id(snarl_layer_id)
+            .max_rect(Rect::EVERYTHING)
+            .sense(Sense::click_and_drag()),
     );
 
-    let snarl_clip_rect = node_ui.clip_rect();
-    inputs_ui.shrink_clip_rect(payload_clip_rect);
+    if style.crisp_magnified_text() {
+        style.scale(max_scale);
+        ui.style_mut().scale(max_scale);
 
-    let pin_layout = Layout::left_to_right(Align::Min);
-    let mut new_heights = SmallVec::with_capacity(inputs.len());
+        min_scale /= max_scale;
+        max_scale = 1.0;
+    }
 
-    for in_pin in inputs {
-        // Show input pin.
-        let cursor = inputs_ui.cursor();
-        let (height, height_outer) = heights.get(in_pin.id.input);
+    clamp_scale(&mut to_global, min_scale, max_scale, ui_rect);
 
-        let margin = (height_outer - height) / 2.0;
-        let outer_rect = cursor.with_max_y(cursor.top() + height_outer);
-        let inner_rect = outer_rect.shrink2(vec2(0.0, margin));
+    let before_pan = to_global;
 
-        let builder = UiBuilder::new().layout(pin_layout).max_rect(inner_rect);
+    let mut snarl_resp = ui.response();
+    let (scroll_delta, zoom_delta, scroll_zoom) = ui.input(|i| {
+        (
+            i.smooth_scroll_delta,
+            i.zoom_delta(),
+            i.modifiers.command && i.multi_touch().is_none(),
+        )
+    });
+    let zoom_range = if config.scroll_mode == ScrollMode::ZoomDefault && scroll_zoom {
+        // Command + scroll pans instead, lock the zoom applied by the scene.
+        to_global.scaling..=to_global.scaling
+    } else {
+        min_scale..=max_scale
+    };
+    if !config.viewport_locked {
+        Scene::new().zoom_range(zoom_range).register_pan_and_zoom(
+            &ui,
+            &mut snarl_resp,
+            &mut to_global,
+        );
+    }
 
-        inputs_ui.scope_builder(builder, |pin_ui| {
-            if let Some(input_spacing) = input_spacing {
-                let min = pin_ui.next_widget_position();
-                pin_ui.advance_cursor_after_rect(Rect::from_min_size(
-                    min,
-                    vec2(input_spacing, pin_size),
-                ));
+    if !config.viewport_locked
+        && config.scroll_mode == ScrollMode::ZoomDefault
+        && snarl_resp.contains_pointer()
+        && let Some(pointer) = ui.input(|i| i.pointer.latest_pos())
+    {
+        let speed = ui.ctx().options(|o| o.input_options.scroll_zoom_speed);
+
+        if scroll_zoom {
+            // Scroll with command is reported as zoom, turn it back into vertical pan.
+            if (zoom_delta - 1.0).abs() > f32::EPSILON {
+                to_global.translation.y += zoom_delta.ln() / speed;
+                snarl_resp.mark_changed();
             }
+        } else if scroll_delta != Vec2::ZERO {
+            // Revert the pan applied by the scene and zoom around pointer instead.
+            to_global.translation -= scroll_delta;
 
-            // Compute PinContext based on style.pin_label_visibility()
-            let is_connecting = snarl_state.has_new_wires();
-            let label_visible = match style.pin_label_visibility() {
-                PinLabelVisibility::Always => true,
-                PinLabelVisibility::OnHover => false, // Will show via hover tooltip instead
-                PinLabelVisibility::WhileConnecting => is_connecting,
-                PinLabelVisibility::NearWire { .. } => is_connecting, // Simplified: show all when connecting
-            };
-            let pin_context = PinContext {
-                label_visible,
-                is_connecting,
-                is_hovered: false,             // Updated after interaction
-                wire_near_node: is_connecting, // Simplified for now
+            let zoom = (speed * (scroll_delta.x + scroll_delta.y)).exp();
+            zoom_around(&mut to_global, pointer, zoom, min_scale, max_scale);
+            snarl_resp.mark_changed();
+        }
+    }
+
+    if !config.viewport_locked {
+        let mut zoom_steps = 0;
+        if style.show_zoom_buttons() {
+            zoom_steps += show_zoom_buttons(&mut ui, snarl_id, snarl_layer_id, ui_rect);
+        }
+        if snarl_resp.contains_pointer() && !ui.ctx().wants_keyboard_input() {
+            let consume_shortcut = |shortcut: Option<KeyboardShortcut>| {
+                shortcut.is_some_and(|shortcut| ui.input_mut(|i| i.consume_shortcut(&shortcut)))
             };
-            let snarl_pin = viewer.show_input(in_pin, pin_ui, pin_context, snarl);
-            if !snarl.nodes.contains(node.0) {
-                // If removed
-                return;
+            if consume_shortcut(config.zoom_in) {
+                zoom_steps += 1;
+            }
+            if consume_shortcut(config.zoom_out) {
+                zoom_steps -= 1;
             }
+        }
+        if zoom_steps != 0 {
+            let zoom = config.zoom_step.max(1.0).powi(zoom_steps);
+            zoom_around(&mut to_global, ui_rect.center(), zoom, min_scale, max_scale);
+            snarl_resp.mark_changed();
+        }
+    }
 
-            // Use min_rect after content is shown to get actual content bounds.
-            // This ensures pin is aligned with actual content, not pre-allocated space.
-            let content_rect = pin_ui.min_rect();
-            let y0 = content_rect.min.y;
-            let y1 = content_rect.max.y;
+    if let Some(friction) = style.pan_inertia()
+        && !config.viewport_locked
+    {
+        let (dt, pressed) = ui.input(|i| (i.stable_dt, i.pointer.any_pressed()));
+        if snarl_resp.dragged() {
+            snarl_state.track_pan(to_global.translation - before_pan.translation, dt);
+        } else if snarl_resp.drag_stopped() {
+            // Keep velocity to glide from the next frame.
+        } else if pressed || snarl_resp.changed() {
+            // Any new input stops the glide.
+            snarl_state.stop_pan_glide();
+        } else if let Some(delta) = snarl_state.step_pan_glide(friction, dt) {
+            to_global.translation += delta;
+            snarl_state.request_repaint();
+        }
+    }
 
-            let pin_rect = snarl_pin.pin_rect(
-                input_x,
-                min_pin_y_top.max(y0),
-                min_pin_y_bottom.max(y1),
-                pin_size,
-            );
+    if snarl_resp.changed() {
+        // User pan and zoom interrupts viewport animation.
+        snarl_state.stop_animation();
+        snarl_state.request_repaint();
+    }
 
-            // Interact with pin shape.
-            pin_ui.set_clip_rect(snarl_clip_rect);
+    if config.clamp_pan_to_bounds
+        && let Some(bounds) = config.bounds
+    {
+        let view = to_global.inverse() * ui_rect;
+        let delta = pan_to_contain(bounds, view);
+        to_global.translation -= delta * to_global.scaling;
+    }
 
-            let r = pin_ui.interact(pin_rect, pin_ui.next_auto_id(), Sense::click_and_drag());
+    // Inform viewer about current transform.
+    viewer.current_transform(&mut to_global, snarl);
 
-            pin_ui.skip_ahead_auto_ids(1);
+    snarl_state.set_to_global(to_global);
 
-            if r.clicked_by(config.click_pin.mouse_button) {
-                if snarl_state.has_new_wires() {
-                    snarl_state.remove_new_wire_in(in_pin.id);
-                } else {
-                    viewer.drop_inputs(in_pin, snarl);
-                    if !snarl.nodes.contains(node.0) {
-                        // If removed
-                        return;
-                    }
-                }
-            }
-            if r.drag_started_by(config.drag_pin.mouse_button) {
-                // TODO: I am not sure what start_new_wires_out() does?
-                // Is that for a future feature where you can draw several wires at once?
-                if modifiers.command {
-                    snarl_state.start_new_wires_out(&in_pin.remotes);
-                    if modifiers != config.drag_pin.modifiers {
-                        snarl.drop_inputs(in_pin.id);
-                        if !snarl.nodes.contains(node.0) {
-                            // If removed
-                            return;
-                        }
-                    }
-                } else {
-                    snarl_state.start_new_wire_in(in_pin.id);
-                }
-            }
+    let to_global = to_global;
+    let from_global = to_global.inverse();
 
-            if r.drag_stopped() {
-                drag_released = true;
-            }
+    // Graph viewport
+    let viewport = (from_global * ui_rect).round_ui();
+    let viewport_clip = from_global * clip_rect;
 
-            let mut visual_pin_rect = r.rect;
+    ui.set_clip_rect(viewport.intersect(viewport_clip));
+    ui.expand_to_include_rect(viewport);
 
-            if r.contains_pointer() {
-                if snarl_state.has_new_wires_in() {
-                    // TODO: And here I am also not sure what these modifiers do
-                    if modifiers.shift && !modifiers.command {
-                        snarl_state.add_new_wire_in(in_pin.id);
-                    }
-                    if !modifiers.shift && modifiers.command {
-                        snarl_state.remove_new_wire_in(in_pin.id);
-                    }
-                }
-                pin_hovered = Some(AnyPin::In(in_pin.id));
-                visual_pin_rect = visual_pin_rect.scale_from_center(style.pin_hover_scale());
-            }
+    // Set transform for snarl layer.
+    ui.ctx().set_transform_layer(snarl_layer_id, to_global);
 
-            let wire_info =
-                snarl_pin.draw(style, pin_ui.style(), visual_pin_rect, pin_ui.painter());
+    // Map latest pointer position to graph space.
+    latest_pos = latest_pos.map(|pos| from_global * pos);
 
-            input_positions.insert(
-                in_pin.id,
-                PinResponse {
-                    pos: r.rect.center(),
-                    wire_color: wire_info.color,
-                    wire_style: wire_info.style,
-                },
-            );
+    style.bg_style().draw(&viewport, ui.painter());
 
-            new_heights.push(pin_ui.min_rect().height());
+    viewer.draw_background(
+        style.bg_pattern.as_ref(),
+        &viewport,
+        &style,
+        ui.style(),
+        ui.painter(),
+        snarl,
+    );
 
-            pin_ui.expand_to_include_y(outer_rect.bottom());
-        });
+    // Draw snap grid if visible
+    let node_dragging_id = snarl_id.with("node-dragging");
+    if let Some(mut grid) = config.grid_snap {
+        if config.show_grid_while_dragging {
+            // Drag state is known after nodes are processed, so last frame state is used.
+            grid.visible = ui.data(|d| d.get_temp::<bool>(node_dragging_id).unwrap_or(false));
+        }
+        grid.draw(&viewport, ui.painter());
     }
 
-    let final_rect = inputs_ui.min_rect();
-    node_ui.expand_to_include_rect(final_rect.intersect(payload_clip_rect));
-
-    DrawPinsResponse {
-        drag_released,
-        pin_hovered,
-        final_rect,
-        new_heights,
+    if let Some(bounds) = config.bounds {
+        ui.painter().rect_stroke(
+            bounds,
+            0.0,
+            style.bg_pattern_stroke(ui.style()),
+            StrokeKind::Outside,
+        );
     }
-}
 
-#[allow(clippy::too_many_arguments)]
-#[allow(clippy::too_many_lines)]
-fn draw_outputs<T, V>(
-    snarl: &mut Snarl<T>,
-    viewer: &mut V,
-    node: NodeId,
-    outputs: &[OutPin],
-    pin_size: f32,
-    style: &SnarlStyle,
-    node_ui: &mut Ui,
-    outputs_rect: Rect,
-    payload_clip_rect: Rect,
-    output_x: f32,
-    min_pin_y_top: f32,
-    min_pin_y_bottom: f32,
-    output_spacing: Option<f32>,
-    snarl_state: &mut SnarlState,
-    config: &mut SnarlConfig,
-    modifiers: Modifiers,
-    output_positions: &mut HashMap<OutPinId, PinResponse>,
-    heights: Heights,
-) -> DrawPinsResponse
-where
-    V: SnarlViewer<T>,
-{
-    let mut drag_released = false;
-    let mut pin_hovered = None;
+    let mut node_moved = None;
+    let mut node_drag_stopped = None;
+    let mut node_to_top = None;
 
-    let mut outputs_ui = node_ui.new_child(
-        UiBuilder::new()
-            .max_rect(outputs_rect.round_ui())
-            .layout(Layout::top_down(Align::Max))
-            .id_salt("outputs"),
+    // Process wire cut line.
+    let (cut_wires_active, cut_line_ended) = cut::process_cut_input(
+        &ui,
+        snarl_resp.rect,
+        snarl_id,
+        &config,
+        input.modifiers,
+        &mut snarl_state,
     );
 
-    let snarl_clip_rect = node_ui.clip_rect();
-    outputs_ui.shrink_clip_rect(payload_clip_rect);
+    // Process selection rect and lasso.
+    let select_ended = if cut_wires_active {
+        None
+    } else {
+        select::process_select_input(
+            &ui,
+            snarl_resp.rect,
+            snarl_id,
+            &config,
+            input.modifiers,
+            &mut snarl_state,
+        )
+    };
 
-    let pin_layout = Layout::right_to_left(Align::Min);
-    let mut new_heights = SmallVec::with_capacity(outputs.len());
+    let wire_frame_size = style.wire_frame_size(ui.style());
+    let wire_width = style.wire_width(ui.style());
+    let wire_threshold = style.wire_smoothness();
 
-    // Output pins on the right.
-    for out_pin in outputs {
-        // Show output pin.
-        let cursor = outputs_ui.cursor();
-        let (height, height_outer) = heights.get(out_pin.id.output);
+    let wire_shape_idx = match style.wire_layer() {
+        WireLayer::BehindNodes => Some(ui.painter().add(Shape::Noop)),
+        WireLayer::AboveNodes => None,
+    };
 
-        let margin = (height_outer - height) / 2.0;
-        let outer_rect = cursor.with_max_y(cursor.top() + height_outer);
-        let inner_rect = outer_rect.shrink2(vec2(0.0, margin));
+    let mut input_info = HashMap::new();
+    let mut output_info = HashMap::new();
+    let mut input_pins = HashMap::new();
+    let mut output_pins = HashMap::new();
 
-        let builder = UiBuilder::new().layout(pin_layout).max_rect(inner_rect);
+    let mut pin_candidates = Vec::new();
 
-        outputs_ui.scope_builder(builder, |pin_ui| {
-            // Allocate space for pin shape.
-            if let Some(output_spacing) = output_spacing {
-                let min = pin_ui.next_widget_position();
-                pin_ui.advance_cursor_after_rect(Rect::from_min_size(
-                    min,
-                    vec2(output_spacing, pin_size),
-                ));
-            }
+    let draw_order = snarl_state.update_draw_order(snarl);
+    let mut drag_released = false;
 
-            // Compute PinContext based on style.pin_label_visibility()
-            let is_connecting = snarl_state.has_new_wires();
-            let label_visible = match style.pin_label_visibility() {
-                PinLabelVisibility::Always => true,
-                PinLabelVisibility::OnHover => false, // Will show via hover tooltip instead
-                PinLabelVisibility::WhileConnecting => is_connecting,
-                PinLabelVisibility::NearWire { .. } => is_connecting, // Simplified: show all when connecting
-            };
-            let pin_context = PinContext {
-                label_visible,
-                is_connecting,
-                is_hovered: false,             // Updated after interaction
-                wire_near_node: is_connecting, // Simplified for now
-            };
-            let snarl_pin = viewer.show_output(out_pin, pin_ui, pin_context, snarl);
-            if !snarl.nodes.contains(node.0) {
-                // If removed
-                return;
-            }
+    // Wires pulled off a pin in previous frames are finished by click.
+    let wires_pulled = snarl_state.has_pulled_new_wires();
 
-            // Use min_rect after content is shown to get actual content bounds.
-            // This ensures pin is aligned with actual content, not pre-allocated space.
-            let content_rect = pin_ui.min_rect();
-            let y0 = content_rect.min.y;
-            let y1 = content_rect.max.y;
+    let mut nodes_bb = Rect::NOTHING;
+    let mut node_rects = Vec::new();
+    let mut hit_nodes = Vec::new();
 
-            let pin_rect = snarl_pin.pin_rect(
-                output_x,
-                min_pin_y_top.max(y0),
-                min_pin_y_bottom.max(y1),
-                pin_size,
-            );
+    // Offset and size of node frames relative to node positions.
+    let mut node_frames = HashMap::new();
 
-            pin_ui.set_clip_rect(snarl_clip_rect);
+    // Snapping node centers requires node frames.
+    let snap_center = config
+        .grid_snap
+        .is_some_and(|grid| grid.anchor == SnapAnchor::Center);
 
-            let r = pin_ui.interact(pin_rect, pin_ui.next_auto_id(), Sense::click_and_drag());
+    // Nodes follow the cursor freely and snap on release.
+    let snap_ghost = style.show_snap_ghost() && config.grid_snap.is_some();
 
-            pin_ui.skip_ahead_auto_ids(1);
+    let viewport_request = viewport::take_viewport_request(&ui, snarl_id, &config, &snarl_resp);
+    let mut requested_node_rect = None;
 
-            if r.clicked_by(config.click_pin.mouse_button) {
-                if snarl_state.has_new_wires() {
-                    snarl_state.remove_new_wire_out(out_pin.id);
-                } else {
-                    viewer.drop_outputs(out_pin, snarl);
-                    if !snarl.nodes.contains(node.0) {
-                        // If removed
-                        return;
-                    }
-                }
-            }
-            if r.drag_started_by(config.drag_pin.mouse_button) {
-                if modifiers.contains(config.drag_pin.modifiers) {
-                    snarl_state.start_new_wires_in(&out_pin.remotes);
+    // Opacity of nodes that are not fully opaque.
+    let mut node_opacity = HashMap::new();
 
-                    if !modifiers.contains(config.no_menu.modifiers) {
-                        snarl.drop_outputs(out_pin.id);
-                        if !snarl.nodes.contains(node.0) {
-                            // If removed
-                            return;
-                        }
-                    }
-                } else {
-                    snarl_state.start_new_wire_out(out_pin.id);
-                }
-            }
+    // Collapsed node hovered long enough is drawn expanded above other nodes.
+    let hover_expanded =
+        hover_expand::hover_expanded_node(&ui, snarl_id, &style, snarl, &mut snarl_state, time);
 
-            if r.drag_stopped() {
-                drag_released = true;
-            }
+    let draw_order = draw_order
+        .into_iter()
+        .filter(|&node| Some(node) != hover_expanded)
+        .chain(hover_expanded);
 
-            let mut visual_pin_rect = r.rect;
+    let node_detail = NodeDetail::load(ui.ctx(), snarl_id).update(&style, to_global.scaling);
+    node_detail.save(ui.ctx(), snarl_id);
 
-            if r.contains_pointer() {
-                // TODO: What is happening here?
-                if snarl_state.has_new_wires_out() {
-                    if modifiers.shift && !modifiers.command {
-                        snarl_state.add_new_wire_out(out_pin.id);
-                    }
-                    if !modifiers.shift && modifiers.command {
-                        snarl_state.remove_new_wire_out(out_pin.id);
-                    }
-                }
-                pin_hovered = Some(AnyPin::Out(out_pin.id));
-                visual_pin_rect = visual_pin_rect.scale_from_center(style.pin_hover_scale());
+    for node_idx in draw_order {
+        if !snarl.nodes.contains(node_idx.0) {
+            continue;
+        }
+
+        let opacity = viewer.node_opacity(node_idx, snarl).clamp(0.0, 1.0);
+        if opacity < 1.0 {
+            node_opacity.insert(node_idx, opacity);
+        }
+
+        let prev_opacity = ui.opacity();
+        ui.multiply_opacity(opacity);
+
+        let anchor = viewer.node_anchor(node_idx);
+        let response = match anchor {
+            NodeAnchor::World => draw_node(
+                snarl,
+                &mut ui,
+                node_idx,
+                viewer,
+                &mut snarl_state,
+                &mut config,
+                &style,
+                snarl_id,
+                &mut input_info,
+                input.modifiers,
+                &mut output_info,
+                hover_expanded == Some(node_idx),
+                node_detail,
+            ),
+            NodeAnchor::Screen(align) => draw_screen_node(
+                snarl,
+                &mut ui,
+                node_idx,
+                align,
+                ui_rect.intersect(clip_rect),
+                to_global,
+                viewer,
+                &mut snarl_state,
+                &mut config,
+                &style,
+                snarl_id,
+                &mut input_info,
+                input.modifiers,
+                &mut output_info,
+            ),
+        };
+
+        ui.set_opacity(prev_opacity);
+
+        if let Some(response) = response {
+            if let Some(v) = response.node_to_top {
+                node_to_top = Some(v);
+            }
+            if let Some(v) = response.node_moved {
+                node_moved = Some(v);
             }
+            if let Some(v) = response.node_drag_stopped {
+                node_drag_stopped = Some(v);
+            }
+            pin_candidates.extend(response.pin_hovered);
+            drag_released |= response.drag_released;
 
-            let wire_info =
-                snarl_pin.draw(style, pin_ui.style(), visual_pin_rect, pin_ui.painter());
+            if response.menu_passthrough {
+                // Open graph menu instead.
+                snarl_resp.flags.insert(Flags::LONG_TOUCHED);
+            }
 
-            output_positions.insert(
-                out_pin.id,
-                PinResponse {
-                    pos: r.rect.center(),
-                    wire_color: wire_info.color,
-                    wire_style: wire_info.style,
-                },
-            );
+            if anchor == NodeAnchor::World {
+                nodes_bb = nodes_bb.union(response.final_rect);
+            }
+            if anchor == NodeAnchor::World
+                && (config.bounds.is_some()
+                    || snap_ghost
+                    || snap_center
+                    || config.avoid_overlap_on_insert)
+            {
+                let pos = snarl.nodes[node_idx.0].pos;
+                node_frames.insert(
+                    node_idx,
+                    (response.final_rect.min - pos, response.final_rect.size()),
+                );
+            }
+            if let Some(
+                ViewportRequest::EnsureNodeVisible { node, .. }
+                | ViewportRequest::FocusNode { node },
+            ) = viewport_request
+                && node == node_idx
+            {
+                requested_node_rect = Some(response.final_rect);
+            }
+            hit_nodes.push((node_idx, response.final_rect, response.hit_shape));
+            if select_ended.is_some() {
+                node_rects.push((node_idx, response.final_rect));
+            }
+            for pin in response.in_pins {
+                input_pins.insert(pin.id, pin);
+            }
+            for pin in response.out_pins {
+                output_pins.insert(pin.id, pin);
+            }
+        }
+    }
 
-            new_heights.push(pin_ui.min_rect().height());
+    let pin_hovered = nearest_pin(&pin_candidates).map(|hovered| hovered.pin);
 
-            pin_ui.expand_to_include_y(outer_rect.bottom());
+    let mut hovered_wire = None;
+    let mut hovered_wire_disconnect = false;
+    let mut wire_shapes = Vec::new();
+    let mut wire_widgets = Vec::new();
+    let mut hit_wires = Vec::new();
+
+    let wire_offsets = if style.bundle_parallel_wires() {
+        bundle_wire_offsets(snarl, &output_info, &input_info, wire_width.max(1.0) * 3.0)
+    } else {
+        HashMap::new()
+    };
+
+    let (merged_wires, double_headed_wires) = if style.merge_bidirectional() {
+        bidirectional_wire_pairs(snarl, &output_info, &input_info)
+    } else {
+        (HashSet::new(), HashSet::new())
+    };
+
+    // Node dragged over wires to splice it into one on release.
+    let splice_node = config
+        .drop_node_on_wire
+        .then(|| node_moved.map(|(node, _, _)| node).or(node_drag_stopped))
+        .flatten();
+
+    // Wires behind nodes can't be hovered through them,
+    // wires above nodes are hovered anywhere.
+    let wires_covered = style.wire_layer() == WireLayer::BehindNodes
+        && latest_pos.is_some_and(|pos| {
+            hit_nodes
+                .iter()
+                .filter(|(node, _, _)| Some(*node) != splice_node)
+                .any(|(_, rect, shape)| shape.contains(*rect, pos))
         });
-    }
-    let final_rect = outputs_ui.min_rect();
-    node_ui.expand_to_include_rect(final_rect.intersect(payload_clip_rect));
 
-    DrawPinsResponse {
-        drag_released,
-        pin_hovered,
-        final_rect,
-        new_heights,
+    // Resolve wire paths first to find topmost hovered wire before drawing.
+    let mut wire_paths = Vec::new();
+    for wire in snarl.wires.iter() {
+        if merged_wires.contains(&wire) {
+            // Drawn as part of its reciprocal wire.
+            continue;
+        }
+        let Some(from_r) = output_info.get(&wire.out_pin) else {
+            continue;
+        };
+        let Some(to_r) = input_info.get(&wire.in_pin) else {
+            continue;
+        };
+        if !output_pins.contains_key(&wire.out_pin) || !input_pins.contains_key(&wire.in_pin) {
+            continue;
+        }
+
+        // Offset wire path when bundling parallel wires.
+        // Both hit-test and drawing use the offset path.
+        let offset = wire_offsets.get(&wire).copied().unwrap_or(Vec2::ZERO);
+        let attach = snarl.wires.attach(&wire);
+        let from_r = PinResponse {
+            pos: from_r.attach_pos(attach.out_pin) + offset,
+            ..*from_r
+        };
+        let to_r = PinResponse {
+            pos: to_r.attach_pos(attach.in_pin) + offset,
+            ..*to_r
+        };
+        wire_paths.push((wire, from_r, to_r));
     }
-}
 
-/// Draws input pins in a horizontal row (for vertical sandwich layouts).
-/// Pins are arranged left-to-right with pin icons at the edge and labels below/above.
-#[allow(clippy::too_many_arguments)]
-#[allow(clippy::too_many_lines)]
-fn draw_inputs_horizontal<T, V>(
-    snarl: &mut Snarl<T>,
-    viewer: &mut V,
-    node: NodeId,
-    inputs: &[InPin],
-    pin_size: f32,
-    style: &SnarlStyle,
-    node_ui: &mut Ui,
-    inputs_rect: Rect,
-    payload_clip_rect: Rect,
-    input_y: f32,
-    min_pin_x_left: f32,
-    min_pin_x_right: f32,
-    snarl_state: &mut SnarlState,
-    config: &mut SnarlConfig,
-    modifiers: Modifiers,
-    input_positions: &mut HashMap<InPinId, PinResponse>,
-    widths: Widths,
-    pins_at_top: bool,
-) -> DrawPinsHorizontalResponse
-where
-    V: SnarlViewer<T>,
-{
-    let mut drag_released = false;
-    let mut pin_hovered = None;
+    // Try to find hovered wire
+    // If not dragging new wire
+    // And not hovering over item above.
+    // Wires are drawn in order, so the last one hit is on top.
+    if !snarl_state.has_new_wires()
+        && snarl_resp.contains_pointer()
+        && !wires_covered
+        && let Some(latest_pos) = latest_pos
+        && let Some(&(wire, _, _)) = wire_paths.iter().rev().find(|(wire, from_r, to_r)| {
+            splice_node.is_none_or(|node| wire.out_pin.node != node && wire.in_pin.node != node)
+                && hit_wire(
+                    ui.ctx(),
+                    WireId::Connected {
+                        snarl_id,
+                        out_pin: wire.out_pin,
+                        in_pin: wire.in_pin,
+                    },
+                    wire_frame_size,
+                    style.upscale_wire_frame(),
+                    style.downscale_wire_frame(),
+                    style.bezier_tension(),
+                    from_r.pos,
+                    to_r.pos,
+                    latest_pos,
+                    wire_width.max(2.0),
+                    pick_wire_style(from_r.wire_style, to_r.wire_style),
+                    is_vertical_wire(from_r.pos, to_r.pos, from_r.vertical || to_r.vertical),
+                )
+        })
+    {
+        hovered_wire = Some(wire);
 
-    // Input pins in horizontal row
-    let mut inputs_ui = node_ui.new_child(
-        UiBuilder::new()
-            .max_rect(inputs_rect.round_ui())
-            .layout(Layout::left_to_right(Align::Center))
-            .id_salt("inputs_horizontal"),
-    );
+        let wire_r = ui.interact(snarl_resp.rect, ui.make_persistent_id(wire), Sense::click());
 
-    let snarl_clip_rect = node_ui.clip_rect();
-    inputs_ui.shrink_clip_rect(payload_clip_rect);
+        //Remove hovered wire by second click
+        hovered_wire_disconnect |=
+            !config.read_only && wire_r.clicked_by(config.remove_hovered_wire.mouse_button);
 
-    // Pin layout: vertical stack with pin icon at edge, label on other side
-    let pin_layout = if pins_at_top {
-        Layout::top_down(Align::Center) // Pin at top, label below
-    } else {
-        Layout::bottom_up(Align::Center) // Pin at bottom, label above
+        if config.select_wire.matches(input.modifiers)
+            && wire_r.clicked_by(config.select_wire.mouse_button)
+        {
+            snarl_state.toggle_wire_selection(wire.out_pin, wire.in_pin);
+        }
+    }
+
+    if let Some(wire) = hovered_wire {
+        let out_pin = OutPin::new(snarl, wire.out_pin);
+        let in_pin = InPin::new(snarl, wire.in_pin);
+        viewer.on_wire_hovered(&out_pin, &in_pin, snarl);
+    }
+
+    // Wires crossed by released cut line.
+    let cut_wires = match cut_line_ended {
+        Some(line) if !config.read_only => cut::crossed_wires(&ui, &style, &wire_paths, line),
+        _ => Vec::new(),
     };
 
-    let mut new_widths = SmallVec::with_capacity(inputs.len());
+    // Draw and interact with wires
+    for (wire, from_r, to_r) in &wire_paths {
+        let wire = *wire;
+        let Some(out_pin) = output_pins.get(&wire.out_pin) else {
+            continue;
+        };
+        let Some(in_pin) = input_pins.get(&wire.in_pin) else {
+            continue;
+        };
 
-    for in_pin in inputs {
-        let cursor = inputs_ui.cursor();
-        let (width, width_outer) = widths.get(in_pin.id.input);
+        let opacity = f32::min(
+            node_opacity.get(&wire.out_pin.node).copied().unwrap_or(1.0),
+            node_opacity.get(&wire.in_pin.node).copied().unwrap_or(1.0),
+        );
+        let custom_color = snarl.wires.data(&wire).and_then(|data| data.color);
+        let color = custom_color
+            .unwrap_or_else(|| mix_colors(from_r.wire_color, to_r.wire_color))
+            .gamma_multiply(opacity);
 
-        let margin = (width_outer - width) / 2.0;
-        let outer_rect = cursor.with_max_x(cursor.left() + width_outer);
-        let inner_rect = outer_rect.shrink2(vec2(margin, 0.0));
+        let mut draw_width = wire_width;
+        if hovered_wire == Some(wire) {
+            draw_width *= 1.5;
+        }
 
-        let builder = UiBuilder::new().layout(pin_layout).max_rect(inner_rect);
+        let selected = snarl_state.is_wire_selected(wire.out_pin, wire.in_pin);
+        let color = if selected {
+            draw_width *= 2.0;
+            style.select_style(ui.style()).stroke.color
+        } else {
+            color
+        };
 
-        inputs_ui.scope_builder(builder, |pin_ui| {
-            // Compute PinContext based on style.pin_label_visibility()
-            let is_connecting = snarl_state.has_new_wires();
-            let label_visible = match style.pin_label_visibility() {
-                PinLabelVisibility::Always => true,
-                PinLabelVisibility::OnHover => false,
-                PinLabelVisibility::WhileConnecting => is_connecting,
-                PinLabelVisibility::NearWire { .. } => is_connecting,
-            };
-            let pin_context = PinContext {
-                label_visible,
-                is_connecting,
-                is_hovered: false,
-                wire_near_node: is_connecting,
-            };
-            let snarl_pin = viewer.show_input(in_pin, pin_ui, pin_context, snarl);
-            if !snarl.nodes.contains(node.0) {
-                return;
+        let vertical_wire =
+            is_vertical_wire(from_r.pos, to_r.pos, from_r.vertical || to_r.vertical);
+
+        let first_shape = wire_shapes.len();
+        draw_wire(
+            &ui,
+            WireId::Connected {
+                snarl_id,
+                out_pin: wire.out_pin,
+                in_pin: wire.in_pin,
+            },
+            &mut wire_shapes,
+            wire_frame_size,
+            style.upscale_wire_frame(),
+            style.downscale_wire_frame(),
+            style.bezier_tension(),
+            from_r.pos,
+            to_r.pos,
+            Stroke::new(draw_width, color),
+            wire_threshold,
+            pick_wire_style(from_r.wire_style, to_r.wire_style),
+            vertical_wire,
+            style.long_wire_fade(),
+        );
+        if style.gradient_wires()
+            && custom_color.is_none()
+            && !selected
+            && from_r.wire_color != to_r.wire_color
+        {
+            for shape in &mut wire_shapes[first_shape..] {
+                gradient_wire(
+                    shape,
+                    from_r.pos,
+                    to_r.pos,
+                    color,
+                    from_r.wire_color.gamma_multiply(opacity),
+                    to_r.wire_color.gamma_multiply(opacity),
+                );
             }
+        }
+        if double_headed_wires.contains(&wire) {
+            let size = draw_width.max(1.0) * 4.0;
+            wire_shapes.push(wire_arrowhead(to_r, false, size, color));
+            wire_shapes.push(wire_arrowhead(from_r, true, size, color));
+        }
+        hit_wires.push(HitWire {
+            out_pin: wire.out_pin,
+            in_pin: wire.in_pin,
+            from: from_r.pos,
+            to: to_r.pos,
+            style: pick_wire_style(from_r.wire_style, to_r.wire_style),
+            vertical: vertical_wire,
+        });
+        if viewer.has_wire_widget(&wire.out_pin, &wire.in_pin, snarl) {
+            let center = Pos2::new(
+                f32::midpoint(from_r.pos.x, to_r.pos.x),
+                f32::midpoint(from_r.pos.y, to_r.pos.y),
+            );
+            let wire_x_length =
+                f32::max(from_r.pos.x, to_r.pos.x) - f32::min(from_r.pos.x, to_r.pos.x);
+            wire_widgets.push((out_pin, in_pin, center, wire_x_length));
+        }
+    }
 
-            // Use min_rect after content is shown to get actual content bounds.
-            let content_rect = pin_ui.min_rect();
-            let x0 = content_rect.min.x;
-            let x1 = content_rect.max.x;
+    let hovered_node = latest_pos
+        .filter(|_| snarl_resp.contains_pointer())
+        .and_then(|pos| {
+            hit_nodes
+                .iter()
+                .rev()
+                .find(|(_, rect, shape)| shape.contains(*rect, pos))
+        })
+        .map(|&(node, _, _)| node);
+    Hovered {
+        node: hovered_node,
+        pin: pin_hovered,
+    }
+    .save(ui.ctx(), snarl_id);
+
+    HitGeometry {
+        nodes: hit_nodes,
+        in_pins: input_info
+            .iter()
+            .map(|(&pin, r)| (pin, r.pos, r.hit_rect))
+            .collect(),
+        out_pins: output_info
+            .iter()
+            .map(|(&pin, r)| (pin, r.pos, r.hit_rect))
+            .collect(),
+        wires: hit_wires,
+        wire_frame_size,
+        upscale_wire_frame: style.upscale_wire_frame(),
+        downscale_wire_frame: style.downscale_wire_frame(),
+        bezier_tension: style.bezier_tension(),
+        wire_hit_threshold: wire_width.max(2.0),
+        wire_smoothness: wire_threshold,
+        wire_layer: style.wire_layer(),
+        to_global,
+    }
+    .save(ui.ctx(), snarl_id);
 
-            let pin_rect = snarl_pin.pin_rect_horizontal(
-                input_y,
-                min_pin_x_left.max(x0),
-                min_pin_x_right.max(x1),
-                pin_size,
-            );
+    // Remove hovered wire by second click
+    if hovered_wire_disconnect && let Some(wire) = hovered_wire {
+        let out_pin = OutPin::new(snarl, wire.out_pin);
+        let in_pin = InPin::new(snarl, wire.in_pin);
+        viewer.disconnect_with_source(&out_pin, &in_pin, EditSource::UserRemove, snarl);
+    }
 
-            // Interact with pin shape.
-            pin_ui.set_clip_rect(snarl_clip_rect);
+    for wire in cut_wires {
+        if snarl.nodes.contains(wire.out_pin.node.0) && snarl.nodes.contains(wire.in_pin.node.0) {
+            let out_pin = OutPin::new(snarl, wire.out_pin);
+            let in_pin = InPin::new(snarl, wire.in_pin);
+            viewer.disconnect_with_source(&out_pin, &in_pin, EditSource::UserRemove, snarl);
+        }
+    }
 
-            let r = pin_ui.interact(pin_rect, pin_ui.next_auto_id(), Sense::click_and_drag());
-            pin_ui.skip_ahead_auto_ids(1);
+    // Splice node released over the wire into it.
+    if let Some(node) = node_drag_stopped
+        && splice_node == Some(node)
+        && let Some(wire) = hovered_wire
+        && snarl.nodes.contains(node.0)
+        && let Some((input, output)) = viewer.splice_node(node, (wire.out_pin, wire.in_pin), snarl)
+        && snarl.nodes.contains(node.0)
+    {
+        let node_in = InPinId { node, input };
+        let node_out = OutPinId { node, output };
 
-            if r.clicked_by(config.click_pin.mouse_button) {
-                if snarl_state.has_new_wires() {
-                    snarl_state.remove_new_wire_in(in_pin.id);
-                } else {
-                    viewer.drop_inputs(in_pin, snarl);
-                    if !snarl.nodes.contains(node.0) {
-                        return;
-                    }
-                }
-            }
-            if r.drag_started_by(config.drag_pin.mouse_button) {
-                if modifiers.command {
-                    snarl_state.start_new_wires_out(&in_pin.remotes);
-                    if modifiers != config.drag_pin.modifiers {
-                        snarl.drop_inputs(in_pin.id);
-                        if !snarl.nodes.contains(node.0) {
-                            return;
+        let out_pin = OutPin::new(snarl, wire.out_pin);
+        let in_pin = InPin::new(snarl, wire.in_pin);
+        viewer.disconnect_with_source(&out_pin, &in_pin, EditSource::Splice, snarl);
+
+        if snarl.nodes.contains(wire.out_pin.node.0) && snarl.nodes.contains(node.0) {
+            let out_pin = OutPin::new(snarl, wire.out_pin);
+            let in_pin = InPin::new(snarl, node_in);
+            viewer.connect_with_source(&out_pin, &in_pin, EditSource::Splice, snarl);
+        }
+        if snarl.nodes.contains(node.0) && snarl.nodes.contains(wire.in_pin.node.0) {
+            let out_pin = OutPin::new(snarl, node_out);
+            let in_pin = InPin::new(snarl, wire.in_pin);
+            viewer.connect_with_source(&out_pin, &in_pin, EditSource::Splice, snarl);
+        }
+        snarl_state.request_repaint();
+    }
+
+    if let Some(gesture) = select_ended {
+        select::finish_selection(
+            gesture,
+            node_rects,
+            &style,
+            &config,
+            input.modifiers,
+            &mut snarl_state,
+        );
+    }
+
+    select::paint_select_gesture(&ui, &style, &snarl_state);
+    cut::paint_cut_line(&ui, &style, &snarl_state);
+
+    // If right button is clicked while new wire is being dragged, cancel it.
+    // This is to provide way to 'not open' the link graph node menu, but just
+    // releasing the new wire to empty space.
+    //
+    // This uses `button_down` directly, instead of `clicked_by` to improve
+    // responsiveness of the cancel action.
+    if snarl_state.has_new_wires()
+        && ui.input(|x| x.pointer.button_down(config.cancel_wire_drag.mouse_button))
+    {
+        let _ = snarl_state.take_new_wires();
+        snarl_resp.flags.remove(Flags::CLICKED);
+    }
+
+    if let Some(request) = viewport_request {
+        viewport::apply_viewport_request(
+            request,
+            requested_node_rect,
+            nodes_bb,
+            ui_rect,
+            to_global,
+            (min_scale, max_scale),
+            &style,
+            time,
+            &mut snarl_state,
+        );
+    }
+
+    // Do centering unless no nodes are present.
+    if style.centering() && snarl_resp.double_clicked() && nodes_bb.is_finite() {
+        let nodes_bb = nodes_bb.expand(100.0);
+        snarl_state.look_at(nodes_bb, ui_rect, min_scale, max_scale);
+    }
+
+    if config.deselect_all_nodes.matches(input.modifiers)
+        && snarl_resp.clicked_by(config.deselect_all_nodes.mouse_button)
+    {
+        snarl_state.deselect_all_nodes();
+        snarl_state.deselect_all_wires();
+    }
+
+    if snarl_resp.clicked()
+        && !snarl_state.has_new_wires()
+        && let Some(pos) = latest_pos
+    {
+        viewer.on_background_click(pos, snarl);
+    }
+
+    // Wire end position will be overridden when link graph menu is opened.
+    let mut wire_end_pos = latest_pos.unwrap_or(snarl_resp.rect.center());
+
+    if let Some(source) = snarl_state.new_wires().and_then(NewWires::first_pin)
+        && let Some(pos) = viewer.wire_drag_target(source, wire_end_pos, pin_hovered, snarl)
+    {
+        wire_end_pos = pos;
+    }
+
+    // Preview whether wire dragged over a pin would be connected.
+    if let Some(pin) = pin_hovered
+        && let Some(new_wires) = snarl_state.new_wires()
+    {
+        let target = match (new_wires, pin) {
+            (NewWires::In(in_pins), AnyPinId::Out(out_pin)) => output_info.get(&out_pin).map(|r| {
+                let valid = !is_pin_full(viewer, AnyPinId::Out(out_pin), snarl)
+                    && in_pins
+                        .iter()
+                        .all(|in_pin| viewer.validate_connection(&out_pin, in_pin, snarl));
+                (r.pos, valid)
+            }),
+            (NewWires::Out(out_pins), AnyPinId::In(in_pin)) => input_info.get(&in_pin).map(|r| {
+                let valid = !is_pin_full(viewer, AnyPinId::In(in_pin), snarl)
+                    && out_pins
+                        .iter()
+                        .all(|out_pin| viewer.validate_connection(out_pin, &in_pin, snarl));
+                (r.pos, valid)
+            }),
+            (new_wires, pin) if config.allow_same_kind_connections => {
+                let info = match pin {
+                    AnyPinId::In(in_pin) => input_info.get(&in_pin),
+                    AnyPinId::Out(out_pin) => output_info.get(&out_pin),
+                };
+                info.map(|r| {
+                    let valid = same_kind_sources(new_wires, pin).is_some_and(|sources| {
+                        sources
+                            .iter()
+                            .all(|&source| viewer.validate_same_kind_connection(source, pin, snarl))
+                    });
+                    (r.pos, valid)
+                })
+            }
+            _ => None,
+        };
+
+        if let Some((pos, valid)) = target {
+            let color = if valid {
+                style.valid_connection_color(ui.style())
+            } else {
+                ui.ctx().set_cursor_icon(CursorIcon::NotAllowed);
+                style.invalid_connection_color(ui.style())
+            };
+
+            let radius = style.pin_size(ui.style()) * style.pin_hover_scale() * 0.5;
+            ui.painter().circle_stroke(
+                pos,
+                radius + wire_width.max(1.0) * 2.0,
+                Stroke::new(wire_width.max(1.0) * 2.0, color),
+            );
+        }
+    }
+
+    if pull::pulled_wires_dropped(&ui, &config, wires_pulled) {
+        drag_released = true;
+    }
+
+    if drag_released {
+        let source_rail = snarl_state.new_wires_rail();
+        let new_wires = snarl_state.take_new_wires();
+        if new_wires.is_some() {
+            snarl_state.request_repaint();
+        }
+        let mut port_choices = Vec::new();
+        let drop_target = |pin: AnyPinId| {
+            let rail = match pin {
+                AnyPinId::In(in_pin) => input_info.get(&in_pin),
+                AnyPinId::Out(out_pin) => output_info.get(&out_pin),
+            };
+            let target_rail = rail.zip(latest_pos).and_then(|(r, pos)| r.rail_t(pos));
+            (pin, target_rail)
+        };
+        let ties = match &new_wires {
+            Some(new_wires) if style.disambiguate_pin_drop() => pin_ties(
+                &pin_candidates,
+                new_wires,
+                config.allow_same_kind_connections,
+            ),
+            _ => Vec::new(),
+        };
+        match (new_wires, pin_hovered) {
+            (Some(new_wires), Some(_)) if ties.len() > 1 => {
+                // Equally close pins are picked by user.
+                if let Some(pos) = latest_pos {
+                    PinDropChoice {
+                        new_wires,
+                        source_rail,
+                        pins: ties.into_iter().map(drop_target).collect(),
+                        pos,
+                        fresh: true,
+                    }
+                    .save(ui.ctx(), snarl_id);
+                }
+            }
+            (Some(new_wires), Some(pin)) => {
+                let (pin, target_rail) = drop_target(pin);
+                port_choices = connect_dropped_wires(
+                    new_wires,
+                    pin,
+                    source_rail,
+                    target_rail,
+                    &config,
+                    viewer,
+                    snarl,
+                );
+            }
+            (Some(new_wires), None) if snarl_resp.hovered() => {
+                match config.wire_drop_on_empty {
+                    WireDropAction::Menu => {
+                        let pins = match &new_wires {
+                            NewWires::In(x) => AnyPins::In(x),
+                            NewWires::Out(x) => AnyPins::Out(x),
+                        };
+
+                        if viewer.has_dropped_wire_menu(pins, snarl)
+                            || viewer.has_graph_menu_with_source(
+                                wire_end_pos,
+                                new_wires.first_pin(),
+                                snarl,
+                            )
+                        {
+                            // A wire is dropped without connecting to a pin.
+                            // Show context menu for the wire drop.
+                            if config.menu_open_delay > 0.0
+                                && let Some(pos) = ui.input(|i| i.pointer.latest_pos())
+                            {
+                                // Wait for the pointer to settle before opening the menu.
+                                snarl_state.set_new_wires_menu_pending(new_wires, pos, time);
+                            } else {
+                                snarl_state.set_new_wires_menu(new_wires);
+
+                                // Force open context menu.
+                                snarl_resp.flags.insert(Flags::LONG_TOUCHED);
+                            }
+                        }
+                    }
+                    WireDropAction::CreateNode => {
+                        if let Some(source) = new_wires.first_pin() {
+                            if nodes_capped(&config, snarl) {
+                                viewer.on_capacity_reached(CapacityLimit::Nodes, snarl);
+                            } else {
+                                let guard = CapacityGuard::new(&config, snarl);
+                                let overlap = OverlapGuard::new(&config, snarl);
+                                viewer.create_node_from_pin(source, wire_end_pos, snarl);
+                                guard.enforce(&config, viewer, snarl);
+                                overlap.nudge(&node_frames, viewer, snarl);
+                            }
                         }
                     }
+                    WireDropAction::Cancel => {}
+                }
+            }
+            _ => {}
+        }
+
+        if !port_choices.is_empty()
+            && let Some(pos) = latest_pos
+        {
+            PortChoice {
+                wires: port_choices,
+                pos,
+                fresh: true,
+            }
+            .save(ui.ctx(), snarl_id);
+        }
+    }
+
+    // If right button is clicked while new wire is being dragged, cancel it.
+    // This is to provide way to 'not open' the link graph node menu, but just
+    // releasing the new wire to empty space.
+    //
+    // This uses `button_down` directly, instead of `clicked_by` to improve
+    // responsiveness of the cancel action.
+    if snarl_state.has_new_wires() && ui.input(|x| x.pointer.button_down(PointerButton::Secondary))
+    {
+        let _ = snarl_state.take_new_wires();
+    }
+
+    // Wires pulled or armed by click wait for next click, so Escape cancels them.
+    pull::cancel_pulled_wires(input.escape_pressed, &mut snarl_state);
+
+    if input.modifiers.command || input.escape_pressed {
+        snarl_state.deselect_all_nodes();
+        snarl_state.deselect_all_wires();
+    }
+
+    if let Some((pending_pos, since)) = snarl_state.new_wires_menu_pending() {
+        let settled = ui
+            .input(|i| i.pointer.latest_pos())
+            .is_some_and(|pos| pos.distance(pending_pos) <= ui.style().interaction.interact_radius);
+
+        if !settled {
+            // Pointer moved away, cancel the menu.
+            snarl_state.finish_new_wires_menu_pending(true);
+        } else if time - since >= f64::from(config.menu_open_delay) {
+            snarl_state.finish_new_wires_menu_pending(false);
+
+            // Force open context menu.
+            snarl_resp.flags.insert(Flags::LONG_TOUCHED);
+        } else {
+            #[allow(clippy::cast_possible_truncation)]
+            let remaining = (f64::from(config.menu_open_delay) - (time - since)) as f32;
+            ui.ctx()
+                .request_repaint_after(std::time::Duration::from_secs_f32(remaining));
+        }
+    }
+
+    if !config.read_only
+        && snarl_state.new_wires_menu_pending().is_none()
+        && let Some(interact_pos) = ui.ctx().input(|i| i.pointer.interact_pos())
+    {
+        if let Some(new_wires) = snarl_state.take_new_wires_menu() {
+            let pins = match &new_wires {
+                NewWires::In(x) => AnyPins::In(x),
+                NewWires::Out(x) => AnyPins::Out(x),
+            };
+
+            let source = new_wires.first_pin();
+            let dropped_wire_menu = viewer.has_dropped_wire_menu(pins, snarl);
+
+            if dropped_wire_menu
+                || viewer.has_graph_menu_with_source(from_global * interact_pos, source, snarl)
+            {
+                snarl_resp.context_menu(|ui| {
+                    let pins = match &new_wires {
+                        NewWires::In(x) => AnyPins::In(x),
+                        NewWires::Out(x) => AnyPins::Out(x),
+                    };
+
+                    let menu_pos = from_global * ui.cursor().min;
+
+                    // Override wire end position when the wire-drop context menu is opened.
+                    wire_end_pos = menu_pos;
+
+                    // The context menu is opened as *link* graph menu.
+                    let guard = CapacityGuard::new(&config, snarl);
+                    let overlap = OverlapGuard::new(&config, snarl);
+                    if dropped_wire_menu {
+                        viewer.show_dropped_wire_menu(menu_pos, ui, pins, snarl);
+                    } else {
+                        viewer.show_graph_menu_with_source(menu_pos, ui, source, snarl);
+                    }
+                    guard.enforce(&config, viewer, snarl);
+                    overlap.nudge(&node_frames, viewer, snarl);
+
+                    // Even though menu could be closed in `show_dropped_wire_menu`,
+                    // we need to revert the new wires here, because menu state is inaccessible.
+                    // Next frame context menu won't be shown and wires will be removed.
+                    snarl_state.set_new_wires_menu(new_wires);
+                });
+            }
+        } else {
+            // Remember where the menu was opened in graph space,
+            // so the menu can place nodes exactly at the click point.
+            let menu_pos_id = snarl_id.with("graph-menu-pos");
+            if snarl_resp.secondary_clicked() || snarl_resp.long_touched() {
+                ui.ctx()
+                    .data_mut(|d| d.insert_temp(menu_pos_id, from_global * interact_pos));
+            }
+            let click_pos = ui.ctx().data(|d| d.get_temp::<Pos2>(menu_pos_id));
+
+            if viewer.has_graph_menu_with_source(
+                click_pos.unwrap_or(from_global * interact_pos),
+                None,
+                snarl,
+            ) {
+                snarl_resp.context_menu(|ui| {
+                    let menu_pos = click_pos.unwrap_or(from_global * ui.cursor().min);
+
+                    let guard = CapacityGuard::new(&config, snarl);
+                    let overlap = OverlapGuard::new(&config, snarl);
+                    viewer.show_graph_menu_with_source(menu_pos, ui, None, snarl);
+                    guard.enforce(&config, viewer, snarl);
+                    overlap.nudge(&node_frames, viewer, snarl);
+                });
+            }
+        }
+    }
+
+    show_pin_drop_choice(snarl_id, &config, to_global, viewer, &ui, snarl);
+    show_port_choice(snarl_id, &config, to_global, viewer, &ui, snarl);
+
+    let interaction = if snarl_state.has_new_wires() {
+        Interaction::DraggingWire
+    } else if snarl_state.is_rect_selection() {
+        Interaction::RubberBandSelect
+    } else if snarl_state.is_lasso_selection() {
+        Interaction::LassoSelect
+    } else if snarl_state.is_cut_line() {
+        Interaction::CuttingWires
+    } else if let Some((node, _, _)) = node_moved {
+        Interaction::DraggingNode(node)
+    } else if snarl_resp.dragged() && !config.viewport_locked {
+        Interaction::PanningCanvas
+    } else {
+        Interaction::Idle
+    };
+    interaction.save(ui.ctx(), snarl_id);
+
+    InProgressWire::save(
+        snarl_state
+            .new_wires()
+            .map(|new_wires| InProgressWire::new(new_wires, wire_end_pos)),
+        ui.ctx(),
+        snarl_id,
+    );
+
+    match snarl_state.new_wires() {
+        None => {}
+        Some(NewWires::In(in_pins)) => {
+            for &in_pin in in_pins {
+                let from_pos = wire_end_pos;
+                let to_r = &input_info[&in_pin];
+                let to_r = &PinResponse {
+                    pos: to_r.attach_pos(snarl_state.new_wires_rail()),
+                    ..*to_r
+                };
+
+                let vertical_wire = is_vertical_wire(from_pos, to_r.pos, to_r.vertical);
+
+                draw_wire(
+                    &ui,
+                    WireId::NewInput { snarl_id, in_pin },
+                    &mut wire_shapes,
+                    wire_frame_size,
+                    style.upscale_wire_frame(),
+                    style.downscale_wire_frame(),
+                    style.bezier_tension(),
+                    from_pos,
+                    to_r.pos,
+                    Stroke::new(wire_width, to_r.wire_color),
+                    wire_threshold,
+                    style.preview_wire_style().unwrap_or(to_r.wire_style),
+                    vertical_wire,
+                    None,
+                );
+            }
+        }
+        Some(NewWires::Out(out_pins)) => {
+            for &out_pin in out_pins {
+                let from_r = &output_info[&out_pin];
+                let from_r = &PinResponse {
+                    pos: from_r.attach_pos(snarl_state.new_wires_rail()),
+                    ..*from_r
+                };
+                let to_pos = wire_end_pos;
+
+                let vertical_wire = is_vertical_wire(from_r.pos, to_pos, from_r.vertical);
+
+                draw_wire(
+                    &ui,
+                    WireId::NewOutput { snarl_id, out_pin },
+                    &mut wire_shapes,
+                    wire_frame_size,
+                    style.upscale_wire_frame(),
+                    style.downscale_wire_frame(),
+                    style.bezier_tension(),
+                    from_r.pos,
+                    to_pos,
+                    Stroke::new(wire_width, from_r.wire_color),
+                    wire_threshold,
+                    style.preview_wire_style().unwrap_or(from_r.wire_style),
+                    vertical_wire,
+                    None,
+                );
+            }
+        }
+    }
+
+    match wire_shape_idx {
+        None => {
+            ui.painter().add(Shape::Vec(wire_shapes));
+        }
+        Some(idx) => {
+            ui.painter().set(idx, Shape::Vec(wire_shapes));
+        }
+    }
+
+    for (out_pin, in_pin, center, wire_x_length) in wire_widgets {
+        let id = Id::new("wire-widget").with((out_pin.id, in_pin.id));
+        let cached = ui
+            .ctx()
+            .memory(|mem| mem.data.get_temp::<WireWidgetCache>(id));
+        let widget_rect = cached.map_or_else(
+            || {
+                RectAlign {
+                    parent: Align2::CENTER_CENTER,
+                    child: style.wire_widget_align.unwrap_or(Align2::CENTER_CENTER),
+                }
+                .align_rect(
+                    &Rect::from_center_size(center, [wire_x_length, 0.0].into()),
+                    Vec2::new(wire_x_length, 0.0),
+                    style.wire_widget_gap.unwrap_or(0.0),
+                )
+            },
+            |cached| {
+                #[allow(clippy::float_cmp)]
+                if cached.wire_len == wire_x_length {
+                    cached.widget_rect
                 } else {
-                    snarl_state.start_new_wire_in(in_pin.id);
+                    // calculate a new rect if the wire length has been changed due to user moving
+                    // the nodes connected by the wire
+
+                    RectAlign {
+                        parent: Align2::CENTER_CENTER,
+                        child: style.wire_widget_align.unwrap_or(Align2::CENTER_CENTER),
+                    }
+                    .align_rect(
+                        &Rect::from_center_size(center, [wire_x_length, 0.0].into()),
+                        Vec2::new(wire_x_length, 0.0),
+                        style.wire_widget_gap.unwrap_or(0.0),
+                    )
+                }
+            },
+        );
+        let mut wire_ui = ui.new_child(
+            UiBuilder::new()
+                .max_rect(widget_rect)
+                .layout(Layout::default())
+                .id_salt(id),
+        );
+        viewer.show_wire_widget(out_pin, in_pin, &mut wire_ui, snarl);
+        ui.ctx().memory_mut(|mem| {
+            mem.data.insert_temp(
+                id,
+                WireWidgetCache {
+                    widget_rect: Rect::from_center_size(
+                        center,
+                        Vec2::new(wire_ui.min_rect().width(), wire_ui.min_rect().height()),
+                    ),
+                    wire_len: wire_x_length,
+                },
+            );
+        });
+    }
+
+    ui.advance_cursor_after_rect(Rect::from_min_size(snarl_resp.rect.min, Vec2::ZERO));
+
+    if let Some(node) = node_to_top
+        && snarl.nodes.contains(node.0)
+    {
+        snarl_state.node_to_top(node);
+    }
+
+    if config.show_grid_while_dragging {
+        let node_dragging = node_moved.is_some();
+        let was_dragging = ui.data(|d| d.get_temp::<bool>(node_dragging_id).unwrap_or(false));
+        if node_dragging != was_dragging {
+            ui.data_mut(|d| d.insert_temp(node_dragging_id, node_dragging));
+            snarl_state.request_repaint();
+        }
+    }
+
+    // Whether the current node drag moves downstream subtree.
+    let drag_subtree_id = snarl_id.with("drag-subtree");
+    let mut drag_subtree = ui.data(|d| d.get_temp::<bool>(drag_subtree_id).unwrap_or(false));
+
+    if let Some((_, _, true)) = node_moved {
+        drag_subtree = config
+            .drag_subtree_modifier
+            .is_some_and(|m| !m.is_none() && input.modifiers.contains(m));
+        ui.data_mut(|d| d.insert_temp(drag_subtree_id, drag_subtree));
+    }
+
+    if drag_subtree
+        && let Some((node, delta, _)) = node_moved
+        && snarl.nodes.contains(node.0)
+    {
+        snarl_state.request_repaint();
+
+        // Snap dragged node once and translate the whole subtree uniformly.
+        let pos = snarl.nodes[node.0].pos;
+        let mut target = pos + delta;
+        if let Some(ref grid) = config.grid_snap {
+            target = snap_node(grid, &node_frames, node, target);
+        }
+        let delta = target - pos;
+
+        if delta != Vec2::ZERO {
+            for node_id in downstream_nodes(snarl, node) {
+                let node_data = &mut snarl.nodes[node_id.0];
+                node_data.pos += delta;
+                node_data.pos =
+                    clamp_to_bounds(config.bounds, &node_frames, node_id, node_data.pos);
+                let new_pos = node_data.pos;
+                viewer.node_moved(node_id, new_pos, snarl);
+            }
+        }
+    } else if let Some((node, delta, _)) = node_moved
+        && snarl.nodes.contains(node.0)
+    {
+        snarl_state.request_repaint();
+
+        // Helper to snap position to grid if enabled
+        let snap_to_grid = |node_id: NodeId, pos: Pos2| -> Pos2 {
+            match config.grid_snap {
+                Some(ref grid) if !snap_ghost => snap_node(grid, &node_frames, node_id, pos),
+                _ => pos,
+            }
+        };
+
+        let moved_nodes: SmallVec<[NodeId; 8]> = if snarl_state.selected_nodes().contains(&node) {
+            snarl_state.selected_nodes().into()
+        } else {
+            smallvec::smallvec![node]
+        };
+
+        for node_id in moved_nodes {
+            let node_data = &mut snarl.nodes[node_id.0];
+            node_data.pos += delta;
+            node_data.pos = snap_to_grid(node_id, node_data.pos);
+            node_data.pos = clamp_to_bounds(config.bounds, &node_frames, node_id, node_data.pos);
+            let new_pos = node_data.pos;
+            viewer.node_moved(node_id, new_pos, snarl);
+
+            if snap_ghost
+                && let Some(ref grid) = config.grid_snap
+                && let Some(&(offset, size)) = node_frames.get(&node_id)
+            {
+                // Draw ghost of the node at the position it snaps to on release.
+                let snapped = clamp_to_bounds(
+                    config.bounds,
+                    &node_frames,
+                    node_id,
+                    snap_node(grid, &node_frames, node_id, new_pos),
+                );
+                let ghost = Rect::from_min_size(snapped + offset, size);
+                ui.painter().rect(
+                    ghost,
+                    style.node_frame(ui.style()).corner_radius,
+                    style.select_fill(ui.style()),
+                    style.select_stroke(ui.style()),
+                    StrokeKind::Inside,
+                );
+            }
+        }
+    }
+
+    // Pins of the dropped node to snap to pins of other nodes.
+    let magnet = config
+        .pin_magnet_wiring
+        .zip(node_drag_stopped)
+        .filter(|&(_, node)| snarl.nodes.contains(node.0))
+        .and_then(|(threshold, node)| {
+            let moving = if snarl_state.selected_nodes().contains(&node) {
+                snarl_state.selected_nodes()
+            } else {
+                std::slice::from_ref(&node)
+            };
+            magnet_pins(
+                node,
+                moving,
+                threshold,
+                &input_info,
+                &output_info,
+                viewer,
+                snarl,
+            )
+        });
+
+    // Commit snapped positions when node drag ends.
+    if snap_ghost
+        && !drag_subtree
+        && magnet.is_none()
+        && let Some(node) = node_drag_stopped
+        && let Some(ref grid) = config.grid_snap
+        && snarl.nodes.contains(node.0)
+    {
+        let snapped_nodes: SmallVec<[NodeId; 8]> = if snarl_state.selected_nodes().contains(&node) {
+            snarl_state.selected_nodes().into()
+        } else {
+            smallvec::smallvec![node]
+        };
+
+        for node_id in snapped_nodes {
+            let node_data = &mut snarl.nodes[node_id.0];
+            let new_pos = clamp_to_bounds(
+                config.bounds,
+                &node_frames,
+                node_id,
+                snap_node(grid, &node_frames, node_id, node_data.pos),
+            );
+            if new_pos != node_data.pos {
+                node_data.pos = new_pos;
+                viewer.node_moved(node_id, new_pos, snarl);
+            }
+        }
+        snarl_state.request_repaint();
+    }
+
+    // Move nodes dropped onto occupied grid cells to the nearest free ones.
+    if config.grid_no_overlap
+        && !drag_subtree
+        && magnet.is_none()
+        && let Some(node) = node_drag_stopped
+        && let Some(ref grid) = config.grid_snap
+        && snarl.nodes.contains(node.0)
+    {
+        let dropped_nodes: SmallVec<[NodeId; 8]> = if snarl_state.selected_nodes().contains(&node) {
+            snarl_state.selected_nodes().into()
+        } else {
+            smallvec::smallvec![node]
+        };
+
+        let mut occupied = snarl
+            .nodes
+            .iter()
+            .map(|(idx, node)| (NodeId(idx), node.pos))
+            .filter(|(node_id, _)| !dropped_nodes.contains(node_id))
+            .map(|(node_id, pos)| node_cell(grid, &node_frames, node_id, pos))
+            .collect::<HashSet<_>>();
+
+        for node_id in dropped_nodes {
+            let pos = snarl.nodes[node_id.0].pos;
+            let cell = node_cell(grid, &node_frames, node_id, pos);
+            let Some(free) = grid.nearest_free_cell(cell, |cell| !occupied.contains(&cell)) else {
+                continue;
+            };
+            occupied.insert(free);
+
+            if free != cell {
+                let new_pos = grid.cell_pos(free) - snap_anchor(grid, &node_frames, node_id);
+                let new_pos = clamp_to_bounds(config.bounds, &node_frames, node_id, new_pos);
+                snarl.nodes[node_id.0].pos = new_pos;
+                viewer.node_moved(node_id, new_pos, snarl);
+            }
+        }
+        snarl_state.request_repaint();
+    }
+
+    // Move dropped nodes to make pins coincide and connect them.
+    if let Some(node) = node_drag_stopped
+        && let Some((out_pin, in_pin, delta)) = magnet
+    {
+        let dropped_nodes: SmallVec<[NodeId; 8]> = if snarl_state.selected_nodes().contains(&node) {
+            snarl_state.selected_nodes().into()
+        } else {
+            smallvec::smallvec![node]
+        };
+
+        for node_id in dropped_nodes {
+            if let Some(node_data) = snarl.nodes.get_mut(node_id.0) {
+                node_data.pos += delta;
+                let new_pos = node_data.pos;
+                viewer.node_moved(node_id, new_pos, snarl);
+            }
+        }
+
+        if wires_capped(&config, snarl) {
+            viewer.on_capacity_reached(CapacityLimit::Wires, snarl);
+        } else if snarl.nodes.contains(out_pin.node.0) && snarl.nodes.contains(in_pin.node.0) {
+            let out_pin = OutPin::new(snarl, out_pin);
+            let in_pin = InPin::new(snarl, in_pin);
+            viewer.connect_with_source(&out_pin, &in_pin, EditSource::UserDrag, snarl);
+        }
+        snarl_state.request_repaint();
+    }
+
+    // Keep nodes positioned programmatically inside the bounds.
+    if config.bounds.is_some() {
+        for &node in node_frames.keys() {
+            if let Some(node_data) = snarl.nodes.get_mut(node.0) {
+                let pos = clamp_to_bounds(config.bounds, &node_frames, node, node_data.pos);
+                if pos != node_data.pos {
+                    node_data.pos = pos;
+                    snarl_state.request_repaint();
+                }
+            }
+        }
+    }
+
+    // Draw foreground elements (comments, annotations, overlays)
+    viewer.draw_foreground(&viewport, &style, ui.style(), ui.painter(), snarl);
+
+    if snarl.nodes.is_empty() {
+        // Separate layer above the graph without its transform.
+        let empty_layer_id = LayerId::new(snarl_layer_id.order, snarl_id.with("empty"));
+        ui.ctx().set_sublayer(snarl_layer_id, empty_layer_id);
+
+        let mut empty_ui = ui.new_child(
+            UiBuilder::new()
+                .layer_id(empty_layer_id)
+                .max_rect(ui_rect)
+                .layout(Layout::centered_and_justified(egui::Direction::TopDown)),
+        );
+        empty_ui.set_clip_rect(ui_rect.intersect(clip_rect));
+        viewer.show_empty(&mut empty_ui, snarl);
+    }
+
+    // Separate layer above everything drawn by the widget.
+    let overlay_layer_id = LayerId::new(snarl_layer_id.order, snarl_id.with("overlay"));
+    ui.ctx().set_sublayer(snarl_layer_id, overlay_layer_id);
+    let overlay_painter = ui
+        .ctx()
+        .layer_painter(overlay_layer_id)
+        .with_clip_rect(ui_rect.intersect(clip_rect));
+    viewer.draw_overlay(&overlay_painter, to_global, snarl);
+
+    if let Some(shortcut) = config.delete_selection
+        && !config.read_only
+        && snarl_resp.contains_pointer()
+        && !ui.ctx().wants_keyboard_input()
+        && (!snarl_state.selected_nodes().is_empty() || !snarl_state.selected_wires().is_empty())
+        && ui.input_mut(|i| i.consume_shortcut(&shortcut))
+    {
+        let nodes = snarl_state.selected_nodes().to_vec();
+
+        // Selected wires are kept too if the viewer refuses to delete nodes.
+        if nodes.is_empty() || viewer.confirm_delete_nodes(&nodes, snarl) {
+            let wires = snarl_state.selected_wires().to_vec();
+            if !wires.is_empty() {
+                for (out_pin, in_pin) in wires {
+                    let out_pin = OutPin::new(snarl, out_pin);
+                    let in_pin = InPin::new(snarl, in_pin);
+                    viewer.disconnect_with_source(&out_pin, &in_pin, EditSource::UserRemove, snarl);
+                }
+                snarl_state.deselect_all_wires();
+            }
+
+            if !nodes.is_empty() {
+                let bridges = if config.remove_node_reconnect {
+                    removal_bridges(&nodes, snarl)
+                } else {
+                    Vec::new()
+                };
+
+                let removed = snarl.remove_nodes(nodes);
+                if !removed.is_empty() {
+                    snarl_state.deselect_all_nodes();
+                    viewer.on_nodes_removed(removed, snarl);
+
+                    for (out_pin, in_pin) in bridges {
+                        if wires_capped(&config, snarl) {
+                            viewer.on_capacity_reached(CapacityLimit::Wires, snarl);
+                            break;
+                        }
+                        if snarl.nodes.contains(out_pin.node.0)
+                            && snarl.nodes.contains(in_pin.node.0)
+                        {
+                            let out_pin = OutPin::new(snarl, out_pin);
+                            let in_pin = InPin::new(snarl, in_pin);
+                            viewer.connect_with_source(
+                                &out_pin,
+                                &in_pin,
+                                EditSource::Reconnect,
+                                snarl,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let selection_after = snarl_state
+        .selected_nodes()
+        .iter()
+        .copied()
+        .filter(|node| snarl.nodes.contains(node.0))
+        .collect::<HashSet<_>>();
+
+    if selection_after != selection_before {
+        viewer.on_selection_changed(&selection_after, snarl);
+    }
+
+    if snarl_state.to_global() != to_global_before {
+        viewer.on_viewport_changed(snarl_state.to_global(), snarl);
+    }
+
+    snarl.set_draw_order(snarl_state.draw_order());
+
+    snarl_state.store(snarl, ui.ctx());
+
+    snarl_resp
+}
+
+/// Returns interactive area of the pin.
+///
+/// Pin rect is expanded to `hit_radius` around the pin,
+/// but not past the pin's `slot` along the column of pins,
+/// so overlapping hit areas of neighbouring pins are split between them
+/// and the pin with nearest center is picked.
+fn pin_hit_rect(
+    pin_rect: Rect,
+    pin_size: f32,
+    hit_radius: f32,
+    slot: Rect,
+    horizontal: bool,
+) -> Rect {
+    let mut rect = pin_rect.expand(hit_radius - pin_size * 0.5);
+    let slot = slot.union(pin_rect);
+    if horizontal {
+        rect.min.x = rect.min.x.max(slot.min.x);
+        rect.max.x = rect.max.x.min(slot.max.x);
+    } else {
+        rect.min.y = rect.min.y.max(slot.min.y);
+        rect.max.y = rect.max.y.min(slot.max.y);
+    }
+    rect
+}
+
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::too_many_lines)]
+fn draw_inputs<T, V>(
+    snarl: &mut Snarl<T>,
+    viewer: &mut V,
+    node: NodeId,
+    inputs: &[InPin],
+    pin_size: f32,
+    style: &SnarlStyle,
+    node_ui: &mut Ui,
+    inputs_rect: Rect,
+    payload_clip_rect: Rect,
+    input_x: f32,
+    min_pin_y_top: f32,
+    min_pin_y_bottom: f32,
+    input_spacing: Option<f32>,
+    snarl_state: &mut SnarlState,
+    config: &mut SnarlConfig,
+    modifiers: Modifiers,
+    input_positions: &mut HashMap<InPinId, PinResponse>,
+    heights: Heights,
+) -> DrawPinsResponse
+where
+    V: SnarlViewer<T>,
+{
+    let mut drag_released = false;
+    let mut pin_hovered = None;
+    let pin_hit_radius = style.pin_hit_radius(node_ui.style());
+
+    // Input pins on the left.
+    let mut inputs_ui = node_ui.new_child(
+        UiBuilder::new()
+            .max_rect(inputs_rect.round_ui())
+            .layout(Layout::top_down(Align::Min))
+            .id_salt("inputs"),
+    );
+
+    let snarl_clip_rect = node_ui.clip_rect();
+    inputs_ui.shrink_clip_rect(payload_clip_rect);
+
+    let pin_layout = match viewer.pin_layout(node) {
+        PinLayout::Stacked => Layout::left_to_right(Align::Min),
+        PinLayout::Inline => Layout::left_to_right(Align::Center),
+    };
+    let mut new_heights = SmallVec::with_capacity(inputs.len());
+
+    // Rows are spaced by the style, pin contents keep `Ui` spacing.
+    let item_spacing_y = inputs_ui.spacing().item_spacing.y;
+    inputs_ui.spacing_mut().item_spacing.y = style.pin_row_spacing(inputs_ui.style());
+
+    let groups = viewer.input_groups(node, snarl);
+    let mut collapsed_group = None;
+
+    for in_pin in inputs {
+        let idx = in_pin.id.input;
+        if let Some((label, range)) = groups.iter().find(|(_, range)| range.start == idx) {
+            let id = inputs_ui.id().with(("pin-group", idx));
+            let (open, y) = draw_pin_group_header(&mut inputs_ui, id, label, pin_layout);
+            collapsed_group = (!open).then(|| (range.clone(), y));
+        }
+
+        if let Some((range, y)) = &collapsed_group
+            && range.contains(&idx)
+        {
+            let pin = AnyPinId::In(in_pin.id);
+            let r = collapsed_pin_response(style, viewer, pin, pos2(input_x, *y), snarl, node_ui);
+            input_positions.insert(in_pin.id, r);
+            new_heights.push(0.0);
+            continue;
+        }
+
+        // Show input pin.
+        let cursor = inputs_ui.cursor();
+        let (height, height_outer) = heights.get(in_pin.id.input);
+
+        let margin = (height_outer - height) / 2.0;
+        let outer_rect = cursor.with_max_y(cursor.top() + height_outer);
+        let inner_rect = outer_rect.shrink2(vec2(0.0, margin));
+
+        let builder = UiBuilder::new().layout(pin_layout).max_rect(inner_rect);
+
+        inputs_ui.scope_builder(builder, |pin_ui| {
+            pin_ui.spacing_mut().item_spacing.y = item_spacing_y;
+
+            if let Some(input_spacing) = input_spacing {
+                let min = pin_ui.next_widget_position();
+                pin_ui.advance_cursor_after_rect(Rect::from_min_size(
+                    min,
+                    vec2(input_spacing, pin_size),
+                ));
+            }
+
+            // Compute PinContext based on style.pin_label_visibility()
+            let is_connecting = snarl_state.has_new_wires();
+            let label_visible = match style.pin_label_visibility() {
+                PinLabelVisibility::Always => true,
+                PinLabelVisibility::OnHover => false, // Will show via hover tooltip instead
+                // Simplified: `NearWire` shows all labels when connecting.
+                PinLabelVisibility::WhileConnecting | PinLabelVisibility::NearWire { .. } => {
+                    is_connecting
+                }
+            };
+            let pin_context = PinContext {
+                label_visible,
+                is_connecting,
+                is_hovered: false,             // Updated after interaction
+                wire_near_node: is_connecting, // Simplified for now
+            };
+            let snarl_pin = viewer.show_input(in_pin, pin_ui, pin_context, snarl);
+            if !snarl.nodes.contains(node.0) {
+                // If removed
+                return;
+            }
+
+            // Use min_rect after content is shown to get actual content bounds.
+            // This ensures pin is aligned with actual content, not pre-allocated space.
+            let content_rect = pin_ui.min_rect();
+            let y0 = content_rect.min.y;
+            let y1 = content_rect.max.y;
+
+            let pin_rect = if snarl_pin.is_rail() {
+                Rail::pin_rect(input_x, y0, y1, pin_size, false)
+            } else {
+                snarl_pin.pin_rect(
+                    input_x,
+                    min_pin_y_top.max(y0),
+                    min_pin_y_bottom.max(y1),
+                    pin_size,
+                )
+            };
+            let rail = snarl_pin
+                .is_rail()
+                .then(|| Rail::new(pin_rect, pin_size, false));
+
+            // Interact with pin shape.
+            pin_ui.set_clip_rect(snarl_clip_rect);
+
+            let hit_rect = pin_hit_rect(pin_rect, pin_size, pin_hit_radius, outer_rect, false);
+            let pin_sense = if config.read_only {
+                Sense::hover()
+            } else {
+                Sense::click_and_drag()
+            };
+            let r = pin_ui.interact(hit_rect, pin_ui.next_auto_id(), pin_sense);
+
+            pin_ui.skip_ahead_auto_ids(1);
+
+            pull::pull_wires_on_click(config, &r, in_pin, snarl_state, snarl);
+            if r.clicked_by(config.click_pin.mouse_button) {
+                if snarl_state.has_new_wires() {
+                    snarl_state.remove_new_wire_in(in_pin.id);
+                } else {
+                    viewer.drop_inputs(in_pin, snarl);
+                    if !snarl.nodes.contains(node.0) {
+                        // If removed
+                        return;
+                    }
+                }
+            }
+            if r.drag_started_by(config.drag_pin.mouse_button) {
+                // TODO: I am not sure what start_new_wires_out() does?
+                // Is that for a future feature where you can draw several wires at once?
+                if config.drag_pin.matches(modifiers) {
+                    snarl_state.start_new_wires_out(&in_pin.remotes);
+                    if !config.drag_pin.matches_exactly(modifiers) {
+                        snarl.drop_inputs(in_pin.id);
+                        if !snarl.nodes.contains(node.0) {
+                            // If removed
+                            return;
+                        }
+                    }
+                } else if config.bidirectional_drag {
+                    snarl_state.start_new_wire_in(in_pin.id);
+                }
+            }
+
+            if let Some(rail) = rail
+                && r.drag_started_by(config.drag_pin.mouse_button)
+                && let Some(pos) = r.interact_pointer_pos()
+            {
+                snarl_state.set_new_wires_rail(rail.t(pos));
+            }
+
+            if r.drag_stopped() {
+                drag_released = true;
+            }
+
+            let mut visual_pin_rect = pin_rect;
+
+            if r.contains_pointer() {
+                if snarl_state.has_new_wires_in() {
+                    // TODO: And here I am also not sure what these modifiers do
+                    if modifiers.shift && !modifiers.command {
+                        snarl_state.add_new_wire_in(in_pin.id);
+                    }
+                    if !modifiers.shift && modifiers.command {
+                        snarl_state.remove_new_wire_in(in_pin.id);
+                    }
+                }
+                if let Some(pos) = r.hover_pos() {
+                    pick_hovered_pin(
+                        &mut pin_hovered,
+                        HoveredPin {
+                            pin: AnyPinId::In(in_pin.id),
+                            dist_sq: pin_distance_sq(pin_rect, rail, pos),
+                        },
+                    );
+                }
+                visual_pin_rect = visual_pin_rect.scale_from_center(style.pin_hover_scale());
+            }
+
+            let mut pin_painter = pin_ui.painter().clone();
+            if pin_ui.clip_rect().intersects(pin_rect)
+                && is_pin_dimmed(style, snarl_state, viewer, AnyPinId::In(in_pin.id), snarl)
+            {
+                pin_painter.multiply_opacity(INCOMPATIBLE_PIN_OPACITY);
+            }
+
+            let wire_info = snarl_pin.draw_connected(
+                &pin_style(style, viewer, AnyPinId::In(in_pin.id), snarl),
+                pin_ui.style(),
+                visual_pin_rect,
+                &pin_painter,
+                in_pin.remotes.len(),
+            );
+
+            if is_pin_full(viewer, AnyPinId::In(in_pin.id), snarl) {
+                draw_full_pin_marker(style, pin_ui.style(), visual_pin_rect, &pin_painter);
+            }
+
+            input_positions.insert(
+                in_pin.id,
+                PinResponse {
+                    pos: pin_rect.center(),
+                    hit_rect,
+                    wire_color: wire_info.color,
+                    wire_style: wire_info.style,
+                    vertical: false,
+                    rail,
+                },
+            );
+
+            new_heights.push(pin_ui.min_rect().height());
+
+            pin_ui.expand_to_include_y(outer_rect.bottom());
+        });
+    }
+
+    draw_pin_count_buttons(
+        &mut inputs_ui,
+        node,
+        inputs.len(),
+        true,
+        Layout::left_to_right(Align::Center),
+        config,
+        viewer,
+        snarl,
+    );
+
+    let final_rect = inputs_ui.min_rect();
+    node_ui.expand_to_include_rect(final_rect.intersect(payload_clip_rect));
+
+    DrawPinsResponse {
+        drag_released,
+        pin_hovered,
+        final_rect,
+        new_heights,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::too_many_lines)]
+fn draw_outputs<T, V>(
+    snarl: &mut Snarl<T>,
+    viewer: &mut V,
+    node: NodeId,
+    outputs: &[OutPin],
+    pin_size: f32,
+    style: &SnarlStyle,
+    node_ui: &mut Ui,
+    outputs_rect: Rect,
+    payload_clip_rect: Rect,
+    output_x: f32,
+    min_pin_y_top: f32,
+    min_pin_y_bottom: f32,
+    output_spacing: Option<f32>,
+    snarl_state: &mut SnarlState,
+    config: &mut SnarlConfig,
+    modifiers: Modifiers,
+    output_positions: &mut HashMap<OutPinId, PinResponse>,
+    heights: Heights,
+) -> DrawPinsResponse
+where
+    V: SnarlViewer<T>,
+{
+    let mut drag_released = false;
+    let mut pin_hovered = None;
+    let pin_hit_radius = style.pin_hit_radius(node_ui.style());
+
+    let mut outputs_ui = node_ui.new_child(
+        UiBuilder::new()
+            .max_rect(outputs_rect.round_ui())
+            .layout(Layout::top_down(Align::Max))
+            .id_salt("outputs"),
+    );
+
+    let snarl_clip_rect = node_ui.clip_rect();
+    outputs_ui.shrink_clip_rect(payload_clip_rect);
+
+    let pin_layout = match viewer.pin_layout(node) {
+        PinLayout::Stacked => Layout::right_to_left(Align::Min),
+        PinLayout::Inline => Layout::right_to_left(Align::Center),
+    };
+    let mut new_heights = SmallVec::with_capacity(outputs.len());
+
+    // Rows are spaced by the style, pin contents keep `Ui` spacing.
+    let item_spacing_y = outputs_ui.spacing().item_spacing.y;
+    outputs_ui.spacing_mut().item_spacing.y = style.pin_row_spacing(outputs_ui.style());
+
+    let groups = viewer.output_groups(node, snarl);
+    let mut collapsed_group = None;
+
+    // Output pins on the right.
+    for out_pin in outputs {
+        let idx = out_pin.id.output;
+        if let Some((label, range)) = groups.iter().find(|(_, range)| range.start == idx) {
+            let id = outputs_ui.id().with(("pin-group", idx));
+            let (open, y) = draw_pin_group_header(&mut outputs_ui, id, label, pin_layout);
+            collapsed_group = (!open).then(|| (range.clone(), y));
+        }
+
+        if let Some((range, y)) = &collapsed_group
+            && range.contains(&idx)
+        {
+            let pin = AnyPinId::Out(out_pin.id);
+            let r = collapsed_pin_response(style, viewer, pin, pos2(output_x, *y), snarl, node_ui);
+            output_positions.insert(out_pin.id, r);
+            new_heights.push(0.0);
+            continue;
+        }
+
+        // Show output pin.
+        let cursor = outputs_ui.cursor();
+        let (height, height_outer) = heights.get(out_pin.id.output);
+
+        let margin = (height_outer - height) / 2.0;
+        let outer_rect = cursor.with_max_y(cursor.top() + height_outer);
+        let inner_rect = outer_rect.shrink2(vec2(0.0, margin));
+
+        let builder = UiBuilder::new().layout(pin_layout).max_rect(inner_rect);
+
+        outputs_ui.scope_builder(builder, |pin_ui| {
+            pin_ui.spacing_mut().item_spacing.y = item_spacing_y;
+
+            // Allocate space for pin shape.
+            if let Some(output_spacing) = output_spacing {
+                let min = pin_ui.next_widget_position();
+                pin_ui.advance_cursor_after_rect(Rect::from_min_size(
+                    min,
+                    vec2(output_spacing, pin_size),
+                ));
+            }
+
+            // Compute PinContext based on style.pin_label_visibility()
+            let is_connecting = snarl_state.has_new_wires();
+            let label_visible = match style.pin_label_visibility() {
+                PinLabelVisibility::Always => true,
+                PinLabelVisibility::OnHover => false, // Will show via hover tooltip instead
+                // Simplified: `NearWire` shows all labels when connecting.
+                PinLabelVisibility::WhileConnecting | PinLabelVisibility::NearWire { .. } => {
+                    is_connecting
+                }
+            };
+            let pin_context = PinContext {
+                label_visible,
+                is_connecting,
+                is_hovered: false,             // Updated after interaction
+                wire_near_node: is_connecting, // Simplified for now
+            };
+            let snarl_pin = viewer.show_output(out_pin, pin_ui, pin_context, snarl);
+            if !snarl.nodes.contains(node.0) {
+                // If removed
+                return;
+            }
+
+            // Use min_rect after content is shown to get actual content bounds.
+            // This ensures pin is aligned with actual content, not pre-allocated space.
+            let content_rect = pin_ui.min_rect();
+            let y0 = content_rect.min.y;
+            let y1 = content_rect.max.y;
+
+            let pin_rect = if snarl_pin.is_rail() {
+                Rail::pin_rect(output_x, y0, y1, pin_size, false)
+            } else {
+                snarl_pin.pin_rect(
+                    output_x,
+                    min_pin_y_top.max(y0),
+                    min_pin_y_bottom.max(y1),
+                    pin_size,
+                )
+            };
+            let rail = snarl_pin
+                .is_rail()
+                .then(|| Rail::new(pin_rect, pin_size, false));
+
+            pin_ui.set_clip_rect(snarl_clip_rect);
+
+            let hit_rect = pin_hit_rect(pin_rect, pin_size, pin_hit_radius, outer_rect, false);
+            let pin_sense = if config.read_only {
+                Sense::hover()
+            } else {
+                Sense::click_and_drag()
+            };
+            let r = pin_ui.interact(hit_rect, pin_ui.next_auto_id(), pin_sense);
+
+            pin_ui.skip_ahead_auto_ids(1);
+
+            if config.click_connect
+                && r.clicked_by(config.drag_pin.mouse_button)
+                && !snarl_state.has_new_wires()
+            {
+                // Arm the wire to connect it with next click.
+                snarl_state.arm_new_wire_out(out_pin.id);
+            }
+            if r.clicked_by(config.click_pin.mouse_button) {
+                if snarl_state.has_new_wires() {
+                    snarl_state.remove_new_wire_out(out_pin.id);
+                } else {
+                    viewer.drop_outputs(out_pin, snarl);
+                    if !snarl.nodes.contains(node.0) {
+                        // If removed
+                        return;
+                    }
+                }
+            }
+            if r.drag_started_by(config.drag_pin.mouse_button) {
+                if config.drag_pin.matches(modifiers) {
+                    snarl_state.start_new_wires_in(&out_pin.remotes);
+
+                    if !config.no_menu.matches(modifiers) {
+                        snarl.drop_outputs(out_pin.id);
+                        if !snarl.nodes.contains(node.0) {
+                            // If removed
+                            return;
+                        }
+                    }
+                } else {
+                    snarl_state.start_new_wire_out(out_pin.id);
+                }
+            }
+
+            if let Some(rail) = rail
+                && r.drag_started_by(config.drag_pin.mouse_button)
+                && let Some(pos) = r.interact_pointer_pos()
+            {
+                snarl_state.set_new_wires_rail(rail.t(pos));
+            }
+
+            if r.drag_stopped() {
+                drag_released = true;
+            }
+
+            let mut visual_pin_rect = pin_rect;
+
+            if r.contains_pointer() {
+                // TODO: What is happening here?
+                if snarl_state.has_new_wires_out() {
+                    if modifiers.shift && !modifiers.command {
+                        snarl_state.add_new_wire_out(out_pin.id);
+                    }
+                    if !modifiers.shift && modifiers.command {
+                        snarl_state.remove_new_wire_out(out_pin.id);
+                    }
+                }
+                if let Some(pos) = r.hover_pos() {
+                    pick_hovered_pin(
+                        &mut pin_hovered,
+                        HoveredPin {
+                            pin: AnyPinId::Out(out_pin.id),
+                            dist_sq: pin_distance_sq(pin_rect, rail, pos),
+                        },
+                    );
+                }
+                visual_pin_rect = visual_pin_rect.scale_from_center(style.pin_hover_scale());
+            }
+
+            let mut pin_painter = pin_ui.painter().clone();
+            if pin_ui.clip_rect().intersects(pin_rect)
+                && is_pin_dimmed(style, snarl_state, viewer, AnyPinId::Out(out_pin.id), snarl)
+            {
+                pin_painter.multiply_opacity(INCOMPATIBLE_PIN_OPACITY);
+            }
+
+            let wire_info = snarl_pin.draw_connected(
+                &pin_style(style, viewer, AnyPinId::Out(out_pin.id), snarl),
+                pin_ui.style(),
+                visual_pin_rect,
+                &pin_painter,
+                out_pin.remotes.len(),
+            );
+
+            if is_pin_full(viewer, AnyPinId::Out(out_pin.id), snarl) {
+                draw_full_pin_marker(style, pin_ui.style(), visual_pin_rect, &pin_painter);
+            }
+
+            output_positions.insert(
+                out_pin.id,
+                PinResponse {
+                    pos: pin_rect.center(),
+                    hit_rect,
+                    wire_color: wire_info.color,
+                    wire_style: wire_info.style,
+                    vertical: false,
+                    rail,
+                },
+            );
+
+            new_heights.push(pin_ui.min_rect().height());
+
+            pin_ui.expand_to_include_y(outer_rect.bottom());
+        });
+    }
+    draw_pin_count_buttons(
+        &mut outputs_ui,
+        node,
+        outputs.len(),
+        false,
+        Layout::right_to_left(Align::Center),
+        config,
+        viewer,
+        snarl,
+    );
+
+    let final_rect = outputs_ui.min_rect();
+    node_ui.expand_to_include_rect(final_rect.intersect(payload_clip_rect));
+
+    DrawPinsResponse {
+        drag_released,
+        pin_hovered,
+        final_rect,
+        new_heights,
+    }
+}
+
+/// Draws input pins in a horizontal row (for vertical sandwich layouts).
+/// Pins are arranged left-to-right with pin icons at the edge and labels below/above.
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::too_many_lines)]
+fn draw_inputs_horizontal<T, V>(
+    snarl: &mut Snarl<T>,
+    viewer: &mut V,
+    node: NodeId,
+    inputs: &[InPin],
+    pin_size: f32,
+    style: &SnarlStyle,
+    node_ui: &mut Ui,
+    inputs_rect: Rect,
+    payload_clip_rect: Rect,
+    input_y: f32,
+    min_pin_x_left: f32,
+    min_pin_x_right: f32,
+    snarl_state: &mut SnarlState,
+    config: &mut SnarlConfig,
+    modifiers: Modifiers,
+    input_positions: &mut HashMap<InPinId, PinResponse>,
+    widths: Widths,
+    pins_at_top: bool,
+) -> DrawPinsHorizontalResponse
+where
+    V: SnarlViewer<T>,
+{
+    let mut drag_released = false;
+    let mut pin_hovered = None;
+    let pin_hit_radius = style.pin_hit_radius(node_ui.style());
+
+    // Input pins in horizontal row
+    let mut inputs_ui = node_ui.new_child(
+        UiBuilder::new()
+            .max_rect(inputs_rect.round_ui())
+            .layout(Layout::left_to_right(Align::Center))
+            .id_salt("inputs_horizontal"),
+    );
+
+    let snarl_clip_rect = node_ui.clip_rect();
+    inputs_ui.shrink_clip_rect(payload_clip_rect);
+
+    // Pin layout: vertical stack with pin icon at edge, label on other side
+    let pin_layout = if viewer.pin_layout(node) == PinLayout::Inline {
+        Layout::left_to_right(Align::Center) // Pin at edge, label and widget in a row
+    } else if pins_at_top {
+        Layout::top_down(Align::Center) // Pin at top, label below
+    } else {
+        Layout::bottom_up(Align::Center) // Pin at bottom, label above
+    };
+
+    let mut new_widths = SmallVec::with_capacity(inputs.len());
+
+    for in_pin in inputs {
+        let cursor = inputs_ui.cursor();
+        let (width, width_outer) = widths.get(in_pin.id.input);
+
+        let margin = (width_outer - width) / 2.0;
+        let outer_rect = cursor.with_max_x(cursor.left() + width_outer);
+        let inner_rect = outer_rect.shrink2(vec2(margin, 0.0));
+
+        let builder = UiBuilder::new().layout(pin_layout).max_rect(inner_rect);
+
+        inputs_ui.scope_builder(builder, |pin_ui| {
+            // Compute PinContext based on style.pin_label_visibility()
+            let is_connecting = snarl_state.has_new_wires();
+            let label_visible = match style.pin_label_visibility() {
+                PinLabelVisibility::Always => true,
+                PinLabelVisibility::OnHover => false,
+                PinLabelVisibility::WhileConnecting | PinLabelVisibility::NearWire { .. } => {
+                    is_connecting
+                }
+            };
+            let pin_context = PinContext {
+                label_visible,
+                is_connecting,
+                is_hovered: false,
+                wire_near_node: is_connecting,
+            };
+            let snarl_pin = viewer.show_input(in_pin, pin_ui, pin_context, snarl);
+            if !snarl.nodes.contains(node.0) {
+                return;
+            }
+
+            // Use min_rect after content is shown to get actual content bounds.
+            let content_rect = pin_ui.min_rect();
+            let x0 = content_rect.min.x;
+            let x1 = content_rect.max.x;
+
+            let pin_rect = if snarl_pin.is_rail() {
+                Rail::pin_rect(input_y, x0, x1, pin_size, true)
+            } else {
+                snarl_pin.pin_rect_horizontal(
+                    input_y,
+                    min_pin_x_left.max(x0),
+                    min_pin_x_right.max(x1),
+                    pin_size,
+                )
+            };
+            let rail = snarl_pin
+                .is_rail()
+                .then(|| Rail::new(pin_rect, pin_size, true));
+
+            // Interact with pin shape.
+            pin_ui.set_clip_rect(snarl_clip_rect);
+
+            let hit_rect = pin_hit_rect(pin_rect, pin_size, pin_hit_radius, outer_rect, true);
+            let pin_sense = if config.read_only {
+                Sense::hover()
+            } else {
+                Sense::click_and_drag()
+            };
+            let r = pin_ui.interact(hit_rect, pin_ui.next_auto_id(), pin_sense);
+            pin_ui.skip_ahead_auto_ids(1);
+
+            pull::pull_wires_on_click(config, &r, in_pin, snarl_state, snarl);
+            if r.clicked_by(config.click_pin.mouse_button) {
+                if snarl_state.has_new_wires() {
+                    snarl_state.remove_new_wire_in(in_pin.id);
+                } else {
+                    viewer.drop_inputs(in_pin, snarl);
+                    if !snarl.nodes.contains(node.0) {
+                        return;
+                    }
+                }
+            }
+            if r.drag_started_by(config.drag_pin.mouse_button) {
+                if config.drag_pin.matches(modifiers) {
+                    snarl_state.start_new_wires_out(&in_pin.remotes);
+                    if !config.drag_pin.matches_exactly(modifiers) {
+                        snarl.drop_inputs(in_pin.id);
+                        if !snarl.nodes.contains(node.0) {
+                            return;
+                        }
+                    }
+                } else if config.bidirectional_drag {
+                    snarl_state.start_new_wire_in(in_pin.id);
+                }
+            }
+
+            if let Some(rail) = rail
+                && r.drag_started_by(config.drag_pin.mouse_button)
+                && let Some(pos) = r.interact_pointer_pos()
+            {
+                snarl_state.set_new_wires_rail(rail.t(pos));
+            }
+
+            if r.drag_stopped() {
+                drag_released = true;
+            }
+
+            let mut visual_pin_rect = pin_rect;
+
+            if r.contains_pointer() {
+                if snarl_state.has_new_wires_in() {
+                    if modifiers.shift && !modifiers.command {
+                        snarl_state.add_new_wire_in(in_pin.id);
+                    }
+                    if !modifiers.shift && modifiers.command {
+                        snarl_state.remove_new_wire_in(in_pin.id);
+                    }
+                }
+                if let Some(pos) = r.hover_pos() {
+                    pick_hovered_pin(
+                        &mut pin_hovered,
+                        HoveredPin {
+                            pin: AnyPinId::In(in_pin.id),
+                            dist_sq: pin_distance_sq(pin_rect, rail, pos),
+                        },
+                    );
+                }
+                visual_pin_rect = visual_pin_rect.scale_from_center(style.pin_hover_scale());
+            }
+
+            let mut pin_painter = pin_ui.painter().clone();
+            if pin_ui.clip_rect().intersects(pin_rect)
+                && is_pin_dimmed(style, snarl_state, viewer, AnyPinId::In(in_pin.id), snarl)
+            {
+                pin_painter.multiply_opacity(INCOMPATIBLE_PIN_OPACITY);
+            }
+
+            let wire_info = snarl_pin.draw_connected(
+                &pin_style(style, viewer, AnyPinId::In(in_pin.id), snarl),
+                pin_ui.style(),
+                visual_pin_rect,
+                &pin_painter,
+                in_pin.remotes.len(),
+            );
+
+            if is_pin_full(viewer, AnyPinId::In(in_pin.id), snarl) {
+                draw_full_pin_marker(style, pin_ui.style(), visual_pin_rect, &pin_painter);
+            }
+
+            input_positions.insert(
+                in_pin.id,
+                PinResponse {
+                    pos: pin_rect.center(),
+                    hit_rect,
+                    wire_color: wire_info.color,
+                    wire_style: wire_info.style,
+                    vertical: true,
+                    rail,
+                },
+            );
+
+            new_widths.push(pin_ui.min_rect().width());
+
+            pin_ui.expand_to_include_x(outer_rect.right());
+        });
+    }
+
+    draw_pin_count_buttons(
+        &mut inputs_ui,
+        node,
+        inputs.len(),
+        true,
+        Layout::left_to_right(Align::Center),
+        config,
+        viewer,
+        snarl,
+    );
+
+    let final_rect = inputs_ui.min_rect();
+    node_ui.expand_to_include_rect(final_rect.intersect(payload_clip_rect));
+
+    DrawPinsHorizontalResponse {
+        drag_released,
+        pin_hovered,
+        final_rect,
+        new_widths,
+    }
+}
+
+/// Draws output pins in a horizontal row (for vertical sandwich layouts).
+/// Pins are arranged left-to-right with pin icons at the edge and labels below/above.
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::too_many_lines)]
+fn draw_outputs_horizontal<T, V>(
+    snarl: &mut Snarl<T>,
+    viewer: &mut V,
+    node: NodeId,
+    outputs: &[OutPin],
+    pin_size: f32,
+    style: &SnarlStyle,
+    node_ui: &mut Ui,
+    outputs_rect: Rect,
+    payload_clip_rect: Rect,
+    output_y: f32,
+    min_pin_x_left: f32,
+    min_pin_x_right: f32,
+    snarl_state: &mut SnarlState,
+    config: &mut SnarlConfig,
+    modifiers: Modifiers,
+    output_positions: &mut HashMap<OutPinId, PinResponse>,
+    widths: Widths,
+    pins_at_top: bool,
+) -> DrawPinsHorizontalResponse
+where
+    V: SnarlViewer<T>,
+{
+    let mut drag_released = false;
+    let mut pin_hovered = None;
+    let pin_hit_radius = style.pin_hit_radius(node_ui.style());
+
+    let mut outputs_ui = node_ui.new_child(
+        UiBuilder::new()
+            .max_rect(outputs_rect.round_ui())
+            .layout(Layout::left_to_right(Align::Center))
+            .id_salt("outputs_horizontal"),
+    );
+
+    let snarl_clip_rect = node_ui.clip_rect();
+    outputs_ui.shrink_clip_rect(payload_clip_rect);
+
+    // Pin layout: vertical stack with pin icon at edge, label on other side
+    let pin_layout = if viewer.pin_layout(node) == PinLayout::Inline {
+        Layout::left_to_right(Align::Center) // Pin at edge, label and widget in a row
+    } else if pins_at_top {
+        Layout::top_down(Align::Center) // Pin at top, label below
+    } else {
+        Layout::bottom_up(Align::Center) // Pin at bottom, label above
+    };
+
+    let mut new_widths = SmallVec::with_capacity(outputs.len());
+
+    for out_pin in outputs {
+        let cursor = outputs_ui.cursor();
+        let (width, width_outer) = widths.get(out_pin.id.output);
+
+        let margin = (width_outer - width) / 2.0;
+        let outer_rect = cursor.with_max_x(cursor.left() + width_outer);
+        let inner_rect = outer_rect.shrink2(vec2(margin, 0.0));
+
+        let builder = UiBuilder::new().layout(pin_layout).max_rect(inner_rect);
+
+        outputs_ui.scope_builder(builder, |pin_ui| {
+            // Compute PinContext based on style.pin_label_visibility()
+            let is_connecting = snarl_state.has_new_wires();
+            let label_visible = match style.pin_label_visibility() {
+                PinLabelVisibility::Always => true,
+                PinLabelVisibility::OnHover => false,
+                PinLabelVisibility::WhileConnecting | PinLabelVisibility::NearWire { .. } => {
+                    is_connecting
+                }
+            };
+            let pin_context = PinContext {
+                label_visible,
+                is_connecting,
+                is_hovered: false,
+                wire_near_node: is_connecting,
+            };
+            let snarl_pin = viewer.show_output(out_pin, pin_ui, pin_context, snarl);
+            if !snarl.nodes.contains(node.0) {
+                return;
+            }
+
+            let content_rect = pin_ui.min_rect();
+            let x0 = content_rect.min.x;
+            let x1 = content_rect.max.x;
+
+            let pin_rect = if snarl_pin.is_rail() {
+                Rail::pin_rect(output_y, x0, x1, pin_size, true)
+            } else {
+                snarl_pin.pin_rect_horizontal(
+                    output_y,
+                    min_pin_x_left.max(x0),
+                    min_pin_x_right.max(x1),
+                    pin_size,
+                )
+            };
+            let rail = snarl_pin
+                .is_rail()
+                .then(|| Rail::new(pin_rect, pin_size, true));
+
+            pin_ui.set_clip_rect(snarl_clip_rect);
+
+            let hit_rect = pin_hit_rect(pin_rect, pin_size, pin_hit_radius, outer_rect, true);
+            let pin_sense = if config.read_only {
+                Sense::hover()
+            } else {
+                Sense::click_and_drag()
+            };
+            let r = pin_ui.interact(hit_rect, pin_ui.next_auto_id(), pin_sense);
+            pin_ui.skip_ahead_auto_ids(1);
+
+            if config.click_connect
+                && r.clicked_by(config.drag_pin.mouse_button)
+                && !snarl_state.has_new_wires()
+            {
+                // Arm the wire to connect it with next click.
+                snarl_state.arm_new_wire_out(out_pin.id);
+            }
+            if r.clicked_by(config.click_pin.mouse_button) {
+                if snarl_state.has_new_wires() {
+                    snarl_state.remove_new_wire_out(out_pin.id);
+                } else {
+                    viewer.drop_outputs(out_pin, snarl);
+                    if !snarl.nodes.contains(node.0) {
+                        return;
+                    }
+                }
+            }
+            if r.drag_started_by(config.drag_pin.mouse_button) {
+                if config.drag_pin.matches(modifiers) {
+                    snarl_state.start_new_wires_in(&out_pin.remotes);
+                    if !config.no_menu.matches(modifiers) {
+                        snarl.drop_outputs(out_pin.id);
+                        if !snarl.nodes.contains(node.0) {
+                            return;
+                        }
+                    }
+                } else {
+                    snarl_state.start_new_wire_out(out_pin.id);
+                }
+            }
+
+            if let Some(rail) = rail
+                && r.drag_started_by(config.drag_pin.mouse_button)
+                && let Some(pos) = r.interact_pointer_pos()
+            {
+                snarl_state.set_new_wires_rail(rail.t(pos));
+            }
+
+            if r.drag_stopped() {
+                drag_released = true;
+            }
+
+            let mut visual_pin_rect = pin_rect;
+
+            if r.contains_pointer() {
+                if snarl_state.has_new_wires_out() {
+                    if modifiers.shift && !modifiers.command {
+                        snarl_state.add_new_wire_out(out_pin.id);
+                    }
+                    if !modifiers.shift && modifiers.command {
+                        snarl_state.remove_new_wire_out(out_pin.id);
+                    }
+                }
+                if let Some(pos) = r.hover_pos() {
+                    pick_hovered_pin(
+                        &mut pin_hovered,
+                        HoveredPin {
+                            pin: AnyPinId::Out(out_pin.id),
+                            dist_sq: pin_distance_sq(pin_rect, rail, pos),
+                        },
+                    );
+                }
+                visual_pin_rect = visual_pin_rect.scale_from_center(style.pin_hover_scale());
+            }
+
+            let mut pin_painter = pin_ui.painter().clone();
+            if pin_ui.clip_rect().intersects(pin_rect)
+                && is_pin_dimmed(style, snarl_state, viewer, AnyPinId::Out(out_pin.id), snarl)
+            {
+                pin_painter.multiply_opacity(INCOMPATIBLE_PIN_OPACITY);
+            }
+
+            let wire_info = snarl_pin.draw_connected(
+                &pin_style(style, viewer, AnyPinId::Out(out_pin.id), snarl),
+                pin_ui.style(),
+                visual_pin_rect,
+                &pin_painter,
+                out_pin.remotes.len(),
+            );
+
+            if is_pin_full(viewer, AnyPinId::Out(out_pin.id), snarl) {
+                draw_full_pin_marker(style, pin_ui.style(), visual_pin_rect, &pin_painter);
+            }
+
+            output_positions.insert(
+                out_pin.id,
+                PinResponse {
+                    pos: pin_rect.center(),
+                    hit_rect,
+                    wire_color: wire_info.color,
+                    wire_style: wire_info.style,
+                    vertical: true,
+                    rail,
+                },
+            );
+
+            new_widths.push(pin_ui.min_rect().width());
+
+            pin_ui.expand_to_include_x(outer_rect.right());
+        });
+    }
+    draw_pin_count_buttons(
+        &mut outputs_ui,
+        node,
+        outputs.len(),
+        false,
+        Layout::left_to_right(Align::Center),
+        config,
+        viewer,
+        snarl,
+    );
+
+    let final_rect = outputs_ui.min_rect();
+    node_ui.expand_to_include_rect(final_rect.intersect(payload_clip_rect));
+
+    DrawPinsHorizontalResponse {
+        drag_released,
+        pin_hovered,
+        final_rect,
+        new_widths,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_body<T, V>(
+    snarl: &mut Snarl<T>,
+    viewer: &mut V,
+    node: NodeId,
+    inputs: &[InPin],
+    outputs: &[OutPin],
+    ui: &mut Ui,
+    body_rect: Rect,
+    payload_clip_rect: Rect,
+    _snarl_state: &SnarlState,
+    snarl_id: Id,
+) -> DrawBodyResponse
+where
+    V: SnarlViewer<T>,
+{
+    // Body id doesn't depend on parent `Ui`,
+    // so widget state is kept as long as the viewer returns the same id source.
+    let body_id = snarl_id.with(("snarl-body", viewer.node_id_source(node, snarl)));
+
+    let mut body_ui = ui.new_child(
+        UiBuilder::new()
+            .max_rect(body_rect.round_ui())
+            .layout(Layout::left_to_right(Align::Min))
+            .id(body_id),
+    );
+
+    body_ui.shrink_clip_rect(payload_clip_rect);
+
+    viewer.show_body(node, inputs, outputs, &mut body_ui, snarl);
+
+    let final_rect = body_ui.min_rect();
+    ui.expand_to_include_rect(final_rect.intersect(payload_clip_rect));
+    // node_state.set_body_width(body_size.x);
+
+    DrawBodyResponse { final_rect }
+}
+
+//First step for split big function to parts
+/// Draw one node. Return Pins info
+#[inline]
+#[allow(clippy::too_many_lines)]
+#[allow(clippy::too_many_arguments)]
+fn draw_node<T, V>(
+    snarl: &mut Snarl<T>,
+    ui: &mut Ui,
+    node: NodeId,
+    viewer: &mut V,
+    snarl_state: &mut SnarlState,
+    config: &mut SnarlConfig,
+    style: &SnarlStyle,
+    snarl_id: Id,
+    input_positions: &mut HashMap<InPinId, PinResponse>,
+    modifiers: Modifiers,
+    output_positions: &mut HashMap<OutPinId, PinResponse>,
+    force_open: bool,
+    detail: NodeDetail,
+) -> Option<DrawNodeResponse>
+where
+    V: SnarlViewer<T>,
+{
+    let Node {
+        pos,
+        open,
+        ref value,
+    } = snarl.nodes[node.0];
+
+    // Node is shown expanded, but remains collapsed.
+    let shown_open = open || force_open;
+
+    // Collect pins
+    let inputs_count = viewer.inputs(value);
+    let outputs_count = viewer.outputs(value);
+
+    let inputs = (0..inputs_count)
+        .map(|idx| InPin::new(snarl, InPinId { node, input: idx }))
+        .collect::<Vec<_>>();
+
+    let outputs = (0..outputs_count)
+        .map(|idx| OutPin::new(snarl, OutPinId { node, output: idx }))
+        .collect::<Vec<_>>();
+
+    let node_pos = pos.round_ui();
+
+    // Generate persistent id for the node.
+    let node_id = snarl_id.with(("snarl-node", node));
+
+    let openness = ui.ctx().animate_bool(node_id, shown_open);
+
+    let mut node_state = NodeState::load(ui.ctx(), node_id, ui.spacing());
+
+    let node_rect = node_state.node_rect(node_pos, openness);
+
+    let mut node_to_top = None;
+    let mut node_moved = None;
+    let mut drag_released = false;
+    let mut pin_hovered = None;
+
+    let node_frame =
+        viewer.node_frame(style.node_frame(ui.style()), node, &inputs, &outputs, snarl);
+
+    let header_frame = viewer.header_frame(
+        style.header_frame(ui.style()),
+        node,
+        &inputs,
+        &outputs,
+        snarl,
+    );
+
+    // Rect for node + frame margin.
+    let node_frame_rect = node_rect + node_frame.total_margin();
+
+    let selected = snarl_state.selected_nodes().contains(&node);
+
+    let elevation = if selected {
+        style.selection_shadow()
+    } else if Hovered::load(ui.ctx(), snarl_id).node == Some(node) {
+        style.hover_shadow()
+    } else {
+        None
+    };
+
+    if let Some(shadow) = elevation {
+        // Painted before the node, so it covers only nodes drawn beneath.
+        ui.painter().add(shadow.as_shape(
+            node_frame_rect - node_frame.outer_margin,
+            node_frame.corner_radius,
+        ));
+    }
+
+    let selection_visual = style.selection_visual();
+    let select_style = style.select_style(ui.style());
+    let select_rect = node_frame_rect + select_style.margin;
+
+    // Fill overlay is painted after the node contents.
+    if selected && selection_visual != SelectionVisual::Fill {
+        paint_selection(ui.painter(), selection_visual, select_rect, &select_style);
+    }
+
+    // Size of the pin.
+    // Side of the square or diameter of the circle.
+    let pin_size = style.pin_size(ui.style()).max(0.0);
+
+    let pin_placement = style.pin_placement();
+
+    let header_drag_space = style.header_drag_space(ui.style()).max(Vec2::ZERO);
+
+    let hit_shape = viewer.node_hit_shape(node);
+
+    // Pointer outside of the hit shape interacts with what is beneath the node.
+    let frame_id = node_id.with("frame");
+    let frame_sense = if hit_shape == NodeHitShape::Rect
+        || ui.ctx().is_being_dragged(frame_id)
+        || ui.ctx().pointer_hover_pos().is_none_or(|pos| {
+            let pos = ui
+                .ctx()
+                .layer_transform_from_global(ui.layer_id())
+                .map_or(pos, |t| t * pos);
+            hit_shape.contains(node_frame_rect, pos)
+        }) {
+        Sense::click_and_drag()
+    } else {
+        Sense::hover()
+    };
+
+    // Interact with node frame.
+    let r = ui.interact(node_frame_rect, frame_id, frame_sense);
+
+    let handle_rect = match viewer.drag_handle(node) {
+        DragHandle::WholeNode => None,
+        DragHandle::Header => Some(Rect::from_min_size(
+            node_frame_rect.min,
+            vec2(
+                node_frame_rect.width(),
+                node_state.header_height() + header_frame.total_margin().sum().y,
+            ),
+        )),
+        DragHandle::Custom(rect) => Some(rect.translate(node_frame_rect.min.to_vec2())),
+    };
+
+    // Drag moves the node only if it started on the drag handle.
+    let on_handle_id = node_id.with("drag-on-handle");
+    if r.drag_started() {
+        let on_handle = handle_rect.is_none_or(|rect| {
+            ui.input(|i| i.pointer.press_origin()).is_some_and(|pos| {
+                let pos = ui
+                    .ctx()
+                    .layer_transform_from_global(ui.layer_id())
+                    .map_or(pos, |t| t * pos);
+                rect.contains(pos)
+            })
+        });
+        ui.data_mut(|d| d.insert_temp(on_handle_id, on_handle));
+    }
+    let on_handle = ui.data(|d| d.get_temp(on_handle_id)).unwrap_or(true);
+
+    let drag_modifiers = config.drag_node.matches(modifiers)
+        && !config.select_node.matches(modifiers)
+        && !config.deselect_node.matches(modifiers);
+
+    if on_handle
+        && !config.read_only
+        && drag_modifiers
+        && r.dragged_by(config.click_node.mouse_button)
+    {
+        node_moved = Some((node, r.drag_delta(), r.drag_started()));
+    }
+
+    let node_drag_stopped =
+        (on_handle && !config.read_only && r.drag_stopped_by(config.click_node.mouse_button))
+            .then_some(node);
+
+    if r.clicked_by(config.click_node.mouse_button) || r.dragged_by(config.drag_node.mouse_button) {
+        if config.select_node.matches(modifiers) {
+            // In single_select mode, always clear previous selection
+            let reset = config.single_select || modifiers.command;
+            snarl_state.select_one_node(reset, node);
+        } else if config.deselect_node.matches(modifiers) {
+            // Cmd+click: deselect
+            snarl_state.deselect_one_node(node);
+        } else if config.click_toggles_selection {
+            // Plain click: toggle, dragging keeps the node selected
+            if !snarl_state.selected_nodes().contains(&node) {
+                snarl_state.select_one_node(config.single_select, node);
+            } else if r.clicked_by(config.click_node.mouse_button) {
+                snarl_state.deselect_one_node(node);
+            }
+        } else {
+            // Plain click: select exclusively (deselect all others first)
+            snarl_state.select_one_node(true, node);
+        }
+    }
+
+    if r.clicked() || r.dragged() {
+        node_to_top = Some(node);
+    }
+
+    let title_edit_id = node_id.with("title-edit");
+
+    if r.double_clicked()
+        && !config.read_only
+        && viewer.title_editable(node)
+        && let Some(pointer) = r.interact_pointer_pos()
+    {
+        let header_rect = Rect::from_min_size(
+            node_frame_rect.min,
+            vec2(
+                node_frame_rect.width(),
+                node_state.header_height() + header_frame.total_margin().sum().y,
+            ),
+        );
+
+        if header_rect.contains(pointer) {
+            let title = viewer.title(&snarl.nodes[node.0].value);
+            snarl_state.start_title_edit(node, title);
+            ui.memory_mut(|m| m.request_focus(title_edit_id));
+        }
+    }
+
+    let mut menu_passthrough = false;
+    if !config.read_only && viewer.has_node_menu_by_id(node, snarl) {
+        r.context_menu(|ui| {
+            viewer.show_node_menu(node, &inputs, &outputs, ui, snarl);
+        });
+    } else if r.secondary_clicked() {
+        menu_passthrough = true;
+    }
+
+    if !snarl.nodes.contains(node.0) {
+        node_state.clear(ui.ctx());
+        // If removed
+        return None;
+    }
+
+    if detail != NodeDetail::Full {
+        let frame_rect = node_frame_rect - node_frame.outer_margin;
+        let header_height = node_state.header_height() + header_frame.total_margin().sum().y;
+        let title = (detail == NodeDetail::Title).then(|| viewer.title(&snarl.nodes[node.0].value));
+        draw_simplified_node(
+            ui,
+            &node_frame,
+            &header_frame,
+            frame_rect,
+            header_height,
+            title,
+        );
+
+        let default_layout = default_node_layout(viewer, style, node, snarl);
+        let vertical = viewer
+            .node_layout(default_layout, node, &inputs, &outputs, snarl)
+            .kind
+            .is_vertical();
+
+        // Wires attach to the middle of the frame sides pins are placed on.
+        let (in_pos, out_pos) = if vertical {
+            (frame_rect.center_top(), frame_rect.center_bottom())
+        } else {
+            (frame_rect.left_center(), frame_rect.right_center())
+        };
+        let wire_style = style.wire_style();
+        for pin in &inputs {
+            let color = pin_style(style, viewer, AnyPinId::In(pin.id), snarl).pin_fill(ui.style());
+            input_positions.insert(
+                pin.id,
+                PinResponse {
+                    pos: in_pos,
+                    hit_rect: Rect::NOTHING,
+                    wire_color: color,
+                    wire_style,
+                    vertical,
+                    rail: None,
+                },
+            );
+        }
+        for pin in &outputs {
+            let color = pin_style(style, viewer, AnyPinId::Out(pin.id), snarl).pin_fill(ui.style());
+            output_positions.insert(
+                pin.id,
+                PinResponse {
+                    pos: out_pos,
+                    hit_rect: Rect::NOTHING,
+                    wire_color: color,
+                    wire_style,
+                    vertical,
+                    rail: None,
+                },
+            );
+        }
+
+        if selected && selection_visual == SelectionVisual::Fill {
+            paint_selection(ui.painter(), selection_visual, select_rect, &select_style);
+        }
+
+        node_state.store(ui.ctx());
+        return Some(DrawNodeResponse {
+            node_moved,
+            node_drag_stopped,
+            node_to_top,
+            drag_released,
+            pin_hovered: None,
+            final_rect: frame_rect,
+            hit_shape,
+            in_pins: inputs,
+            out_pins: outputs,
+            menu_passthrough,
+        });
+    }
+
+    if viewer.has_on_hover_popup(&snarl.nodes[node.0].value) {
+        r.clone().on_hover_ui_at_pointer(|ui| {
+            viewer.show_on_hover_popup(node, &inputs, &outputs, ui, snarl);
+        });
+    }
+
+    if !snarl.nodes.contains(node.0) {
+        node_state.clear(ui.ctx());
+        // If removed
+        return None;
+    }
+
+    let node_ui = &mut ui.new_child(
+        UiBuilder::new()
+            .max_rect(node_frame_rect.round_ui())
+            .layout(Layout::top_down(Align::Center))
+            .id_salt(node_id),
+    );
+    if config.read_only {
+        node_ui.disable();
+    }
+
+    let mut new_pins_size = Vec2::ZERO;
+
+    let frame_r = node_frame.show(node_ui, |ui| {
+        if viewer.has_node_style(node, &inputs, &outputs, snarl) {
+            viewer.apply_node_style(ui.style_mut(), node, &inputs, &outputs, snarl);
+        }
+
+        // Input pins' center side by X axis.
+        let input_x = match pin_placement {
+            PinPlacement::Inside => pin_size.mul_add(
+                0.5,
+                node_frame_rect.left() + node_frame.inner_margin.leftf(),
+            ),
+            PinPlacement::Edge => node_frame_rect.left(),
+            PinPlacement::Outside { margin } => {
+                pin_size.mul_add(-0.5, node_frame_rect.left() - margin)
+            }
+        };
+
+        // Input pins' spacing required.
+        let input_spacing = match pin_placement {
+            PinPlacement::Inside => Some(pin_size),
+            PinPlacement::Edge => Some(
+                pin_size
+                    .mul_add(0.5, -node_frame.inner_margin.leftf())
+                    .max(0.0),
+            ),
+            PinPlacement::Outside { .. } => None,
+        };
+
+        // Output pins' center side by X axis.
+        let output_x = match pin_placement {
+            PinPlacement::Inside => pin_size.mul_add(
+                -0.5,
+                node_frame_rect.right() - node_frame.inner_margin.rightf(),
+            ),
+            PinPlacement::Edge => node_frame_rect.right(),
+            PinPlacement::Outside { margin } => {
+                pin_size.mul_add(0.5, node_frame_rect.right() + margin)
+            }
+        };
+
+        // Output pins' spacing required.
+        let output_spacing = match pin_placement {
+            PinPlacement::Inside => Some(pin_size),
+            PinPlacement::Edge => Some(
+                pin_size
+                    .mul_add(0.5, -node_frame.inner_margin.rightf())
+                    .max(0.0),
+            ),
+            PinPlacement::Outside { .. } => None,
+        };
+
+        // Input/output pin block
+
+        if (openness < 1.0 && shown_open) || (openness > 0.0 && !shown_open) {
+            snarl_state.request_repaint();
+        }
+
+        // Pins are placed under the header and must not go outside of the header frame.
+        let payload_rect = Rect::from_min_max(
+            pos2(
+                node_rect.min.x,
+                node_rect.min.y
+                    + node_state.header_height()
+                    + header_frame.total_margin().bottom
+                    + ui.spacing().item_spacing.y
+                    - node_state.payload_offset(openness),
+            ),
+            node_rect.max,
+        );
+
+        let default_layout = default_node_layout(viewer, style, node, snarl);
+        let node_layout = viewer.node_layout(default_layout, node, &inputs, &outputs, snarl);
+
+        let payload_clip_rect =
+            Rect::from_min_max(node_rect.min, pos2(node_rect.max.x, f32::INFINITY));
+
+        let pins_rect = match node_layout.kind {
+            NodeLayoutKind::Coil => {
+                // Show input pins.
+                let r = draw_inputs(
+                    snarl,
+                    viewer,
+                    node,
+                    &inputs,
+                    pin_size,
+                    style,
+                    ui,
+                    payload_rect,
+                    payload_clip_rect,
+                    input_x,
+                    node_rect.min.y,
+                    node_rect.min.y + node_state.header_height(),
+                    input_spacing,
+                    snarl_state,
+                    config,
+                    modifiers,
+                    input_positions,
+                    node_layout.input_heights(&node_state),
+                );
+
+                let new_input_heights = r.new_heights;
+
+                drag_released |= r.drag_released;
+
+                if let Some(hovered) = r.pin_hovered {
+                    pick_hovered_pin(&mut pin_hovered, hovered);
+                }
+
+                let inputs_rect = r.final_rect;
+                let inputs_size = inputs_rect.size();
+
+                if !snarl.nodes.contains(node.0) {
+                    // If removed
+                    return;
+                }
+
+                // Show output pins.
+
+                let r = draw_outputs(
+                    snarl,
+                    viewer,
+                    node,
+                    &outputs,
+                    pin_size,
+                    style,
+                    ui,
+                    payload_rect,
+                    payload_clip_rect,
+                    output_x,
+                    node_rect.min.y,
+                    node_rect.min.y + node_state.header_height(),
+                    output_spacing,
+                    snarl_state,
+                    config,
+                    modifiers,
+                    output_positions,
+                    node_layout.output_heights(&node_state),
+                );
+
+                let new_output_heights = r.new_heights;
+
+                drag_released |= r.drag_released;
+
+                if let Some(hovered) = r.pin_hovered {
+                    pick_hovered_pin(&mut pin_hovered, hovered);
+                }
+
+                let outputs_rect = r.final_rect;
+                let outputs_size = outputs_rect.size();
+
+                if !snarl.nodes.contains(node.0) {
+                    // If removed
+                    return;
+                }
+
+                node_state.set_input_heights(new_input_heights);
+                node_state.set_output_heights(new_output_heights);
+
+                new_pins_size = vec2(
+                    inputs_size.x + outputs_size.x + ui.spacing().item_spacing.x,
+                    f32::max(inputs_size.y, outputs_size.y),
+                );
+
+                let mut pins_rect = inputs_rect.union(outputs_rect);
+
+                // Show body if there's one.
+                if viewer.has_body(&snarl.nodes.get(node.0).unwrap().value) {
+                    let body_rect = Rect::from_min_max(
+                        pos2(
+                            inputs_rect.right() + ui.spacing().item_spacing.x,
+                            payload_rect.top(),
+                        ),
+                        pos2(
+                            outputs_rect.left() - ui.spacing().item_spacing.x,
+                            payload_rect.bottom(),
+                        ),
+                    );
+
+                    let r = draw_body(
+                        snarl,
+                        viewer,
+                        node,
+                        &inputs,
+                        &outputs,
+                        ui,
+                        body_rect,
+                        payload_clip_rect,
+                        snarl_state,
+                        snarl_id,
+                    );
+
+                    new_pins_size.x += r.final_rect.width() + ui.spacing().item_spacing.x;
+                    new_pins_size.y = f32::max(new_pins_size.y, r.final_rect.height());
+
+                    pins_rect = pins_rect.union(body_rect);
+
+                    if !snarl.nodes.contains(node.0) {
+                        // If removed
+                        return;
+                    }
+                }
+
+                pins_rect
+            }
+            NodeLayoutKind::Sandwich => {
+                // Show input pins.
+
+                let r = draw_inputs(
+                    snarl,
+                    viewer,
+                    node,
+                    &inputs,
+                    pin_size,
+                    style,
+                    ui,
+                    payload_rect,
+                    payload_clip_rect,
+                    input_x,
+                    node_rect.min.y,
+                    node_rect.min.y + node_state.header_height(),
+                    input_spacing,
+                    snarl_state,
+                    config,
+                    modifiers,
+                    input_positions,
+                    node_layout.input_heights(&node_state),
+                );
+
+                let new_input_heights = r.new_heights;
+
+                drag_released |= r.drag_released;
+
+                if let Some(hovered) = r.pin_hovered {
+                    pick_hovered_pin(&mut pin_hovered, hovered);
+                }
+
+                let inputs_rect = r.final_rect;
+
+                new_pins_size = inputs_rect.size();
+
+                let mut next_y = inputs_rect.bottom() + ui.spacing().item_spacing.y;
+
+                if !snarl.nodes.contains(node.0) {
+                    // If removed
+                    return;
+                }
+
+                let mut pins_rect = inputs_rect;
+
+                // Show body if there's one.
+                if viewer.has_body(&snarl.nodes.get(node.0).unwrap().value) {
+                    let body_rect = payload_rect.intersect(Rect::everything_below(next_y));
+
+                    let r = draw_body(
+                        snarl,
+                        viewer,
+                        node,
+                        &inputs,
+                        &outputs,
+                        ui,
+                        body_rect,
+                        payload_clip_rect,
+                        snarl_state,
+                        snarl_id,
+                    );
+
+                    let body_rect = r.final_rect;
+
+                    new_pins_size.x = f32::max(new_pins_size.x, body_rect.width());
+                    new_pins_size.y += body_rect.height() + ui.spacing().item_spacing.y;
+
+                    if !snarl.nodes.contains(node.0) {
+                        // If removed
+                        return;
+                    }
+
+                    pins_rect = pins_rect.union(body_rect);
+                    next_y = body_rect.bottom() + ui.spacing().item_spacing.y;
+                }
+
+                // Show output pins.
+
+                let outputs_rect = payload_rect.intersect(Rect::everything_below(next_y));
+
+                let r = draw_outputs(
+                    snarl,
+                    viewer,
+                    node,
+                    &outputs,
+                    pin_size,
+                    style,
+                    ui,
+                    outputs_rect,
+                    payload_clip_rect,
+                    output_x,
+                    node_rect.min.y,
+                    node_rect.min.y + node_state.header_height(),
+                    output_spacing,
+                    snarl_state,
+                    config,
+                    modifiers,
+                    output_positions,
+                    node_layout.output_heights(&node_state),
+                );
+
+                let new_output_heights = r.new_heights;
+
+                drag_released |= r.drag_released;
+
+                if let Some(hovered) = r.pin_hovered {
+                    pick_hovered_pin(&mut pin_hovered, hovered);
+                }
+
+                let outputs_rect = r.final_rect;
+
+                if !snarl.nodes.contains(node.0) {
+                    // If removed
+                    return;
+                }
+
+                node_state.set_input_heights(new_input_heights);
+                node_state.set_output_heights(new_output_heights);
+
+                new_pins_size.x = f32::max(new_pins_size.x, outputs_rect.width());
+                new_pins_size.y += outputs_rect.height() + ui.spacing().item_spacing.y;
+
+                pins_rect = pins_rect.union(outputs_rect);
+
+                pins_rect
+            }
+            NodeLayoutKind::FlippedSandwich => {
+                // Show input pins.
+
+                let outputs_rect = payload_rect;
+                let r = draw_outputs(
+                    snarl,
+                    viewer,
+                    node,
+                    &outputs,
+                    pin_size,
+                    style,
+                    ui,
+                    outputs_rect,
+                    payload_clip_rect,
+                    output_x,
+                    node_rect.min.y,
+                    node_rect.min.y + node_state.header_height(),
+                    output_spacing,
+                    snarl_state,
+                    config,
+                    modifiers,
+                    output_positions,
+                    node_layout.output_heights(&node_state),
+                );
+
+                let new_output_heights = r.new_heights;
+
+                drag_released |= r.drag_released;
+
+                if let Some(hovered) = r.pin_hovered {
+                    pick_hovered_pin(&mut pin_hovered, hovered);
+                }
+
+                let outputs_rect = r.final_rect;
+
+                new_pins_size = outputs_rect.size();
+
+                let mut next_y = outputs_rect.bottom() + ui.spacing().item_spacing.y;
+
+                if !snarl.nodes.contains(node.0) {
+                    // If removed
+                    return;
+                }
+
+                let mut pins_rect = outputs_rect;
+
+                // Show body if there's one.
+                if viewer.has_body(&snarl.nodes.get(node.0).unwrap().value) {
+                    let body_rect = payload_rect.intersect(Rect::everything_below(next_y));
+
+                    let r = draw_body(
+                        snarl,
+                        viewer,
+                        node,
+                        &inputs,
+                        &outputs,
+                        ui,
+                        body_rect,
+                        payload_clip_rect,
+                        snarl_state,
+                        snarl_id,
+                    );
+
+                    let body_rect = r.final_rect;
+
+                    new_pins_size.x = f32::max(new_pins_size.x, body_rect.width());
+                    new_pins_size.y += body_rect.height() + ui.spacing().item_spacing.y;
+
+                    if !snarl.nodes.contains(node.0) {
+                        // If removed
+                        return;
+                    }
+
+                    pins_rect = pins_rect.union(body_rect);
+                    next_y = body_rect.bottom() + ui.spacing().item_spacing.y;
+                }
+
+                // Show output pins.
+
+                let inputs_rect = payload_rect.intersect(Rect::everything_below(next_y));
+
+                let r = draw_inputs(
+                    snarl,
+                    viewer,
+                    node,
+                    &inputs,
+                    pin_size,
+                    style,
+                    ui,
+                    inputs_rect,
+                    payload_clip_rect,
+                    input_x,
+                    node_rect.min.y,
+                    node_rect.min.y + node_state.header_height(),
+                    input_spacing,
+                    snarl_state,
+                    config,
+                    modifiers,
+                    input_positions,
+                    node_layout.input_heights(&node_state),
+                );
+
+                let new_input_heights = r.new_heights;
+
+                drag_released |= r.drag_released;
+
+                if let Some(hovered) = r.pin_hovered {
+                    pick_hovered_pin(&mut pin_hovered, hovered);
+                }
+
+                let inputs_rect = r.final_rect;
+
+                if !snarl.nodes.contains(node.0) {
+                    // If removed
+                    return;
+                }
+
+                node_state.set_input_heights(new_input_heights);
+                node_state.set_output_heights(new_output_heights);
+
+                new_pins_size.x = f32::max(new_pins_size.x, inputs_rect.width());
+                new_pins_size.y += inputs_rect.height() + ui.spacing().item_spacing.y;
+
+                pins_rect = pins_rect.union(inputs_rect);
+
+                pins_rect
+            }
+
+            NodeLayoutKind::VerticalSandwichDown => {
+                // VerticalSandwichDown: inputs at top (horizontal), body, outputs at bottom (horizontal)
+                // Pin Y positions at top and bottom edges
+                let top_pin_y = match pin_placement {
+                    PinPlacement::Inside => node_rect.min.y + pin_size / 2.0,
+                    PinPlacement::Edge => node_rect.min.y,
+                    PinPlacement::Outside { margin } => node_rect.min.y - margin - pin_size / 2.0,
+                };
+                let bottom_pin_y = match pin_placement {
+                    PinPlacement::Inside => node_rect.max.y - pin_size / 2.0,
+                    PinPlacement::Edge => node_rect.max.y,
+                    PinPlacement::Outside { margin } => node_rect.max.y + margin + pin_size / 2.0,
+                };
+
+                // Draw inputs at top (horizontal row)
+                let r = draw_inputs_horizontal(
+                    snarl,
+                    viewer,
+                    node,
+                    &inputs,
+                    pin_size,
+                    style,
+                    ui,
+                    payload_rect,
+                    payload_clip_rect,
+                    top_pin_y,
+                    node_rect.min.x,
+                    node_rect.max.x,
+                    snarl_state,
+                    config,
+                    modifiers,
+                    input_positions,
+                    node_layout.input_widths(&node_state),
+                    true, // pins at top
+                );
+
+                let new_input_widths = r.new_widths;
+                drag_released |= r.drag_released;
+                if let Some(hovered) = r.pin_hovered {
+                    pick_hovered_pin(&mut pin_hovered, hovered);
+                }
+
+                let inputs_rect = r.final_rect;
+                new_pins_size = inputs_rect.size();
+                let mut next_y = inputs_rect.bottom() + ui.spacing().item_spacing.y;
+
+                if !snarl.nodes.contains(node.0) {
+                    return;
+                }
+
+                let mut pins_rect = inputs_rect;
+
+                // Show body
+                if viewer.has_body(&snarl.nodes[node.0].value) {
+                    let body_rect = payload_rect.intersect(Rect::everything_below(next_y));
+
+                    let r = draw_body(
+                        snarl,
+                        viewer,
+                        node,
+                        &inputs,
+                        &outputs,
+                        ui,
+                        body_rect,
+                        payload_clip_rect,
+                        snarl_state,
+                        snarl_id,
+                    );
+
+                    new_pins_size.x = f32::max(new_pins_size.x, r.final_rect.width());
+                    new_pins_size.y += r.final_rect.height() + ui.spacing().item_spacing.y;
+
+                    pins_rect = pins_rect.union(r.final_rect);
+                    next_y = r.final_rect.bottom() + ui.spacing().item_spacing.y;
+                }
+
+                // Draw outputs at bottom (horizontal row)
+                let outputs_area = payload_rect.intersect(Rect::everything_below(next_y));
+
+                let r = draw_outputs_horizontal(
+                    snarl,
+                    viewer,
+                    node,
+                    &outputs,
+                    pin_size,
+                    style,
+                    ui,
+                    outputs_area,
+                    payload_clip_rect,
+                    bottom_pin_y,
+                    node_rect.min.x,
+                    node_rect.max.x,
+                    snarl_state,
+                    config,
+                    modifiers,
+                    output_positions,
+                    node_layout.output_widths(&node_state),
+                    false, // pins at bottom
+                );
+
+                let new_output_widths = r.new_widths;
+                drag_released |= r.drag_released;
+                if let Some(hovered) = r.pin_hovered {
+                    pick_hovered_pin(&mut pin_hovered, hovered);
+                }
+
+                let outputs_rect = r.final_rect;
+
+                if !snarl.nodes.contains(node.0) {
+                    return;
+                }
+
+                node_state.set_input_widths(new_input_widths);
+                node_state.set_output_widths(new_output_widths);
+
+                new_pins_size.x = f32::max(new_pins_size.x, outputs_rect.width());
+                new_pins_size.y += outputs_rect.height() + ui.spacing().item_spacing.y;
+
+                pins_rect = pins_rect.union(outputs_rect);
+
+                pins_rect
+            }
+
+            NodeLayoutKind::VerticalSandwichUp => {
+                // VerticalSandwichUp: outputs at top (horizontal), body, inputs at bottom (horizontal)
+                // Pin Y positions at top and bottom edges
+                let top_pin_y = match pin_placement {
+                    PinPlacement::Inside => node_rect.min.y + pin_size / 2.0,
+                    PinPlacement::Edge => node_rect.min.y,
+                    PinPlacement::Outside { margin } => node_rect.min.y - margin - pin_size / 2.0,
+                };
+                let bottom_pin_y = match pin_placement {
+                    PinPlacement::Inside => node_rect.max.y - pin_size / 2.0,
+                    PinPlacement::Edge => node_rect.max.y,
+                    PinPlacement::Outside { margin } => node_rect.max.y + margin + pin_size / 2.0,
+                };
+
+                // Draw outputs at top (horizontal row)
+                let r = draw_outputs_horizontal(
+                    snarl,
+                    viewer,
+                    node,
+                    &outputs,
+                    pin_size,
+                    style,
+                    ui,
+                    payload_rect,
+                    payload_clip_rect,
+                    top_pin_y,
+                    node_rect.min.x,
+                    node_rect.max.x,
+                    snarl_state,
+                    config,
+                    modifiers,
+                    output_positions,
+                    node_layout.output_widths(&node_state),
+                    true, // pins at top
+                );
+
+                let new_output_widths = r.new_widths;
+                drag_released |= r.drag_released;
+                if let Some(hovered) = r.pin_hovered {
+                    pick_hovered_pin(&mut pin_hovered, hovered);
                 }
-            }
 
-            if r.drag_stopped() {
-                drag_released = true;
-            }
+                let outputs_rect = r.final_rect;
+                new_pins_size = outputs_rect.size();
+                let mut next_y = outputs_rect.bottom() + ui.spacing().item_spacing.y;
+
+                if !snarl.nodes.contains(node.0) {
+                    return;
+                }
 
-            let mut visual_pin_rect = r.rect;
+                let mut pins_rect = outputs_rect;
 
-            if r.contains_pointer() {
-                if snarl_state.has_new_wires_in() {
-                    if modifiers.shift && !modifiers.command {
-                        snarl_state.add_new_wire_in(in_pin.id);
-                    }
-                    if !modifiers.shift && modifiers.command {
-                        snarl_state.remove_new_wire_in(in_pin.id);
-                    }
+                // Show body
+                if viewer.has_body(&snarl.nodes[node.0].value) {
+                    let body_rect = payload_rect.intersect(Rect::everything_below(next_y));
+
+                    let r = draw_body(
+                        snarl,
+                        viewer,
+                        node,
+                        &inputs,
+                        &outputs,
+                        ui,
+                        body_rect,
+                        payload_clip_rect,
+                        snarl_state,
+                        snarl_id,
+                    );
+
+                    new_pins_size.x = f32::max(new_pins_size.x, r.final_rect.width());
+                    new_pins_size.y += r.final_rect.height() + ui.spacing().item_spacing.y;
+
+                    pins_rect = pins_rect.union(r.final_rect);
+                    next_y = r.final_rect.bottom() + ui.spacing().item_spacing.y;
                 }
-                pin_hovered = Some(AnyPin::In(in_pin.id));
-                visual_pin_rect = visual_pin_rect.scale_from_center(style.pin_hover_scale());
-            }
 
-            let wire_info =
-                snarl_pin.draw(style, pin_ui.style(), visual_pin_rect, pin_ui.painter());
+                // Draw inputs at bottom (horizontal row)
+                let inputs_area = payload_rect.intersect(Rect::everything_below(next_y));
 
-            input_positions.insert(
-                in_pin.id,
-                PinResponse {
-                    pos: r.rect.center(),
-                    wire_color: wire_info.color,
-                    wire_style: wire_info.style,
-                },
-            );
+                let r = draw_inputs_horizontal(
+                    snarl,
+                    viewer,
+                    node,
+                    &inputs,
+                    pin_size,
+                    style,
+                    ui,
+                    inputs_area,
+                    payload_clip_rect,
+                    bottom_pin_y,
+                    node_rect.min.x,
+                    node_rect.max.x,
+                    snarl_state,
+                    config,
+                    modifiers,
+                    input_positions,
+                    node_layout.input_widths(&node_state),
+                    false, // pins at bottom
+                );
 
-            new_widths.push(pin_ui.min_rect().width());
+                let new_input_widths = r.new_widths;
+                drag_released |= r.drag_released;
+                if let Some(hovered) = r.pin_hovered {
+                    pick_hovered_pin(&mut pin_hovered, hovered);
+                }
 
-            pin_ui.expand_to_include_x(outer_rect.right());
-        });
-    }
+                let inputs_rect = r.final_rect;
 
-    let final_rect = inputs_ui.min_rect();
-    node_ui.expand_to_include_rect(final_rect.intersect(payload_clip_rect));
+                if !snarl.nodes.contains(node.0) {
+                    return;
+                }
 
-    DrawPinsHorizontalResponse {
-        drag_released,
-        pin_hovered,
-        final_rect,
-        new_widths,
-    }
-}
+                node_state.set_input_widths(new_input_widths);
+                node_state.set_output_widths(new_output_widths);
 
-/// Draws output pins in a horizontal row (for vertical sandwich layouts).
-/// Pins are arranged left-to-right with pin icons at the edge and labels below/above.
-#[allow(clippy::too_many_arguments)]
-#[allow(clippy::too_many_lines)]
-fn draw_outputs_horizontal<T, V>(
-    snarl: &mut Snarl<T>,
-    viewer: &mut V,
-    node: NodeId,
-    outputs: &[OutPin],
-    pin_size: f32,
-    style: &SnarlStyle,
-    node_ui: &mut Ui,
-    outputs_rect: Rect,
-    payload_clip_rect: Rect,
-    output_y: f32,
-    min_pin_x_left: f32,
-    min_pin_x_right: f32,
-    snarl_state: &mut SnarlState,
-    config: &mut SnarlConfig,
-    modifiers: Modifiers,
-    output_positions: &mut HashMap<OutPinId, PinResponse>,
-    widths: Widths,
-    pins_at_top: bool,
-) -> DrawPinsHorizontalResponse
-where
-    V: SnarlViewer<T>,
-{
-    let mut drag_released = false;
-    let mut pin_hovered = None;
+                new_pins_size.x = f32::max(new_pins_size.x, inputs_rect.width());
+                new_pins_size.y += inputs_rect.height() + ui.spacing().item_spacing.y;
 
-    let mut outputs_ui = node_ui.new_child(
-        UiBuilder::new()
-            .max_rect(outputs_rect.round_ui())
-            .layout(Layout::left_to_right(Align::Center))
-            .id_salt("outputs_horizontal"),
-    );
+                pins_rect = pins_rect.union(inputs_rect);
 
-    let snarl_clip_rect = node_ui.clip_rect();
-    outputs_ui.shrink_clip_rect(payload_clip_rect);
+                pins_rect
+            }
+        };
 
-    // Pin layout: vertical stack with pin icon at edge, label on other side
-    let pin_layout = if pins_at_top {
-        Layout::top_down(Align::Center) // Pin at top, label below
-    } else {
-        Layout::bottom_up(Align::Center) // Pin at bottom, label above
-    };
+        if viewer.has_footer(&snarl.nodes[node.0].value) {
+            let footer_rect = Rect::from_min_max(
+                pos2(
+                    node_rect.left(),
+                    pins_rect.bottom() + ui.spacing().item_spacing.y,
+                ),
+                pos2(node_rect.right(), node_rect.bottom()),
+            );
 
-    let mut new_widths = SmallVec::with_capacity(outputs.len());
+            let mut footer_ui = ui.new_child(
+                UiBuilder::new()
+                    .max_rect(footer_rect.round_ui())
+                    .layout(Layout::left_to_right(Align::Min))
+                    .id_salt("footer"),
+            );
+            footer_ui.shrink_clip_rect(payload_clip_rect);
 
-    for out_pin in outputs {
-        let cursor = outputs_ui.cursor();
-        let (width, width_outer) = widths.get(out_pin.id.output);
+            viewer.show_footer(node, &inputs, &outputs, &mut footer_ui, snarl);
 
-        let margin = (width_outer - width) / 2.0;
-        let outer_rect = cursor.with_max_x(cursor.left() + width_outer);
-        let inner_rect = outer_rect.shrink2(vec2(margin, 0.0));
+            let final_rect = footer_ui.min_rect();
+            ui.expand_to_include_rect(final_rect.intersect(payload_clip_rect));
+            let footer_size = final_rect.size();
 
-        let builder = UiBuilder::new().layout(pin_layout).max_rect(inner_rect);
+            new_pins_size.x = f32::max(new_pins_size.x, footer_size.x);
+            new_pins_size.y += footer_size.y + ui.spacing().item_spacing.y;
 
-        outputs_ui.scope_builder(builder, |pin_ui| {
-            // Compute PinContext based on style.pin_label_visibility()
-            let is_connecting = snarl_state.has_new_wires();
-            let label_visible = match style.pin_label_visibility() {
-                PinLabelVisibility::Always => true,
-                PinLabelVisibility::OnHover => false,
-                PinLabelVisibility::WhileConnecting => is_connecting,
-                PinLabelVisibility::NearWire { .. } => is_connecting,
-            };
-            let pin_context = PinContext {
-                label_visible,
-                is_connecting,
-                is_hovered: false,
-                wire_near_node: is_connecting,
-            };
-            let snarl_pin = viewer.show_output(out_pin, pin_ui, pin_context, snarl);
             if !snarl.nodes.contains(node.0) {
+                // If removed
                 return;
             }
+        }
 
-            let content_rect = pin_ui.min_rect();
-            let x0 = content_rect.min.x;
-            let x1 = content_rect.max.x;
+        // Render header frame.
+        let mut header_rect = Rect::NAN;
 
-            let pin_rect = snarl_pin.pin_rect_horizontal(
-                output_y,
-                min_pin_x_left.max(x0),
-                min_pin_x_right.max(x1),
-                pin_size,
-            );
+        let mut header_frame_rect = Rect::NAN; //node_rect + header_frame.total_margin();
 
-            pin_ui.set_clip_rect(snarl_clip_rect);
+        // Show node's header
+        let header_ui: &mut Ui = &mut ui.new_child(
+            UiBuilder::new()
+                .max_rect(node_rect.round_ui() + header_frame.total_margin())
+                .layout(Layout::top_down(Align::Center))
+                .id_salt("header"),
+        );
 
-            let r = pin_ui.interact(pin_rect, pin_ui.next_auto_id(), Sense::click_and_drag());
-            pin_ui.skip_ahead_auto_ids(1);
+        header_frame.show(header_ui, |ui: &mut Ui| {
+            ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
+                if style.collapsible() {
+                    let (_, r) = ui.allocate_exact_size(
+                        vec2(ui.spacing().icon_width, ui.spacing().icon_width),
+                        Sense::click(),
+                    );
+                    paint_default_icon(ui, openness, &r);
 
-            if r.clicked_by(config.click_pin.mouse_button) {
-                if snarl_state.has_new_wires() {
-                    snarl_state.remove_new_wire_out(out_pin.id);
-                } else {
-                    viewer.drop_outputs(out_pin, snarl);
-                    if !snarl.nodes.contains(node.0) {
-                        return;
+                    if r.clicked_by(config.click_header.mouse_button) {
+                        // Toggle node's openness.
+                        snarl.open_node(node, !open);
                     }
                 }
-            }
-            if r.drag_started_by(config.drag_pin.mouse_button) {
-                if modifiers.contains(config.drag_pin.modifiers) {
-                    snarl_state.start_new_wires_in(&out_pin.remotes);
-                    if !modifiers.contains(config.no_menu.modifiers) {
-                        snarl.drop_outputs(out_pin.id);
-                        if !snarl.nodes.contains(node.0) {
-                            return;
-                        }
+
+                ui.allocate_exact_size(header_drag_space, Sense::hover());
+
+                if let Some(text) = snarl_state.title_edit_mut(node) {
+                    let r = ui.add(TextEdit::singleline(text).id(title_edit_id));
+
+                    if ui.input(|i| i.key_pressed(Key::Escape)) {
+                        let _ = snarl_state.take_title_edit();
+                    } else if r.lost_focus()
+                        && let Some(new_title) = snarl_state.take_title_edit()
+                    {
+                        viewer.rename_node(node, new_title, snarl);
                     }
                 } else {
-                    snarl_state.start_new_wire_out(out_pin.id);
+                    viewer.show_header(node, &inputs, &outputs, ui, snarl);
                 }
-            }
 
-            if r.drag_stopped() {
-                drag_released = true;
-            }
+                header_rect = ui.min_rect();
+            });
+
+            header_frame_rect = header_rect + header_frame.total_margin();
+
+            ui.advance_cursor_after_rect(Rect::from_min_max(
+                header_rect.min,
+                pos2(
+                    f32::max(header_rect.max.x, node_rect.max.x),
+                    header_rect.min.y,
+                ),
+            ));
+        });
 
-            let mut visual_pin_rect = r.rect;
+        ui.expand_to_include_rect(header_rect);
+        let header_size = header_rect.size();
+        node_state.set_header_height(header_size.y);
+        node_state.set_header_width(header_size.x);
 
-            if r.contains_pointer() {
-                if snarl_state.has_new_wires_out() {
-                    if modifiers.shift && !modifiers.command {
-                        snarl_state.add_new_wire_out(out_pin.id);
-                    }
-                    if !modifiers.shift && modifiers.command {
-                        snarl_state.remove_new_wire_out(out_pin.id);
-                    }
-                }
-                pin_hovered = Some(AnyPin::Out(out_pin.id));
-                visual_pin_rect = visual_pin_rect.scale_from_center(style.pin_hover_scale());
-            }
+        node_state.set_size(vec2(
+            f32::max(header_size.x, new_pins_size.x),
+            header_size.y
+                + header_frame.total_margin().bottom
+                + ui.spacing().item_spacing.y
+                + new_pins_size.y,
+        ));
+    });
 
-            let wire_info =
-                snarl_pin.draw(style, pin_ui.style(), visual_pin_rect, pin_ui.painter());
+    if !snarl.nodes.contains(node.0) {
+        snarl_state.request_repaint();
+        node_state.clear(ui.ctx());
+        // If removed
+        return None;
+    }
 
-            output_positions.insert(
-                out_pin.id,
-                PinResponse {
-                    pos: r.rect.center(),
-                    wire_color: wire_info.color,
-                    wire_style: wire_info.style,
-                },
-            );
+    if pin_hovered.is_none()
+        && !r.dragged()
+        && !snarl_state.has_new_wires()
+        && r.hovered()
+        && let Some(tooltip) = viewer.node_tooltip(node, snarl)
+    {
+        r.on_hover_text_at_pointer(tooltip);
+    }
 
-            new_widths.push(pin_ui.min_rect().width());
+    if style.wire_attach() == WireEndpoint::NodeEdge {
+        let frame_rect = frame_r.response.rect;
+        for pin in &inputs {
+            if let Some(r) = input_positions.get_mut(&pin.id) {
+                r.pos = edge_attach_pos(frame_rect, r);
+            }
+        }
+        for pin in &outputs {
+            if let Some(r) = output_positions.get_mut(&pin.id) {
+                r.pos = edge_attach_pos(frame_rect, r);
+            }
+        }
+    }
 
-            pin_ui.expand_to_include_x(outer_rect.right());
-        });
+    if selected && selection_visual == SelectionVisual::Fill {
+        paint_selection(ui.painter(), selection_visual, select_rect, &select_style);
     }
 
-    let final_rect = outputs_ui.min_rect();
-    node_ui.expand_to_include_rect(final_rect.intersect(payload_clip_rect));
+    viewer.final_node_rect(node, frame_r.response.rect, ui, snarl);
 
-    DrawPinsHorizontalResponse {
+    node_state.store(ui.ctx());
+    Some(DrawNodeResponse {
+        node_moved,
+        node_drag_stopped,
+        node_to_top,
         drag_released,
         pin_hovered,
-        final_rect,
-        new_widths,
-    }
+        final_rect: frame_r.response.rect,
+        hit_shape,
+        in_pins: inputs,
+        out_pins: outputs,
+        menu_passthrough,
+    })
 }
 
-#[allow(clippy::too_many_arguments)]
-fn draw_body<T, V>(
-    snarl: &mut Snarl<T>,
-    viewer: &mut V,
-    node: NodeId,
-    inputs: &[InPin],
-    outputs: &[OutPin],
-    ui: &mut Ui,
-    body_rect: Rect,
-    payload_clip_rect: Rect,
-    _snarl_state: &SnarlState,
-) -> DrawBodyResponse
-where
-    V: SnarlViewer<T>,
-{
-    let mut body_ui = ui.new_child(
-        UiBuilder::new()
-            .max_rect(body_rect.round_ui())
-            .layout(Layout::left_to_right(Align::Min))
-            .id_salt("body"),
-    );
-
-    body_ui.shrink_clip_rect(payload_clip_rect);
-
-    viewer.show_body(node, inputs, outputs, &mut body_ui, snarl);
-
-    let final_rect = body_ui.min_rect();
-    ui.expand_to_include_rect(final_rect.intersect(payload_clip_rect));
-    // node_state.set_body_width(body_size.x);
-
-    DrawBodyResponse { final_rect }
+/// Paints selection of the node with `visual` into `rect`.
+fn paint_selection(
+    painter: &egui::Painter,
+    visual: SelectionVisual,
+    rect: Rect,
+    select_style: &SelectionStyle,
+) {
+    match visual {
+        SelectionVisual::Highlight => {
+            painter.rect(
+                rect,
+                select_style.rounding,
+                select_style.fill,
+                select_style.stroke,
+                StrokeKind::Inside,
+            );
+        }
+        SelectionVisual::Outline => {
+            painter.rect_stroke(
+                rect,
+                select_style.rounding,
+                select_style.stroke,
+                StrokeKind::Inside,
+            );
+        }
+        SelectionVisual::Fill => {
+            painter.rect_filled(rect, select_style.rounding, select_style.fill);
+        }
+        SelectionVisual::CornerHandles => {
+            let len = (rect.size().min_elem() * 0.25).min(SELECTION_HANDLE_LENGTH);
+            let corners = [
+                (rect.left_top(), vec2(1.0, 1.0)),
+                (rect.right_top(), vec2(-1.0, 1.0)),
+                (rect.left_bottom(), vec2(1.0, -1.0)),
+                (rect.right_bottom(), vec2(-1.0, -1.0)),
+            ];
+            for (corner, dir) in corners {
+                painter.line(
+                    vec![
+                        corner + vec2(0.0, dir.y * len),
+                        corner,
+                        corner + vec2(dir.x * len, 0.0),
+                    ],
+                    select_style.stroke,
+                );
+            }
+        }
+    }
 }
 
-//First step for split big function to parts
-/// Draw one node. Return Pins info
-#[inline]
-#[allow(clippy::too_many_lines)]
+/// Draws node pinned to the widget at `align`, unaffected by pan and zoom.
+///
+/// Node is drawn in its own layer above the graph.
+/// Returned frame rect and positions of its pins are converted to graph space,
+/// so wires and hit-testing treat it like other nodes.
 #[allow(clippy::too_many_arguments)]
-fn draw_node<T, V>(
+fn draw_screen_node<T, V>(
     snarl: &mut Snarl<T>,
     ui: &mut Ui,
     node: NodeId,
+    align: Align2,
+    screen_rect: Rect,
+    to_global: TSTransform,
     viewer: &mut V,
     snarl_state: &mut SnarlState,
     config: &mut SnarlConfig,
@@ -2616,1086 +6368,2160 @@ fn draw_node<T, V>(
 where
     V: SnarlViewer<T>,
 {
-    let Node {
-        pos,
-        open,
-        ref value,
-    } = snarl.nodes[node.0];
+    // Frame offset from node position and size from the last frame.
+    let frame_id = snarl_id.with(("snarl-screen-node", node));
+    let (offset, size) = ui
+        .ctx()
+        .data(|d| d.get_temp::<(Vec2, Vec2)>(frame_id))
+        .unwrap_or_default();
+
+    let margin = ui.spacing().item_spacing.x * 2.0;
+    let frame = align.align_size_within_rect(size, screen_rect.shrink(margin));
+    let screen_pos = frame.min - offset;
+
+    // Separate layer above the graph without its transform.
+    let layer_id = LayerId::new(ui.layer_id().order, snarl_id.with("screen-nodes"));
+    ui.ctx().set_sublayer(ui.layer_id(), layer_id);
+
+    let mut screen_ui = ui.new_child(
+        UiBuilder::new()
+            .layer_id(layer_id)
+            .max_rect(screen_rect)
+            .id_salt(("screen-node", node)),
+    );
+    screen_ui.set_clip_rect(screen_rect);
 
-    // Collect pins
-    let inputs_count = viewer.inputs(value);
-    let outputs_count = viewer.outputs(value);
+    // Node is drawn at screen position, its graph position is restored after.
+    let world_pos = std::mem::replace(&mut snarl.nodes[node.0].pos, screen_pos);
+    let response = draw_node(
+        snarl,
+        &mut screen_ui,
+        node,
+        viewer,
+        snarl_state,
+        config,
+        style,
+        snarl_id,
+        input_positions,
+        modifiers,
+        output_positions,
+        false,
+        NodeDetail::Full,
+    );
+    if let Some(node) = snarl.nodes.get_mut(node.0) {
+        node.pos = world_pos;
+    }
+    let mut response = response?;
 
-    let inputs = (0..inputs_count)
-        .map(|idx| InPin::new(snarl, InPinId { node, input: idx }))
-        .collect::<Vec<_>>();
+    let new_frame = (
+        response.final_rect.min - screen_pos,
+        response.final_rect.size(),
+    );
+    if new_frame != (offset, size) {
+        ui.ctx().data_mut(|d| d.insert_temp(frame_id, new_frame));
+        snarl_state.request_repaint();
+    }
 
-    let outputs = (0..outputs_count)
-        .map(|idx| OutPin::new(snarl, OutPinId { node, output: idx }))
-        .collect::<Vec<_>>();
+    let from_global = to_global.inverse();
+    let to_graph = |r: &mut PinResponse| {
+        r.pos = from_global * r.pos;
+        if let Some(rail) = &mut r.rail {
+            rail.from = from_global * rail.from;
+            rail.to = from_global * rail.to;
+        }
+    };
+    for pin in &response.in_pins {
+        if let Some(r) = input_positions.get_mut(&pin.id) {
+            to_graph(r);
+        }
+    }
+    for pin in &response.out_pins {
+        if let Some(r) = output_positions.get_mut(&pin.id) {
+            to_graph(r);
+        }
+    }
 
-    let node_pos = pos.round_ui();
+    response.final_rect = from_global * response.final_rect;
+    response.node_moved = None;
+    response.node_drag_stopped = None;
+    response.node_to_top = None;
+    Some(response)
+}
 
-    // Generate persistent id for the node.
-    let node_id = snarl_id.with(("snarl-node", node));
+const fn mix_colors(a: Color32, b: Color32) -> Color32 {
+    #![allow(clippy::cast_possible_truncation)]
 
-    let openness = ui.ctx().animate_bool(node_id, open);
+    Color32::from_rgba_premultiplied(
+        u8::midpoint(a.r(), b.r()),
+        u8::midpoint(a.g(), b.g()),
+        u8::midpoint(a.b(), b.b()),
+        u8::midpoint(a.a(), b.a()),
+    )
+}
 
-    let mut node_state = NodeState::load(ui.ctx(), node_id, ui.spacing());
+// fn mix_colors(mut colors: impl Iterator<Item = Color32>) -> Option<Color32> {
+//     let color = colors.next()?;
 
-    let node_rect = node_state.node_rect(node_pos, openness);
+//     let mut r = color.r() as u32;
+//     let mut g = color.g() as u32;
+//     let mut b = color.b() as u32;
+//     let mut a = color.a() as u32;
+//     let mut w = 1;
 
-    let mut node_to_top = None;
-    let mut node_moved = None;
-    let mut drag_released = false;
-    let mut pin_hovered = None;
+//     for c in colors {
+//         r += c.r() as u32;
+//         g += c.g() as u32;
+//         b += c.b() as u32;
+//         a += c.a() as u32;
+//         w += 1;
+//     }
 
-    let node_frame =
-        viewer.node_frame(style.node_frame(ui.style()), node, &inputs, &outputs, snarl);
+//     Some(Color32::from_rgba_premultiplied(
+//         (r / w) as u8,
+//         (g / w) as u8,
+//         (b / w) as u8,
+//         (a / w) as u8,
+//     ))
+// }
 
-    let header_frame = viewer.header_frame(
-        style.header_frame(ui.style()),
-        node,
-        &inputs,
-        &outputs,
-        snarl,
-    );
+// fn mix_sizes(mut sizes: impl Iterator<Item = f32>) -> Option<f32> {
+//     let mut size = sizes.next()?;
+//     let mut w = 1;
+
+//     for s in sizes {
+//         size += s;
+//         w += 1;
+//     }
+
+//     Some(size / w as f32)
+// }
+
+// fn mix_strokes(mut strokes: impl Iterator<Item = Stroke>) -> Option<Stroke> {
+//     let stoke = strokes.next()?;
+
+//     let mut width = stoke.width;
+//     let mut r = stoke.color.r() as u32;
+//     let mut g = stoke.color.g() as u32;
+//     let mut b = stoke.color.b() as u32;
+//     let mut a = stoke.color.a() as u32;
+
+//     let mut w = 1;
+
+//     for s in strokes {
+//         width += s.width;
+//         r += s.color.r() as u32;
+//         g += s.color.g() as u32;
+//         b += s.color.b() as u32;
+//         a += s.color.a() as u32;
+//         w += 1;
+//     }
+
+//     Some(Stroke {
+//         width: width / w as f32,
+//         color: Color32::from_rgba_premultiplied(
+//             (r / w) as u8,
+//             (g / w) as u8,
+//             (b / w) as u8,
+//             (a / w) as u8,
+//         ),
+//     })
+// }
+
+impl<T> Snarl<T> {
+    /// Render [`Snarl`] using given viewer and style into the [`Ui`].
+    #[inline]
+    pub fn show<V>(
+        &mut self,
+        viewer: &mut V,
+        style: &SnarlStyle,
+        config: &SnarlConfig,
+        id_salt: impl Hash,
+        ui: &mut Ui,
+    ) where
+        V: SnarlViewer<T>,
+    {
+        show_snarl(
+            ui.make_persistent_id(id_salt),
+            *style,
+            *config,
+            Vec2::ZERO,
+            Vec2::INFINITY,
+            self,
+            viewer,
+            ui,
+        );
+    }
+
+    /// Renders `value` as a node at `pos` in screen space without adding it to any graph.
+    ///
+    /// Node is drawn with the same frame, header, pins and body as nodes of the graph,
+    /// semi-transparent, above other layers and without interaction.
+    /// Use it to preview a node dragged from an external palette before it is dropped.
+    ///
+    /// Call it every frame while the preview should be visible.
+    /// Node size is kept between frames under `id_salt`.
+    pub fn show_ghost_node<V>(
+        value: T,
+        pos: Pos2,
+        viewer: &mut V,
+        style: &SnarlStyle,
+        id_salt: impl Hash,
+        ui: &Ui,
+    ) where
+        V: SnarlViewer<T>,
+    {
+        let ghost_id = ui.make_persistent_id(id_salt);
 
-    // Rect for node + frame margin.
-    let node_frame_rect = node_rect + node_frame.total_margin();
+        let mut snarl = Snarl::new();
+        let node = snarl.insert_node(pos, value);
 
-    if snarl_state.selected_nodes().contains(&node) {
-        let select_style = style.select_style(ui.style());
+        egui::Area::new(ghost_id)
+            .order(egui::Order::Tooltip)
+            .fixed_pos(pos)
+            .interactable(false)
+            .show(ui.ctx(), |ui| {
+                ui.multiply_opacity(GHOST_NODE_OPACITY);
 
-        let select_rect = node_frame_rect + select_style.margin;
+                let mut snarl_state = SnarlState::detached(ghost_id, &snarl);
+                let mut config = SnarlConfig::new();
 
-        ui.painter().rect(
-            select_rect,
-            select_style.rounding,
-            select_style.fill,
-            select_style.stroke,
-            StrokeKind::Inside,
-        );
+                draw_node(
+                    &mut snarl,
+                    ui,
+                    node,
+                    viewer,
+                    &mut snarl_state,
+                    &mut config,
+                    style,
+                    ghost_id,
+                    &mut HashMap::new(),
+                    Modifiers::NONE,
+                    &mut HashMap::new(),
+                    false,
+                    NodeDetail::Full,
+                );
+            });
     }
 
-    // Size of the pin.
-    // Side of the square or diameter of the circle.
-    let pin_size = style.pin_size(ui.style()).max(0.0);
+    /// Measures size of the `node` as it is shown with `style`, without painting it.
+    ///
+    /// Header, pins, body and footer of the node are laid out in invisible `Ui`
+    /// until the size settles, so it matches the size the node is rendered at.
+    /// Use it to place nodes with auto-layout before they are shown.
+    ///
+    /// Layout depends on the style and fonts of `ctx`,
+    /// so this must be called while a frame is in progress.
+    ///
+    /// Returns size of the node frame in graph space,
+    /// or [`Vec2::ZERO`] if the node does not exist.
+    pub fn measure_node<V>(
+        &mut self,
+        node: NodeId,
+        viewer: &mut V,
+        style: &SnarlStyle,
+        ctx: &egui::Context,
+    ) -> Vec2
+    where
+        V: SnarlViewer<T>,
+    {
+        if !self.nodes.contains(node.0) {
+            return Vec2::ZERO;
+        }
 
-    let pin_placement = style.pin_placement();
+        let measure_id = Id::new("snarl-measure-node");
+        let mut ui = Ui::new(ctx.clone(), measure_id, UiBuilder::new().invisible());
+
+        // Node sizes are stored between passes, same as between frames.
+        NodeState::seed(ctx, measure_id.with(("snarl-node", node)), ui.spacing());
+
+        // Passes reuse widget ids of the node.
+        let warn_on_id_clash = ctx.options(|o| o.warn_on_id_clash);
+        ctx.options_mut(|o| o.warn_on_id_clash = false);
+
+        let mut size = Vec2::ZERO;
+        for _ in 0..MEASURE_NODE_PASSES {
+            let mut snarl_state = SnarlState::detached(measure_id, self);
+            let mut config = SnarlConfig::new();
+
+            let Some(response) = draw_node(
+                self,
+                &mut ui,
+                node,
+                viewer,
+                &mut snarl_state,
+                &mut config,
+                style,
+                measure_id,
+                &mut HashMap::new(),
+                Modifiers::NONE,
+                &mut HashMap::new(),
+                false,
+                NodeDetail::Full,
+            ) else {
+                size = Vec2::ZERO;
+                break;
+            };
 
-    let header_drag_space = style.header_drag_space(ui.style()).max(Vec2::ZERO);
+            let new_size = response.final_rect.size();
+            let settled = (new_size - size).length_sq() < MEASURE_NODE_EPSILON;
+            size = new_size;
+            if settled {
+                break;
+            }
+        }
 
-    // Interact with node frame.
-    let r = ui.interact(
-        node_frame_rect,
-        node_id.with("frame"),
-        Sense::click_and_drag(),
-    );
+        ctx.options_mut(|o| o.warn_on_id_clash = warn_on_id_clash);
+        size
+    }
 
-    if !modifiers.contains(config.select_node.modifiers)
-        && !modifiers.contains(config.deselect_node.modifiers)
-        && r.dragged_by(config.click_node.mouse_button)
+    /// Removes wires connected to pins that no longer exist.
+    /// Returns number of removed wires.
+    ///
+    /// Pin counts of each node are queried with [`SnarlViewer::inputs`] and [`SnarlViewer::outputs`].
+    /// Call this after node state changes reduce number of its pins.
+    pub fn prune_invalid_wires<V>(&mut self, viewer: &mut V) -> usize
+    where
+        V: SnarlViewer<T>,
     {
-        node_moved = Some((node, r.drag_delta()));
+        let pins = self
+            .nodes
+            .iter()
+            .map(|(idx, node)| {
+                let inputs = viewer.inputs(&node.value);
+                let outputs = viewer.outputs(&node.value);
+                (NodeId(idx), (inputs, outputs))
+            })
+            .collect::<HashMap<_, _>>();
+
+        self.wires.retain(|wire| {
+            let in_valid = pins
+                .get(&wire.in_pin.node)
+                .is_some_and(|&(inputs, _)| wire.in_pin.input < inputs);
+            let out_valid = pins
+                .get(&wire.out_pin.node)
+                .is_some_and(|&(_, outputs)| wire.out_pin.output < outputs);
+            in_valid && out_valid
+        })
     }
+}
 
-    if r.clicked_by(config.click_node.mouse_button) || r.dragged_by(config.drag_node.mouse_button) {
-        if modifiers.contains(config.select_node.modifiers) {
-            // In single_select mode, always clear previous selection
-            let reset = config.single_select || modifiers.command;
-            snarl_state.select_one_node(reset, node);
-        } else if modifiers.contains(config.deselect_node.modifiers) {
-            // Cmd+click: deselect
-            snarl_state.deselect_one_node(node);
-        } else {
-            // Plain click: select exclusively (deselect all others first)
-            snarl_state.select_one_node(true, node);
+/// Returns `node` and all nodes reachable from it following output to input wires.
+fn downstream_nodes<T>(snarl: &Snarl<T>, node: NodeId) -> Vec<NodeId> {
+    let mut visited = HashSet::new();
+    visited.insert(node);
+
+    let mut nodes = vec![node];
+    let mut idx = 0;
+    while let Some(&current) = nodes.get(idx) {
+        idx += 1;
+        for wire in snarl.wires.iter() {
+            if wire.out_pin.node == current && visited.insert(wire.in_pin.node) {
+                nodes.push(wire.in_pin.node);
+            }
         }
     }
+    nodes
+}
 
-    if r.clicked() || r.dragged() {
-        node_to_top = Some(node);
-    }
-
-    if viewer.has_node_menu(&snarl.nodes[node.0].value) {
-        r.context_menu(|ui| {
-            viewer.show_node_menu(node, &inputs, &outputs, ui, snarl);
-        });
-    }
+/// Returns true if wire should leave and enter pins vertically.
+///
+/// Wires attached to pins on top or bottom edges are always vertical.
+/// Otherwise vertical wire is used when Y distance > X distance (Houdini-style).
+fn is_vertical_wire(from: Pos2, to: Pos2, vertical_pins: bool) -> bool {
+    vertical_pins || (to.y - from.y).abs() > (to.x - from.x).abs()
+}
 
-    if !snarl.nodes.contains(node.0) {
-        node_state.clear(ui.ctx());
-        // If removed
-        return None;
+/// Returns node layout passed as default to [`SnarlViewer::node_layout`].
+///
+/// It is taken from the style and switched to top/bottom pins
+/// if [`SnarlViewer::pin_sides`] requests it.
+fn default_node_layout<T, V>(
+    viewer: &mut V,
+    style: &SnarlStyle,
+    node: NodeId,
+    snarl: &Snarl<T>,
+) -> NodeLayout
+where
+    V: SnarlViewer<T>,
+{
+    let mut layout = style.node_layout();
+    if viewer.pin_sides(node, snarl) == PinSides::TopBottom && !layout.kind.is_vertical() {
+        layout.kind = NodeLayoutKind::VerticalSandwichDown;
     }
+    layout
+}
 
-    if viewer.has_on_hover_popup(&snarl.nodes[node.0].value) {
-        r.on_hover_ui_at_pointer(|ui| {
-            viewer.show_on_hover_popup(node, &inputs, &outputs, ui, snarl);
-        });
-    }
+/// Computes endpoint offsets fanning out parallel wires.
+///
+/// Wires are grouped by pair of nodes they connect.
+/// Wires in groups with more than one wire are spread `spacing` apart
+/// perpendicular to the average direction of the group.
+fn bundle_wire_offsets<T>(
+    snarl: &Snarl<T>,
+    output_info: &HashMap<OutPinId, PinResponse>,
+    input_info: &HashMap<InPinId, PinResponse>,
+    spacing: f32,
+) -> HashMap<crate::Wire, Vec2> {
+    let mut groups = HashMap::<(NodeId, NodeId), Vec<crate::Wire>>::new();
 
-    if !snarl.nodes.contains(node.0) {
-        node_state.clear(ui.ctx());
-        // If removed
-        return None;
+    for wire in snarl.wires.iter() {
+        if output_info.contains_key(&wire.out_pin) && input_info.contains_key(&wire.in_pin) {
+            groups
+                .entry((wire.out_pin.node, wire.in_pin.node))
+                .or_default()
+                .push(wire);
+        }
     }
 
-    let node_ui = &mut ui.new_child(
-        UiBuilder::new()
-            .max_rect(node_frame_rect.round_ui())
-            .layout(Layout::top_down(Align::Center))
-            .id_salt(node_id),
-    );
-
-    let mut new_pins_size = Vec2::ZERO;
+    let mut offsets = HashMap::new();
 
-    let r = node_frame.show(node_ui, |ui| {
-        if viewer.has_node_style(node, &inputs, &outputs, snarl) {
-            viewer.apply_node_style(ui.style_mut(), node, &inputs, &outputs, snarl);
+    for mut wires in groups.into_values() {
+        if wires.len() < 2 {
+            continue;
         }
 
-        // Input pins' center side by X axis.
-        let input_x = match pin_placement {
-            PinPlacement::Inside => pin_size.mul_add(
-                0.5,
-                node_frame_rect.left() + node_frame.inner_margin.leftf(),
-            ),
-            PinPlacement::Edge => node_frame_rect.left(),
-            PinPlacement::Outside { margin } => {
-                pin_size.mul_add(-0.5, node_frame_rect.left() - margin)
-            }
-        };
-
-        // Input pins' spacing required.
-        let input_spacing = match pin_placement {
-            PinPlacement::Inside => Some(pin_size),
-            PinPlacement::Edge => Some(
-                pin_size
-                    .mul_add(0.5, -node_frame.inner_margin.leftf())
-                    .max(0.0),
-            ),
-            PinPlacement::Outside { .. } => None,
-        };
+        // Keep order stable across frames.
+        wires.sort_by_key(|wire| (wire.out_pin.output, wire.in_pin.input));
 
-        // Output pins' center side by X axis.
-        let output_x = match pin_placement {
-            PinPlacement::Inside => pin_size.mul_add(
-                -0.5,
-                node_frame_rect.right() - node_frame.inner_margin.rightf(),
-            ),
-            PinPlacement::Edge => node_frame_rect.right(),
-            PinPlacement::Outside { margin } => {
-                pin_size.mul_add(0.5, node_frame_rect.right() + margin)
-            }
-        };
+        let dir = wires
+            .iter()
+            .map(|wire| input_info[&wire.in_pin].pos - output_info[&wire.out_pin].pos)
+            .fold(Vec2::ZERO, |acc, v| acc + v)
+            .normalized();
 
-        // Output pins' spacing required.
-        let output_spacing = match pin_placement {
-            PinPlacement::Inside => Some(pin_size),
-            PinPlacement::Edge => Some(
-                pin_size
-                    .mul_add(0.5, -node_frame.inner_margin.rightf())
-                    .max(0.0),
-            ),
-            PinPlacement::Outside { .. } => None,
+        let perp = if dir == Vec2::ZERO {
+            Vec2::Y
+        } else {
+            dir.rot90()
         };
 
-        // Input/output pin block
+        #[allow(clippy::cast_precision_loss)]
+        let center = (wires.len() - 1) as f32 / 2.0;
 
-        if (openness < 1.0 && open) || (openness > 0.0 && !open) {
-            ui.ctx().request_repaint();
+        for (idx, wire) in wires.into_iter().enumerate() {
+            #[allow(clippy::cast_precision_loss)]
+            let offset = (idx as f32 - center) * spacing;
+            offsets.insert(wire, perp * offset);
         }
+    }
 
-        // Pins are placed under the header and must not go outside of the header frame.
-        let payload_rect = Rect::from_min_max(
-            pos2(
-                node_rect.min.x,
-                node_rect.min.y
-                    + node_state.header_height()
-                    + header_frame.total_margin().bottom
-                    + ui.spacing().item_spacing.y
-                    - node_state.payload_offset(openness),
-            ),
-            node_rect.max,
-        );
-
-        let node_layout = viewer.node_layout(style.node_layout(), node, &inputs, &outputs, snarl);
-
-        let payload_clip_rect =
-            Rect::from_min_max(node_rect.min, pos2(node_rect.max.x, f32::INFINITY));
-
-        let pins_rect = match node_layout.kind {
-            NodeLayoutKind::Coil => {
-                // Show input pins.
-                let r = draw_inputs(
-                    snarl,
-                    viewer,
-                    node,
-                    &inputs,
-                    pin_size,
-                    style,
-                    ui,
-                    payload_rect,
-                    payload_clip_rect,
-                    input_x,
-                    node_rect.min.y,
-                    node_rect.min.y + node_state.header_height(),
-                    input_spacing,
-                    snarl_state,
-                    config,
-                    modifiers,
-                    input_positions,
-                    node_layout.input_heights(&node_state),
-                );
-
-                let new_input_heights = r.new_heights;
-
-                drag_released |= r.drag_released;
-
-                if r.pin_hovered.is_some() {
-                    pin_hovered = r.pin_hovered;
-                }
-
-                let inputs_rect = r.final_rect;
-                let inputs_size = inputs_rect.size();
-
-                if !snarl.nodes.contains(node.0) {
-                    // If removed
-                    return;
-                }
-
-                // Show output pins.
-
-                let r = draw_outputs(
-                    snarl,
-                    viewer,
-                    node,
-                    &outputs,
-                    pin_size,
-                    style,
-                    ui,
-                    payload_rect,
-                    payload_clip_rect,
-                    output_x,
-                    node_rect.min.y,
-                    node_rect.min.y + node_state.header_height(),
-                    output_spacing,
-                    snarl_state,
-                    config,
-                    modifiers,
-                    output_positions,
-                    node_layout.output_heights(&node_state),
-                );
-
-                let new_output_heights = r.new_heights;
-
-                drag_released |= r.drag_released;
-
-                if r.pin_hovered.is_some() {
-                    pin_hovered = r.pin_hovered;
-                }
-
-                let outputs_rect = r.final_rect;
-                let outputs_size = outputs_rect.size();
-
-                if !snarl.nodes.contains(node.0) {
-                    // If removed
-                    return;
-                }
-
-                node_state.set_input_heights(new_input_heights);
-                node_state.set_output_heights(new_output_heights);
-
-                new_pins_size = vec2(
-                    inputs_size.x + outputs_size.x + ui.spacing().item_spacing.x,
-                    f32::max(inputs_size.y, outputs_size.y),
-                );
-
-                let mut pins_rect = inputs_rect.union(outputs_rect);
+    offsets
+}
 
-                // Show body if there's one.
-                if viewer.has_body(&snarl.nodes.get(node.0).unwrap().value) {
-                    let body_rect = Rect::from_min_max(
-                        pos2(
-                            inputs_rect.right() + ui.spacing().item_spacing.x,
-                            payload_rect.top(),
-                        ),
-                        pos2(
-                            outputs_rect.left() - ui.spacing().item_spacing.x,
-                            payload_rect.bottom(),
-                        ),
-                    );
+/// Pairs reciprocal wires running in opposite directions between the same nodes.
+///
+/// Returns wires hidden by merging and wires drawn in their place with two arrowheads.
+fn bidirectional_wire_pairs<T>(
+    snarl: &Snarl<T>,
+    output_info: &HashMap<OutPinId, PinResponse>,
+    input_info: &HashMap<InPinId, PinResponse>,
+) -> (HashSet<crate::Wire>, HashSet<crate::Wire>) {
+    let mut groups = HashMap::<(NodeId, NodeId), (Vec<crate::Wire>, Vec<crate::Wire>)>::new();
 
-                    let r = draw_body(
-                        snarl,
-                        viewer,
-                        node,
-                        &inputs,
-                        &outputs,
-                        ui,
-                        body_rect,
-                        payload_clip_rect,
-                        snarl_state,
-                    );
+    for wire in snarl.wires.iter() {
+        let (a, b) = (wire.out_pin.node, wire.in_pin.node);
+        if a == b
+            || !output_info.contains_key(&wire.out_pin)
+            || !input_info.contains_key(&wire.in_pin)
+        {
+            continue;
+        }
 
-                    new_pins_size.x += r.final_rect.width() + ui.spacing().item_spacing.x;
-                    new_pins_size.y = f32::max(new_pins_size.y, r.final_rect.height());
+        if a < b {
+            groups.entry((a, b)).or_default().0.push(wire);
+        } else {
+            groups.entry((b, a)).or_default().1.push(wire);
+        }
+    }
 
-                    pins_rect = pins_rect.union(body_rect);
+    let mut merged = HashSet::new();
+    let mut double_headed = HashSet::new();
 
-                    if !snarl.nodes.contains(node.0) {
-                        // If removed
-                        return;
-                    }
-                }
+    for (mut forward, mut backward) in groups.into_values() {
+        // Keep pairing stable across frames.
+        forward.sort_unstable();
+        backward.sort_unstable();
 
-                pins_rect
-            }
-            NodeLayoutKind::Sandwich => {
-                // Show input pins.
+        for (drawn, hidden) in forward.into_iter().zip(backward) {
+            double_headed.insert(drawn);
+            merged.insert(hidden);
+        }
+    }
 
-                let r = draw_inputs(
-                    snarl,
-                    viewer,
-                    node,
-                    &inputs,
-                    pin_size,
-                    style,
-                    ui,
-                    payload_rect,
-                    payload_clip_rect,
-                    input_x,
-                    node_rect.min.y,
-                    node_rect.min.y + node_state.header_height(),
-                    input_spacing,
-                    snarl_state,
-                    config,
-                    modifiers,
-                    input_positions,
-                    node_layout.input_heights(&node_state),
-                );
+    (merged, double_headed)
+}
 
-                let new_input_heights = r.new_heights;
+/// Returns arrowhead pointing into the pin at the end of the wire.
+///
+/// Wires leave output pins and enter input pins along the node edge normal,
+/// so arrowhead direction is derived from the pin side.
+fn wire_arrowhead(pin: &PinResponse, output: bool, size: f32, color: Color32) -> Shape {
+    let dir = match (pin.vertical, output) {
+        (false, false) => Vec2::X,
+        (false, true) => -Vec2::X,
+        (true, false) => Vec2::Y,
+        (true, true) => -Vec2::Y,
+    };
 
-                drag_released |= r.drag_released;
+    let tip = pin.pos;
+    let base = tip - dir * size;
+    let half_width = dir.rot90() * size * 0.5;
 
-                if r.pin_hovered.is_some() {
-                    pin_hovered = r.pin_hovered;
-                }
+    Shape::convex_polygon(
+        vec![tip, base + half_width, base - half_width],
+        color,
+        Stroke::NONE,
+    )
+}
 
-                let inputs_rect = r.final_rect;
+/// Opacity of pins dimmed with [`SnarlStyle::highlight_compatible_pins`].
+const INCOMPATIBLE_PIN_OPACITY: f32 = 0.3;
 
-                new_pins_size = inputs_rect.size();
+/// Opacity of nodes shown with [`Snarl::show_ghost_node`].
+const GHOST_NODE_OPACITY: f32 = 0.6;
 
-                let mut next_y = inputs_rect.bottom() + ui.spacing().item_spacing.y;
+/// Maximum length of corner strokes of [`SelectionVisual::CornerHandles`].
+const SELECTION_HANDLE_LENGTH: f32 = 12.0;
 
-                if !snarl.nodes.contains(node.0) {
-                    // If removed
-                    return;
-                }
+/// Maximum number of layout passes made by [`Snarl::measure_node`].
+const MEASURE_NODE_PASSES: usize = 4;
 
-                let mut pins_rect = inputs_rect;
+/// Squared size change below which [`Snarl::measure_node`] considers the size settled.
+const MEASURE_NODE_EPSILON: f32 = 0.01;
 
-                // Show body if there's one.
-                if viewer.has_body(&snarl.nodes.get(node.0).unwrap().value) {
-                    let body_rect = payload_rect.intersect(Rect::everything_below(next_y));
+/// Scales `to_global` by `zoom` clamped to scale range, keeping `pos` in place.
+fn zoom_around(to_global: &mut TSTransform, pos: Pos2, zoom: f32, min_scale: f32, max_scale: f32) {
+    let scaling = (to_global.scaling * zoom).clamp(min_scale, max_scale);
+    let pos_in_graph = to_global.inverse() * pos;
+    to_global.scaling = scaling;
+    to_global.translation = pos.to_vec2() - pos_in_graph.to_vec2() * scaling;
+}
 
-                    let r = draw_body(
-                        snarl,
-                        viewer,
-                        node,
-                        &inputs,
-                        &outputs,
-                        ui,
-                        body_rect,
-                        payload_clip_rect,
-                        snarl_state,
-                    );
+/// Shows zoom buttons at the top-right corner of the graph.
+/// Returns number of zoom steps, positive to zoom in.
+fn show_zoom_buttons(ui: &mut Ui, snarl_id: Id, snarl_layer_id: LayerId, ui_rect: Rect) -> i32 {
+    // Separate layer above the graph without its transform.
+    let layer_id = LayerId::new(snarl_layer_id.order, snarl_id.with("zoom-buttons"));
+    ui.ctx().set_sublayer(snarl_layer_id, layer_id);
 
-                    let body_rect = r.final_rect;
+    let margin = ui.spacing().item_spacing.x * 2.0;
+    let mut buttons_ui = ui.new_child(
+        UiBuilder::new()
+            .layer_id(layer_id)
+            .max_rect(ui_rect.shrink(margin))
+            .layout(Layout::right_to_left(Align::Min)),
+    );
+    buttons_ui.set_clip_rect(ui_rect);
 
-                    new_pins_size.x = f32::max(new_pins_size.x, body_rect.width());
-                    new_pins_size.y += body_rect.height() + ui.spacing().item_spacing.y;
+    let mut steps = 0;
+    if buttons_ui.button("+").on_hover_text("Zoom in").clicked() {
+        steps += 1;
+    }
+    if buttons_ui.button("−").on_hover_text("Zoom out").clicked() {
+        steps -= 1;
+    }
+    steps
+}
 
-                    if !snarl.nodes.contains(node.0) {
-                        // If removed
-                        return;
-                    }
+/// Returns `style` with pin fill from [`SnarlViewer::pin_color`] if the viewer sets it.
+fn pin_style<T, V>(
+    style: &SnarlStyle,
+    viewer: &mut V,
+    pin: AnyPinId,
+    snarl: &Snarl<T>,
+) -> SnarlStyle
+where
+    V: SnarlViewer<T>,
+{
+    match viewer.pin_color(pin, snarl) {
+        Some(color) => SnarlStyle {
+            pin_fill: Some(color),
+            ..*style
+        },
+        None => *style,
+    }
+}
 
-                    pins_rect = pins_rect.union(body_rect);
-                    next_y = body_rect.bottom() + ui.spacing().item_spacing.y;
-                }
+/// Returns true if `pin` can't be connected to any of the wires being dragged
+/// and [`SnarlStyle::highlight_compatible_pins`] is enabled.
+fn is_pin_dimmed<T, V>(
+    style: &SnarlStyle,
+    snarl_state: &SnarlState,
+    viewer: &mut V,
+    pin: AnyPinId,
+    snarl: &Snarl<T>,
+) -> bool
+where
+    V: SnarlViewer<T>,
+{
+    let Some(new_wires) = snarl_state.new_wires() else {
+        return false;
+    };
 
-                // Show output pins.
+    // Full pins can't accept dragged wires regardless of compatibility.
+    let opposite = matches!(
+        (new_wires, pin),
+        (NewWires::In(_), AnyPinId::Out(_)) | (NewWires::Out(_), AnyPinId::In(_))
+    );
+    if opposite && is_pin_full(viewer, pin, snarl) {
+        return true;
+    }
 
-                let outputs_rect = payload_rect.intersect(Rect::everything_below(next_y));
+    if !style.highlight_compatible_pins() {
+        return false;
+    }
 
-                let r = draw_outputs(
-                    snarl,
-                    viewer,
-                    node,
-                    &outputs,
-                    pin_size,
-                    style,
-                    ui,
-                    outputs_rect,
-                    payload_clip_rect,
-                    output_x,
-                    node_rect.min.y,
-                    node_rect.min.y + node_state.header_height(),
-                    output_spacing,
-                    snarl_state,
-                    config,
-                    modifiers,
-                    output_positions,
-                    node_layout.output_heights(&node_state),
-                );
+    match new_wires {
+        NewWires::In(sources) => !sources.iter().any(|&source| {
+            AnyPinId::In(source) == pin || viewer.pin_compatible(AnyPinId::In(source), pin, snarl)
+        }),
+        NewWires::Out(sources) => !sources.iter().any(|&source| {
+            AnyPinId::Out(source) == pin || viewer.pin_compatible(AnyPinId::Out(source), pin, snarl)
+        }),
+    }
+}
 
-                let new_output_heights = r.new_heights;
+/// Returns true if `pin` has as many wires as [`SnarlViewer::max_connections`] allows.
+fn is_pin_full<T, V>(viewer: &V, pin: AnyPinId, snarl: &Snarl<T>) -> bool
+where
+    V: SnarlViewer<T>,
+{
+    viewer.max_connections(pin).is_some_and(|max| {
+        let wires = match pin {
+            AnyPinId::In(pin) => snarl.wires.wired_outputs(pin).count(),
+            AnyPinId::Out(pin) => snarl.wires.wired_inputs(pin).count(),
+        };
+        wires >= max
+    })
+}
 
-                drag_released |= r.drag_released;
+/// Draws buttons adding and removing pins after the node's input or output pins.
+///
+/// Buttons are shown only when allowed by the viewer.
+/// Wires of the removed last pin are dropped before the viewer removes it.
+#[allow(clippy::too_many_arguments)]
+fn draw_pin_count_buttons<T, V>(
+    ui: &mut Ui,
+    node: NodeId,
+    count: usize,
+    input: bool,
+    layout: Layout,
+    config: &SnarlConfig,
+    viewer: &mut V,
+    snarl: &mut Snarl<T>,
+) where
+    V: SnarlViewer<T>,
+{
+    if config.read_only {
+        return;
+    }
 
-                if r.pin_hovered.is_some() {
-                    pin_hovered = r.pin_hovered;
-                }
+    let (can_add, can_remove) = if input {
+        (
+            viewer.can_add_input(node, snarl),
+            count > 0 && viewer.can_remove_input(node, snarl),
+        )
+    } else {
+        (
+            viewer.can_add_output(node, snarl),
+            count > 0 && viewer.can_remove_output(node, snarl),
+        )
+    };
 
-                let outputs_rect = r.final_rect;
+    if !can_add && !can_remove {
+        return;
+    }
 
-                if !snarl.nodes.contains(node.0) {
-                    // If removed
-                    return;
-                }
+    let (add, remove) = ui
+        .with_layout(layout, |ui| {
+            let add = can_add && ui.small_button("+").clicked();
+            let remove = can_remove && ui.small_button("-").clicked();
+            (add, remove)
+        })
+        .inner;
 
-                node_state.set_input_heights(new_input_heights);
-                node_state.set_output_heights(new_output_heights);
+    if add {
+        if input {
+            viewer.add_input(node, snarl);
+        } else {
+            viewer.add_output(node, snarl);
+        }
+    }
 
-                new_pins_size.x = f32::max(new_pins_size.x, outputs_rect.width());
-                new_pins_size.y += outputs_rect.height() + ui.spacing().item_spacing.y;
+    if remove && snarl.nodes.contains(node.0) {
+        if input {
+            snarl.drop_inputs(InPinId {
+                node,
+                input: count - 1,
+            });
+            viewer.remove_input(node, snarl);
+        } else {
+            snarl.drop_outputs(OutPinId {
+                node,
+                output: count - 1,
+            });
+            viewer.remove_output(node, snarl);
+        }
+    }
+}
 
-                pins_rect = pins_rect.union(outputs_rect);
+/// Draws header row of the pin group with a toggle to collapse it.
+/// Returns whether the group is open and vertical center of the row.
+fn draw_pin_group_header(ui: &mut Ui, id: Id, label: &str, layout: Layout) -> (bool, f32) {
+    let mut state = CollapsingState::load_with_default_open(ui.ctx(), id, true);
+    let r = ui.with_layout(layout.with_cross_align(Align::Center), |ui| {
+        state.show_toggle_button(ui, paint_default_icon);
+        ui.label(RichText::new(label).small().weak());
+    });
+    let open = state.is_open();
+    state.store(ui.ctx());
+    (open, r.response.rect.center().y)
+}
 
-                pins_rect
-            }
-            NodeLayoutKind::FlippedSandwich => {
-                // Show input pins.
+/// Returns response for the pin hidden in collapsed group.
+/// Its wires attach to the group header.
+fn collapsed_pin_response<T, V>(
+    style: &SnarlStyle,
+    viewer: &mut V,
+    pin: AnyPinId,
+    pos: Pos2,
+    snarl: &Snarl<T>,
+    ui: &Ui,
+) -> PinResponse
+where
+    V: SnarlViewer<T>,
+{
+    PinResponse {
+        pos,
+        hit_rect: Rect::NOTHING,
+        wire_color: viewer
+            .pin_color(pin, snarl)
+            .unwrap_or_else(|| style.pin_fill(ui.style())),
+        wire_style: style.wire_style(),
+        vertical: false,
+        rail: None,
+    }
+}
 
-                let outputs_rect = payload_rect;
-                let r = draw_outputs(
-                    snarl,
-                    viewer,
-                    node,
-                    &outputs,
-                    pin_size,
-                    style,
-                    ui,
-                    outputs_rect,
-                    payload_clip_rect,
-                    output_x,
-                    node_rect.min.y,
-                    node_rect.min.y + node_state.header_height(),
-                    output_spacing,
-                    snarl_state,
-                    config,
-                    modifiers,
-                    output_positions,
-                    node_layout.output_heights(&node_state),
-                );
+/// Draws ring around the pin that reached [`SnarlViewer::max_connections`].
+fn draw_full_pin_marker(
+    snarl_style: &SnarlStyle,
+    style: &Style,
+    rect: Rect,
+    painter: &egui::Painter,
+) {
+    let stroke = snarl_style.pin_stroke(style);
+    let width = stroke.width.max(1.0);
+    painter.circle_stroke(
+        rect.center(),
+        rect.size().max_elem() * 0.5 + width * 2.0,
+        Stroke::new(width, stroke.color),
+    );
+}
 
-                let new_output_heights = r.new_heights;
+/// Wires dropped on pins with multiple ports
+/// waiting for the port to be chosen with [`SnarlViewer::choose_port`].
+#[derive(Clone)]
+struct PortChoice {
+    wires: Vec<(OutPinId, InPinId)>,
 
-                drag_released |= r.drag_released;
+    /// Drop position in graph space.
+    pos: Pos2,
 
-                if r.pin_hovered.is_some() {
-                    pin_hovered = r.pin_hovered;
-                }
+    /// Set on the frame of the drop, which must not cancel the choice.
+    fresh: bool,
+}
 
-                let outputs_rect = r.final_rect;
+impl PortChoice {
+    fn save(self, cx: &egui::Context, id: Id) {
+        cx.data_mut(|d| d.insert_temp(id.with("port-choice"), self));
+    }
 
-                new_pins_size = outputs_rect.size();
+    fn load(cx: &egui::Context, id: Id) -> Option<Self> {
+        cx.data(|d| d.get_temp(id.with("port-choice")))
+    }
 
-                let mut next_y = outputs_rect.bottom() + ui.spacing().item_spacing.y;
+    fn clear(cx: &egui::Context, id: Id) {
+        cx.data_mut(|d| d.remove::<Self>(id.with("port-choice")));
+    }
+}
 
-                if !snarl.nodes.contains(node.0) {
-                    // If removed
-                    return;
-                }
+/// Shows port picker for the first wire waiting for port choice.
+fn show_port_choice<T, V>(
+    snarl_id: Id,
+    config: &SnarlConfig,
+    to_global: TSTransform,
+    viewer: &mut V,
+    ui: &Ui,
+    snarl: &mut Snarl<T>,
+) where
+    V: SnarlViewer<T>,
+{
+    let Some(mut choice) = PortChoice::load(ui.ctx(), snarl_id) else {
+        return;
+    };
+
+    let Some(&(out_pin, in_pin)) = choice.wires.first() else {
+        PortChoice::clear(ui.ctx(), snarl_id);
+        return;
+    };
+
+    if !snarl.nodes.contains(out_pin.node.0) || !snarl.nodes.contains(in_pin.node.0) {
+        PortChoice::clear(ui.ctx(), snarl_id);
+        return;
+    }
+
+    let r = egui::Area::new(snarl_id.with("port-choice-area"))
+        .order(egui::Order::Foreground)
+        .fixed_pos(to_global * choice.pos)
+        .show(ui.ctx(), |ui| {
+            Frame::popup(ui.style())
+                .show(ui, |ui| viewer.choose_port(in_pin, out_pin, ui, snarl))
+                .inner
+        });
+
+    let cancel = !choice.fresh
+        && (r.response.clicked_elsewhere() || ui.input(|i| i.key_pressed(Key::Escape)));
 
-                let mut pins_rect = outputs_rect;
+    match r.inner {
+        Some(port) => {
+            if wires_capped(config, snarl) {
+                viewer.on_capacity_reached(CapacityLimit::Wires, snarl);
+                PortChoice::clear(ui.ctx(), snarl_id);
+                return;
+            }
 
-                // Show body if there's one.
-                if viewer.has_body(&snarl.nodes.get(node.0).unwrap().value) {
-                    let body_rect = payload_rect.intersect(Rect::everything_below(next_y));
+            viewer.connect_port(
+                &OutPin::new(snarl, out_pin),
+                &InPin::new(snarl, in_pin),
+                port,
+                snarl,
+            );
 
-                    let r = draw_body(
-                        snarl,
-                        viewer,
-                        node,
-                        &inputs,
-                        &outputs,
-                        ui,
-                        body_rect,
-                        payload_clip_rect,
-                        snarl_state,
-                    );
+            choice.wires.remove(0);
+            if choice.wires.is_empty() {
+                PortChoice::clear(ui.ctx(), snarl_id);
+            } else {
+                choice.save(ui.ctx(), snarl_id);
+            }
+        }
+        None if cancel => PortChoice::clear(ui.ctx(), snarl_id),
+        None if choice.fresh => {
+            choice.fresh = false;
+            choice.save(ui.ctx(), snarl_id);
+        }
+        None => {}
+    }
+}
 
-                    let body_rect = r.final_rect;
+/// Wires dropped onto several equally close pins, waiting for the pin choice.
+#[derive(Clone)]
+struct PinDropChoice {
+    new_wires: NewWires,
+    source_rail: Option<f32>,
 
-                    new_pins_size.x = f32::max(new_pins_size.x, body_rect.width());
-                    new_pins_size.y += body_rect.height() + ui.spacing().item_spacing.y;
+    /// Candidate pins with rail positions at the drop.
+    pins: Vec<(AnyPinId, Option<f32>)>,
 
-                    if !snarl.nodes.contains(node.0) {
-                        // If removed
-                        return;
-                    }
+    /// Drop position in graph space.
+    pos: Pos2,
 
-                    pins_rect = pins_rect.union(body_rect);
-                    next_y = body_rect.bottom() + ui.spacing().item_spacing.y;
-                }
+    /// Set on the frame of the drop, which must not cancel the choice.
+    fresh: bool,
+}
 
-                // Show output pins.
+impl PinDropChoice {
+    fn save(self, cx: &egui::Context, id: Id) {
+        cx.data_mut(|d| d.insert_temp(id.with("pin-drop-choice"), self));
+    }
 
-                let inputs_rect = payload_rect.intersect(Rect::everything_below(next_y));
+    fn load(cx: &egui::Context, id: Id) -> Option<Self> {
+        cx.data(|d| d.get_temp(id.with("pin-drop-choice")))
+    }
 
-                let r = draw_inputs(
-                    snarl,
-                    viewer,
-                    node,
-                    &inputs,
-                    pin_size,
-                    style,
-                    ui,
-                    inputs_rect,
-                    payload_clip_rect,
-                    input_x,
-                    node_rect.min.y,
-                    node_rect.min.y + node_state.header_height(),
-                    input_spacing,
-                    snarl_state,
-                    config,
-                    modifiers,
-                    input_positions,
-                    node_layout.input_heights(&node_state),
-                );
+    fn clear(cx: &egui::Context, id: Id) {
+        cx.data_mut(|d| d.remove::<Self>(id.with("pin-drop-choice")));
+    }
+}
 
-                let new_input_heights = r.new_heights;
+/// Shows picker of the pin to connect wires dropped onto equally close pins.
+fn show_pin_drop_choice<T, V>(
+    snarl_id: Id,
+    config: &SnarlConfig,
+    to_global: TSTransform,
+    viewer: &mut V,
+    ui: &Ui,
+    snarl: &mut Snarl<T>,
+) where
+    V: SnarlViewer<T>,
+{
+    let Some(mut choice) = PinDropChoice::load(ui.ctx(), snarl_id) else {
+        return;
+    };
 
-                drag_released |= r.drag_released;
+    choice.pins.retain(|(pin, _)| {
+        let node = match pin {
+            AnyPinId::In(in_pin) => in_pin.node,
+            AnyPinId::Out(out_pin) => out_pin.node,
+        };
+        snarl.nodes.contains(node.0)
+    });
 
-                if r.pin_hovered.is_some() {
-                    pin_hovered = r.pin_hovered;
-                }
+    if choice.pins.is_empty() {
+        PinDropChoice::clear(ui.ctx(), snarl_id);
+        return;
+    }
 
-                let inputs_rect = r.final_rect;
+    let r = egui::Area::new(snarl_id.with("pin-drop-choice-area"))
+        .order(egui::Order::Foreground)
+        .fixed_pos(to_global * choice.pos)
+        .show(ui.ctx(), |ui| {
+            Frame::popup(ui.style())
+                .show(ui, |ui| {
+                    let mut picked = None;
+                    for &(pin, rail) in &choice.pins {
+                        let label = match pin {
+                            AnyPinId::In(in_pin) => format!(
+                                "{} input {}",
+                                viewer.title(&snarl[in_pin.node]),
+                                in_pin.input
+                            ),
+                            AnyPinId::Out(out_pin) => format!(
+                                "{} output {}",
+                                viewer.title(&snarl[out_pin.node]),
+                                out_pin.output
+                            ),
+                        };
+                        if ui.button(label).clicked() {
+                            picked = Some((pin, rail));
+                        }
+                    }
+                    picked
+                })
+                .inner
+        });
 
-                if !snarl.nodes.contains(node.0) {
-                    // If removed
-                    return;
+    let cancel = !choice.fresh
+        && (r.response.clicked_elsewhere() || ui.input(|i| i.key_pressed(Key::Escape)));
+
+    match r.inner {
+        Some((pin, target_rail)) => {
+            PinDropChoice::clear(ui.ctx(), snarl_id);
+
+            let port_choices = connect_dropped_wires(
+                choice.new_wires,
+                pin,
+                choice.source_rail,
+                target_rail,
+                config,
+                viewer,
+                snarl,
+            );
+            if !port_choices.is_empty() {
+                PortChoice {
+                    wires: port_choices,
+                    pos: choice.pos,
+                    fresh: true,
                 }
+                .save(ui.ctx(), snarl_id);
+            }
+        }
+        None if cancel => PinDropChoice::clear(ui.ctx(), snarl_id),
+        None if choice.fresh => {
+            choice.fresh = false;
+            choice.save(ui.ctx(), snarl_id);
+        }
+        None => {}
+    }
+}
 
-                node_state.set_input_heights(new_input_heights);
-                node_state.set_output_heights(new_output_heights);
+/// Connects `new_wires` dropped onto `target` pin.
+///
+/// Returns wires waiting for the port choice.
+fn connect_dropped_wires<T, V>(
+    new_wires: NewWires,
+    target: AnyPinId,
+    source_rail: Option<f32>,
+    target_rail: Option<f32>,
+    config: &SnarlConfig,
+    viewer: &mut V,
+    snarl: &mut Snarl<T>,
+) -> Vec<(OutPinId, InPinId)>
+where
+    V: SnarlViewer<T>,
+{
+    if config.allow_same_kind_connections
+        && let Some(sources) = same_kind_sources(&new_wires, target)
+    {
+        for source in sources {
+            if viewer.validate_same_kind_connection(source, target, snarl) {
+                viewer.connect_same_kind(source, target, snarl);
+            }
+        }
+        return Vec::new();
+    }
 
-                new_pins_size.x = f32::max(new_pins_size.x, inputs_rect.width());
-                new_pins_size.y += inputs_rect.height() + ui.spacing().item_spacing.y;
+    let wires: Vec<_> = match (new_wires, target) {
+        (NewWires::In(in_pins), AnyPinId::Out(out_pin)) => in_pins
+            .into_iter()
+            .map(|in_pin| (out_pin, in_pin, target_rail, source_rail))
+            .collect(),
+        (NewWires::Out(out_pins), AnyPinId::In(in_pin)) => out_pins
+            .into_iter()
+            .map(|out_pin| (out_pin, in_pin, source_rail, target_rail))
+            .collect(),
+        _ => return Vec::new(),
+    };
 
-                pins_rect = pins_rect.union(inputs_rect);
+    let mut port_choices = Vec::new();
+    for (out_pin, in_pin, out_rail, in_rail) in wires {
+        if !viewer.validate_connection(&out_pin, &in_pin, snarl) {
+            continue;
+        }
+        if is_pin_full(viewer, AnyPinId::Out(out_pin), snarl)
+            || is_pin_full(viewer, AnyPinId::In(in_pin), snarl)
+        {
+            continue;
+        }
+        if wires_capped(config, snarl) {
+            viewer.on_capacity_reached(CapacityLimit::Wires, snarl);
+            break;
+        }
+        if viewer.input_ports(in_pin, snarl) > 1 {
+            // Connection completes after the port is chosen.
+            port_choices.push((out_pin, in_pin));
+            continue;
+        }
+        viewer.connect_with_source(
+            &OutPin {
+                rail_pos: out_rail,
+                ..OutPin::new(snarl, out_pin)
+            },
+            &InPin {
+                rail_pos: in_rail,
+                ..InPin::new(snarl, in_pin)
+            },
+            EditSource::UserDrag,
+            snarl,
+        );
+    }
+    port_choices
+}
 
-                pins_rect
+/// Returns wires that bridge removed `nodes` for [`SnarlConfig::remove_node_reconnect`].
+///
+/// Node with exactly one incoming and one outgoing wire passes the connection through.
+/// Chains of such nodes are followed until the first node that is not removed.
+fn removal_bridges<T>(nodes: &[NodeId], snarl: &Snarl<T>) -> Vec<(OutPinId, InPinId)> {
+    let removed = nodes.iter().copied().collect::<HashSet<_>>();
+
+    let pass_through = |node: NodeId| {
+        let mut incoming = snarl.wires.iter().filter(|wire| wire.in_pin.node == node);
+        let mut outgoing = snarl.wires.iter().filter(|wire| wire.out_pin.node == node);
+        match (
+            incoming.next(),
+            incoming.next(),
+            outgoing.next(),
+            outgoing.next(),
+        ) {
+            (Some(incoming), None, Some(outgoing), None) => {
+                Some((incoming.out_pin, outgoing.in_pin))
             }
+            _ => None,
+        }
+    };
 
-            NodeLayoutKind::VerticalSandwichDown => {
-                // VerticalSandwichDown: inputs at top (horizontal), body, outputs at bottom (horizontal)
-                // Pin Y positions at top and bottom edges
-                let top_pin_y = match pin_placement {
-                    PinPlacement::Inside => node_rect.min.y + pin_size / 2.0,
-                    PinPlacement::Edge => node_rect.min.y,
-                    PinPlacement::Outside { margin } => node_rect.min.y - margin - pin_size / 2.0,
-                };
-                let bottom_pin_y = match pin_placement {
-                    PinPlacement::Inside => node_rect.max.y - pin_size / 2.0,
-                    PinPlacement::Edge => node_rect.max.y,
-                    PinPlacement::Outside { margin } => node_rect.max.y + margin + pin_size / 2.0,
-                };
+    let mut bridges = Vec::new();
+    for &node in nodes {
+        let Some((out_pin, mut in_pin)) = pass_through(node) else {
+            continue;
+        };
 
-                // Draw inputs at top (horizontal row)
-                let r = draw_inputs_horizontal(
-                    snarl,
-                    viewer,
-                    node,
-                    &inputs,
-                    pin_size,
-                    style,
-                    ui,
-                    payload_rect,
-                    payload_clip_rect,
-                    top_pin_y,
-                    node_rect.min.x,
-                    node_rect.max.x,
-                    snarl_state,
-                    config,
-                    modifiers,
-                    input_positions,
-                    node_layout.input_widths(&node_state),
-                    true, // pins at top
-                );
+        // Chain is bridged once, from the node fed by a kept node.
+        if removed.contains(&out_pin.node) {
+            continue;
+        }
 
-                let new_input_widths = r.new_widths;
-                drag_released |= r.drag_released;
-                if r.pin_hovered.is_some() {
-                    pin_hovered = r.pin_hovered;
-                }
+        // Each node in the chain has single input, so it can't loop back.
+        while removed.contains(&in_pin.node) {
+            let Some((_, next)) = pass_through(in_pin.node) else {
+                break;
+            };
+            in_pin = next;
+        }
 
-                let inputs_rect = r.final_rect;
-                new_pins_size = inputs_rect.size();
-                let mut next_y = inputs_rect.bottom() + ui.spacing().item_spacing.y;
+        if !removed.contains(&in_pin.node) && in_pin.node != out_pin.node {
+            bridges.push((out_pin, in_pin));
+        }
+    }
+    bridges
+}
 
-                if !snarl.nodes.contains(node.0) {
-                    return;
+/// Returns true if graph has [`SnarlConfig::max_nodes`] nodes.
+fn nodes_capped<T>(config: &SnarlConfig, snarl: &Snarl<T>) -> bool {
+    config
+        .max_nodes
+        .is_some_and(|max| snarl.node_count() >= max)
+}
+
+/// Finds closest pair of compatible pins within `threshold`,
+/// one of the `node` and one of another node that is not `moving` with it.
+///
+/// Returns pins to connect and offset that moves the `node` to make them coincide.
+fn magnet_pins<T, V>(
+    node: NodeId,
+    moving: &[NodeId],
+    threshold: f32,
+    input_info: &HashMap<InPinId, PinResponse>,
+    output_info: &HashMap<OutPinId, PinResponse>,
+    viewer: &mut V,
+    snarl: &Snarl<T>,
+) -> Option<(OutPinId, InPinId, Vec2)>
+where
+    V: SnarlViewer<T>,
+{
+    let pairs = input_info.iter().flat_map(|(&in_pin, in_r)| {
+        output_info
+            .iter()
+            .filter(move |(out_pin, _)| {
+                if in_pin.node == node {
+                    !moving.contains(&out_pin.node)
+                } else {
+                    out_pin.node == node && !moving.contains(&in_pin.node)
                 }
+            })
+            .map(move |(&out_pin, out_r)| {
+                // Dropped node moves its pin onto the other one.
+                let delta = if in_pin.node == node {
+                    out_r.pos - in_r.pos
+                } else {
+                    in_r.pos - out_r.pos
+                };
+                (out_pin, in_pin, delta)
+            })
+    });
 
-                let mut pins_rect = inputs_rect;
+    let mut best = None;
+    let mut best_distance = threshold;
+    for (out_pin, in_pin, delta) in pairs {
+        let distance = delta.length();
+        if distance > best_distance
+            || snarl.wires.contains(&Wire { out_pin, in_pin })
+            || !viewer.validate_connection(&out_pin, &in_pin, snarl)
+            || is_pin_full(viewer, AnyPinId::Out(out_pin), snarl)
+            || is_pin_full(viewer, AnyPinId::In(in_pin), snarl)
+        {
+            continue;
+        }
+        best = Some((out_pin, in_pin, delta));
+        best_distance = distance;
+    }
+    best
+}
 
-                // Show body
-                if viewer.has_body(&snarl.nodes[node.0].value) {
-                    let body_rect = payload_rect.intersect(Rect::everything_below(next_y));
+/// Returns true if graph has [`SnarlConfig::max_wires`] wires.
+fn wires_capped<T>(config: &SnarlConfig, snarl: &Snarl<T>) -> bool {
+    config
+        .max_wires
+        .is_some_and(|max| snarl.wire_count() >= max)
+}
 
-                    let r = draw_body(
-                        snarl,
-                        viewer,
-                        node,
-                        &inputs,
-                        &outputs,
-                        ui,
-                        body_rect,
-                        payload_clip_rect,
-                        snarl_state,
-                    );
+/// Reverts nodes and wires added by the viewer past capacity limits.
+///
+/// Viewer inserts nodes and wires itself in menus and [`SnarlViewer::create_node_from_pin`],
+/// so graph contents are remembered before calling it
+/// and everything new above the limits is removed afterwards.
+struct CapacityGuard {
+    nodes: Option<HashSet<NodeId>>,
+    wires: Option<HashSet<(OutPinId, InPinId)>>,
+}
 
-                    new_pins_size.x = f32::max(new_pins_size.x, r.final_rect.width());
-                    new_pins_size.y += r.final_rect.height() + ui.spacing().item_spacing.y;
+impl CapacityGuard {
+    fn new<T>(config: &SnarlConfig, snarl: &Snarl<T>) -> Self {
+        CapacityGuard {
+            nodes: config
+                .max_nodes
+                .map(|_| snarl.node_ids().map(|(id, _)| id).collect()),
+            wires: config.max_wires.map(|_| snarl.wires().collect()),
+        }
+    }
 
-                    pins_rect = pins_rect.union(r.final_rect);
-                    next_y = r.final_rect.bottom() + ui.spacing().item_spacing.y;
+    fn enforce<T, V>(self, config: &SnarlConfig, viewer: &mut V, snarl: &mut Snarl<T>)
+    where
+        V: SnarlViewer<T>,
+    {
+        if let (Some(max), Some(before)) = (config.max_nodes, self.nodes)
+            && snarl.node_count() > max
+        {
+            let added = snarl
+                .node_ids()
+                .map(|(id, _)| id)
+                .filter(|id| !before.contains(id))
+                .collect::<Vec<_>>();
+
+            for node in added.into_iter().rev() {
+                if snarl.node_count() <= max {
+                    break;
                 }
+                snarl.remove_node(node);
+            }
+            viewer.on_capacity_reached(CapacityLimit::Nodes, snarl);
+        }
 
-                // Draw outputs at bottom (horizontal row)
-                let outputs_area = payload_rect.intersect(Rect::everything_below(next_y));
+        if let (Some(max), Some(before)) = (config.max_wires, self.wires)
+            && snarl.wire_count() > max
+        {
+            let added = snarl
+                .wires()
+                .filter(|wire| !before.contains(wire))
+                .collect::<Vec<_>>();
+
+            for (from, to) in added {
+                if snarl.wire_count() <= max {
+                    break;
+                }
+                snarl.disconnect(from, to);
+            }
+            viewer.on_capacity_reached(CapacityLimit::Wires, snarl);
+        }
+    }
+}
 
-                let r = draw_outputs_horizontal(
-                    snarl,
-                    viewer,
-                    node,
-                    &outputs,
-                    pin_size,
-                    style,
-                    ui,
-                    outputs_area,
-                    payload_clip_rect,
-                    bottom_pin_y,
-                    node_rect.min.x,
-                    node_rect.max.x,
-                    snarl_state,
-                    config,
-                    modifiers,
-                    output_positions,
-                    node_layout.output_widths(&node_state),
-                    false, // pins at bottom
-                );
+/// Offset applied to inserted node per step until it does not overlap other nodes.
+const INSERT_NUDGE_OFFSET: Vec2 = vec2(24.0, 24.0);
 
-                let new_output_widths = r.new_widths;
-                drag_released |= r.drag_released;
-                if r.pin_hovered.is_some() {
-                    pin_hovered = r.pin_hovered;
-                }
+/// Maximum number of nudge steps for inserted node.
+const INSERT_NUDGE_STEPS: usize = 32;
 
-                let outputs_rect = r.final_rect;
+/// Moves nodes inserted by the viewer off existing nodes
+/// with [`SnarlConfig::avoid_overlap_on_insert`].
+///
+/// Same as [`CapacityGuard`], graph contents are remembered before calling the viewer.
+struct OverlapGuard {
+    nodes: Option<HashSet<NodeId>>,
+}
 
-                if !snarl.nodes.contains(node.0) {
-                    return;
-                }
+impl OverlapGuard {
+    fn new<T>(config: &SnarlConfig, snarl: &Snarl<T>) -> Self {
+        OverlapGuard {
+            nodes: config
+                .avoid_overlap_on_insert
+                .then(|| snarl.node_ids().map(|(id, _)| id).collect()),
+        }
+    }
 
-                node_state.set_input_widths(new_input_widths);
-                node_state.set_output_widths(new_output_widths);
+    /// Nudges each new node by cascading offset
+    /// while its position lies within frame of another node.
+    /// Nodes not shown yet occupy just their position.
+    fn nudge<T, V>(
+        self,
+        node_frames: &HashMap<NodeId, (Vec2, Vec2)>,
+        viewer: &mut V,
+        snarl: &mut Snarl<T>,
+    ) where
+        V: SnarlViewer<T>,
+    {
+        let Some(before) = self.nodes else {
+            return;
+        };
 
-                new_pins_size.x = f32::max(new_pins_size.x, outputs_rect.width());
-                new_pins_size.y += outputs_rect.height() + ui.spacing().item_spacing.y;
+        let frame = |node: NodeId, pos: Pos2| match node_frames.get(&node) {
+            Some(&(offset, size)) => Rect::from_min_size(pos + offset, size),
+            None => Rect::from_min_size(pos, Vec2::ZERO),
+        };
 
-                pins_rect = pins_rect.union(outputs_rect);
+        let mut occupied = snarl
+            .node_ids()
+            .map(|(id, _)| id)
+            .filter(|id| before.contains(id))
+            .map(|id| frame(id, snarl.nodes[id.0].pos))
+            .collect::<Vec<_>>();
+
+        let added = snarl
+            .node_ids()
+            .map(|(id, _)| id)
+            .filter(|id| !before.contains(id))
+            .collect::<Vec<_>>();
+
+        for node in added {
+            let start = snarl.nodes[node.0].pos;
+            let mut pos = start;
+            for _ in 0..INSERT_NUDGE_STEPS {
+                if !occupied.iter().any(|rect| rect.contains(pos)) {
+                    break;
+                }
+                pos += INSERT_NUDGE_OFFSET;
+            }
+            occupied.push(frame(node, pos));
 
-                pins_rect
+            if pos != start {
+                snarl.nodes[node.0].pos = pos;
+                viewer.node_moved(node, pos, snarl);
             }
+        }
+    }
+}
 
-            NodeLayoutKind::VerticalSandwichUp => {
-                // VerticalSandwichUp: outputs at top (horizontal), body, inputs at bottom (horizontal)
-                // Pin Y positions at top and bottom edges
-                let top_pin_y = match pin_placement {
-                    PinPlacement::Inside => node_rect.min.y + pin_size / 2.0,
-                    PinPlacement::Edge => node_rect.min.y,
-                    PinPlacement::Outside { margin } => node_rect.min.y - margin - pin_size / 2.0,
-                };
-                let bottom_pin_y = match pin_placement {
-                    PinPlacement::Inside => node_rect.max.y - pin_size / 2.0,
-                    PinPlacement::Edge => node_rect.max.y,
-                    PinPlacement::Outside { margin } => node_rect.max.y + margin + pin_size / 2.0,
-                };
+#[inline]
+/// Clamps node position so that node's frame stays inside the `bounds`.
+/// Snaps node position to the grid aligning the grid's anchor point of the node.
+///
+/// Falls back to snapping position if node frame is unknown.
+fn snap_node(
+    grid: &SnapGrid,
+    node_frames: &HashMap<NodeId, (Vec2, Vec2)>,
+    node: NodeId,
+    pos: Pos2,
+) -> Pos2 {
+    let anchor = snap_anchor(grid, node_frames, node);
+    grid.snap(pos + anchor) - anchor
+}
 
-                // Draw outputs at top (horizontal row)
-                let r = draw_outputs_horizontal(
-                    snarl,
-                    viewer,
-                    node,
-                    &outputs,
-                    pin_size,
-                    style,
-                    ui,
-                    payload_rect,
-                    payload_clip_rect,
-                    top_pin_y,
-                    node_rect.min.x,
-                    node_rect.max.x,
-                    snarl_state,
-                    config,
-                    modifiers,
-                    output_positions,
-                    node_layout.output_widths(&node_state),
-                    true, // pins at top
-                );
+/// Returns offset of the node's point aligned to the grid from node position.
+fn snap_anchor(grid: &SnapGrid, node_frames: &HashMap<NodeId, (Vec2, Vec2)>, node: NodeId) -> Vec2 {
+    match (grid.anchor, node_frames.get(&node)) {
+        (SnapAnchor::Center, Some(&(offset, size))) => offset + size * 0.5,
+        _ => Vec2::ZERO,
+    }
+}
 
-                let new_output_widths = r.new_widths;
-                drag_released |= r.drag_released;
-                if r.pin_hovered.is_some() {
-                    pin_hovered = r.pin_hovered;
-                }
+/// Returns index of the grid cell the node at `pos` snaps to.
+fn node_cell(
+    grid: &SnapGrid,
+    node_frames: &HashMap<NodeId, (Vec2, Vec2)>,
+    node: NodeId,
+    pos: Pos2,
+) -> [i32; 2] {
+    grid.snap_to_cell(pos + snap_anchor(grid, node_frames, node))
+        .1
+}
 
-                let outputs_rect = r.final_rect;
-                new_pins_size = outputs_rect.size();
-                let mut next_y = outputs_rect.bottom() + ui.spacing().item_spacing.y;
+fn clamp_to_bounds(
+    bounds: Option<Rect>,
+    node_frames: &HashMap<NodeId, (Vec2, Vec2)>,
+    node: NodeId,
+    pos: Pos2,
+) -> Pos2 {
+    let Some(bounds) = bounds else {
+        return pos;
+    };
 
-                if !snarl.nodes.contains(node.0) {
-                    return;
-                }
+    let (offset, size) = node_frames.get(&node).copied().unwrap_or_default();
+    let frame = Rect::from_min_size(pos + offset, size);
 
-                let mut pins_rect = outputs_rect;
+    pos + pan_to_contain(bounds, frame)
+}
 
-                // Show body
-                if viewer.has_body(&snarl.nodes[node.0].value) {
-                    let body_rect = payload_rect.intersect(Rect::everything_below(next_y));
+fn clamp_scale(to_global: &mut TSTransform, min_scale: f32, max_scale: f32, ui_rect: Rect) {
+    if to_global.scaling >= min_scale && to_global.scaling <= max_scale {
+        return;
+    }
 
-                    let r = draw_body(
-                        snarl,
-                        viewer,
-                        node,
-                        &inputs,
-                        &outputs,
-                        ui,
-                        body_rect,
-                        payload_clip_rect,
-                        snarl_state,
-                    );
+    let new_scaling = to_global.scaling.clamp(min_scale, max_scale);
+    *to_global = scale_transform_around(to_global, new_scaling, ui_rect.center());
+}
 
-                    new_pins_size.x = f32::max(new_pins_size.x, r.final_rect.width());
-                    new_pins_size.y += r.final_rect.height() + ui.spacing().item_spacing.y;
+#[inline]
+#[must_use]
+fn transform_matching_points(from: Pos2, to: Pos2, scaling: f32) -> TSTransform {
+    TSTransform {
+        scaling,
+        translation: to.to_vec2() - from.to_vec2() * scaling,
+    }
+}
 
-                    pins_rect = pins_rect.union(r.final_rect);
-                    next_y = r.final_rect.bottom() + ui.spacing().item_spacing.y;
-                }
+/// Returns transform that fits `view` in graph space into `ui_rect`.
+#[inline]
+#[must_use]
+fn look_at_transform(view: Rect, ui_rect: Rect, min_scale: f32, max_scale: f32) -> TSTransform {
+    let scaling2 = ui_rect.size() / view.size();
+    let scaling = scaling2.min_elem().clamp(min_scale, max_scale);
 
-                // Draw inputs at bottom (horizontal row)
-                let inputs_area = payload_rect.intersect(Rect::everything_below(next_y));
+    transform_matching_points(view.center(), ui_rect.center(), scaling)
+}
 
-                let r = draw_inputs_horizontal(
-                    snarl,
-                    viewer,
-                    node,
-                    &inputs,
-                    pin_size,
-                    style,
-                    ui,
-                    inputs_area,
-                    payload_clip_rect,
-                    bottom_pin_y,
-                    node_rect.min.x,
-                    node_rect.max.x,
-                    snarl_state,
-                    config,
-                    modifiers,
-                    input_positions,
-                    node_layout.input_widths(&node_state),
-                    false, // pins at bottom
-                );
+#[inline]
+#[must_use]
+fn scale_transform_around(transform: &mut TSTransform, scaling: f32, point: Pos2) -> TSTransform {
+    let from = (point - transform.translation) / transform.scaling;
+    transform_matching_points(from, point, scaling)
+}
 
-                let new_input_widths = r.new_widths;
-                drag_released |= r.drag_released;
-                if r.pin_hovered.is_some() {
-                    pin_hovered = r.pin_hovered;
-                }
+#[test]
+const fn snarl_style_is_send_sync() {
+    const fn is_send_sync<T: Send + Sync>() {}
+    is_send_sync::<SnarlStyle>();
+}
 
-                let inputs_rect = r.final_rect;
+#[test]
+fn equidistant_pins_pick_topmost() {
+    let lower = HoveredPin {
+        pin: AnyPinId::In(InPinId {
+            node: NodeId(0),
+            input: 0,
+        }),
+        dist_sq: 4.0,
+    };
+    let upper = HoveredPin {
+        pin: AnyPinId::In(InPinId {
+            node: NodeId(1),
+            input: 0,
+        }),
+        dist_sq: 4.0,
+    };
 
-                if !snarl.nodes.contains(node.0) {
-                    return;
-                }
+    for _ in 0..4 {
+        assert_eq!(nearest_pin(&[lower, upper]), Some(upper));
+    }
+    assert_eq!(nearest_pin(&[upper, lower]), Some(lower));
 
-                node_state.set_input_widths(new_input_widths);
-                node_state.set_output_widths(new_output_widths);
+    let closer = HoveredPin {
+        dist_sq: 1.0,
+        ..lower
+    };
+    assert_eq!(nearest_pin(&[closer, upper]), Some(closer));
+
+    let new_wires = NewWires::Out(smallvec::smallvec![OutPinId {
+        node: NodeId(2),
+        output: 0,
+    }]);
+    assert_eq!(
+        pin_ties(&[lower, upper], &new_wires, false),
+        vec![upper.pin, lower.pin]
+    );
+    assert_eq!(
+        pin_ties(&[closer, upper], &new_wires, false),
+        vec![closer.pin]
+    );
+}
 
-                new_pins_size.x = f32::max(new_pins_size.x, inputs_rect.width());
-                new_pins_size.y += inputs_rect.height() + ui.spacing().item_spacing.y;
+#[test]
+fn measured_node_matches_rendered_size() {
+    struct Viewer(Option<Rect>);
 
-                pins_rect = pins_rect.union(inputs_rect);
+    impl SnarlViewer<u32> for Viewer {
+        fn title(&mut self, node: &u32) -> String {
+            format!("Node number {node}")
+        }
+        fn inputs(&mut self, _: &u32) -> usize {
+            2
+        }
+        fn outputs(&mut self, _: &u32) -> usize {
+            1
+        }
+        fn show_input(
+            &mut self,
+            _: &InPin,
+            ui: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<u32>,
+        ) -> impl SnarlPin + 'static {
+            ui.label("input pin");
+            PinInfo::circle()
+        }
+        fn show_output(
+            &mut self,
+            _: &OutPin,
+            ui: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<u32>,
+        ) -> impl SnarlPin + 'static {
+            ui.label("out");
+            PinInfo::circle()
+        }
+        fn final_node_rect(&mut self, _: NodeId, rect: Rect, _: &mut Ui, _: &mut Snarl<u32>) {
+            self.0 = Some(rect);
+        }
+    }
 
-                pins_rect
+    let ctx = egui::Context::default();
+    let style = SnarlStyle::new();
+    let mut snarl = Snarl::new();
+    let node = snarl.insert_node(pos2(0.0, 0.0), 7);
+    let mut viewer = Viewer(None);
+
+    let mut measured = Vec2::ZERO;
+    for _ in 0..8 {
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            measured = snarl.measure_node(node, &mut viewer, &style, ctx);
+            egui::CentralPanel::default().show(ctx, |ui| {
+                snarl.show(&mut viewer, &style, &SnarlConfig::new(), "snarl", ui);
+            });
+        });
+    }
+
+    let rendered = viewer.0.expect("node is rendered").size();
+    assert!(measured.x > 0.0 && measured.y > 0.0);
+    assert!(
+        (measured - rendered).length() < 0.5,
+        "measured {measured:?}, rendered {rendered:?}"
+    );
+}
+
+#[test]
+fn node_detail_hysteresis() {
+    let style = SnarlStyle::new()
+        .with_lod_title_zoom(0.5)
+        .with_lod_plain_zoom(0.25);
+
+    let detail = NodeDetail::Full.update(&style, 0.49);
+    assert_eq!(detail, NodeDetail::Title);
+    assert_eq!(detail.update(&style, 0.2), NodeDetail::Plain);
+
+    // Zooming back just past the threshold keeps reduced detail.
+    assert_eq!(detail.update(&style, 0.51), NodeDetail::Title);
+    assert_eq!(detail.update(&style, 0.6), NodeDetail::Full);
+    assert_eq!(NodeDetail::Plain.update(&style, 0.26), NodeDetail::Plain);
+    assert_eq!(NodeDetail::Plain.update(&style, 0.3), NodeDetail::Title);
+
+    assert_eq!(
+        NodeDetail::Full.update(&SnarlStyle::new(), 0.01),
+        NodeDetail::Full
+    );
+}
+
+#[test]
+fn pin_group_headers_add_rows() {
+    struct Viewer(bool);
+
+    impl SnarlViewer<()> for Viewer {
+        fn title(&mut self, (): &()) -> String {
+            "Node".to_owned()
+        }
+        fn inputs(&mut self, (): &()) -> usize {
+            3
+        }
+        fn outputs(&mut self, (): &()) -> usize {
+            0
+        }
+        fn show_input(
+            &mut self,
+            _: &InPin,
+            ui: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<()>,
+        ) -> impl SnarlPin + 'static {
+            ui.label("input");
+            PinInfo::circle()
+        }
+        fn show_output(
+            &mut self,
+            _: &OutPin,
+            _: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<()>,
+        ) -> impl SnarlPin + 'static {
+            PinInfo::circle()
+        }
+        fn input_groups(
+            &mut self,
+            _: NodeId,
+            _: &Snarl<()>,
+        ) -> Vec<(String, std::ops::Range<usize>)> {
+            if self.0 {
+                vec![("Main".to_owned(), 0..1), ("Options".to_owned(), 1..3)]
+            } else {
+                Vec::new()
             }
-        };
+        }
+    }
 
-        if viewer.has_footer(&snarl.nodes[node.0].value) {
-            let footer_rect = Rect::from_min_max(
-                pos2(
-                    node_rect.left(),
-                    pins_rect.bottom() + ui.spacing().item_spacing.y,
-                ),
-                pos2(node_rect.right(), node_rect.bottom()),
-            );
+    let ctx = egui::Context::default();
+    let style = SnarlStyle::new();
+    let mut snarl = Snarl::new();
+    let node = snarl.insert_node(pos2(0.0, 0.0), ());
 
-            let mut footer_ui = ui.new_child(
-                UiBuilder::new()
-                    .max_rect(footer_rect.round_ui())
-                    .layout(Layout::left_to_right(Align::Min))
-                    .id_salt("footer"),
-            );
-            footer_ui.shrink_clip_rect(payload_clip_rect);
+    let mut flat = Vec2::ZERO;
+    let mut grouped = Vec2::ZERO;
+    let _ = ctx.run(egui::RawInput::default(), |ctx| {
+        flat = snarl.measure_node(node, &mut Viewer(false), &style, ctx);
+        grouped = snarl.measure_node(node, &mut Viewer(true), &style, ctx);
+    });
 
-            viewer.show_footer(node, &inputs, &outputs, &mut footer_ui, snarl);
+    assert!(grouped.y > flat.y, "{grouped:?} <= {flat:?}");
+}
 
-            let final_rect = footer_ui.min_rect();
-            ui.expand_to_include_rect(final_rect.intersect(payload_clip_rect));
-            let footer_size = final_rect.size();
+#[test]
+fn scripted_wire_drag() {
+    struct Viewer;
 
-            new_pins_size.x = f32::max(new_pins_size.x, footer_size.x);
-            new_pins_size.y += footer_size.y + ui.spacing().item_spacing.y;
+    impl SnarlViewer<()> for Viewer {
+        fn title(&mut self, (): &()) -> String {
+            "Node".to_owned()
+        }
+        fn inputs(&mut self, (): &()) -> usize {
+            1
+        }
+        fn outputs(&mut self, (): &()) -> usize {
+            1
+        }
+        fn show_input(
+            &mut self,
+            _: &InPin,
+            _: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<()>,
+        ) -> impl SnarlPin + 'static {
+            PinInfo::circle()
+        }
+        fn show_output(
+            &mut self,
+            _: &OutPin,
+            _: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<()>,
+        ) -> impl SnarlPin + 'static {
+            PinInfo::circle()
+        }
+    }
 
-            if !snarl.nodes.contains(node.0) {
-                // If removed
-                return;
-            }
+    let ctx = egui::Context::default();
+    let style = SnarlStyle::new();
+    let mut snarl = Snarl::new();
+    let node = snarl.insert_node(pos2(0.0, 0.0), ());
+    let widget = SnarlWidget::new().id_salt("snarl");
+    let pin = AnyPinId::Out(OutPinId { node, output: 0 });
+    let missing = AnyPinId::Out(OutPinId { node, output: 1 });
+
+    let mut started = (false, false);
+    let mut interaction = Interaction::Idle;
+    for frame in 0..4 {
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                widget.style(style).show(&mut snarl, &mut Viewer, ui);
+                if frame == 2 {
+                    started = (
+                        widget.begin_wire_drag(ui, missing, &snarl, &mut Viewer),
+                        widget.begin_wire_drag(ui, pin, &snarl, &mut Viewer),
+                    );
+                }
+                interaction = widget.interaction(ui);
+            });
+        });
+    }
+
+    assert_eq!(started, (false, true));
+    assert_eq!(interaction, Interaction::DraggingWire);
+}
+
+#[test]
+fn wire_dragged_from_input_connects_in_canonical_order() {
+    struct Viewer(Vec<(OutPinId, InPinId)>);
+
+    impl SnarlViewer<()> for Viewer {
+        fn title(&mut self, (): &()) -> String {
+            "Node".to_owned()
+        }
+        fn inputs(&mut self, (): &()) -> usize {
+            1
+        }
+        fn outputs(&mut self, (): &()) -> usize {
+            1
+        }
+        fn show_input(
+            &mut self,
+            _: &InPin,
+            _: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<()>,
+        ) -> impl SnarlPin + 'static {
+            PinInfo::circle()
+        }
+        fn show_output(
+            &mut self,
+            _: &OutPin,
+            _: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<()>,
+        ) -> impl SnarlPin + 'static {
+            PinInfo::circle()
+        }
+        fn connect(&mut self, from: &OutPin, to: &InPin, snarl: &mut Snarl<()>) {
+            self.0.push((from.id, to.id));
+            snarl.connect(from.id, to.id);
         }
+    }
 
-        // Render header frame.
-        let mut header_rect = Rect::NAN;
+    let drag = |config: SnarlConfig| {
+        let ctx = egui::Context::default();
+        let screen = Rect::from_min_size(Pos2::ZERO, vec2(800.0, 600.0));
+        let mut snarl = Snarl::new();
+        let source = snarl.insert_node(pos2(0.0, 0.0), ());
+        let target = snarl.insert_node(pos2(300.0, 0.0), ());
+        let out_pin = OutPinId {
+            node: source,
+            output: 0,
+        };
+        let in_pin = InPinId {
+            node: target,
+            input: 0,
+        };
+        let widget = SnarlWidget::new().id_salt("snarl").config(config);
+        let mut viewer = Viewer(Vec::new());
+
+        // Returns screen positions of the pins.
+        let mut frame = |events: Vec<egui::Event>| {
+            let input = egui::RawInput {
+                screen_rect: Some(screen),
+                events,
+                ..Default::default()
+            };
+            let mut pins = (Pos2::ZERO, Pos2::ZERO);
+            let _ = ctx.run(input, |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    widget.show(&mut snarl, &mut viewer, ui);
+                    let geometry = HitGeometry::load(ctx, widget.get_id(ui.id())).unwrap();
+                    let in_pos = geometry.in_pins.iter().find(|p| p.0 == in_pin).unwrap().1;
+                    let out_pos = geometry.out_pins.iter().find(|p| p.0 == out_pin).unwrap().1;
+                    pins = (geometry.to_global * in_pos, geometry.to_global * out_pos);
+                });
+            });
+            pins
+        };
 
-        let mut header_frame_rect = Rect::NAN; //node_rect + header_frame.total_margin();
+        frame(Vec::new());
+        frame(Vec::new());
+        let (from, to) = frame(Vec::new());
+        let button = |pos, pressed| egui::Event::PointerButton {
+            pos,
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: Modifiers::NONE,
+        };
+        frame(vec![egui::Event::PointerMoved(from)]);
+        frame(vec![button(from, true)]);
+        frame(vec![egui::Event::PointerMoved(from + vec2(20.0, 0.0))]);
+        frame(vec![egui::Event::PointerMoved(to)]);
+        frame(vec![button(to, false)]);
+        frame(Vec::new());
+
+        (viewer.0, (out_pin, in_pin))
+    };
 
-        // Show node's header
-        let header_ui: &mut Ui = &mut ui.new_child(
-            UiBuilder::new()
-                .max_rect(node_rect.round_ui() + header_frame.total_margin())
-                .layout(Layout::top_down(Align::Center))
-                .id_salt("header"),
-        );
+    let (connected, wire) = drag(SnarlConfig::new());
+    assert_eq!(connected, [wire]);
 
-        header_frame.show(header_ui, |ui: &mut Ui| {
-            ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
-                if style.collapsible() {
-                    let (_, r) = ui.allocate_exact_size(
-                        vec2(ui.spacing().icon_width, ui.spacing().icon_width),
-                        Sense::click(),
-                    );
-                    paint_default_icon(ui, openness, &r);
+    let config = SnarlConfig {
+        bidirectional_drag: false,
+        ..SnarlConfig::new()
+    };
+    let (connected, _) = drag(config);
+    assert!(connected.is_empty());
+}
 
-                    if r.clicked_by(config.click_header.mouse_button) {
-                        // Toggle node's openness.
-                        snarl.open_node(node, !open);
-                    }
-                }
+#[test]
+fn zoom_keeps_center_in_place() {
+    let mut to_global = TSTransform::new(vec2(10.0, 20.0), 1.0);
+    let center = pos2(100.0, 50.0);
+    let center_in_graph = to_global.inverse() * center;
+
+    zoom_around(&mut to_global, center, 1.25, 0.2, 2.0);
+    assert!((to_global.scaling - 1.25).abs() < 1e-6);
+    assert!((to_global * center_in_graph - center).length() < 1e-3);
+
+    zoom_around(&mut to_global, center, 4.0, 0.2, 2.0);
+    assert!((to_global.scaling - 2.0).abs() < 1e-6);
+    assert!((to_global * center_in_graph - center).length() < 1e-3);
+}
 
-                ui.allocate_exact_size(header_drag_space, Sense::hover());
+#[test]
+fn screen_anchored_node_ignores_position() {
+    struct Viewer(Option<Rect>);
 
-                viewer.show_header(node, &inputs, &outputs, ui, snarl);
+    impl SnarlViewer<()> for Viewer {
+        fn title(&mut self, (): &()) -> String {
+            "Panel".to_owned()
+        }
+        fn inputs(&mut self, (): &()) -> usize {
+            0
+        }
+        fn outputs(&mut self, (): &()) -> usize {
+            1
+        }
+        fn show_input(
+            &mut self,
+            _: &InPin,
+            _: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<()>,
+        ) -> impl SnarlPin + 'static {
+            PinInfo::circle()
+        }
+        fn show_output(
+            &mut self,
+            _: &OutPin,
+            _: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<()>,
+        ) -> impl SnarlPin + 'static {
+            PinInfo::circle()
+        }
+        fn node_anchor(&self, _: NodeId) -> NodeAnchor {
+            NodeAnchor::Screen(Align2::RIGHT_TOP)
+        }
+        fn final_node_rect(&mut self, _: NodeId, rect: Rect, _: &mut Ui, _: &mut Snarl<()>) {
+            self.0 = Some(rect);
+        }
+    }
 
-                header_rect = ui.min_rect();
+    let ctx = egui::Context::default();
+    let screen = Rect::from_min_size(Pos2::ZERO, vec2(800.0, 600.0));
+    let style = SnarlStyle::new();
+    let mut snarl = Snarl::new();
+    let node = snarl.insert_node(pos2(-3000.0, 5000.0), ());
+    let mut viewer = Viewer(None);
+
+    for _ in 0..4 {
+        let input = egui::RawInput {
+            screen_rect: Some(screen),
+            ..Default::default()
+        };
+        let _ = ctx.run(input, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                snarl.show(&mut viewer, &style, &SnarlConfig::new(), "snarl", ui);
             });
-
-            header_frame_rect = header_rect + header_frame.total_margin();
-
-            ui.advance_cursor_after_rect(Rect::from_min_max(
-                header_rect.min,
-                pos2(
-                    f32::max(header_rect.max.x, node_rect.max.x),
-                    header_rect.min.y,
-                ),
-            ));
         });
+    }
 
-        ui.expand_to_include_rect(header_rect);
-        let header_size = header_rect.size();
-        node_state.set_header_height(header_size.y);
-        node_state.set_header_width(header_size.x);
+    let rect = viewer.0.unwrap();
+    assert!(screen.contains_rect(rect), "{rect:?}");
+    assert!(rect.right() > screen.right() - 40.0, "{rect:?}");
+    assert!(rect.top() < screen.top() + 40.0, "{rect:?}");
+    assert_eq!(snarl.nodes[node.0].pos, pos2(-3000.0, 5000.0));
+}
 
-        node_state.set_size(vec2(
-            f32::max(header_size.x, new_pins_size.x),
-            header_size.y
-                + header_frame.total_margin().bottom
-                + ui.spacing().item_spacing.y
-                + new_pins_size.y,
-        ));
-    });
+#[test]
+fn inserted_nodes_are_nudged_off_others() {
+    struct Viewer;
 
-    if !snarl.nodes.contains(node.0) {
-        ui.ctx().request_repaint();
-        node_state.clear(ui.ctx());
-        // If removed
-        return None;
+    impl SnarlViewer<()> for Viewer {
+        fn title(&mut self, (): &()) -> String {
+            String::new()
+        }
+        fn inputs(&mut self, (): &()) -> usize {
+            0
+        }
+        fn outputs(&mut self, (): &()) -> usize {
+            0
+        }
+        fn show_input(
+            &mut self,
+            _: &InPin,
+            _: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<()>,
+        ) -> impl SnarlPin + 'static {
+            PinInfo::circle()
+        }
+        fn show_output(
+            &mut self,
+            _: &OutPin,
+            _: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<()>,
+        ) -> impl SnarlPin + 'static {
+            PinInfo::circle()
+        }
     }
 
-    viewer.final_node_rect(node, r.response.rect, ui, snarl);
+    let mut config = SnarlConfig::new();
+    config.avoid_overlap_on_insert = true;
 
-    node_state.store(ui.ctx());
-    Some(DrawNodeResponse {
-        node_moved,
-        node_to_top,
-        drag_released,
-        pin_hovered,
-        final_rect: r.response.rect,
-        in_pins: inputs,
-        out_pins: outputs,
-    })
-}
+    let mut snarl = Snarl::new();
+    let existing = snarl.insert_node(pos2(0.0, 0.0), ());
+    let node_frames = HashMap::from([(existing, (Vec2::ZERO, vec2(60.0, 30.0)))]);
 
-const fn mix_colors(a: Color32, b: Color32) -> Color32 {
-    #![allow(clippy::cast_possible_truncation)]
+    let guard = OverlapGuard::new(&config, &snarl);
+    let a = snarl.insert_node(pos2(10.0, 10.0), ());
+    let b = snarl.insert_node(pos2(10.0, 10.0), ());
+    guard.nudge(&node_frames, &mut Viewer, &mut snarl);
 
-    Color32::from_rgba_premultiplied(
-        u8::midpoint(a.r(), b.r()),
-        u8::midpoint(a.g(), b.g()),
-        u8::midpoint(a.b(), b.b()),
-        u8::midpoint(a.a(), b.a()),
-    )
+    assert_eq!(snarl.nodes[existing.0].pos, pos2(0.0, 0.0));
+    assert_eq!(snarl.nodes[a.0].pos, pos2(34.0, 34.0));
+    assert_eq!(snarl.nodes[b.0].pos, pos2(58.0, 58.0));
 }
 
-// fn mix_colors(mut colors: impl Iterator<Item = Color32>) -> Option<Color32> {
-//     let color = colors.next()?;
+#[test]
+fn pin_count_buttons_add_row() {
+    struct Viewer(bool);
 
-//     let mut r = color.r() as u32;
-//     let mut g = color.g() as u32;
-//     let mut b = color.b() as u32;
-//     let mut a = color.a() as u32;
-//     let mut w = 1;
+    impl SnarlViewer<()> for Viewer {
+        fn title(&mut self, (): &()) -> String {
+            "Node".to_owned()
+        }
+        fn inputs(&mut self, (): &()) -> usize {
+            2
+        }
+        fn outputs(&mut self, (): &()) -> usize {
+            0
+        }
+        fn show_input(
+            &mut self,
+            _: &InPin,
+            ui: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<()>,
+        ) -> impl SnarlPin + 'static {
+            ui.label("input");
+            PinInfo::circle()
+        }
+        fn show_output(
+            &mut self,
+            _: &OutPin,
+            _: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<()>,
+        ) -> impl SnarlPin + 'static {
+            PinInfo::circle()
+        }
+        fn can_add_input(&mut self, _: NodeId, _: &Snarl<()>) -> bool {
+            self.0
+        }
+    }
 
-//     for c in colors {
-//         r += c.r() as u32;
-//         g += c.g() as u32;
-//         b += c.b() as u32;
-//         a += c.a() as u32;
-//         w += 1;
-//     }
+    let ctx = egui::Context::default();
+    let style = SnarlStyle::new();
+    let mut snarl = Snarl::new();
+    let node = snarl.insert_node(pos2(0.0, 0.0), ());
 
-//     Some(Color32::from_rgba_premultiplied(
-//         (r / w) as u8,
-//         (g / w) as u8,
-//         (b / w) as u8,
-//         (a / w) as u8,
-//     ))
-// }
+    let mut fixed = Vec2::ZERO;
+    let mut variadic = Vec2::ZERO;
+    let _ = ctx.run(egui::RawInput::default(), |ctx| {
+        fixed = snarl.measure_node(node, &mut Viewer(false), &style, ctx);
+        variadic = snarl.measure_node(node, &mut Viewer(true), &style, ctx);
+    });
 
-// fn mix_sizes(mut sizes: impl Iterator<Item = f32>) -> Option<f32> {
-//     let mut size = sizes.next()?;
-//     let mut w = 1;
+    assert!(variadic.y > fixed.y, "{variadic:?} <= {fixed:?}");
+}
 
-//     for s in sizes {
-//         size += s;
-//         w += 1;
-//     }
+#[test]
+fn removed_chain_is_bridged() {
+    let mut snarl = Snarl::new();
+    let a = snarl.insert_node(pos2(0.0, 0.0), ());
+    let b = snarl.insert_node(pos2(100.0, 0.0), ());
+    let c = snarl.insert_node(pos2(200.0, 0.0), ());
+    let d = snarl.insert_node(pos2(300.0, 0.0), ());
+
+    let out_pin = |node, output| OutPinId { node, output };
+    let in_pin = |node, input| InPinId { node, input };
+
+    snarl.connect(out_pin(a, 0), in_pin(b, 0));
+    snarl.connect(out_pin(b, 0), in_pin(c, 1));
+    snarl.connect(out_pin(c, 0), in_pin(d, 2));
+
+    assert_eq!(
+        removal_bridges(&[b, c], &snarl),
+        vec![(out_pin(a, 0), in_pin(d, 2))]
+    );
 
-//     Some(size / w as f32)
-// }
+    // Node with two outgoing wires is not bridged.
+    snarl.connect(out_pin(c, 1), in_pin(a, 0));
+    assert_eq!(removal_bridges(&[c], &snarl), Vec::new());
+}
 
-// fn mix_strokes(mut strokes: impl Iterator<Item = Stroke>) -> Option<Stroke> {
-//     let stoke = strokes.next()?;
+#[test]
+fn body_id_follows_id_source() {
+    struct Viewer(Option<Id>);
 
-//     let mut width = stoke.width;
-//     let mut r = stoke.color.r() as u32;
-//     let mut g = stoke.color.g() as u32;
-//     let mut b = stoke.color.b() as u32;
-//     let mut a = stoke.color.a() as u32;
+    impl SnarlViewer<u32> for Viewer {
+        fn title(&mut self, _: &u32) -> String {
+            "Node".to_owned()
+        }
+        fn inputs(&mut self, _: &u32) -> usize {
+            0
+        }
+        fn outputs(&mut self, _: &u32) -> usize {
+            0
+        }
+        fn show_input(
+            &mut self,
+            _: &InPin,
+            _: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<u32>,
+        ) -> impl SnarlPin + 'static {
+            PinInfo::circle()
+        }
+        fn show_output(
+            &mut self,
+            _: &OutPin,
+            _: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<u32>,
+        ) -> impl SnarlPin + 'static {
+            PinInfo::circle()
+        }
+        fn has_body(&mut self, _: &u32) -> bool {
+            true
+        }
+        fn show_body(
+            &mut self,
+            _: NodeId,
+            _: &[InPin],
+            _: &[OutPin],
+            ui: &mut Ui,
+            _: &mut Snarl<u32>,
+        ) {
+            self.0 = Some(ui.id());
+        }
+        fn node_id_source(&mut self, node: NodeId, snarl: &Snarl<u32>) -> Id {
+            Id::new(snarl[node])
+        }
+    }
 
-//     let mut w = 1;
+    let ctx = egui::Context::default();
+    let widget = SnarlWidget::new().id_salt("snarl");
+    let show = |snarl: &mut Snarl<u32>| {
+        let mut viewer = Viewer(None);
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                widget.show(snarl, &mut viewer, ui);
+            });
+        });
+        viewer.0
+    };
 
-//     for s in strokes {
-//         width += s.width;
-//         r += s.color.r() as u32;
-//         g += s.color.g() as u32;
-//         b += s.color.b() as u32;
-//         a += s.color.a() as u32;
-//         w += 1;
-//     }
+    let mut snarl = Snarl::new();
+    snarl.insert_node(pos2(0.0, 0.0), 7);
+    let before = show(&mut snarl);
 
-//     Some(Stroke {
-//         width: width / w as f32,
-//         color: Color32::from_rgba_premultiplied(
-//             (r / w) as u8,
-//             (g / w) as u8,
-//             (b / w) as u8,
-//             (a / w) as u8,
-//         ),
-//     })
-// }
+    // Same node reloaded under different id.
+    let mut reloaded = Snarl::new();
+    reloaded.insert_node(pos2(0.0, 0.0), 1);
+    reloaded.insert_node(pos2(0.0, 0.0), 7);
+    reloaded.remove_node(NodeId(0));
+    let after = show(&mut reloaded);
 
-impl<T> Snarl<T> {
-    /// Render [`Snarl`] using given viewer and style into the [`Ui`].
-    #[inline]
-    pub fn show<V>(
-        &mut self,
-        viewer: &mut V,
-        style: &SnarlStyle,
-        config: &SnarlConfig,
-        id_salt: impl Hash,
-        ui: &mut Ui,
-    ) where
-        V: SnarlViewer<T>,
-    {
-        show_snarl(
-            ui.make_persistent_id(id_salt),
-            *style,
-            *config,
-            Vec2::ZERO,
-            Vec2::INFINITY,
-            self,
-            viewer,
-            ui,
-        );
-    }
+    assert!(before.is_some());
+    assert_eq!(before, after);
 }
 
-#[inline]
-fn clamp_scale(to_global: &mut TSTransform, min_scale: f32, max_scale: f32, ui_rect: Rect) {
-    if to_global.scaling >= min_scale && to_global.scaling <= max_scale {
-        return;
-    }
+#[test]
+fn gradient_wire_blends_endpoint_colors() {
+    let from = pos2(0.0, 0.0);
+    let to = pos2(100.0, 50.0);
+    let mut shape = Shape::line(
+        vec![from, pos2(50.0, 25.0), to],
+        Stroke::new(2.0, Color32::WHITE),
+    );
+    gradient_wire(
+        &mut shape,
+        from,
+        to,
+        Color32::WHITE,
+        Color32::RED,
+        Color32::BLUE,
+    );
 
-    let new_scaling = to_global.scaling.clamp(min_scale, max_scale);
-    *to_global = scale_transform_around(to_global, new_scaling, ui_rect.center());
+    let Shape::Path(path) = &shape else {
+        panic!("wire must become a path");
+    };
+    let egui::epaint::ColorMode::UV(color) = &path.stroke.color else {
+        panic!("wire must be colored per vertex");
+    };
+
+    // Tessellator sees the path after the layer transform.
+    let rect = Rect::from_min_max(pos2(10.0, 10.0), pos2(210.0, 110.0));
+    assert_eq!(color(rect, pos2(10.0, 10.0)), Color32::RED);
+    assert_eq!(color(rect, pos2(210.0, 110.0)), Color32::BLUE);
+    assert_ne!(color(rect, pos2(110.0, 60.0)), Color32::RED);
 }
 
-#[inline]
-#[must_use]
-fn transform_matching_points(from: Pos2, to: Pos2, scaling: f32) -> TSTransform {
-    TSTransform {
-        scaling,
-        translation: to.to_vec2() - from.to_vec2() * scaling,
+#[test]
+fn click_toggles_node_selection() {
+    struct Viewer;
+
+    impl SnarlViewer<()> for Viewer {
+        fn title(&mut self, (): &()) -> String {
+            "Node".to_owned()
+        }
+        fn inputs(&mut self, (): &()) -> usize {
+            0
+        }
+        fn outputs(&mut self, (): &()) -> usize {
+            0
+        }
+        fn show_input(
+            &mut self,
+            _: &InPin,
+            _: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<()>,
+        ) -> impl SnarlPin + 'static {
+            PinInfo::circle()
+        }
+        fn show_output(
+            &mut self,
+            _: &OutPin,
+            _: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<()>,
+        ) -> impl SnarlPin + 'static {
+            PinInfo::circle()
+        }
     }
-}
 
-#[inline]
-#[must_use]
-fn scale_transform_around(transform: &mut TSTransform, scaling: f32, point: Pos2) -> TSTransform {
-    let from = (point - transform.translation) / transform.scaling;
-    transform_matching_points(from, point, scaling)
-}
+    let clicks = |config: SnarlConfig| {
+        let ctx = egui::Context::default();
+        let screen = Rect::from_min_size(Pos2::ZERO, vec2(800.0, 600.0));
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), ());
+        let b = snarl.insert_node(pos2(300.0, 0.0), ());
+        let widget = SnarlWidget::new().id_salt("snarl").config(config);
+
+        // Returns screen centers of the nodes and selected nodes.
+        let mut frame = |events: Vec<egui::Event>| {
+            let input = egui::RawInput {
+                screen_rect: Some(screen),
+                events,
+                ..Default::default()
+            };
+            let mut centers = HashMap::new();
+            let mut selected = Vec::new();
+            let _ = ctx.run(input, |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    widget.show(&mut snarl, &mut Viewer, ui);
+                    let geometry = HitGeometry::load(ctx, widget.get_id(ui.id())).unwrap();
+                    for &(node, rect, _) in &geometry.nodes {
+                        centers.insert(node, geometry.to_global * rect.center());
+                    }
+                    selected = widget.selected_nodes(ui);
+                });
+            });
+            (centers, selected)
+        };
 
-#[test]
-const fn snarl_style_is_send_sync() {
-    const fn is_send_sync<T: Send + Sync>() {}
-    is_send_sync::<SnarlStyle>();
+        frame(Vec::new());
+        frame(Vec::new());
+        let (centers, _) = frame(Vec::new());
+        let mut click = |node: NodeId| {
+            let pos = centers[&node];
+            let button = |pressed| egui::Event::PointerButton {
+                pos,
+                button: egui::PointerButton::Primary,
+                pressed,
+                modifiers: Modifiers::NONE,
+            };
+            frame(vec![egui::Event::PointerMoved(pos)]);
+            frame(vec![button(true)]);
+            let mut selected = frame(vec![button(false)]).1;
+            selected.sort_unstable();
+            selected
+        };
+
+        [click(a), click(b), click(a)].map(|selected| {
+            selected
+                .into_iter()
+                .map(|node| if node == a { 'a' } else { 'b' })
+                .collect::<String>()
+        })
+    };
+
+    assert_eq!(clicks(SnarlConfig::new()), ["a", "b", "a"]);
+
+    let config = SnarlConfig {
+        click_toggles_selection: true,
+        ..SnarlConfig::new()
+    };
+    assert_eq!(clicks(config), ["a", "ab", "b"]);
 }
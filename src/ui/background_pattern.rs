@@ -1,4 +1,4 @@
-use egui::{Painter, Rect, Style, Vec2, emath::Rot2, vec2};
+use egui::{Color32, Mesh, Painter, Rect, Style, Vec2, emath::Rot2, pos2, vec2};
 
 use super::SnarlStyle;
 
@@ -138,3 +138,135 @@ impl BackgroundPattern {
         }
     }
 }
+
+/// Decorative fill of the graph background drawn beneath [`BackgroundPattern`].
+///
+/// Unlike snap grid it does not affect node placement.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "egui-probe", derive(egui_probe::EguiProbe))]
+pub enum BackgroundStyle {
+    /// Only the background frame fill.
+    #[default]
+    Plain,
+
+    /// Solid fill over the background frame.
+    Solid(Color32),
+
+    /// Checkerboard with cells of `size` in graph space,
+    /// every other cell filled with `color`.
+    Checkerboard {
+        /// Size of the cell.
+        size: f32,
+
+        /// Color of the filled cells.
+        color: Color32,
+    },
+
+    /// Darkening towards the edges of the viewport.
+    Vignette {
+        /// Color at the edges.
+        color: Color32,
+
+        /// Width of the gradient as fraction of the smaller viewport side.
+        #[cfg_attr(feature = "egui-probe", egui_probe(range = 0.0..=0.5))]
+        width: f32,
+    },
+}
+
+impl BackgroundStyle {
+    /// Maximum number of checkerboard cells drawn.
+    /// Cells are merged when viewport is zoomed out further.
+    const MAX_CELLS: f32 = 10000.0;
+
+    /// Draws background style.
+    pub fn draw(&self, viewport: &Rect, painter: &Painter) {
+        match *self {
+            BackgroundStyle::Plain => {}
+            BackgroundStyle::Solid(color) => {
+                painter.rect_filled(*viewport, 0.0, color);
+            }
+            BackgroundStyle::Checkerboard { size, color } => {
+                draw_checkerboard(viewport, size, color, painter);
+            }
+            BackgroundStyle::Vignette { color, width } => {
+                draw_vignette(viewport, color, width, painter);
+            }
+        }
+    }
+}
+
+fn draw_checkerboard(viewport: &Rect, size: f32, color: Color32, painter: &Painter) {
+    if !viewport.is_positive() {
+        return;
+    }
+
+    let mut size = size.max(1.0);
+    while (viewport.width() / size) * (viewport.height() / size) > BackgroundStyle::MAX_CELLS {
+        size *= 2.0;
+    }
+
+    let min_x = (viewport.min.x / size).floor();
+    let min_y = (viewport.min.y / size).floor();
+    let max_x = (viewport.max.x / size).ceil();
+    let max_y = (viewport.max.y / size).ceil();
+
+    let mut mesh = Mesh::default();
+
+    #[allow(clippy::cast_possible_truncation)]
+    for y in min_y as i64..max_y as i64 {
+        #[allow(clippy::cast_possible_truncation)]
+        for x in min_x as i64..max_x as i64 {
+            if (x + y).rem_euclid(2) != 0 {
+                continue;
+            }
+
+            #[allow(clippy::cast_precision_loss)]
+            let cell =
+                Rect::from_min_size(pos2(x as f32 * size, y as f32 * size), vec2(size, size));
+            mesh.add_colored_rect(cell.intersect(*viewport), color);
+        }
+    }
+
+    painter.add(mesh);
+}
+
+fn draw_vignette(viewport: &Rect, color: Color32, width: f32, painter: &Painter) {
+    let width = viewport.width().min(viewport.height()) * width.clamp(0.0, 0.5);
+    if width <= 0.0 {
+        return;
+    }
+
+    let outer = *viewport;
+    let inner = viewport.shrink(width);
+
+    let mut mesh = Mesh::default();
+
+    let outer_corners = [
+        outer.left_top(),
+        outer.right_top(),
+        outer.right_bottom(),
+        outer.left_bottom(),
+    ];
+    let inner_corners = [
+        inner.left_top(),
+        inner.right_top(),
+        inner.right_bottom(),
+        inner.left_bottom(),
+    ];
+
+    for (&outer, &inner) in outer_corners.iter().zip(&inner_corners) {
+        mesh.colored_vertex(outer, color);
+        mesh.colored_vertex(inner, Color32::TRANSPARENT);
+    }
+
+    // Four trapezoids between outer and inner rects.
+    for side in 0..4 {
+        let a = side * 2;
+        let b = (side * 2 + 2) % 8;
+        mesh.add_triangle(a, b, a + 1);
+        mesh.add_triangle(a + 1, b, b + 1);
+    }
+
+    painter.add(mesh);
+}
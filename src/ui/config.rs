@@ -1,4 +1,9 @@
-use egui::{Color32, Modifiers, Painter, PointerButton, Pos2, Rect, Stroke};
+use egui::{
+    Align2, Color32, KeyboardShortcut, Margin, Modifiers, Painter, PointerButton, Pos2, Rect,
+    Stroke, ahash::HashMap, emath::Rot2,
+};
+
+use crate::Snarl;
 
 /// Struct holding keyboard modifiers and mouse button.
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -9,6 +14,98 @@ pub struct ModifierClick {
 
     /// Mouse buttons for this action.
     pub mouse_button: PointerButton,
+
+    /// How pressed modifiers are compared with [`ModifierClick::modifiers`].
+    /// Defaults to [`ModifierMatch::Exact`] when missing in deserialized data,
+    /// except for bindings of [`SnarlConfig`] documented to match [`ModifierMatch::AtLeast`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub matching: ModifierMatch,
+}
+
+/// How pressed modifiers are compared with modifiers of [`ModifierClick`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ModifierMatch {
+    /// Pressed modifiers must be exactly the action's modifiers.
+    #[default]
+    Exact,
+
+    /// Action's modifiers must be pressed, extra modifiers are allowed.
+    AtLeast,
+}
+
+impl ModifierClick {
+    /// Creates new [`ModifierClick`] that requires exactly `modifiers` to be held.
+    #[must_use]
+    pub const fn new(modifiers: Modifiers, mouse_button: PointerButton) -> Self {
+        ModifierClick {
+            modifiers,
+            mouse_button,
+            matching: ModifierMatch::Exact,
+        }
+    }
+
+    /// Sets how pressed modifiers are compared with this action's modifiers.
+    #[must_use]
+    pub const fn with_matching(mut self, matching: ModifierMatch) -> Self {
+        self.matching = matching;
+        self
+    }
+
+    /// Returns true if pressed `modifiers` match this action's modifiers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui::{Modifiers, PointerButton};
+    /// # use egui_snarl::ui::{ModifierClick, ModifierMatch};
+    /// let mut click = ModifierClick::new(Modifiers::SHIFT, PointerButton::Primary);
+    /// assert!(!click.matches(Modifiers::SHIFT | Modifiers::ALT));
+    ///
+    /// click.matching = ModifierMatch::AtLeast;
+    /// assert!(click.matches(Modifiers::SHIFT | Modifiers::ALT));
+    /// assert!(!click.matches(Modifiers::ALT));
+    /// ```
+    #[must_use]
+    pub fn matches(&self, modifiers: Modifiers) -> bool {
+        match self.matching {
+            ModifierMatch::Exact => modifiers == self.modifiers,
+            ModifierMatch::AtLeast => modifiers.contains(self.modifiers),
+        }
+    }
+
+    /// Returns true if pressed `modifiers` are exactly this action's modifiers,
+    /// whatever [`ModifierClick::matching`] is.
+    pub(crate) fn matches_exactly(self, modifiers: Modifiers) -> bool {
+        ModifierClick {
+            matching: ModifierMatch::Exact,
+            ..self
+        }
+        .matches(modifiers)
+    }
+}
+
+/// Deserializes [`ModifierClick`] matching [`ModifierMatch::AtLeast`] when `matching` is missing,
+/// as bindings deserialized with it were matched before the field was added.
+#[cfg(feature = "serde")]
+fn deserialize_at_least<'de, D>(deserializer: D) -> Result<ModifierClick, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    struct Repr {
+        modifiers: Modifiers,
+        mouse_button: PointerButton,
+        #[serde(default)]
+        matching: Option<ModifierMatch>,
+    }
+
+    let repr = <Repr as serde::Deserialize>::deserialize(deserializer)?;
+    Ok(ModifierClick {
+        modifiers: repr.modifiers,
+        mouse_button: repr.mouse_button,
+        matching: repr.matching.unwrap_or(ModifierMatch::AtLeast),
+    })
 }
 
 /// Type of snap grid for node positioning.
@@ -25,6 +122,10 @@ pub enum SnapGridType {
     /// Hexagonal grid with flat tops (horizontal orientation).
     /// Columns are offset vertically.
     HexFlat,
+    /// Vertical lanes, only x position is snapped.
+    Columns,
+    /// Horizontal lanes, only y position is snapped.
+    Rows,
 }
 
 /// Configuration for snap grid.
@@ -32,8 +133,9 @@ pub enum SnapGridType {
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SnapGrid {
     /// The size of each grid cell.
+    /// Zero, negative and non-finite sizes are treated as 1.0.
     pub size: f32,
-    /// The type of grid (quad or hex).
+    /// The type of grid (quad, hex or lanes).
     pub grid_type: SnapGridType,
     /// Whether to show the grid visually.
     pub visible: bool,
@@ -43,6 +145,36 @@ pub struct SnapGrid {
     /// Size of the snap point indicators when visible.
     /// Defaults to 3.0.
     pub point_size: f32,
+    /// Rotation of the grid lattice around graph origin in radians.
+    /// Positions are rotated into grid space, snapped and rotated back.
+    /// Defaults to 0.0.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rotation: f32,
+    /// Point of the node that is aligned to the grid.
+    /// Defaults to [`SnapAnchor::TopLeft`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub anchor: SnapAnchor,
+    /// Largest number of grid points or lanes drawn at once.
+    /// Denser grids are drawn with every n-th point or lane only.
+    /// Defaults to 10 000.
+    #[cfg_attr(feature = "serde", serde(default = "default_max_points"))]
+    pub max_points: usize,
+    /// Draw major points or lanes with stronger color every given number of cells.
+    /// Snapping is not affected.
+    /// Defaults to `None`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub major_every: Option<u32>,
+}
+
+/// Point of the node aligned to the snap grid.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SnapAnchor {
+    /// Node position, which is the top-left corner of the node.
+    #[default]
+    TopLeft,
+    /// Center of the node frame.
+    Center,
 }
 
 impl Default for SnapGrid {
@@ -53,20 +185,108 @@ impl Default for SnapGrid {
             visible: false,
             color: None,
             point_size: 3.0,
+            rotation: 0.0,
+            anchor: SnapAnchor::TopLeft,
+            max_points: DEFAULT_MAX_SNAP_GRID_POINTS,
+            major_every: None,
         }
     }
 }
 
+/// Smallest size of the snap grid cell.
+/// Zero, negative and non-finite sizes are replaced with it.
+const MIN_SNAP_GRID_SIZE: f32 = 1.0;
+
+/// Default [`SnapGrid::max_points`].
+const DEFAULT_MAX_SNAP_GRID_POINTS: usize = 10_000;
+
+/// Largest distance from graph origin to drawn part of the grid, in grid cells.
+const MAX_SNAP_GRID_EXTENT: f32 = 1e9;
+
+#[cfg(feature = "serde")]
+const fn default_max_points() -> usize {
+    DEFAULT_MAX_SNAP_GRID_POINTS
+}
+
+/// Number of rings of cells around occupied cell searched for a free one.
+const MAX_FREE_CELL_RINGS: i32 = 32;
+
+/// Clamps the snap grid cell `size` to a usable value.
+const fn valid_size(size: f32) -> f32 {
+    if size.is_finite() {
+        size.max(MIN_SNAP_GRID_SIZE)
+    } else {
+        MIN_SNAP_GRID_SIZE
+    }
+}
+
+/// Snaps coordinates to the nearest point of staggered lattice.
+///
+/// Lattice points lie on lines `line_spacing` apart across `line` axis,
+/// `point_spacing` apart along each line, with odd lines shifted by half of it.
+/// Both lines around the position are tried, as the nearest point of the closest line
+/// may be farther than one on the other line with different shift.
+///
+/// Returns snapped `(line, point)` coordinates and their `(line, point)` indices.
+#[allow(clippy::cast_possible_truncation)]
+fn snap_staggered(
+    line: f32,
+    point: f32,
+    line_spacing: f32,
+    point_spacing: f32,
+) -> ((f32, f32), (i32, i32)) {
+    let first = (line / line_spacing).floor();
+
+    let mut best = ((line, point), (0, 0));
+    let mut best_distance = f32::INFINITY;
+
+    for line_idx in [first, first + 1.0] {
+        let offset = if (line_idx as i64).rem_euclid(2) == 1 {
+            point_spacing / 2.0
+        } else {
+            0.0
+        };
+
+        let point_idx = ((point - offset) / point_spacing).round();
+
+        let snapped_line = line_idx * line_spacing;
+        let snapped_point = point_idx * point_spacing + offset;
+
+        let distance = (line - snapped_line).powi(2) + (point - snapped_point).powi(2);
+        if distance < best_distance {
+            best = (
+                (snapped_line, snapped_point),
+                (line_idx as i32, point_idx as i32),
+            );
+            best_distance = distance;
+        }
+    }
+
+    best
+}
+
+/// Iterates lattice indices in `min..=max` that are multiples of `stride`,
+/// so coarsened lattice stays in place while the view is panned.
+#[allow(clippy::cast_sign_loss)]
+fn strided(min: i32, max: i32, stride: i32) -> impl Iterator<Item = i32> {
+    let start = min.div_euclid(stride).saturating_mul(stride);
+    (start..=max).step_by(stride as usize)
+}
+
 impl SnapGrid {
     /// Create a new quad snap grid with the given size.
     #[must_use]
     pub const fn quad(size: f32) -> Self {
         Self {
-            size,
+            size: valid_size(size),
             grid_type: SnapGridType::Quad,
             visible: false,
             color: None,
             point_size: 3.0,
+            rotation: 0.0,
+            anchor: SnapAnchor::TopLeft,
+            max_points: DEFAULT_MAX_SNAP_GRID_POINTS,
+            major_every: None,
         }
     }
 
@@ -74,11 +294,15 @@ impl SnapGrid {
     #[must_use]
     pub const fn hex_pointy(size: f32) -> Self {
         Self {
-            size,
+            size: valid_size(size),
             grid_type: SnapGridType::HexPointy,
             visible: false,
             color: None,
             point_size: 3.0,
+            rotation: 0.0,
+            anchor: SnapAnchor::TopLeft,
+            max_points: DEFAULT_MAX_SNAP_GRID_POINTS,
+            major_every: None,
         }
     }
 
@@ -86,11 +310,47 @@ impl SnapGrid {
     #[must_use]
     pub const fn hex_flat(size: f32) -> Self {
         Self {
-            size,
+            size: valid_size(size),
             grid_type: SnapGridType::HexFlat,
             visible: false,
             color: None,
             point_size: 3.0,
+            rotation: 0.0,
+            anchor: SnapAnchor::TopLeft,
+            max_points: DEFAULT_MAX_SNAP_GRID_POINTS,
+            major_every: None,
+        }
+    }
+
+    /// Create a new snap grid of vertical lanes with the given spacing.
+    #[must_use]
+    pub const fn columns(spacing: f32) -> Self {
+        Self {
+            size: valid_size(spacing),
+            grid_type: SnapGridType::Columns,
+            visible: false,
+            color: None,
+            point_size: 3.0,
+            rotation: 0.0,
+            anchor: SnapAnchor::TopLeft,
+            max_points: DEFAULT_MAX_SNAP_GRID_POINTS,
+            major_every: None,
+        }
+    }
+
+    /// Create a new snap grid of horizontal lanes with the given spacing.
+    #[must_use]
+    pub const fn rows(spacing: f32) -> Self {
+        Self {
+            size: valid_size(spacing),
+            grid_type: SnapGridType::Rows,
+            visible: false,
+            color: None,
+            point_size: 3.0,
+            rotation: 0.0,
+            anchor: SnapAnchor::TopLeft,
+            max_points: DEFAULT_MAX_SNAP_GRID_POINTS,
+            major_every: None,
         }
     }
 
@@ -115,61 +375,204 @@ impl SnapGrid {
         self
     }
 
+    /// Set the grid rotation in radians.
+    #[must_use]
+    pub const fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Set the point of the node aligned to the grid.
+    #[must_use]
+    pub const fn with_anchor(mut self, anchor: SnapAnchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Set the largest number of grid points or lanes drawn at once.
+    #[must_use]
+    pub const fn with_max_points(mut self, max_points: usize) -> Self {
+        self.max_points = max_points;
+        self
+    }
+
+    /// Set number of cells between major points or lanes.
+    #[must_use]
+    pub const fn with_major_every(mut self, cells: u32) -> Self {
+        self.major_every = Some(cells);
+        self
+    }
+
     /// Snap a position to the nearest grid point.
     #[must_use]
     pub fn snap(&self, pos: Pos2) -> Pos2 {
-        match self.grid_type {
+        self.snap_to_cell(pos).0
+    }
+
+    /// Snap a position to the nearest grid point
+    /// and return it together with `[column, row]` index of the cell.
+    ///
+    /// Cell `[0, 0]` is at the graph origin.
+    /// Hex grids use offset coordinates:
+    /// odd rows of [`SnapGridType::HexPointy`] and odd columns of [`SnapGridType::HexFlat`]
+    /// are shifted by half of the cell towards positive axis.
+    /// Lane grids have only one index, the other is always `0`.
+    ///
+    /// Nodes snapped to the same cell get the same index.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::ui::SnapGrid;
+    /// let grid = SnapGrid::quad(10.0);
+    /// let (pos, cell) = grid.snap_to_cell(egui::pos2(21.0, -9.0));
+    /// assert_eq!(pos, egui::pos2(20.0, -10.0));
+    /// assert_eq!(cell, [2, -1]);
+    /// ```
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn snap_to_cell(&self, pos: Pos2) -> (Pos2, [i32; 2]) {
+        let size = self.cell_size();
+        let pos = self.graph_to_grid(pos);
+        let (snapped, cell) = match self.grid_type {
             SnapGridType::Quad => self.snap_quad(pos),
             SnapGridType::HexPointy => self.snap_hex_pointy(pos),
             SnapGridType::HexFlat => self.snap_hex_flat(pos),
-        }
+            SnapGridType::Columns => {
+                let col = (pos.x / size).round();
+                (Pos2::new(col * size, pos.y), [col as i32, 0])
+            }
+            SnapGridType::Rows => {
+                let row = (pos.y / size).round();
+                (Pos2::new(pos.x, row * size), [0, row as i32])
+            }
+        };
+        (self.grid_to_graph(snapped), cell)
     }
 
-    fn snap_quad(&self, pos: Pos2) -> Pos2 {
-        Pos2::new(
-            (pos.x / self.size).round() * self.size,
-            (pos.y / self.size).round() * self.size,
-        )
+    /// Returns position of the grid point of the cell with `[column, row]` index.
+    ///
+    /// Inverse of [`SnapGrid::snap_to_cell`].
+    /// Lane grids return point of the lane on the other axis through the origin.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn cell_pos(&self, cell: [i32; 2]) -> Pos2 {
+        let size = self.cell_size();
+        let stagger = |idx: i32| {
+            if idx.rem_euclid(2) == 1 {
+                size / 2.0
+            } else {
+                0.0
+            }
+        };
+        let [col, row] = cell;
+        let pos = match self.grid_type {
+            SnapGridType::Quad | SnapGridType::Columns | SnapGridType::Rows => {
+                Pos2::new(col as f32 * size, row as f32 * size)
+            }
+            SnapGridType::HexPointy => Pos2::new(
+                col as f32 * size + stagger(row),
+                row as f32 * size * 0.866_025_4,
+            ),
+            SnapGridType::HexFlat => Pos2::new(
+                col as f32 * size * 0.866_025_4,
+                row as f32 * size + stagger(col),
+            ),
+        };
+        self.grid_to_graph(pos)
     }
 
-    fn snap_hex_pointy(&self, pos: Pos2) -> Pos2 {
-        // Pointy-top hex: horizontal spacing is size, vertical spacing is size * sqrt(3)/2
-        // Odd rows are offset by size/2
-        let vert_spacing = self.size * 0.866_025_4; // sqrt(3)/2
-        let horiz_spacing = self.size;
+    /// Returns free cell nearest to the `cell` searching outward ring by ring.
+    ///
+    /// Returns `None` for lane grids or if no free cell is found nearby.
+    pub(crate) fn nearest_free_cell(
+        &self,
+        cell: [i32; 2],
+        is_free: impl Fn([i32; 2]) -> bool,
+    ) -> Option<[i32; 2]> {
+        if matches!(self.grid_type, SnapGridType::Columns | SnapGridType::Rows) {
+            return None;
+        }
+        if is_free(cell) {
+            return Some(cell);
+        }
 
-        // Find the row
-        let row = (pos.y / vert_spacing).round();
-        let snapped_y = row * vert_spacing;
+        let origin = self.cell_pos(cell);
+        let mut best: Option<([i32; 2], f32)> = None;
 
-        // Determine if this is an odd row (offset)
-        #[allow(clippy::cast_possible_truncation)]
-        let is_odd_row = (row as i32).abs() % 2 == 1;
-        let x_offset = if is_odd_row { horiz_spacing / 2.0 } else { 0.0 };
+        for ring in 1..=MAX_FREE_CELL_RINGS {
+            // Hex cells of the next ring may be closer than farthest ones of this ring,
+            // so finish one more ring after the first free cell is found.
+            if best.is_some_and(|(found, _)| {
+                (found[0] - cell[0]).abs().max((found[1] - cell[1]).abs()) < ring - 1
+            }) {
+                break;
+            }
 
-        let snapped_x = ((pos.x - x_offset) / horiz_spacing).round() * horiz_spacing + x_offset;
+            for dy in -ring..=ring {
+                for dx in -ring..=ring {
+                    if dx.abs() != ring && dy.abs() != ring {
+                        continue;
+                    }
+                    let candidate = [cell[0] + dx, cell[1] + dy];
+                    if !is_free(candidate) {
+                        continue;
+                    }
+                    let distance = self.cell_pos(candidate).distance_sq(origin);
+                    if best.is_none_or(|(_, best_distance)| distance < best_distance) {
+                        best = Some((candidate, distance));
+                    }
+                }
+            }
+        }
 
-        Pos2::new(snapped_x, snapped_y)
+        best.map(|(cell, _)| cell)
     }
 
-    fn snap_hex_flat(&self, pos: Pos2) -> Pos2 {
-        // Flat-top hex: vertical spacing is size, horizontal spacing is size * sqrt(3)/2
-        // Odd columns are offset by size/2
-        let horiz_spacing = self.size * 0.866_025_4; // sqrt(3)/2
-        let vert_spacing = self.size;
+    /// Returns size of the cell, clamped to a usable value
+    /// in case [`SnapGrid::size`] was set directly.
+    const fn cell_size(&self) -> f32 {
+        valid_size(self.size)
+    }
 
-        // Find the column
-        let col = (pos.x / horiz_spacing).round();
-        let snapped_x = col * horiz_spacing;
+    /// Rotates position from graph space into unrotated grid space.
+    fn graph_to_grid(&self, pos: Pos2) -> Pos2 {
+        if self.rotation == 0.0 {
+            return pos;
+        }
+        (Rot2::from_angle(-self.rotation) * pos.to_vec2()).to_pos2()
+    }
 
-        // Determine if this is an odd column (offset)
-        #[allow(clippy::cast_possible_truncation)]
-        let is_odd_col = (col as i32).abs() % 2 == 1;
-        let y_offset = if is_odd_col { vert_spacing / 2.0 } else { 0.0 };
+    /// Rotates position from unrotated grid space back into graph space.
+    fn grid_to_graph(&self, pos: Pos2) -> Pos2 {
+        if self.rotation == 0.0 {
+            return pos;
+        }
+        (Rot2::from_angle(self.rotation) * pos.to_vec2()).to_pos2()
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn snap_quad(&self, pos: Pos2) -> (Pos2, [i32; 2]) {
+        let size = self.cell_size();
+        let col = (pos.x / size).round();
+        let row = (pos.y / size).round();
+        (Pos2::new(col * size, row * size), [col as i32, row as i32])
+    }
 
-        let snapped_y = ((pos.y - y_offset) / vert_spacing).round() * vert_spacing + y_offset;
+    fn snap_hex_pointy(&self, pos: Pos2) -> (Pos2, [i32; 2]) {
+        // Pointy-top hex: horizontal spacing is size, vertical spacing is size * sqrt(3)/2
+        // Odd rows are offset by size/2
+        let size = self.cell_size();
+        let ((y, x), (row, col)) = snap_staggered(pos.y, pos.x, size * 0.866_025_4, size);
+        (Pos2::new(x, y), [col, row])
+    }
 
-        Pos2::new(snapped_x, snapped_y)
+    fn snap_hex_flat(&self, pos: Pos2) -> (Pos2, [i32; 2]) {
+        // Flat-top hex: vertical spacing is size, horizontal spacing is size * sqrt(3)/2
+        // Odd columns are offset by size/2
+        let size = self.cell_size();
+        let ((x, y), (col, row)) = snap_staggered(pos.x, pos.y, size * 0.866_025_4, size);
+        (Pos2::new(x, y), [col, row])
     }
 
     /// Get the effective stroke for drawing the grid.
@@ -188,6 +591,34 @@ impl SnapGrid {
             .unwrap_or(Color32::from_rgba_unmultiplied(128, 128, 128, 120))
     }
 
+    /// Get the effective stroke for drawing major lanes.
+    #[must_use]
+    pub fn major_stroke(&self) -> Stroke {
+        Stroke::new(2.0, self.major_point_color())
+    }
+
+    /// Get the effective color for drawing major points.
+    #[must_use]
+    pub fn major_point_color(&self) -> Color32 {
+        let [r, g, b, a] = self.point_color().to_srgba_unmultiplied();
+        Color32::from_rgba_unmultiplied(r, g, b, a.saturating_mul(2))
+    }
+
+    /// Checks if lattice line with index `idx` is major.
+    fn is_major(&self, idx: i32) -> bool {
+        self.major_every
+            .is_some_and(|n| n > 0 && i64::from(idx).rem_euclid(i64::from(n)) == 0)
+    }
+
+    /// Returns color and size of the lattice point with given indices.
+    fn point_style(&self, a: i32, b: i32, color: Color32, point_size: f32) -> (Color32, f32) {
+        if self.is_major(a) && self.is_major(b) {
+            (self.major_point_color(), point_size * 1.5)
+        } else {
+            (color, point_size)
+        }
+    }
+
     /// Draw the snap grid within the given viewport.
     pub fn draw(&self, viewport: &Rect, painter: &Painter) {
         if !self.visible {
@@ -197,38 +628,141 @@ impl SnapGrid {
         let color = self.point_color();
         let point_size = self.point_size;
 
+        // Lattice is iterated in grid space over bounding rect of rotated viewport.
+        let viewport = &self.grid_viewport(viewport);
+
+        // Lattice indices must fit in `i32`.
+        let extent = viewport
+            .min
+            .to_vec2()
+            .abs()
+            .max(viewport.max.to_vec2().abs())
+            .max_elem()
+            / self.cell_size();
+        if extent.is_nan() || extent > MAX_SNAP_GRID_EXTENT {
+            return;
+        }
+
         match self.grid_type {
             SnapGridType::Quad => self.draw_quad(viewport, painter, color, point_size),
             SnapGridType::HexPointy => self.draw_hex_pointy(viewport, painter, color, point_size),
             SnapGridType::HexFlat => self.draw_hex_flat(viewport, painter, color, point_size),
+            SnapGridType::Columns => self.draw_lanes(viewport, painter, true),
+            SnapGridType::Rows => self.draw_lanes(viewport, painter, false),
+        }
+    }
+
+    /// Returns bounding rect of the viewport in grid space.
+    fn grid_viewport(&self, viewport: &Rect) -> Rect {
+        if self.rotation == 0.0 {
+            return *viewport;
+        }
+        Rect::from_points(&[
+            self.graph_to_grid(viewport.left_top()),
+            self.graph_to_grid(viewport.right_top()),
+            self.graph_to_grid(viewport.left_bottom()),
+            self.graph_to_grid(viewport.right_bottom()),
+        ])
+    }
+
+    /// Returns step over lattice indices that keeps number of drawn marks
+    /// within [`SnapGrid::max_points`] for given ranges of lattice indices along each axis.
+    ///
+    /// Returns `None` if nothing can be drawn.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    fn mark_stride(&self, ranges: &[(i32, i32)]) -> Option<i32> {
+        let budget = self.max_points as f64;
+        let drawn = |stride: f64| {
+            ranges
+                .iter()
+                .map(|&(min, max)| ((f64::from(max) - f64::from(min)) / stride).ceil() + 1.0)
+                .product::<f64>()
+        };
+
+        if budget < 1.0 {
+            return None;
+        }
+        if drawn(1.0) <= budget {
+            return Some(1);
         }
+
+        let mut stride = (drawn(1.0) / budget).powf(1.0 / ranges.len() as f64).ceil();
+        while drawn(stride) > budget {
+            stride += 1.0;
+        }
+        (stride <= f64::from(i32::MAX)).then_some(stride as i32)
     }
 
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
     fn draw_quad(&self, viewport: &Rect, painter: &Painter, color: Color32, point_size: f32) {
-        let min_x = (viewport.min.x / self.size).floor() as i32;
-        let max_x = (viewport.max.x / self.size).ceil() as i32;
-        let min_y = (viewport.min.y / self.size).floor() as i32;
-        let max_y = (viewport.max.y / self.size).ceil() as i32;
-
-        for xi in min_x..=max_x {
-            for yi in min_y..=max_y {
-                let x = xi as f32 * self.size;
-                let y = yi as f32 * self.size;
-                painter.circle_filled(Pos2::new(x, y), point_size, color);
+        let size = self.cell_size();
+        let min_x = (viewport.min.x / size).floor() as i32;
+        let max_x = (viewport.max.x / size).ceil() as i32;
+        let min_y = (viewport.min.y / size).floor() as i32;
+        let max_y = (viewport.max.y / size).ceil() as i32;
+
+        let Some(stride) = self.mark_stride(&[(min_x, max_x), (min_y, max_y)]) else {
+            return;
+        };
+
+        for xi in strided(min_x, max_x, stride) {
+            for yi in strided(min_y, max_y, stride) {
+                let x = xi as f32 * size;
+                let y = yi as f32 * size;
+                let (color, point_size) = self.point_style(xi, yi, color, point_size);
+                painter.circle_filled(self.grid_to_graph(Pos2::new(x, y)), point_size, color);
             }
         }
     }
 
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    fn draw_lanes(&self, viewport: &Rect, painter: &Painter, columns: bool) {
+        let size = self.cell_size();
+        let stroke = self.stroke();
+        let (min, max) = if columns {
+            (viewport.min.x, viewport.max.x)
+        } else {
+            (viewport.min.y, viewport.max.y)
+        };
+
+        let first = (min / size).floor() as i32;
+        let last = (max / size).ceil() as i32;
+        let Some(stride) = self.mark_stride(&[(first, last)]) else {
+            return;
+        };
+
+        for i in strided(first, last, stride) {
+            let at = i as f32 * size;
+            let (a, b) = if columns {
+                (Pos2::new(at, viewport.min.y), Pos2::new(at, viewport.max.y))
+            } else {
+                (Pos2::new(viewport.min.x, at), Pos2::new(viewport.max.x, at))
+            };
+            let stroke = if self.is_major(i) {
+                self.major_stroke()
+            } else {
+                stroke
+            };
+            painter.line_segment([self.grid_to_graph(a), self.grid_to_graph(b)], stroke);
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
     fn draw_hex_pointy(&self, viewport: &Rect, painter: &Painter, color: Color32, point_size: f32) {
-        let vert_spacing = self.size * 0.866_025_4; // sqrt(3)/2
-        let horiz_spacing = self.size;
+        let size = self.cell_size();
+        let vert_spacing = size * 0.866_025_4; // sqrt(3)/2
+        let horiz_spacing = size;
 
         let min_row = (viewport.min.y / vert_spacing).floor() as i32 - 1;
         let max_row = (viewport.max.y / vert_spacing).ceil() as i32 + 1;
         let min_col = (viewport.min.x / horiz_spacing).floor() as i32 - 1;
         let max_col = (viewport.max.x / horiz_spacing).ceil() as i32 + 1;
 
-        for row in min_row..=max_row {
+        let Some(stride) = self.mark_stride(&[(min_col, max_col), (min_row, max_row)]) else {
+            return;
+        };
+
+        for row in strided(min_row, max_row, stride) {
             let y = row as f32 * vert_spacing;
             let x_offset = if row.abs() % 2 == 1 {
                 horiz_spacing / 2.0
@@ -236,26 +770,33 @@ impl SnapGrid {
                 0.0
             };
 
-            for col in min_col..=max_col {
+            for col in strided(min_col, max_col, stride) {
                 let x = col as f32 * horiz_spacing + x_offset;
                 let pos = Pos2::new(x, y);
                 if viewport.contains(pos) {
-                    painter.circle_filled(pos, point_size, color);
+                    let (color, point_size) = self.point_style(col, row, color, point_size);
+                    painter.circle_filled(self.grid_to_graph(pos), point_size, color);
                 }
             }
         }
     }
 
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
     fn draw_hex_flat(&self, viewport: &Rect, painter: &Painter, color: Color32, point_size: f32) {
-        let horiz_spacing = self.size * 0.866_025_4; // sqrt(3)/2
-        let vert_spacing = self.size;
+        let size = self.cell_size();
+        let horiz_spacing = size * 0.866_025_4; // sqrt(3)/2
+        let vert_spacing = size;
 
         let min_col = (viewport.min.x / horiz_spacing).floor() as i32 - 1;
         let max_col = (viewport.max.x / horiz_spacing).ceil() as i32 + 1;
         let min_row = (viewport.min.y / vert_spacing).floor() as i32 - 1;
         let max_row = (viewport.max.y / vert_spacing).ceil() as i32 + 1;
 
-        for col in min_col..=max_col {
+        let Some(stride) = self.mark_stride(&[(min_col, max_col), (min_row, max_row)]) else {
+            return;
+        };
+
+        for col in strided(min_col, max_col, stride) {
             let x = col as f32 * horiz_spacing;
             let y_offset = if col.abs() % 2 == 1 {
                 vert_spacing / 2.0
@@ -263,20 +804,358 @@ impl SnapGrid {
                 0.0
             };
 
-            for row in min_row..=max_row {
+            for row in strided(min_row, max_row, stride) {
                 let y = row as f32 * vert_spacing + y_offset;
                 let pos = Pos2::new(x, y);
                 if viewport.contains(pos) {
-                    painter.circle_filled(pos, point_size, color);
+                    let (color, point_size) = self.point_style(col, row, color, point_size);
+                    painter.circle_filled(self.grid_to_graph(pos), point_size, color);
+                }
+            }
+        }
+    }
+}
+
+/// Smallest grid size suggested by [`Snarl::infer_grid`].
+const MIN_INFERRED_GRID_SIZE: f32 = 8.0;
+
+/// Largest distance from a multiple of grid size
+/// at which [`Snarl::infer_grid`] considers distance between nodes aligned.
+const INFERRED_GRID_TOLERANCE: f32 = 1.0;
+
+/// Share of distances between nodes that must be aligned to grid suggested by [`Snarl::infer_grid`].
+const INFERRED_GRID_MIN_SUPPORT: f32 = 0.8;
+
+/// Number of most frequent distances tried as grid size by [`Snarl::infer_grid`].
+const INFERRED_GRID_CANDIDATES: usize = 8;
+
+impl<T> Snarl<T> {
+    /// Suggests quad snap grid matching spacing of existing nodes.
+    ///
+    /// Horizontal and vertical distances between positions of all pairs of nodes
+    /// are collected into a histogram.
+    /// Most frequent distances and their halves, thirds and quarters are tried as grid size,
+    /// and the largest one that at least 80% of distances are multiples of is suggested.
+    /// Distances below 8.0 are ignored, as they don't define spacing.
+    ///
+    /// Suggested grid is anchored at graph origin like any [`SnapGrid`],
+    /// so nodes placed with a common offset are not on its points.
+    ///
+    /// Returns `None` if graph has less than three nodes or no clear spacing is found.
+    /// All pairs of nodes are compared, so call this on demand rather than every frame.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::Snarl;
+    /// let mut snarl = Snarl::new();
+    /// snarl.insert_node(egui::pos2(0.0, 0.0), ());
+    /// snarl.insert_node(egui::pos2(120.0, 60.0), ());
+    /// snarl.insert_node(egui::pos2(240.0, 0.0), ());
+    /// snarl.insert_node(egui::pos2(360.0, 60.0), ());
+    ///
+    /// let grid = snarl.infer_grid().unwrap();
+    /// assert_eq!(grid.size, 60.0);
+    /// ```
+    #[must_use]
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    pub fn infer_grid(&self) -> Option<SnapGrid> {
+        let positions = self
+            .nodes
+            .iter()
+            .map(|(_, node)| node.pos)
+            .filter(|pos| pos.is_finite())
+            .collect::<Vec<_>>();
+
+        if positions.len() < 3 {
+            return None;
+        }
+
+        let mut histogram = HashMap::<u32, usize>::default();
+        for (idx, a) in positions.iter().enumerate() {
+            for b in &positions[idx + 1..] {
+                for delta in [(a.x - b.x).abs(), (a.y - b.y).abs()] {
+                    if delta >= MIN_INFERRED_GRID_SIZE {
+                        *histogram.entry(delta.round() as u32).or_default() += 1;
+                    }
+                }
+            }
+        }
+
+        let total = histogram.values().sum::<usize>();
+        if total == 0 {
+            return None;
+        }
+
+        let mut bins = histogram.into_iter().collect::<Vec<_>>();
+        bins.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let mut best = None::<f32>;
+        for &(delta, _) in bins.iter().take(INFERRED_GRID_CANDIDATES) {
+            for divisor in 1..=4u8 {
+                let size = delta as f32 / f32::from(divisor);
+                if size < MIN_INFERRED_GRID_SIZE || best.is_some_and(|best| best >= size) {
+                    continue;
+                }
+
+                let aligned = bins
+                    .iter()
+                    .filter(|&&(delta, _)| {
+                        let cells = delta as f32 / size;
+                        (cells - cells.round()).abs() * size <= INFERRED_GRID_TOLERANCE
+                    })
+                    .map(|&(_, count)| count)
+                    .sum::<usize>();
+
+                if aligned as f32 >= total as f32 * INFERRED_GRID_MIN_SUPPORT {
+                    best = Some(size);
                 }
             }
         }
+
+        best.map(SnapGrid::quad)
+    }
+}
+
+/// How graph is framed by [`SnarlWidget::fit_to_view`](crate::ui::SnarlWidget::fit_to_view).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FitMode {
+    /// Whole graph is visible, leaving empty space along one axis.
+    #[default]
+    Contain,
+
+    /// Graph fills the whole view, cropping it along one axis.
+    Cover,
+}
+
+/// Options for [`SnarlWidget::fit_to_view`](crate::ui::SnarlWidget::fit_to_view).
+///
+/// Can be created from `f32`, which sets uniform padding and keeps other defaults.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FitOptions {
+    /// Padding around nodes in graph space.
+    /// Defaults to 16 on every side.
+    pub padding: Margin,
+
+    /// Largest scale the view is zoomed to,
+    /// so small graphs are not magnified too much.
+    /// The view is also limited by [`SnarlStyle::min_scale`](crate::ui::SnarlStyle::min_scale)
+    /// and [`SnarlStyle::max_scale`](crate::ui::SnarlStyle::max_scale).
+    /// Defaults to 1.0.
+    pub max_zoom: f32,
+
+    /// Whether graph is contained in the view or covers it.
+    /// Defaults to [`FitMode::Contain`].
+    pub mode: FitMode,
+
+    #[doc(hidden)]
+    #[cfg_attr(feature = "serde", serde(skip_serializing, default))]
+    /// Do not access other than with .., here to emulate `#[non_exhaustive(pub)]`
+    pub _non_exhaustive: (),
+}
+
+impl FitOptions {
+    /// Creates new [`FitOptions`] with default values.
+    #[must_use]
+    pub const fn new() -> Self {
+        FitOptions {
+            padding: Margin::same(16),
+            max_zoom: 1.0,
+            mode: FitMode::Contain,
+            _non_exhaustive: (),
+        }
     }
 }
 
+impl Default for FitOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<f32> for FitOptions {
+    #[inline]
+    fn from(padding: f32) -> Self {
+        FitOptions {
+            padding: Margin::from(padding),
+            ..Self::new()
+        }
+    }
+}
+
+/// Default action of mouse wheel and touchpad scroll over the graph.
+///
+/// Opposite action is performed while [`Modifiers::COMMAND`] is held.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScrollMode {
+    /// Scroll zooms the graph around the pointer, scroll with command pans.
+    ZoomDefault,
+
+    /// Scroll pans the graph, scroll with command zooms.
+    /// This is default.
+    #[default]
+    PanDefault,
+}
+
+/// Action performed when new wire is dropped on empty space.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WireDropAction {
+    /// Open dropped wire menu.
+    /// See [`SnarlViewer::show_dropped_wire_menu`](crate::ui::SnarlViewer::show_dropped_wire_menu).
+    /// This is default.
+    #[default]
+    Menu,
+
+    /// Create new node at drop position.
+    /// See [`SnarlViewer::create_node_from_pin`](crate::ui::SnarlViewer::create_node_from_pin).
+    CreateNode,
+
+    /// Cancel the wire.
+    Cancel,
+}
+
+/// Kind of capacity limit reached.
+/// See [`SnarlConfig::max_nodes`] and [`SnarlConfig::max_wires`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CapacityLimit {
+    /// Graph has [`SnarlConfig::max_nodes`] nodes.
+    Nodes,
+
+    /// Graph has [`SnarlConfig::max_wires`] wires.
+    Wires,
+}
+
+/// Origin of the connection change.
+/// See [`SnarlViewer::connect_with_source`](crate::ui::SnarlViewer::connect_with_source)
+/// and [`SnarlViewer::disconnect_with_source`](crate::ui::SnarlViewer::disconnect_with_source).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EditSource {
+    /// Wire dragged by the user between pins.
+    UserDrag,
+
+    /// Wire removed by the user with click or key binding.
+    UserRemove,
+
+    /// Wires pasted from clipboard.
+    /// Not produced by the graph UI, for use by the application.
+    Paste,
+
+    /// Change made by the application.
+    /// Not produced by the graph UI, for use by the application.
+    Programmatic,
+
+    /// Node dropped onto the wire is spliced into it.
+    /// See [`SnarlConfig::drop_node_on_wire`].
+    Splice,
+
+    /// Wires of removed node are bridged.
+    /// See [`SnarlConfig::remove_node_reconnect`].
+    Reconnect,
+}
+
+/// Region of the node that starts node drag.
+/// See [`SnarlViewer::drag_handle`](crate::ui::SnarlViewer::drag_handle).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DragHandle {
+    /// Only the node's header.
+    Header,
+
+    /// Any part of the node's frame not covered by interactive widgets.
+    #[default]
+    WholeNode,
+
+    /// Custom rect relative to the top-left corner of the node's frame.
+    Custom(Rect),
+}
+
+/// Shape of the node used to hit-test pointer input.
+/// See [`SnarlViewer::node_hit_shape`](crate::ui::SnarlViewer::node_hit_shape).
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NodeHitShape {
+    /// Whole node's frame.
+    #[default]
+    Rect,
+
+    /// Ellipse inscribed into the node's frame.
+    Circle,
+
+    /// Convex polygon with points relative to the top-left corner of the node's frame.
+    Convex(Vec<Pos2>),
+}
+
+impl NodeHitShape {
+    /// Returns true if `pos` is inside the shape of the node with `frame` rect.
+    #[must_use]
+    pub fn contains(&self, frame: Rect, pos: Pos2) -> bool {
+        if !frame.contains(pos) {
+            return false;
+        }
+
+        match self {
+            NodeHitShape::Rect => true,
+            NodeHitShape::Circle => {
+                let offset = (pos - frame.center()) / (frame.size() * 0.5);
+                offset.length_sq() <= 1.0
+            }
+            NodeHitShape::Convex(points) => {
+                if points.len() < 3 {
+                    return false;
+                }
+
+                // Point is inside if it is on the same side of every edge.
+                let pos = pos - frame.min.to_vec2();
+                let mut side = None;
+                for (&a, &b) in points.iter().zip(points.iter().cycle().skip(1)) {
+                    let edge = b - a;
+                    let to_pos = pos - a;
+                    let cross = edge.x * to_pos.y - edge.y * to_pos.x;
+                    if cross.abs() <= f32::EPSILON {
+                        continue;
+                    }
+                    match side {
+                        None => side = Some(cross > 0.0),
+                        Some(positive) if positive != (cross > 0.0) => return false,
+                        Some(_) => {}
+                    }
+                }
+                true
+            }
+        }
+    }
+}
+
+/// Space the node is positioned in.
+/// See [`SnarlViewer::node_anchor`](crate::ui::SnarlViewer::node_anchor).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NodeAnchor {
+    /// Node is placed in graph space at its position and follows pan and zoom.
+    #[default]
+    World,
+
+    /// Node is pinned to the widget at given alignment, unaffected by pan and zoom,
+    /// like a control panel in the corner.
+    /// Node's position in graph space is kept but not used.
+    Screen(Align2),
+}
+
 /// Config options for Snarl.
 #[derive(Clone, Copy, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[allow(clippy::struct_excessive_bools)]
 pub struct SnarlConfig {
     /// Controls key bindings.
 
@@ -284,6 +1163,19 @@ pub struct SnarlConfig {
     /// Defaults to [`PointerButton::Primary`] && [`Modifiers::SHIFT`].
     pub rect_select: ModifierClick,
 
+    /// Action used to draw freeform selection lasso.
+    /// Nodes with centers inside the lasso path are selected when it is released.
+    /// Must differ from [`SnarlConfig::rect_select`], rect selection takes precedence otherwise.
+    /// Defaults to `None` - lasso selection is disabled.
+    pub lasso_select: Option<ModifierClick>,
+
+    /// Action used to draw a line that cuts wires.
+    /// Every wire crossed by the line is removed when it is released.
+    /// Takes precedence over [`SnarlConfig::rect_select`]
+    /// and [`SnarlConfig::lasso_select`] with the same modifiers.
+    /// Defaults to `None` - wire cutting is disabled.
+    pub cut_wires: Option<ModifierClick>,
+
     /// Action used to remove hovered wire.
     /// Defaults to [`PointerButton::Secondary`].
     pub remove_hovered_wire: ModifierClick,
@@ -301,11 +1193,15 @@ pub struct SnarlConfig {
     pub click_pin: ModifierClick,
 
     /// Action used to drag pin.
-    /// Defaults to [`PointerButton::Primary`] && [`Modifiers::COMMAND`].
+    /// Defaults to [`PointerButton::Primary`] && [`Modifiers::COMMAND`],
+    /// matching [`ModifierMatch::AtLeast`].
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_at_least"))]
     pub drag_pin: ModifierClick,
 
     /// Action used to avoid popup menu on wire drop.
-    /// Defaults to [`PointerButton::Primary`] && [`Modifiers::SHIFT`].
+    /// Defaults to [`PointerButton::Primary`] && [`Modifiers::SHIFT`],
+    /// matching [`ModifierMatch::AtLeast`].
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_at_least"))]
     pub no_menu: ModifierClick,
 
     /// Action used to click node.
@@ -313,17 +1209,30 @@ pub struct SnarlConfig {
     pub click_node: ModifierClick,
 
     /// Action used to drag node.
-    /// Defaults to [`PointerButton::Primary`].
+    /// Node is not dragged while [`SnarlConfig::select_node`]
+    /// or [`SnarlConfig::deselect_node`] modifiers are held.
+    /// Defaults to [`PointerButton::Primary`],
+    /// matching [`ModifierMatch::AtLeast`], so node is dragged with any other modifiers held.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_at_least"))]
     pub drag_node: ModifierClick,
 
     /// Action used to select node.
-    /// Defaults to [`PointerButton::Primary`] && [`Modifiers::SHIFT`].
+    /// Defaults to [`PointerButton::Primary`] && [`Modifiers::SHIFT`],
+    /// matching [`ModifierMatch::AtLeast`].
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_at_least"))]
     pub select_node: ModifierClick,
 
     /// Action used to deselect node.
-    /// Defaults to [`PointerButton::Primary`] && [`Modifiers::COMMAND`].
+    /// Defaults to [`PointerButton::Primary`] && [`Modifiers::COMMAND`],
+    /// matching [`ModifierMatch::AtLeast`].
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_at_least"))]
     pub deselect_node: ModifierClick,
 
+    /// Action used to add hovered wire to selection or remove it from selection.
+    /// Selected wires are removed with [`SnarlConfig::delete_selection`].
+    /// Defaults to [`PointerButton::Primary`] && [`Modifiers::SHIFT`].
+    pub select_wire: ModifierClick,
+
     /// Action used to click node header.
     /// Defaults to [`PointerButton::Primary`].
     pub click_header: ModifierClick,
@@ -333,12 +1242,201 @@ pub struct SnarlConfig {
     /// Defaults to `false`.
     pub single_select: bool,
 
+    /// When true, plain click on a node toggles it in and out of the selection
+    /// instead of replacing the selection with it.
+    /// With [`SnarlConfig::single_select`] at most one node is still selected.
+    /// Defaults to `false`.
+    pub click_toggles_selection: bool,
+
     /// Grid configuration for snapping node positions.
     /// When `Some(grid)`, nodes will snap to the configured grid.
     /// Set to `None` to disable grid snapping.
     /// Defaults to `None`.
     pub grid_snap: Option<SnapGrid>,
 
+    /// Draw the snap grid only while nodes are dragged.
+    /// If set to true, [`SnapGrid::visible`] is ignored
+    /// and the grid is shown during node drag only.
+    /// Defaults to `false`.
+    pub show_grid_while_dragging: bool,
+
+    /// Keep nodes dropped on the snap grid from sharing a cell.
+    /// If set to true, node dropped onto a cell occupied by another node
+    /// is moved to the nearest free cell.
+    /// Has no effect with lane grids.
+    /// Defaults to `false`.
+    pub grid_no_overlap: bool,
+
+    /// Keep nodes inserted from graph menus and by dropping wires on empty space
+    /// from stacking exactly on top of existing nodes.
+    /// If set to true, new node placed within frame of another node
+    /// is moved by cascading offset until it is clear.
+    /// Defaults to `false`.
+    pub avoid_overlap_on_insert: bool,
+
+    /// Controls what happens when new wire is dropped on empty space.
+    /// Defaults to [`WireDropAction::Menu`].
+    pub wire_drop_on_empty: WireDropAction,
+
+    /// Default action of scroll over the graph.
+    /// Defaults to [`ScrollMode::PanDefault`], same as egui's [`Scene`](egui::Scene).
+    pub scroll_mode: ScrollMode,
+
+    /// When true, user can't pan or zoom the view,
+    /// including with [`SnarlConfig::reset_view`] and [`SnarlConfig::fit_view`] key bindings.
+    /// Nodes, wires and hover still respond to the pointer,
+    /// and view can still be changed programmatically.
+    /// Defaults to `false`.
+    pub viewport_locked: bool,
+
+    /// When true, graph can't be edited with the pointer or keyboard.
+    /// Nodes can't be moved, pins can't be connected or disconnected,
+    /// wires and selection can't be removed, titles can't be edited,
+    /// and graph and node menus are not shown.
+    /// Nodes are still rendered with their widgets disabled,
+    /// and can be hovered and selected for inspection.
+    /// Defaults to `false`.
+    pub read_only: bool,
+
+    /// Delay in seconds before the wire-drop menu opens.
+    /// Menu opens only if pointer stays still for this long after the wire is dropped,
+    /// moving the pointer away cancels the menu and the new wires.
+    /// Avoids menu flickering with jittery pointers like touchpads.
+    /// Defaults to `0.0`, which opens the menu immediately.
+    pub menu_open_delay: f32,
+
+    /// Bounds of the canvas in graph space.
+    /// When `Some(rect)`, nodes are kept inside the rect,
+    /// both when dragged and when positioned programmatically.
+    /// Defaults to `None`, which means canvas is unbounded.
+    pub bounds: Option<Rect>,
+
+    /// When true and [`SnarlConfig::bounds`] are set,
+    /// panning is clamped so the view can't be scrolled past the bounds.
+    /// Defaults to `false`.
+    pub clamp_pan_to_bounds: bool,
+
+    /// Maximum number of nodes in the graph.
+    /// When reached, nodes are not created from the UI
+    /// and [`SnarlViewer::on_capacity_reached`](crate::ui::SnarlViewer::on_capacity_reached) is called instead.
+    /// Use [`Snarl::try_insert_node`](crate::Snarl::try_insert_node) to respect it in menus.
+    /// Defaults to `None`, which means unbounded.
+    pub max_nodes: Option<usize>,
+
+    /// Maximum number of wires in the graph.
+    /// When reached, new wires are not connected from the UI
+    /// and [`SnarlViewer::on_capacity_reached`](crate::ui::SnarlViewer::on_capacity_reached) is called instead.
+    /// Use [`Snarl::try_connect`](crate::Snarl::try_connect) to respect it in viewer code.
+    /// Defaults to `None`, which means unbounded.
+    pub max_wires: Option<usize>,
+
+    /// Key binding that removes all selected nodes with their wires
+    /// and disconnects selected wires with [`SnarlViewer::disconnect`](crate::ui::SnarlViewer::disconnect).
+    /// Ignored while a widget has keyboard focus or pointer is outside the graph.
+    /// See [`SnarlViewer::confirm_delete_nodes`](crate::ui::SnarlViewer::confirm_delete_nodes)
+    /// to intercept the deletion.
+    /// Defaults to `None`.
+    pub delete_selection: Option<KeyboardShortcut>,
+
+    /// Key binding that resets the view to graph origin at the top-left corner
+    /// of the widget and scale of 1.0.
+    /// Ignored while a widget has keyboard focus or pointer is outside the graph.
+    /// Defaults to `None`.
+    pub reset_view: Option<KeyboardShortcut>,
+
+    /// Key binding that pans and zooms the view to fit all nodes,
+    /// same as [`SnarlWidget::fit_to_view`](crate::ui::SnarlWidget::fit_to_view)
+    /// with default [`FitOptions`] and padding of 32.
+    /// Ignored while a widget has keyboard focus or pointer is outside the graph.
+    /// Defaults to `None`.
+    pub fit_view: Option<KeyboardShortcut>,
+
+    /// Key binding that zooms the view in by [`SnarlConfig::zoom_step`]
+    /// around the center of the graph.
+    /// Ignored while a widget has keyboard focus or pointer is outside the graph.
+    /// Defaults to `None`.
+    pub zoom_in: Option<KeyboardShortcut>,
+
+    /// Key binding that zooms the view out by [`SnarlConfig::zoom_step`]
+    /// around the center of the graph.
+    /// Ignored while a widget has keyboard focus or pointer is outside the graph.
+    /// Defaults to `None`.
+    pub zoom_out: Option<KeyboardShortcut>,
+
+    /// Factor the zoom changes by with [`SnarlConfig::zoom_in`] and [`SnarlConfig::zoom_out`]
+    /// key bindings and zoom buttons shown with
+    /// [`SnarlStyle::show_zoom_buttons`](crate::ui::SnarlStyle::show_zoom_buttons).
+    /// Zoom stays within the scale range of the style.
+    /// Values below `1.0` are treated as `1.0`.
+    /// Defaults to `1.25`.
+    pub zoom_step: f32,
+
+    /// Modifiers that, when held as node drag starts,
+    /// make the drag move the node with its whole downstream subtree,
+    /// i.e. all nodes reachable following output to input wires.
+    /// The dragged node is snapped to the grid once
+    /// and the subtree is translated by the same delta.
+    /// Defaults to `None`, which disables subtree drag.
+    pub drag_subtree_modifier: Option<Modifiers>,
+
+    /// Whether clicking connected input pin with [`SnarlConfig::drag_pin`] button
+    /// detaches its wires and starts dragging them from the output pins.
+    /// Next click connects them to the pin under cursor
+    /// or drops them same as released wire drag.
+    /// Defaults to `false`.
+    pub pull_wire_on_pin_click: bool,
+
+    /// Whether new wire can be dragged from input pin as well as from output pin.
+    /// Wire dragged from input pin connects to output pin it is dropped on,
+    /// and [`SnarlViewer::connect`](crate::ui::SnarlViewer::connect)
+    /// receives pins in same order as for wire dragged from output.
+    /// If set to false, dragging input pin only moves its existing wires
+    /// with [`SnarlConfig::drag_pin`] modifiers.
+    /// Defaults to `true`.
+    pub bidirectional_drag: bool,
+
+    /// Whether clicking output pin with [`SnarlConfig::drag_pin`] button
+    /// starts new wire from it without dragging.
+    /// Next click connects it to the pin under cursor
+    /// or drops it same as released wire drag.
+    /// Pressing [`Key::Escape`](egui::Key::Escape) cancels the wire.
+    /// Defaults to `false`.
+    pub click_connect: bool,
+
+    /// Whether node dropped onto a wire is spliced into it.
+    /// If set to true, wire under the pointer is highlighted while node is dragged
+    /// and [`SnarlViewer::splice_node`](crate::ui::SnarlViewer::splice_node)
+    /// picks pins to reconnect it through when the node is released.
+    /// Defaults to `false`.
+    pub drop_node_on_wire: bool,
+
+    /// Whether removing nodes with [`SnarlConfig::delete_selection`] heals the graph.
+    /// If set to true, removed node with exactly one incoming and one outgoing wire
+    /// has its source pin connected to its target pin
+    /// with [`SnarlViewer::connect_with_source`](crate::ui::SnarlViewer::connect_with_source),
+    /// like when node is deleted from a chain.
+    /// Chains of such removed nodes are bridged end to end.
+    /// Wires of other removed nodes are dropped.
+    /// Defaults to `false`.
+    pub remove_node_reconnect: bool,
+
+    /// Distance in graph space within which dropped node's pin snaps to a pin of another node.
+    /// When node drag ends with its pin this close to compatible pin,
+    /// node is moved to make the pins coincide and they are connected.
+    /// Closest pair of pins accepted by
+    /// [`SnarlViewer::validate_connection`](crate::ui::SnarlViewer::validate_connection) is chosen.
+    /// Defaults to `None`, which disables snapping to pins.
+    pub pin_magnet_wiring: Option<f32>,
+
+    /// If set to true, wire dragged from output pin may be dropped onto another output pin
+    /// and wire dragged from input pin onto another input pin.
+    /// Such attempts are passed to
+    /// [`SnarlViewer::validate_same_kind_connection`](crate::ui::SnarlViewer::validate_same_kind_connection)
+    /// and [`SnarlViewer::connect_same_kind`](crate::ui::SnarlViewer::connect_same_kind)
+    /// instead of being rejected.
+    /// Defaults to `false`.
+    pub allow_same_kind_connections: bool,
+
     #[doc(hidden)]
     #[cfg_attr(feature = "serde", serde(skip_serializing, default))]
     /// Do not access other than with .., here to emulate `#[non_exhaustive(pub)]`
@@ -359,57 +1457,339 @@ impl SnarlConfig {
             rect_select: ModifierClick {
                 modifiers: Modifiers::SHIFT,
                 mouse_button: PointerButton::Primary,
+                matching: ModifierMatch::Exact,
             },
+            lasso_select: None,
+            cut_wires: None,
             remove_hovered_wire: ModifierClick {
                 modifiers: Modifiers::NONE,
                 mouse_button: PointerButton::Secondary,
+                matching: ModifierMatch::Exact,
             },
             deselect_all_nodes: ModifierClick {
                 modifiers: Modifiers::COMMAND,
                 mouse_button: PointerButton::Primary,
+                matching: ModifierMatch::Exact,
             },
             cancel_wire_drag: ModifierClick {
                 modifiers: Modifiers::NONE,
                 mouse_button: PointerButton::Secondary,
+                matching: ModifierMatch::Exact,
             },
             click_pin: ModifierClick {
                 modifiers: Modifiers::NONE,
                 mouse_button: PointerButton::Secondary,
+                matching: ModifierMatch::Exact,
             },
             drag_pin: ModifierClick {
                 modifiers: Modifiers::COMMAND,
                 mouse_button: PointerButton::Primary,
+                matching: ModifierMatch::AtLeast,
             },
             no_menu: ModifierClick {
                 modifiers: Modifiers::SHIFT,
                 mouse_button: PointerButton::Primary,
+                matching: ModifierMatch::AtLeast,
             },
             click_node: ModifierClick {
                 modifiers: Modifiers::NONE,
                 mouse_button: PointerButton::Primary,
+                matching: ModifierMatch::Exact,
             },
             drag_node: ModifierClick {
                 modifiers: Modifiers::NONE,
                 mouse_button: PointerButton::Primary,
+                matching: ModifierMatch::AtLeast,
             },
             select_node: ModifierClick {
                 modifiers: Modifiers::SHIFT,
                 mouse_button: PointerButton::Primary,
+                matching: ModifierMatch::AtLeast,
             },
             deselect_node: ModifierClick {
                 modifiers: Modifiers::COMMAND,
                 mouse_button: PointerButton::Primary,
+                matching: ModifierMatch::AtLeast,
+            },
+            select_wire: ModifierClick {
+                modifiers: Modifiers::SHIFT,
+                mouse_button: PointerButton::Primary,
+                matching: ModifierMatch::Exact,
             },
             click_header: ModifierClick {
                 modifiers: Modifiers::NONE,
                 mouse_button: PointerButton::Primary,
+                matching: ModifierMatch::Exact,
             },
 
             single_select: false,
+            click_toggles_selection: false,
 
             grid_snap: None,
+            show_grid_while_dragging: false,
+            grid_no_overlap: false,
+            avoid_overlap_on_insert: false,
+
+            wire_drop_on_empty: WireDropAction::Menu,
+            scroll_mode: ScrollMode::PanDefault,
+            viewport_locked: false,
+            read_only: false,
+            menu_open_delay: 0.0,
+
+            bounds: None,
+            clamp_pan_to_bounds: false,
+
+            max_nodes: None,
+            max_wires: None,
+
+            delete_selection: None,
+            reset_view: None,
+            fit_view: None,
+            zoom_in: None,
+            zoom_out: None,
+            zoom_step: 1.25,
+
+            drag_subtree_modifier: None,
+            pull_wire_on_pin_click: false,
+            bidirectional_drag: true,
+            click_connect: false,
+            drop_node_on_wire: false,
+            remove_node_reconnect: false,
+            pin_magnet_wiring: None,
+            allow_same_kind_connections: false,
 
             _non_exhaustive: (),
         }
     }
 }
+
+#[test]
+fn snap_grid_degenerate_size() {
+    let painter = Painter::new(
+        egui::Context::default(),
+        egui::LayerId::background(),
+        Rect::EVERYTHING,
+    );
+
+    for size in [0.0, -10.0, f32::NAN, f32::INFINITY] {
+        let mut grid = SnapGrid::quad(size);
+        assert!(grid.size > 0.0);
+
+        grid.size = size;
+        for grid_type in [
+            SnapGridType::Quad,
+            SnapGridType::HexPointy,
+            SnapGridType::HexFlat,
+            SnapGridType::Columns,
+            SnapGridType::Rows,
+        ] {
+            grid.grid_type = grid_type;
+            let snapped = grid.snap(Pos2::new(12.3, -45.6));
+            assert!(snapped.is_finite(), "{grid_type:?} grid of size {size}");
+
+            grid.visible = true;
+            grid.draw(
+                &Rect::from_min_size(Pos2::ZERO, egui::vec2(1e6, 1e6)),
+                &painter,
+            );
+        }
+    }
+}
+
+#[test]
+#[allow(clippy::cast_precision_loss)]
+fn snap_grid_hex_idempotent() {
+    for grid in [SnapGrid::hex_pointy(20.0), SnapGrid::hex_flat(20.0)] {
+        for xi in -50..50 {
+            for yi in -50..50 {
+                let pos = Pos2::new(xi as f32 * 1.7, yi as f32 * 1.3);
+                let snapped = grid.snap(pos);
+                assert_eq!(
+                    grid.snap(snapped),
+                    snapped,
+                    "{:?} at {pos:?}",
+                    grid.grid_type
+                );
+            }
+        }
+    }
+}
+
+#[test]
+#[allow(clippy::cast_precision_loss)]
+fn snap_grid_hex_picks_nearest_point() {
+    for grid in [SnapGrid::hex_pointy(20.0), SnapGrid::hex_flat(20.0)] {
+        for xi in -40..40 {
+            for yi in -40..40 {
+                // Sample densely around row and column boundaries.
+                let pos = Pos2::new(xi as f32 * 2.35, yi as f32 * 2.15);
+                let (snapped, cell) = grid.snap_to_cell(pos);
+                assert!(
+                    grid.cell_pos(cell).distance(snapped) < 1e-3,
+                    "{:?} at {pos:?} snapped off cell {cell:?}",
+                    grid.grid_type
+                );
+
+                let nearest = (-2..=2)
+                    .flat_map(|dy| (-2..=2).map(move |dx| [cell[0] + dx, cell[1] + dy]))
+                    .map(|cell| grid.cell_pos(cell).distance(pos))
+                    .fold(f32::INFINITY, f32::min);
+                assert!(
+                    snapped.distance(pos) <= nearest + 1e-3,
+                    "{:?} at {pos:?} snapped {} away, nearest point is {nearest} away",
+                    grid.grid_type,
+                    snapped.distance(pos),
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn snap_grid_draw_budget() {
+    let ctx = egui::Context::default();
+
+    for grid_type in [
+        SnapGridType::Quad,
+        SnapGridType::HexPointy,
+        SnapGridType::HexFlat,
+        SnapGridType::Columns,
+        SnapGridType::Rows,
+    ] {
+        let mut grid = SnapGrid::quad(1.0).with_visible(true).with_max_points(1000);
+        grid.grid_type = grid_type;
+
+        let output = ctx.run(egui::RawInput::default(), |ctx| {
+            let painter = ctx.layer_painter(egui::LayerId::background());
+            grid.draw(
+                &Rect::from_min_size(Pos2::new(-5e5, -5e5), egui::vec2(1e6, 1e6)),
+                &painter,
+            );
+        });
+
+        let drawn = output.shapes.len();
+        assert!(drawn > 0, "{grid_type:?} grid is not drawn");
+        assert!(
+            drawn <= grid.max_points,
+            "{grid_type:?} grid drew {drawn} marks"
+        );
+    }
+}
+
+#[test]
+fn default_bindings_match_modifiers() {
+    let config = SnarlConfig::new();
+
+    assert!(config.rect_select.matches(Modifiers::SHIFT));
+    assert!(
+        !config
+            .rect_select
+            .matches(Modifiers::SHIFT | Modifiers::ALT)
+    );
+
+    // Copying wires from pin with extra modifiers moves them instead.
+    let move_wires = Modifiers::COMMAND | Modifiers::SHIFT;
+    assert!(config.drag_pin.matches(move_wires));
+    assert!(!config.drag_pin.matches_exactly(move_wires));
+    assert!(config.drag_pin.matches_exactly(Modifiers::COMMAND));
+
+    assert!(config.drag_node.matches(Modifiers::NONE));
+    assert!(config.drag_node.matches(Modifiers::ALT));
+}
+
+#[test]
+fn modifier_match_modes() {
+    let pressed = [
+        Modifiers::NONE,
+        Modifiers::SHIFT,
+        Modifiers::ALT,
+        Modifiers::COMMAND,
+        Modifiers::SHIFT | Modifiers::COMMAND,
+        Modifiers::SHIFT | Modifiers::ALT | Modifiers::COMMAND,
+    ];
+
+    let any = ModifierClick::new(Modifiers::NONE, PointerButton::Primary)
+        .with_matching(ModifierMatch::AtLeast);
+    assert!(pressed.iter().all(|&modifiers| any.matches(modifiers)));
+
+    let none = ModifierClick::new(Modifiers::NONE, PointerButton::Primary);
+    assert_eq!(
+        pressed.map(|modifiers| none.matches(modifiers)),
+        [true, false, false, false, false, false]
+    );
+
+    let command = ModifierClick::new(Modifiers::COMMAND, PointerButton::Primary);
+    assert_eq!(
+        pressed.map(|modifiers| command.matches(modifiers)),
+        [false, false, false, true, false, false]
+    );
+
+    let command = command.with_matching(ModifierMatch::AtLeast);
+    assert_eq!(
+        pressed.map(|modifiers| command.matches(modifiers)),
+        [false, false, false, true, true, true]
+    );
+    assert!(command.matches_exactly(Modifiers::COMMAND));
+    assert!(!command.matches_exactly(Modifiers::SHIFT | Modifiers::COMMAND));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn missing_matching_keeps_legacy_semantics() {
+    // Config saved before `matching` and other fields were added.
+    let click = |modifiers: Modifiers, mouse_button: PointerButton| {
+        serde_json::json!({
+            "modifiers": modifiers,
+            "mouse_button": mouse_button,
+        })
+    };
+    let value = serde_json::json!({
+        "rect_select": click(Modifiers::SHIFT, PointerButton::Primary),
+        "remove_hovered_wire": click(Modifiers::NONE, PointerButton::Secondary),
+        "deselect_all_nodes": click(Modifiers::COMMAND, PointerButton::Primary),
+        "cancel_wire_drag": click(Modifiers::NONE, PointerButton::Secondary),
+        "click_pin": click(Modifiers::NONE, PointerButton::Secondary),
+        "drag_pin": click(Modifiers::COMMAND, PointerButton::Primary),
+        "no_menu": click(Modifiers::SHIFT, PointerButton::Primary),
+        "click_node": click(Modifiers::NONE, PointerButton::Primary),
+        "drag_node": click(Modifiers::NONE, PointerButton::Primary),
+        "select_node": click(Modifiers::SHIFT, PointerButton::Primary),
+        "deselect_node": click(Modifiers::COMMAND, PointerButton::Primary),
+        "click_header": click(Modifiers::NONE, PointerButton::Primary),
+        "single_select": true,
+        "grid_snap": {
+            "size": 16.0,
+            "grid_type": "HexPointy",
+            "visible": true,
+            "color": null,
+            "point_size": 1.0,
+        },
+    });
+
+    let config: SnarlConfig = serde_json::from_value(value).unwrap();
+    assert!(config.single_select);
+    assert_eq!(
+        config.grid_snap,
+        Some(SnapGrid {
+            visible: true,
+            point_size: 1.0,
+            ..SnapGrid::hex_pointy(16.0)
+        })
+    );
+    assert_eq!(
+        SnarlConfig {
+            single_select: false,
+            grid_snap: None,
+            ..config
+        },
+        SnarlConfig::new()
+    );
+
+    assert_eq!(config.rect_select.matching, ModifierMatch::Exact);
+    assert_eq!(config.select_wire.matching, ModifierMatch::Exact);
+    assert_eq!(config.drag_pin.matching, ModifierMatch::AtLeast);
+    assert_eq!(config.no_menu.matching, ModifierMatch::AtLeast);
+    assert_eq!(config.drag_node.matching, ModifierMatch::AtLeast);
+    assert_eq!(config.select_node.matching, ModifierMatch::AtLeast);
+    assert_eq!(config.deselect_node.matching, ModifierMatch::AtLeast);
+}
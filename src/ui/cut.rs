@@ -0,0 +1,155 @@
+//! Cutting wires by dragging a line across them.
+
+use egui::{Id, Modifiers, Pos2, Rect, Sense, Shape, Stroke, Ui};
+
+use crate::Wire;
+
+use super::{
+    PinResponse, SnarlConfig, SnarlStyle, is_vertical_wire,
+    state::SnarlState,
+    wire::{line_crosses_wire, pick_wire_style, wire_line},
+};
+
+/// Extends and finishes cut line dragged over `rect`.
+///
+/// Returns `true` if the wire cut gesture is active, which suppresses selection,
+/// and the cut line released in this frame.
+pub(super) fn process_cut_input(
+    ui: &Ui,
+    rect: Rect,
+    snarl_id: Id,
+    config: &SnarlConfig,
+    modifiers: Modifiers,
+    snarl_state: &mut SnarlState,
+) -> (bool, Option<(Pos2, Pos2)>) {
+    let active =
+        snarl_state.is_cut_line() || config.cut_wires.is_some_and(|cut| cut.matches(modifiers));
+
+    let mut ended = None;
+    if let Some(cut) = config.cut_wires
+        && active
+    {
+        let cut_resp = ui.interact(rect, snarl_id.with("cut"), Sense::drag());
+
+        if cut_resp.dragged_by(cut.mouse_button)
+            && let Some(pos) = cut_resp.interact_pointer_pos()
+        {
+            snarl_state.extend_cut_line(pos);
+        }
+
+        if cut_resp.drag_stopped_by(cut.mouse_button) {
+            ended = snarl_state.take_cut_line();
+        }
+    }
+
+    (active, ended)
+}
+
+/// Returns wires from `wire_paths` crossed by the line from `start` to `end`.
+pub(super) fn crossed_wires(
+    ui: &Ui,
+    style: &SnarlStyle,
+    wire_paths: &[(Wire, PinResponse, PinResponse)],
+    (start, end): (Pos2, Pos2),
+) -> Vec<Wire> {
+    let wire_frame_size = style.wire_frame_size(ui.style());
+    let wire_threshold = style.wire_smoothness();
+
+    wire_paths
+        .iter()
+        .filter(|(_, from_r, to_r)| {
+            let line = wire_line(
+                wire_frame_size,
+                style.upscale_wire_frame(),
+                style.downscale_wire_frame(),
+                style.bezier_tension(),
+                from_r.pos,
+                to_r.pos,
+                wire_threshold,
+                pick_wire_style(from_r.wire_style, to_r.wire_style),
+                is_vertical_wire(from_r.pos, to_r.pos, from_r.vertical || to_r.vertical),
+            );
+            line_crosses_wire(&line, start, end)
+        })
+        .map(|&(wire, _, _)| wire)
+        .collect()
+}
+
+/// Paints cut line being dragged.
+pub(super) fn paint_cut_line(ui: &Ui, style: &SnarlStyle, snarl_state: &SnarlState) {
+    if let Some((start, end)) = snarl_state.cut_line() {
+        let stroke = Stroke::new(
+            style.select_rect_stroke(ui.style()).width.max(1.0),
+            ui.visuals().error_fg_color,
+        );
+        let dash = style.select_rect_dash().unwrap_or(4.0);
+        ui.painter()
+            .extend(Shape::dashed_line(&[start, end], stroke, dash, dash));
+    }
+}
+
+#[test]
+#[allow(clippy::cast_precision_loss)]
+fn cut_line_picks_crossed_wires() {
+    use egui::{Color32, pos2};
+
+    use crate::{InPinId, NodeId, OutPinId};
+
+    use super::WireStyle;
+
+    let pin = |pos, wire_style| PinResponse {
+        pos,
+        hit_rect: Rect::NOTHING,
+        wire_color: Color32::WHITE,
+        wire_style,
+        vertical: false,
+        rail: None,
+    };
+
+    // Wire of each style runs from x = 0 to x = 200, stacked 100 apart.
+    let wire_paths = [
+        WireStyle::Line,
+        WireStyle::AxisAligned { corner_radius: 5.0 },
+        WireStyle::Bezier3,
+        WireStyle::Bezier5,
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(idx, wire_style)| {
+        let wire = Wire {
+            out_pin: OutPinId {
+                node: NodeId(idx),
+                output: 0,
+            },
+            in_pin: InPinId {
+                node: NodeId(idx + 10),
+                input: 0,
+            },
+        };
+        let y = idx as f32 * 100.0;
+        (
+            wire,
+            pin(pos2(0.0, y), wire_style),
+            pin(pos2(200.0, y + 50.0), wire_style),
+        )
+    })
+    .collect::<Vec<_>>();
+    let wires = |range: std::ops::Range<usize>| {
+        wire_paths[range]
+            .iter()
+            .map(|&(wire, _, _)| wire)
+            .collect::<Vec<_>>()
+    };
+
+    let ctx = egui::Context::default();
+    let _ = ctx.run(egui::RawInput::default(), |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let style = SnarlStyle::new();
+            let crossed = |start, end| crossed_wires(ui, &style, &wire_paths, (start, end));
+
+            assert_eq!(crossed(pos2(150.0, -50.0), pos2(150.0, 500.0)), wires(0..4));
+            assert_eq!(crossed(pos2(150.0, -50.0), pos2(150.0, 60.0)), wires(0..1));
+            assert_eq!(crossed(pos2(300.0, -50.0), pos2(300.0, 500.0)), wires(0..0));
+        });
+    });
+}
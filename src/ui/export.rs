@@ -0,0 +1,333 @@
+//! Layout of [`Snarl`] graph for rendering outside of the graph UI.
+
+use std::collections::HashMap;
+
+use egui::{Color32, Pos2, Rect, Style, TextStyle, Vec2, pos2, vec2};
+
+use crate::{AnyPinId, InPin, InPinId, NodeId, OutPin, OutPinId, Snarl, Wire};
+
+use super::{
+    NodeLayoutKind, SnarlStyle, SnarlViewer, default_node_layout, is_vertical_wire, mix_colors,
+};
+
+/// Pin laid out for export.
+#[derive(Clone, Copy)]
+pub(crate) struct ExportPin {
+    pub pos: Pos2,
+    /// Fill from [`SnarlViewer::pin_color`] or [`SnarlStyle::pin_fill`].
+    pub fill: Color32,
+}
+
+/// Node laid out for export.
+///
+/// Exported graph does not render node bodies,
+/// so node sizes are taken from measured sizes when available
+/// and estimated from title and number of pins otherwise.
+pub(crate) struct ExportNode {
+    pub rect: Rect,
+    pub header: Rect,
+    pub title: String,
+    pub inputs: Vec<ExportPin>,
+    pub outputs: Vec<ExportPin>,
+    /// Pins are on top and bottom edges.
+    pub vertical: bool,
+}
+
+/// Wire laid out for export.
+pub(crate) struct ExportWire {
+    pub from: Pos2,
+    pub to: Pos2,
+    pub vertical: bool,
+    /// Color of the wire, same as in the UI.
+    pub color: Color32,
+    /// Colors to blend from output to input pin with [`SnarlStyle::gradient_wires`].
+    pub gradient: Option<(Color32, Color32)>,
+}
+
+/// Lays out all nodes and wires of the graph for export.
+///
+/// Nodes found in `sizes` get that size, e.g. measured with [`Snarl::measure_node`].
+#[allow(clippy::too_many_lines)]
+pub(crate) fn export_layout<T, V>(
+    snarl: &Snarl<T>,
+    viewer: &mut V,
+    style: &SnarlStyle,
+    egui_style: &Style,
+    sizes: &HashMap<NodeId, Vec2>,
+) -> (Vec<(NodeId, ExportNode)>, Vec<ExportWire>)
+where
+    V: SnarlViewer<T>,
+{
+    let font_size = export_font_size(egui_style);
+    let row_height = egui_style.spacing.interact_size.y;
+    let margin = egui_style.spacing.item_spacing.x * 2.0;
+    let char_width = font_size * 0.6;
+    let pin_fill = style.pin_fill(egui_style);
+
+    let mut nodes = Vec::with_capacity(snarl.nodes.len());
+
+    for (idx, node) in &snarl.nodes {
+        let id = NodeId(idx);
+        let title = viewer.title(&node.value);
+        let inputs_count = viewer.inputs(&node.value);
+        let outputs_count = viewer.outputs(&node.value);
+
+        let inputs = (0..inputs_count)
+            .map(|input| InPin::new(snarl, InPinId { node: id, input }))
+            .collect::<Vec<_>>();
+        let outputs = (0..outputs_count)
+            .map(|output| OutPin::new(snarl, OutPinId { node: id, output }))
+            .collect::<Vec<_>>();
+
+        let default_layout = default_node_layout(viewer, style, id, snarl);
+        let layout = viewer.node_layout(default_layout, id, &inputs, &outputs, snarl);
+        let vertical = layout.kind.is_vertical();
+        let row_height = row_height.max(layout.min_pin_row_height);
+
+        #[allow(clippy::cast_precision_loss)]
+        let title_width = title.chars().count() as f32 * char_width + margin * 2.0;
+
+        #[allow(clippy::cast_precision_loss)]
+        let (width, body_height) = if vertical {
+            let pins = inputs_count.max(outputs_count) as f32;
+            (title_width.max(pins * row_height), row_height * 2.0)
+        } else {
+            let rows = (inputs_count + outputs_count) as f32;
+            let rows = match layout.kind {
+                NodeLayoutKind::Coil => inputs_count.max(outputs_count) as f32,
+                _ => rows,
+            };
+            (title_width.max(row_height * 4.0), rows * row_height)
+        };
+
+        let header_height = row_height + margin;
+        let size = match sizes.get(&id) {
+            Some(&size) if size.x > 0.0 && size.y > 0.0 => size,
+            _ if node.open => vec2(width, header_height + body_height + margin),
+            _ => vec2(width, header_height),
+        };
+        let rect = Rect::from_min_size(node.pos, size);
+        let header = Rect::from_min_size(node.pos, vec2(size.x, header_height.min(size.y)));
+
+        let body = Rect::from_min_max(pos2(rect.min.x, header.max.y), rect.max);
+
+        #[allow(clippy::cast_precision_loss)]
+        let spread = |count: usize, idx: usize, from: f32, to: f32| {
+            from + (to - from) * (idx as f32 + 0.5) / count as f32
+        };
+
+        #[allow(clippy::cast_precision_loss)]
+        let (input_pos, output_pos): (Vec<Pos2>, Vec<Pos2>) = if !node.open {
+            let y = header.center().y;
+            (
+                vec![pos2(rect.min.x, y); inputs_count],
+                vec![pos2(rect.max.x, y); outputs_count],
+            )
+        } else if vertical {
+            let (in_y, out_y) = match layout.kind {
+                NodeLayoutKind::VerticalSandwichUp => (rect.max.y, body.min.y),
+                _ => (body.min.y, rect.max.y),
+            };
+            (
+                (0..inputs_count)
+                    .map(|i| pos2(spread(inputs_count, i, rect.min.x, rect.max.x), in_y))
+                    .collect(),
+                (0..outputs_count)
+                    .map(|i| pos2(spread(outputs_count, i, rect.min.x, rect.max.x), out_y))
+                    .collect(),
+            )
+        } else {
+            let (in_first, out_first, rows) = match layout.kind {
+                NodeLayoutKind::Coil => (0, 0, inputs_count.max(outputs_count)),
+                NodeLayoutKind::Sandwich => (0, inputs_count, inputs_count + outputs_count),
+                _ => (outputs_count, 0, inputs_count + outputs_count),
+            };
+            let row = |i: usize| spread(rows, i, body.min.y, body.max.y);
+            (
+                (0..inputs_count)
+                    .map(|i| pos2(rect.min.x, row(in_first + i)))
+                    .collect(),
+                (0..outputs_count)
+                    .map(|i| pos2(rect.max.x, row(out_first + i)))
+                    .collect(),
+            )
+        };
+
+        let inputs = input_pos
+            .into_iter()
+            .enumerate()
+            .map(|(input, pos)| ExportPin {
+                pos,
+                fill: viewer
+                    .pin_color(AnyPinId::In(InPinId { node: id, input }), snarl)
+                    .unwrap_or(pin_fill),
+            })
+            .collect();
+        let outputs = output_pos
+            .into_iter()
+            .enumerate()
+            .map(|(output, pos)| ExportPin {
+                pos,
+                fill: viewer
+                    .pin_color(AnyPinId::Out(OutPinId { node: id, output }), snarl)
+                    .unwrap_or(pin_fill),
+            })
+            .collect();
+
+        nodes.push((
+            id,
+            ExportNode {
+                rect,
+                header,
+                title,
+                inputs,
+                outputs,
+                vertical,
+            },
+        ));
+    }
+
+    let find = |node: NodeId| nodes.iter().find_map(|(id, n)| (*id == node).then_some(n));
+
+    let mut wires = Vec::new();
+    for (out_pin, in_pin) in snarl.wires() {
+        let (Some(from_node), Some(to_node)) = (find(out_pin.node), find(in_pin.node)) else {
+            continue;
+        };
+        let (Some(&from), Some(&to)) = (
+            from_node.outputs.get(out_pin.output),
+            to_node.inputs.get(in_pin.input),
+        ) else {
+            continue;
+        };
+
+        // Same as in the UI.
+        let vertical = is_vertical_wire(from.pos, to.pos, from_node.vertical || to_node.vertical);
+        let custom_color = snarl
+            .wires
+            .data(&Wire { out_pin, in_pin })
+            .and_then(|data| data.color);
+        let gradient = (style.gradient_wires() && custom_color.is_none() && from.fill != to.fill)
+            .then_some((from.fill, to.fill));
+
+        wires.push(ExportWire {
+            from: from.pos,
+            to: to.pos,
+            vertical,
+            color: custom_color.unwrap_or_else(|| mix_colors(from.fill, to.fill)),
+            gradient,
+        });
+    }
+
+    (nodes, wires)
+}
+
+/// Returns size of the font used for titles in exported graph.
+pub(crate) fn export_font_size(egui_style: &Style) -> f32 {
+    egui_style
+        .text_styles
+        .get(&TextStyle::Body)
+        .map_or(14.0, |font| font.size)
+}
+
+/// Returns bounding rect of laid out nodes.
+pub(crate) fn export_bounding_rect(nodes: &[(NodeId, ExportNode)]) -> Rect {
+    nodes
+        .iter()
+        .fold(Rect::NOTHING, |bb, (_, node)| bb.union(node.rect))
+}
+
+impl<T> Snarl<T> {
+    /// Returns bounding rect of all nodes in graph space,
+    /// as they are laid out for export.
+    ///
+    /// Node sizes are estimated from titles and number of pins,
+    /// so nodes don't need to be shown first.
+    /// Nodes found in `sizes` get that size instead,
+    /// e.g. measured with [`Snarl::measure_node`].
+    ///
+    /// Returns [`Rect::NOTHING`] if graph has no nodes.
+    pub fn nodes_bounding_rect<V>(
+        &self,
+        viewer: &mut V,
+        style: &SnarlStyle,
+        egui_style: &Style,
+        sizes: &HashMap<NodeId, Vec2>,
+    ) -> Rect
+    where
+        V: SnarlViewer<T>,
+    {
+        let (nodes, _) = export_layout(self, viewer, style, egui_style, sizes);
+        export_bounding_rect(&nodes)
+    }
+}
+
+#[test]
+fn measured_sizes_replace_estimates() {
+    use super::{PinContext, PinInfo, SnarlPin};
+    use egui::Ui;
+
+    struct Viewer;
+
+    impl SnarlViewer<()> for Viewer {
+        fn title(&mut self, (): &()) -> String {
+            "Node".to_owned()
+        }
+        fn inputs(&mut self, (): &()) -> usize {
+            2
+        }
+        fn outputs(&mut self, (): &()) -> usize {
+            1
+        }
+        fn show_input(
+            &mut self,
+            _: &InPin,
+            ui: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<()>,
+        ) -> impl SnarlPin + 'static {
+            ui.label("A much longer input label");
+            PinInfo::circle()
+        }
+        fn show_output(
+            &mut self,
+            _: &OutPin,
+            _: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<()>,
+        ) -> impl SnarlPin + 'static {
+            PinInfo::circle()
+        }
+    }
+
+    let ctx = egui::Context::default();
+    let style = SnarlStyle::new();
+    let mut snarl = Snarl::new();
+    let node = snarl.insert_node(pos2(10.0, 20.0), ());
+
+    let mut measured = Vec2::ZERO;
+    let _ = ctx.run(egui::RawInput::default(), |ctx| {
+        measured = snarl.measure_node(node, &mut Viewer, &style, ctx);
+    });
+
+    let egui_style = ctx.style();
+    let (estimated, _) = export_layout(&snarl, &mut Viewer, &style, &egui_style, &HashMap::new());
+    assert_ne!(estimated[0].1.rect.size(), measured);
+
+    let sizes = HashMap::from([(node, measured)]);
+    let (nodes, _) = export_layout(&snarl, &mut Viewer, &style, &egui_style, &sizes);
+    let rect = nodes[0].1.rect;
+    assert_eq!(rect, Rect::from_min_size(pos2(10.0, 20.0), measured));
+    assert!(nodes[0].1.header.height() < rect.height());
+    for pin in nodes[0].1.inputs.iter().chain(&nodes[0].1.outputs) {
+        let pos = pin.pos;
+        assert!(
+            pos.y > nodes[0].1.header.max.y && pos.y < rect.max.y,
+            "{pos:?}"
+        );
+    }
+    assert_eq!(
+        snarl.nodes_bounding_rect(&mut Viewer, &style, &egui_style, &sizes),
+        rect
+    );
+}
@@ -0,0 +1,438 @@
+//! Querying what is under a point of the graph shown in the UI.
+
+use std::sync::Arc;
+
+use egui::{Context, Id, Pos2, Rect, Ui, emath::TSTransform, vec2};
+
+use crate::{AnyPinId, InPinId, NodeId, OutPinId, Snarl, Wire};
+
+use super::{
+    NodeHitShape, SnapAnchor, SnarlWidget, WireLayer, WireStyle,
+    wire::{WireId, WirePath, hit_wire, wire_line},
+};
+
+/// Element of the graph found with [`SnarlWidget::hit_test`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HitTarget {
+    /// Node's frame.
+    Node(NodeId),
+
+    /// Input pin.
+    InPin(InPinId),
+
+    /// Output pin.
+    OutPin(OutPinId),
+
+    /// Wire between output and input pins.
+    Wire(OutPinId, InPinId),
+
+    /// Nothing but the graph background.
+    Background,
+}
+
+/// End of the wire.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WhichEnd {
+    /// End attached to the output pin.
+    Output,
+
+    /// End attached to the input pin.
+    Input,
+}
+
+/// Wire as it was laid out last time the graph was shown.
+#[derive(Clone, Copy)]
+pub(crate) struct HitWire {
+    pub out_pin: OutPinId,
+    pub in_pin: InPinId,
+    pub from: Pos2,
+    pub to: Pos2,
+    pub style: WireStyle,
+    pub vertical: bool,
+}
+
+/// Geometry of the graph elements from the last time the graph was shown.
+///
+/// Stored in egui temp data under the snarl id.
+#[derive(Clone, Default)]
+pub(crate) struct HitGeometry {
+    /// Node rects and hit shapes in draw order, last is on top.
+    pub nodes: Vec<(NodeId, Rect, NodeHitShape)>,
+    /// Pin centers and interactive areas, same as used by pin interaction.
+    pub in_pins: Vec<(InPinId, Pos2, Rect)>,
+    pub out_pins: Vec<(OutPinId, Pos2, Rect)>,
+    pub wires: Vec<HitWire>,
+    pub wire_frame_size: f32,
+    pub upscale_wire_frame: bool,
+    pub downscale_wire_frame: bool,
+    pub bezier_tension: f32,
+    pub wire_hit_threshold: f32,
+    pub wire_smoothness: f32,
+    pub wire_layer: WireLayer,
+
+    /// Transform from graph space to screen space.
+    pub to_global: TSTransform,
+}
+
+impl HitGeometry {
+    pub fn save(self, cx: &Context, id: Id) {
+        cx.data_mut(|d| d.insert_temp(id.with("hit-geometry"), Arc::new(self)));
+    }
+
+    pub fn load(cx: &Context, id: Id) -> Option<Arc<Self>> {
+        cx.data(|d| d.get_temp::<Arc<Self>>(id.with("hit-geometry")))
+    }
+
+    fn hit_test(&self, cx: &Context, snarl_id: Id, pos: Pos2) -> HitTarget {
+        // Pins are drawn above nodes.
+        // Where hit areas overlap the pin with nearest center wins.
+        let in_pin = self
+            .in_pins
+            .iter()
+            .filter(|(_, _, hit_rect)| hit_rect.contains(pos))
+            .map(|&(pin, p, _)| (HitTarget::InPin(pin), p.distance_sq(pos)));
+        let out_pin = self
+            .out_pins
+            .iter()
+            .filter(|(_, _, hit_rect)| hit_rect.contains(pos))
+            .map(|&(pin, p, _)| (HitTarget::OutPin(pin), p.distance_sq(pos)));
+
+        if let Some((target, _)) = in_pin
+            .chain(out_pin)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        {
+            return target;
+        }
+
+        let node = || {
+            self.nodes
+                .iter()
+                .rev()
+                .find(|(_, rect, shape)| shape.contains(*rect, pos))
+                .map(|&(node, _, _)| HitTarget::Node(node))
+        };
+
+        let wire = || {
+            self.wires.iter().rev().find_map(|wire| {
+                let hit = hit_wire(
+                    cx,
+                    WireId::Connected {
+                        snarl_id,
+                        out_pin: wire.out_pin,
+                        in_pin: wire.in_pin,
+                    },
+                    self.wire_frame_size,
+                    self.upscale_wire_frame,
+                    self.downscale_wire_frame,
+                    self.bezier_tension,
+                    wire.from,
+                    wire.to,
+                    pos,
+                    self.wire_hit_threshold,
+                    wire.style,
+                    wire.vertical,
+                );
+                hit.then_some(HitTarget::Wire(wire.out_pin, wire.in_pin))
+            })
+        };
+
+        let target = match self.wire_layer {
+            WireLayer::BehindNodes => node().or_else(wire),
+            WireLayer::AboveNodes => wire().or_else(node),
+        };
+
+        target.unwrap_or(HitTarget::Background)
+    }
+
+    fn wire_path(&self, out_pin: OutPinId, in_pin: InPinId) -> Option<WirePath> {
+        let wire = self
+            .wires
+            .iter()
+            .find(|wire| wire.out_pin == out_pin && wire.in_pin == in_pin)?;
+
+        let line = wire_line(
+            self.wire_frame_size,
+            self.upscale_wire_frame,
+            self.downscale_wire_frame,
+            self.bezier_tension,
+            wire.from,
+            wire.to,
+            self.wire_smoothness,
+            wire.style,
+            wire.vertical,
+        );
+
+        Some(WirePath::new(
+            line.into_iter().map(|p| self.to_global * p).collect(),
+        ))
+    }
+}
+
+/// Node and pin under the pointer the last time the graph was shown.
+///
+/// Stored in egui temp data under the snarl id.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Hovered {
+    pub node: Option<NodeId>,
+    pub pin: Option<AnyPinId>,
+}
+
+impl Hovered {
+    pub fn save(self, cx: &Context, id: Id) {
+        cx.data_mut(|d| d.insert_temp(id.with("hovered"), self));
+    }
+
+    pub fn load(cx: &Context, id: Id) -> Self {
+        cx.data(|d| d.get_temp(id.with("hovered")).unwrap_or_default())
+    }
+}
+
+impl SnarlWidget {
+    /// Returns element of the graph at `graph_pos` in graph space.
+    ///
+    /// Uses layout from the last time the widget was shown
+    /// and same hit-testing as the interaction code,
+    /// including wire hit tolerance.
+    /// Pins take precedence over nodes,
+    /// and topmost node or wire is picked.
+    ///
+    /// Use same `Ui` instance that was used in [`SnarlWidget::show`].
+    #[must_use]
+    #[inline]
+    pub fn hit_test(self, ui: &Ui, graph_pos: Pos2) -> HitTarget {
+        self.hit_test_at(ui.id(), ui.ctx(), graph_pos)
+    }
+
+    /// Returns element of the graph at `graph_pos` in graph space.
+    ///
+    /// See [`SnarlWidget::hit_test`].
+    ///
+    /// `ui_id` must be the Id of the `Ui` instance that was used in [`SnarlWidget::show`].
+    #[must_use]
+    #[inline]
+    pub fn hit_test_at(self, ui_id: Id, ctx: &Context, graph_pos: Pos2) -> HitTarget {
+        let snarl_id = self.get_id(ui_id);
+        match HitGeometry::load(ctx, snarl_id) {
+            None => HitTarget::Background,
+            Some(geometry) => geometry.hit_test(ctx, snarl_id, graph_pos),
+        }
+    }
+
+    /// Returns node under the pointer.
+    ///
+    /// Uses layout from the last time the widget was shown.
+    /// Topmost node is returned when nodes overlap.
+    /// Returns `None` if pointer is not over the graph or over its background.
+    ///
+    /// Use same `Ui` instance that was used in [`SnarlWidget::show`].
+    #[must_use]
+    #[inline]
+    pub fn hovered_node(self, ui: &Ui) -> Option<NodeId> {
+        self.hovered_node_at(ui.id(), ui.ctx())
+    }
+
+    /// Returns node under the pointer.
+    ///
+    /// See [`SnarlWidget::hovered_node`].
+    ///
+    /// `ui_id` must be the Id of the `Ui` instance that was used in [`SnarlWidget::show`].
+    #[must_use]
+    #[inline]
+    pub fn hovered_node_at(self, ui_id: Id, ctx: &Context) -> Option<NodeId> {
+        Hovered::load(ctx, self.get_id(ui_id)).node
+    }
+
+    /// Returns pin under the pointer.
+    ///
+    /// Uses layout from the last time the widget was shown.
+    ///
+    /// Use same `Ui` instance that was used in [`SnarlWidget::show`].
+    #[must_use]
+    #[inline]
+    pub fn hovered_pin(self, ui: &Ui) -> Option<AnyPinId> {
+        self.hovered_pin_at(ui.id(), ui.ctx())
+    }
+
+    /// Returns pin under the pointer.
+    ///
+    /// See [`SnarlWidget::hovered_pin`].
+    ///
+    /// `ui_id` must be the Id of the `Ui` instance that was used in [`SnarlWidget::show`].
+    #[must_use]
+    #[inline]
+    pub fn hovered_pin_at(self, ui_id: Id, ctx: &Context) -> Option<AnyPinId> {
+        Hovered::load(ctx, self.get_id(ui_id)).pin
+    }
+
+    /// Returns path of the wire from `from` to `to` pin in screen space.
+    ///
+    /// Uses layout from the last time the widget was shown
+    /// and same routing as the wire rendering,
+    /// so overlays placed with [`WirePath::point_at`] follow pan and zoom.
+    /// Returns `None` if there is no such wire or it was not laid out.
+    ///
+    /// Use same `Ui` instance that was used in [`SnarlWidget::show`].
+    #[must_use]
+    #[inline]
+    pub fn wire_path(self, ui: &Ui, from: OutPinId, to: InPinId) -> Option<WirePath> {
+        self.wire_path_at(ui.id(), ui.ctx(), from, to)
+    }
+
+    /// Returns path of the wire from `from` to `to` pin in screen space.
+    ///
+    /// See [`SnarlWidget::wire_path`].
+    ///
+    /// `ui_id` must be the Id of the `Ui` instance that was used in [`SnarlWidget::show`].
+    #[must_use]
+    #[inline]
+    pub fn wire_path_at(
+        self,
+        ui_id: Id,
+        ctx: &Context,
+        from: OutPinId,
+        to: InPinId,
+    ) -> Option<WirePath> {
+        HitGeometry::load(ctx, self.get_id(ui_id))?.wire_path(from, to)
+    }
+
+    /// Moves node on `which` end of the wire from `from` to `to` pin vertically,
+    /// so that both pins are at the same height and the wire is straight.
+    /// Returns `true` if the node was moved.
+    /// Does nothing if pins are not connected.
+    ///
+    /// Uses pin positions from the last time the widget was shown.
+    /// With [`SnarlConfig::grid_snap`](crate::ui::SnarlConfig::grid_snap) set,
+    /// node is moved to the nearest grid row instead,
+    /// so wire is straight only if pins are aligned with the grid.
+    ///
+    /// Use same `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn straighten_wire<T>(
+        self,
+        ui: &Ui,
+        snarl: &mut Snarl<T>,
+        from: OutPinId,
+        to: InPinId,
+        which: WhichEnd,
+    ) -> bool {
+        self.straighten_wire_at(ui.id(), ui.ctx(), snarl, from, to, which)
+    }
+
+    /// Moves node on `which` end of the wire from `from` to `to` pin vertically,
+    /// so that both pins are at the same height and the wire is straight.
+    /// Returns `true` if the node was moved.
+    ///
+    /// See [`SnarlWidget::straighten_wire`].
+    ///
+    /// `ui_id` must be the Id of the `Ui` instance that was used in [`SnarlWidget::show`].
+    pub fn straighten_wire_at<T>(
+        self,
+        ui_id: Id,
+        ctx: &Context,
+        snarl: &mut Snarl<T>,
+        from: OutPinId,
+        to: InPinId,
+        which: WhichEnd,
+    ) -> bool {
+        let wire = Wire {
+            out_pin: from,
+            in_pin: to,
+        };
+        if !snarl.wires.contains(&wire) {
+            return false;
+        }
+
+        let Some(geometry) = HitGeometry::load(ctx, self.get_id(ui_id)) else {
+            return false;
+        };
+
+        let out_pos = geometry
+            .out_pins
+            .iter()
+            .find(|&&(pin, _, _)| pin == from)
+            .map(|&(_, pos, _)| pos);
+        let in_pos = geometry
+            .in_pins
+            .iter()
+            .find(|&&(pin, _, _)| pin == to)
+            .map(|&(_, pos, _)| pos);
+        let (Some(out_pos), Some(in_pos)) = (out_pos, in_pos) else {
+            return false;
+        };
+
+        let (node, dy) = match which {
+            WhichEnd::Output => (from.node, in_pos.y - out_pos.y),
+            WhichEnd::Input => (to.node, out_pos.y - in_pos.y),
+        };
+
+        let Some(node_data) = snarl.nodes.get_mut(node.0) else {
+            return false;
+        };
+
+        let mut pos = node_data.pos + vec2(0.0, dy);
+        if let Some(grid) = self.config.grid_snap {
+            let anchor = match grid.anchor {
+                SnapAnchor::TopLeft => egui::Vec2::ZERO,
+                SnapAnchor::Center => geometry
+                    .nodes
+                    .iter()
+                    .find(|&&(n, _, _)| n == node)
+                    .map_or(egui::Vec2::ZERO, |(_, rect, _)| {
+                        rect.center() - node_data.pos
+                    }),
+            };
+            pos.y = (grid.snap(pos + anchor) - anchor).y;
+        }
+
+        if (pos.y - node_data.pos.y).abs() <= f32::EPSILON {
+            return false;
+        }
+
+        node_data.pos = pos;
+        ctx.request_repaint();
+        true
+    }
+}
+
+#[test]
+fn pins_are_hit_within_their_interactive_area() {
+    let pin = |node, input| InPinId {
+        node: NodeId(node),
+        input,
+    };
+    let geometry = HitGeometry {
+        in_pins: vec![
+            (
+                pin(0, 0),
+                egui::pos2(0.0, 0.0),
+                Rect::from_min_max(egui::pos2(-8.0, -8.0), egui::pos2(8.0, 5.0)),
+            ),
+            (
+                pin(0, 1),
+                egui::pos2(0.0, 10.0),
+                Rect::from_min_max(egui::pos2(-8.0, 5.0), egui::pos2(8.0, 18.0)),
+            ),
+        ],
+        out_pins: vec![(
+            OutPinId {
+                node: NodeId(1),
+                output: 0,
+            },
+            egui::pos2(100.0, 0.0),
+            Rect::NOTHING,
+        )],
+        ..HitGeometry::default()
+    };
+
+    let cx = Context::default();
+    let id = Id::new("snarl");
+    let hit = |x, y| geometry.hit_test(&cx, id, egui::pos2(x, y));
+
+    // Outside of the pin shape, but within the hit area.
+    assert_eq!(hit(7.0, -7.0), HitTarget::InPin(pin(0, 0)));
+    assert_eq!(hit(0.0, 6.0), HitTarget::InPin(pin(0, 1)));
+    assert_eq!(hit(9.0, 0.0), HitTarget::Background);
+
+    // Pins without interactive area are never hit.
+    assert_eq!(hit(100.0, 0.0), HitTarget::Background);
+}
@@ -0,0 +1,36 @@
+//! Expanding collapsed nodes hovered for a while.
+
+use egui::{Id, Ui};
+
+use crate::{NodeId, Snarl};
+
+use super::{SnarlStyle, hit::Hovered, state::SnarlState};
+
+/// Returns collapsed node hovered longer than [`SnarlStyle::hover_expand_delay`].
+///
+/// Requests repaint when the delay of the hovered node is about to pass.
+pub(super) fn hover_expanded_node<T>(
+    ui: &Ui,
+    snarl_id: Id,
+    style: &SnarlStyle,
+    snarl: &Snarl<T>,
+    snarl_state: &mut SnarlState,
+    time: f64,
+) -> Option<NodeId> {
+    let delay = style.hover_expand_delay()?;
+
+    let hovered = Hovered::load(ui.ctx(), snarl_id)
+        .node
+        .filter(|node| snarl.nodes.get(node.0).is_some_and(|node| !node.open));
+    let elapsed = snarl_state.track_hover_expand(hovered, time);
+    hovered?;
+
+    let remaining = f64::from(delay.max(0.0)) - elapsed;
+    if remaining > 0.0 {
+        #[allow(clippy::cast_possible_truncation)]
+        ui.ctx().request_repaint_after_secs(remaining as f32);
+        None
+    } else {
+        hovered
+    }
+}
@@ -0,0 +1,255 @@
+//! Searchable menu for creating nodes.
+
+use egui::{Id, Key, ScrollArea, TextEdit, Ui};
+
+/// Searchable list of node types to show in menus.
+///
+/// This is optional helper for [`SnarlViewer::show_graph_menu`](crate::ui::SnarlViewer::show_graph_menu)
+/// and [`SnarlViewer::show_dropped_wire_menu`](crate::ui::SnarlViewer::show_dropped_wire_menu).
+/// It renders search box and list of entries filtered with fuzzy matching,
+/// and returns factory of the chosen entry.
+///
+/// # Examples
+///
+/// ```
+/// # use egui_snarl::{Snarl, ui::SnarlNodeMenu};
+/// # fn show_graph_menu(pos: egui::Pos2, ui: &mut egui::Ui, snarl: &mut Snarl<String>) {
+/// let menu = SnarlNodeMenu::<fn() -> String>::new()
+///     .entry("Math", "Add", || "add".to_owned())
+///     .entry("Math", "Multiply", || "mul".to_owned())
+///     .entry("Text", "Concat", || "concat".to_owned());
+///
+/// if let Some(factory) = menu.show(ui) {
+///     snarl.insert_node(pos, factory());
+///     ui.close();
+/// }
+/// # }
+/// ```
+pub struct SnarlNodeMenu<F> {
+    entries: Vec<NodeMenuEntry<F>>,
+    id_salt: Id,
+    max_height: f32,
+}
+
+struct NodeMenuEntry<F> {
+    category: String,
+    label: String,
+    factory: F,
+}
+
+impl<F> Default for SnarlNodeMenu<F> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F> SnarlNodeMenu<F> {
+    /// Creates new empty menu.
+    #[must_use]
+    #[inline]
+    pub fn new() -> Self {
+        SnarlNodeMenu {
+            entries: Vec::new(),
+            id_salt: Id::new("snarl-node-menu"),
+            max_height: 300.0,
+        }
+    }
+
+    /// Adds entry to the menu.
+    ///
+    /// Entries are grouped by `category` when search query is empty.
+    #[must_use]
+    #[inline]
+    pub fn entry(
+        mut self,
+        category: impl Into<String>,
+        label: impl Into<String>,
+        factory: F,
+    ) -> Self {
+        self.entries.push(NodeMenuEntry {
+            category: category.into(),
+            label: label.into(),
+            factory,
+        });
+        self
+    }
+
+    /// Adds entries to the menu.
+    #[must_use]
+    #[inline]
+    pub fn entries<C, L>(mut self, entries: impl IntoIterator<Item = (C, L, F)>) -> Self
+    where
+        C: Into<String>,
+        L: Into<String>,
+    {
+        for (category, label, factory) in entries {
+            self = self.entry(category, label, factory);
+        }
+        self
+    }
+
+    /// Assign a source for the [`Id`] used to store search query.
+    ///
+    /// It must be locally unique for the current [`Ui`] hierarchy position.
+    #[must_use]
+    #[inline]
+    pub fn id_salt(mut self, id_salt: impl std::hash::Hash) -> Self {
+        self.id_salt = Id::new(id_salt);
+        self
+    }
+
+    /// Sets maximum height of the entries list.
+    #[must_use]
+    #[inline]
+    pub const fn max_height(mut self, max_height: f32) -> Self {
+        self.max_height = max_height;
+        self
+    }
+
+    /// Renders search box and filtered entries.
+    ///
+    /// Returns factory of the entry chosen by click,
+    /// or the best match when `Enter` is pressed.
+    /// Search query is reset when entry is chosen.
+    pub fn show(self, ui: &mut Ui) -> Option<F> {
+        let id = ui.id().with(self.id_salt);
+
+        let mut query = ui.data(|d| d.get_temp::<String>(id)).unwrap_or_default();
+
+        let search = ui.add(
+            TextEdit::singleline(&mut query)
+                .id(id.with("search"))
+                .hint_text("Search…"),
+        );
+
+        if !search.has_focus() && !search.lost_focus() {
+            search.request_focus();
+        }
+
+        let enter = search.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+
+        let mut matches = if query.is_empty() {
+            let mut matches = self
+                .entries
+                .iter()
+                .enumerate()
+                .map(|(idx, _)| (idx, 0))
+                .collect::<Vec<_>>();
+
+            // Group by categories in order of their first appearance.
+            // Stable sort keeps insertion order within categories.
+            matches.sort_by_key(|&(idx, _)| {
+                let category = &self.entries[idx].category;
+                self.entries
+                    .iter()
+                    .position(|entry| entry.category == *category)
+            });
+            matches
+        } else {
+            self.entries
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, entry)| {
+                    let score = fuzzy_score(&query, &entry.label).or_else(|| {
+                        fuzzy_score(&query, &format!("{} {}", entry.category, entry.label))
+                            .map(|score| score - 1)
+                    })?;
+                    Some((idx, score))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        if !query.is_empty() {
+            matches.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+        }
+
+        let mut chosen = if enter {
+            matches.first().map(|&(idx, _)| idx)
+        } else {
+            None
+        };
+
+        ScrollArea::vertical()
+            .max_height(self.max_height)
+            .id_salt(id.with("list"))
+            .show(ui, |ui| {
+                let mut category = None;
+
+                for &(idx, _) in &matches {
+                    let entry = &self.entries[idx];
+
+                    if query.is_empty() {
+                        if category != Some(&entry.category) {
+                            category = Some(&entry.category);
+                            ui.label(egui::RichText::new(&entry.category).weak());
+                        }
+                        if ui.button(&entry.label).clicked() {
+                            chosen = Some(idx);
+                        }
+                    } else {
+                        let r = ui.horizontal(|ui| {
+                            let r = ui.button(&entry.label);
+                            ui.label(egui::RichText::new(&entry.category).weak());
+                            r
+                        });
+                        if r.inner.clicked() {
+                            chosen = Some(idx);
+                        }
+                    }
+                }
+
+                if matches.is_empty() {
+                    ui.label(egui::RichText::new("No matches").weak());
+                }
+            });
+
+        if let Some(idx) = chosen {
+            ui.data_mut(|d| d.remove::<String>(id));
+            self.entries.into_iter().nth(idx).map(|entry| entry.factory)
+        } else {
+            ui.data_mut(|d| d.insert_temp(id, query));
+            None
+        }
+    }
+}
+
+/// Scores how well `pattern` matches `text` as case-insensitive subsequence.
+///
+/// Returns `None` if `pattern` is not a subsequence of `text`.
+/// Consecutive matches and matches at word starts score higher,
+/// gaps between matches lower the score.
+fn fuzzy_score(pattern: &str, text: &str) -> Option<i32> {
+    let mut score = 0;
+    let mut text_chars = text.chars().flat_map(char::to_lowercase).enumerate();
+    let mut prev_char = None;
+    let mut last_match = None;
+
+    for p in pattern.chars().flat_map(char::to_lowercase) {
+        if p.is_whitespace() {
+            continue;
+        }
+
+        loop {
+            let (idx, c) = text_chars.next()?;
+            let word_start = prev_char.is_none_or(|prev: char| !prev.is_alphanumeric());
+            prev_char = Some(c);
+
+            if c == p {
+                score += 1;
+                if word_start {
+                    score += 3;
+                }
+                match last_match {
+                    Some(last) if last + 1 == idx => score += 2,
+                    Some(last) => score -= i32::try_from(idx - last - 1).unwrap_or(i32::MAX).min(3),
+                    None => score -= i32::try_from(idx).unwrap_or(i32::MAX).min(3),
+                }
+                last_match = Some(idx);
+                break;
+            }
+        }
+    }
+
+    Some(score)
+}
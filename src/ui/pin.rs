@@ -1,4 +1,7 @@
-use egui::{Color32, Painter, Rect, Shape, Stroke, Style, Vec2, epaint::PathShape, pos2, vec2};
+use egui::{
+    Align2, Color32, FontId, Painter, Rect, Shape, Stroke, Style, Vec2, epaint::PathShape, pos2,
+    vec2,
+};
 
 use crate::{InPinId, OutPinId};
 
@@ -9,6 +12,7 @@ use super::{SnarlStyle, WireStyle};
 /// This struct is passed to `show_input` and `show_output` methods to inform
 /// the viewer about the current UI state and whether labels should be visible.
 #[derive(Clone, Copy, Debug, Default)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct PinContext {
     /// Whether the label should be visible based on current UI state and style settings.
     pub label_visible: bool,
@@ -47,12 +51,6 @@ impl PinContext {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-pub enum AnyPin {
-    Out(OutPinId),
-    In(InPinId),
-}
-
 /// In the current context, these are the I/O pins of the 'source' node that the newly
 /// created node's I/O pins will connect to.
 #[derive(Debug)]
@@ -96,6 +94,18 @@ pub trait SnarlPin {
         Rect::from_center_size(pin_pos, vec2(size, size))
     }
 
+    /// Returns `true` if the pin is a rail - continuous edge
+    /// that wires can attach to at any position along it.
+    ///
+    /// Rail pins span the whole extent of their row along the node edge.
+    /// Position where wire is dropped on the rail is passed to [`SnarlViewer::connect`](crate::ui::SnarlViewer::connect)
+    /// in [`InPin::rail_pos`](crate::InPin::rail_pos) and [`OutPin::rail_pos`](crate::OutPin::rail_pos).
+    ///
+    /// Returns `false` by default.
+    fn is_rail(&self) -> bool {
+        false
+    }
+
     /// Draws the pin.
     ///
     /// `rect` is the interaction rectangle of the pin.
@@ -111,6 +121,26 @@ pub trait SnarlPin {
         rect: Rect,
         painter: &Painter,
     ) -> PinWireInfo;
+
+    /// Draws the pin that has `connections` wires.
+    ///
+    /// Called by the graph UI instead of [`SnarlPin::draw`].
+    /// By default it ignores `connections` and calls [`SnarlPin::draw`].
+    #[must_use]
+    fn draw_connected(
+        self,
+        snarl_style: &SnarlStyle,
+        style: &Style,
+        rect: Rect,
+        painter: &Painter,
+        connections: usize,
+    ) -> PinWireInfo
+    where
+        Self: Sized,
+    {
+        let _ = connections;
+        self.draw(snarl_style, style, rect, painter)
+    }
 }
 
 /// Shape of a pin.
@@ -133,6 +163,42 @@ pub enum PinShape {
     Star,
 }
 
+/// How a pin indicates its connection state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "egui-probe", derive(egui_probe::EguiProbe))]
+#[cfg_attr(feature = "facet", derive(facet::Facet), repr(u8))]
+pub enum PinConnectionIndicator {
+    /// Pin is filled when connected and hollow otherwise.
+    Fill,
+
+    /// Pin with multiple wires shows badge with the number of wires.
+    Badge,
+
+    /// Both [`PinConnectionIndicator::Fill`] and [`PinConnectionIndicator::Badge`].
+    FillAndBadge,
+}
+
+/// Arrangement of pin contents shown by `SnarlViewer::show_input` and `SnarlViewer::show_output`.
+///
+/// Returned per node by `SnarlViewer::pin_layout`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "egui-probe", derive(egui_probe::EguiProbe))]
+#[cfg_attr(feature = "facet", derive(facet::Facet), repr(u8))]
+pub enum PinLayout {
+    /// Pins on the sides place contents in a row aligned to its top.
+    /// Pins on the top and bottom stack contents in a column
+    /// growing away from the pin.
+    #[default]
+    Stacked,
+
+    /// All pins place contents in a single row centered vertically,
+    /// so label and its widget share one line.
+    /// Reduces height of nodes with pins on the top and bottom.
+    Inline,
+}
+
 /// Information about a pin returned by `SnarlViewer::show_input` and `SnarlViewer::show_output`.
 ///
 /// All fields are optional.
@@ -157,6 +223,14 @@ pub struct PinInfo {
 
     /// Custom vertical position of a pin
     pub position: Option<f32>,
+
+    /// Indication of the pin's connection state.
+    /// If `None`, the pin is drawn the same regardless of its wires.
+    pub connection_indicator: Option<PinConnectionIndicator>,
+
+    /// Whether the pin is a rail that wires can attach to at any position along it.
+    /// See [`SnarlPin::is_rail`].
+    pub rail: bool,
 }
 
 impl PinInfo {
@@ -195,6 +269,20 @@ impl PinInfo {
         self
     }
 
+    /// Sets how the pin indicates its connection state.
+    #[must_use]
+    pub const fn with_connection_indicator(mut self, indicator: PinConnectionIndicator) -> Self {
+        self.connection_indicator = Some(indicator);
+        self
+    }
+
+    /// Makes the pin a rail that wires can attach to at any position along it.
+    #[must_use]
+    pub const fn with_rail(mut self, rail: bool) -> Self {
+        self.rail = rail;
+        self
+    }
+
     /// Creates a circle pin.
     #[must_use]
     pub fn circle() -> Self {
@@ -263,7 +351,63 @@ impl PinInfo {
         let shape = self.shape(snarl_style);
         let fill = self.fill(snarl_style, style);
         let stroke = self.stroke(snarl_style, style);
-        draw_pin(painter, shape, fill, stroke, rect);
+        if self.rail {
+            draw_rail(painter, fill, stroke, rect);
+        } else {
+            draw_pin(painter, shape, fill, stroke, rect);
+        }
+
+        PinWireInfo {
+            color: self.wire_color.unwrap_or(fill),
+            style: self.wire_style.unwrap_or(snarl_style.wire_style()),
+        }
+    }
+
+    /// Draws the pin that has `connections` wires and returns color.
+    ///
+    /// Same as [`PinInfo::draw`] but applies [`PinInfo::connection_indicator`].
+    #[must_use]
+    pub fn draw_connected(
+        &self,
+        snarl_style: &SnarlStyle,
+        style: &Style,
+        rect: Rect,
+        painter: &Painter,
+        connections: usize,
+    ) -> PinWireInfo {
+        let Some(indicator) = self.connection_indicator else {
+            return self.draw(snarl_style, style, rect, painter);
+        };
+
+        let shape = self.shape(snarl_style);
+        let fill = self.fill(snarl_style, style);
+        let mut stroke = self.stroke(snarl_style, style);
+
+        let fill_indicator = matches!(
+            indicator,
+            PinConnectionIndicator::Fill | PinConnectionIndicator::FillAndBadge
+        );
+        let badge_indicator = matches!(
+            indicator,
+            PinConnectionIndicator::Badge | PinConnectionIndicator::FillAndBadge
+        );
+
+        let mut fill_shape = fill;
+        if fill_indicator && connections == 0 {
+            // Hollow pin with outline in the fill color.
+            stroke = Stroke::new(stroke.width.max(1.0), fill);
+            fill_shape = Color32::TRANSPARENT;
+        }
+
+        if self.rail {
+            draw_rail(painter, fill_shape, stroke, rect);
+        } else {
+            draw_pin(painter, shape, fill_shape, stroke, rect);
+        }
+
+        if badge_indicator && connections > 1 {
+            draw_badge(painter, style, rect, fill, connections);
+        }
 
         PinWireInfo {
             color: self.wire_color.unwrap_or(fill),
@@ -282,6 +426,48 @@ impl SnarlPin for PinInfo {
     ) -> PinWireInfo {
         Self::draw(&self, snarl_style, style, rect, painter)
     }
+
+    fn draw_connected(
+        self,
+        snarl_style: &SnarlStyle,
+        style: &Style,
+        rect: Rect,
+        painter: &Painter,
+        connections: usize,
+    ) -> PinWireInfo {
+        Self::draw_connected(&self, snarl_style, style, rect, painter, connections)
+    }
+
+    fn is_rail(&self) -> bool {
+        self.rail
+    }
+}
+
+/// Draws rail pin as a capsule filling the `rect`.
+fn draw_rail(painter: &Painter, fill: Color32, stroke: Stroke, rect: Rect) {
+    let radius = f32::min(rect.width(), rect.height()) * 0.5;
+    painter.rect(rect, radius, fill, stroke, egui::StrokeKind::Middle);
+}
+
+/// Draws badge with number of pin's connections at the top-right corner of the pin.
+fn draw_badge(painter: &Painter, style: &Style, rect: Rect, color: Color32, connections: usize) {
+    let size = f32::min(rect.width(), rect.height());
+    let radius = size * 0.4;
+    let center = rect.right_top();
+
+    painter.circle(
+        center,
+        radius,
+        style.visuals.extreme_bg_color,
+        Stroke::new(1.0, color),
+    );
+    painter.text(
+        center,
+        Align2::CENTER_CENTER,
+        connections.to_string(),
+        FontId::proportional(radius * 1.5),
+        style.visuals.text_color(),
+    );
 }
 
 pub fn draw_pin(painter: &Painter, shape: PinShape, fill: Color32, stroke: Stroke, rect: Rect) {
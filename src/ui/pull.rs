@@ -0,0 +1,43 @@
+//! Pulling wires off an input pin by click, enabled by [`SnarlConfig::pull_wire_on_pin_click`].
+
+use egui::{Response, Ui};
+
+use crate::{InPin, Snarl};
+
+use super::{SnarlConfig, state::SnarlState};
+
+/// Detaches wires of `in_pin` clicked with [`SnarlConfig::drag_pin`] button
+/// so they follow the pointer until the next click.
+pub(super) fn pull_wires_on_click<T>(
+    config: &SnarlConfig,
+    pin_resp: &Response,
+    in_pin: &InPin,
+    snarl_state: &mut SnarlState,
+    snarl: &mut Snarl<T>,
+) {
+    if config.pull_wire_on_pin_click
+        && pin_resp.clicked_by(config.drag_pin.mouse_button)
+        && !snarl_state.has_new_wires()
+        && !in_pin.remotes.is_empty()
+    {
+        // Pull off the wires to reconnect them elsewhere.
+        snarl_state.pull_new_wires_out(&in_pin.remotes);
+        snarl.drop_inputs(in_pin.id);
+    }
+}
+
+/// Returns `true` if wires pulled in previous frames are dropped by click in this frame.
+///
+/// `pulled` must be sampled before pins are drawn, so the click that pulled the wires
+/// doesn't drop them right away.
+pub(super) fn pulled_wires_dropped(ui: &Ui, config: &SnarlConfig, pulled: bool) -> bool {
+    pulled && ui.input(|i| i.pointer.button_clicked(config.drag_pin.mouse_button))
+}
+
+/// Cancels pulled wires when Escape is pressed, since they don't follow a drag
+/// that could be released.
+pub(super) fn cancel_pulled_wires(escape_pressed: bool, snarl_state: &mut SnarlState) {
+    if escape_pressed && snarl_state.has_pulled_new_wires() {
+        let _ = snarl_state.take_new_wires();
+    }
+}
@@ -1,6 +1,8 @@
 use egui_scale::EguiScale;
 
-use super::{BackgroundPattern, PinPlacement, SelectionStyle, SnarlStyle, WireStyle};
+use super::{
+    BackgroundPattern, BackgroundStyle, PinPlacement, SelectionStyle, SnarlStyle, WireStyle,
+};
 
 impl EguiScale for WireStyle {
     #[inline(always)]
@@ -39,23 +41,41 @@ impl EguiScale for BackgroundPattern {
     }
 }
 
+impl EguiScale for BackgroundStyle {
+    fn scale(&mut self, scale: f32) {
+        if let BackgroundStyle::Checkerboard { size, .. } = self {
+            size.scale(scale);
+        }
+    }
+}
+
 impl EguiScale for SnarlStyle {
     fn scale(&mut self, scale: f32) {
         self.node_frame.scale(scale);
         self.header_frame.scale(scale);
         self.header_drag_space.scale(scale);
         self.pin_size.scale(scale);
+        self.pin_hit_radius.scale(scale);
         self.pin_stroke.scale(scale);
         self.pin_placement.scale(scale);
+        self.pin_row_spacing.scale(scale);
+        self.node_padding.scale(scale);
         self.wire_width.scale(scale);
         self.wire_frame_size.scale(scale);
         self.wire_style.scale(scale);
         self.bg_frame.scale(scale);
         self.bg_pattern.scale(scale);
+        self.bg_style.scale(scale);
         self.bg_pattern_stroke.scale(scale);
         self.min_scale.scale(scale);
         self.max_scale.scale(scale);
         self.select_stoke.scale(scale);
         self.select_style.scale(scale);
+        self.select_rect_stroke.scale(scale);
+        self.select_rect_dash.scale(scale);
+        self.long_wire_fade.scale(scale);
+        self.hover_shadow.scale(scale);
+        self.selection_shadow.scale(scale);
+        self.preview_wire_style.scale(scale);
     }
 }
@@ -0,0 +1,243 @@
+//! Selecting nodes by dragging a rectangle or a lasso over the graph.
+
+use egui::{Id, Modifiers, Pos2, Rect, Sense, Shape, StrokeKind, Ui};
+
+use crate::NodeId;
+
+use super::{ModifierClick, SnarlConfig, SnarlStyle, state::SnarlState};
+
+/// Selection gesture released in this frame.
+pub(super) enum SelectGesture {
+    /// Rectangle selection in graph space.
+    Rect(Rect),
+
+    /// Lasso path in graph space.
+    Lasso(Vec<Pos2>),
+}
+
+/// Starts, extends and finishes rectangle and lasso selection dragged over `rect`.
+///
+/// Returns the gesture released in this frame.
+pub(super) fn process_select_input(
+    ui: &Ui,
+    rect: Rect,
+    snarl_id: Id,
+    config: &SnarlConfig,
+    modifiers: Modifiers,
+    snarl_state: &mut SnarlState,
+) -> Option<SelectGesture> {
+    let rect_select_active =
+        config.rect_select.matches(modifiers) || snarl_state.is_rect_selection();
+    let lasso_select_active = snarl_state.is_lasso_selection()
+        || config
+            .lasso_select
+            .is_some_and(|lasso| lasso.matches(modifiers));
+
+    if !rect_select_active && !lasso_select_active {
+        return None;
+    }
+
+    let mut ended = None;
+    let select_resp = ui.interact(rect, snarl_id.with("select"), Sense::drag());
+
+    if rect_select_active
+        && !snarl_state.is_lasso_selection()
+        && select_resp.dragged_by(config.rect_select.mouse_button)
+        && let Some(pos) = select_resp.interact_pointer_pos()
+    {
+        if snarl_state.is_rect_selection() {
+            snarl_state.update_rect_selection(pos);
+        } else {
+            snarl_state.start_rect_selection(pos);
+        }
+    }
+
+    if select_resp.drag_stopped_by(config.rect_select.mouse_button) {
+        if let Some(select_rect) = snarl_state.rect_selection() {
+            ended = Some(SelectGesture::Rect(select_rect));
+        }
+        snarl_state.stop_rect_selection();
+    }
+
+    if let Some(lasso) = config.lasso_select
+        && lasso_select_active
+        && !snarl_state.is_rect_selection()
+    {
+        if select_resp.dragged_by(lasso.mouse_button)
+            && let Some(pos) = select_resp.interact_pointer_pos()
+        {
+            snarl_state.extend_lasso_selection(pos);
+        }
+
+        if select_resp.drag_stopped_by(lasso.mouse_button)
+            && let Some(path) = snarl_state.take_lasso_selection()
+        {
+            ended = Some(SelectGesture::Lasso(path));
+        }
+    }
+
+    ended
+}
+
+/// Selects or deselects nodes from `node_rects` covered by released `gesture`.
+pub(super) fn finish_selection(
+    gesture: SelectGesture,
+    node_rects: Vec<(NodeId, Rect)>,
+    style: &SnarlStyle,
+    config: &SnarlConfig,
+    modifiers: Modifiers,
+    snarl_state: &mut SnarlState,
+) {
+    let (mut select_nodes, select_click): (Vec<NodeId>, ModifierClick) = match gesture {
+        SelectGesture::Rect(select_rect) => {
+            let select_nodes = node_rects
+                .into_iter()
+                .filter_map(|(id, rect)| {
+                    let select = if style.select_rect_contained() {
+                        select_rect.contains_rect(rect)
+                    } else {
+                        select_rect.intersects(rect)
+                    };
+
+                    if select { Some(id) } else { None }
+                })
+                .collect();
+
+            (select_nodes, config.rect_select)
+        }
+        SelectGesture::Lasso(path) => {
+            let Some(lasso) = config.lasso_select else {
+                return;
+            };
+
+            let select_nodes = node_rects
+                .into_iter()
+                .filter(|(_, rect)| point_in_polygon(&path, rect.center()))
+                .map(|(id, _)| id)
+                .collect();
+
+            (select_nodes, lasso)
+        }
+    };
+
+    // In single_select mode, only select one node (the last one found)
+    if config.single_select && select_nodes.len() > 1 {
+        select_nodes = select_nodes.into_iter().last().into_iter().collect();
+    }
+
+    if config.deselect_node.matches(modifiers) {
+        snarl_state.deselect_many_nodes(select_nodes.into_iter());
+    } else {
+        // In single_select mode, always reset (clear previous selection)
+        let reset = config.single_select || !select_click.matches(modifiers);
+        snarl_state.select_many_nodes(reset, select_nodes.into_iter());
+    }
+}
+
+/// Paints selection rectangle or lasso being dragged.
+pub(super) fn paint_select_gesture(ui: &Ui, style: &SnarlStyle, snarl_state: &SnarlState) {
+    if let Some(select_rect) = snarl_state.rect_selection() {
+        let fill = style.select_rect_fill(ui.style());
+        let stroke = style.select_rect_stroke(ui.style());
+
+        match style.select_rect_dash() {
+            None => {
+                ui.painter()
+                    .rect(select_rect, 0.0, fill, stroke, StrokeKind::Inside);
+            }
+            Some(dash) => {
+                ui.painter().rect_filled(select_rect, 0.0, fill);
+
+                let rect = select_rect.shrink(stroke.width * 0.5);
+                let points = [
+                    rect.left_top(),
+                    rect.right_top(),
+                    rect.right_bottom(),
+                    rect.left_bottom(),
+                    rect.left_top(),
+                ];
+                ui.painter()
+                    .extend(Shape::dashed_line(&points, stroke, dash, dash));
+            }
+        }
+    }
+
+    if let Some(path) = snarl_state.lasso_selection()
+        && path.len() > 1
+    {
+        // Lasso path may be concave or self-intersecting, so it is not filled.
+        let stroke = style.select_rect_stroke(ui.style());
+        let mut points = path.to_vec();
+        points.push(path[0]);
+
+        match style.select_rect_dash() {
+            None => {
+                ui.painter().add(Shape::line(points, stroke));
+            }
+            Some(dash) => {
+                ui.painter()
+                    .extend(Shape::dashed_line(&points, stroke, dash, dash));
+            }
+        }
+    }
+}
+
+/// Returns `true` if `point` is inside `polygon` using even-odd rule.
+///
+/// Polygon is implicitly closed and may be self-intersecting.
+fn point_in_polygon(polygon: &[Pos2], point: Pos2) -> bool {
+    let mut inside = false;
+    let Some(&(mut prev)) = polygon.last() else {
+        return false;
+    };
+
+    for &cur in polygon {
+        if (cur.y > point.y) != (prev.y > point.y) {
+            let x = cur.x + (point.y - cur.y) * (prev.x - cur.x) / (prev.y - cur.y);
+            if point.x < x {
+                inside = !inside;
+            }
+        }
+        prev = cur;
+    }
+
+    inside
+}
+
+#[test]
+fn lasso_contains_points_by_even_odd_rule() {
+    use egui::pos2;
+
+    // Concave "U" shape opened at the top.
+    let u = [
+        pos2(0.0, 0.0),
+        pos2(10.0, 0.0),
+        pos2(10.0, 30.0),
+        pos2(20.0, 30.0),
+        pos2(20.0, 0.0),
+        pos2(30.0, 0.0),
+        pos2(30.0, 40.0),
+        pos2(0.0, 40.0),
+    ];
+    assert!(point_in_polygon(&u, pos2(5.0, 20.0)));
+    assert!(point_in_polygon(&u, pos2(15.0, 35.0)));
+    assert!(!point_in_polygon(&u, pos2(15.0, 10.0)));
+    assert!(!point_in_polygon(&u, pos2(35.0, 20.0)));
+
+    // Bow tie crosses itself in the middle.
+    let bow_tie = [
+        pos2(0.0, 0.0),
+        pos2(20.0, 20.0),
+        pos2(20.0, 0.0),
+        pos2(0.0, 20.0),
+    ];
+    assert!(point_in_polygon(&bow_tie, pos2(2.0, 10.0)));
+    assert!(point_in_polygon(&bow_tie, pos2(18.0, 10.0)));
+    assert!(!point_in_polygon(&bow_tie, pos2(10.0, 2.0)));
+
+    assert!(!point_in_polygon(&[], pos2(0.0, 0.0)));
+    assert!(!point_in_polygon(
+        &[pos2(0.0, 0.0), pos2(10.0, 10.0)],
+        pos2(5.0, 5.0)
+    ));
+}
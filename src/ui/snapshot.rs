@@ -0,0 +1,203 @@
+//! Rendering of the whole [`Snarl`] graph regardless of the viewport.
+
+use std::collections::HashMap;
+
+use egui::{
+    Align2, CornerRadius, FontId, Painter, Rect, Shape, Stroke, StrokeKind, Style, Vec2,
+    emath::TSTransform, vec2,
+};
+
+use crate::{NodeId, Snarl};
+
+use super::{
+    SnarlStyle, SnarlViewer,
+    export::{export_bounding_rect, export_font_size, export_layout},
+    wire::{gradient_wire, wire_line},
+};
+
+/// Options for [`Snarl::paint_snapshot`].
+#[derive(Clone, Debug)]
+pub struct SnapshotOptions {
+    /// Style of the graph.
+    /// Colors, pin sizes, wire style and node layout are taken from here.
+    pub style: SnarlStyle,
+
+    /// egui style used to resolve values missing in [`SnapshotOptions::style`],
+    /// same as [`Ui::style`](egui::Ui::style) does when graph is shown.
+    pub egui_style: Style,
+
+    /// Padding around the graph in graph space.
+    pub padding: f32,
+
+    /// Sizes of nodes in graph space, e.g. measured with [`Snarl::measure_node`].
+    ///
+    /// Nodes don't render their bodies in exported graph,
+    /// so sizes of nodes missing here are estimated from titles and number of pins.
+    pub node_sizes: HashMap<NodeId, Vec2>,
+
+    /// Whether to fill the background of the target rect.
+    pub background: bool,
+
+    #[doc(hidden)]
+    /// Do not access other than with .., here to emulate `#[non_exhaustive(pub)]`
+    pub _non_exhaustive: (),
+}
+
+impl SnapshotOptions {
+    /// Creates new [`SnapshotOptions`] with default style.
+    #[must_use]
+    pub fn new() -> Self {
+        SnapshotOptions {
+            style: SnarlStyle::new(),
+            egui_style: Style::default(),
+            padding: 16.0,
+            node_sizes: HashMap::new(),
+            background: true,
+            _non_exhaustive: (),
+        }
+    }
+}
+
+impl Default for SnapshotOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Snarl<T> {
+    /// Paints the whole graph into `target` rect using `painter`.
+    ///
+    /// Graph is framed same as [`Snarl::nodes_bounding_rect`]
+    /// with [`SnapshotOptions::node_sizes`] and scaled to fit `target`,
+    /// so nodes don't need to be on screen or shown at all.
+    /// Paint into offscreen layer and capture it with egui's screenshot support
+    /// or tessellate shapes yourself to render thumbnails of saved graphs.
+    ///
+    /// Same as [`Snarl::to_svg`], only titles, pins and wires are rendered.
+    pub fn paint_snapshot<V>(
+        &self,
+        viewer: &mut V,
+        painter: &Painter,
+        target: Rect,
+        opts: &SnapshotOptions,
+    ) where
+        V: SnarlViewer<T>,
+    {
+        let shapes = self.snapshot_shapes(viewer, painter, target, opts);
+        painter.with_clip_rect(target).extend(shapes);
+    }
+
+    /// Returns shapes of the whole graph scaled to fit `target` rect.
+    ///
+    /// See [`Snarl::paint_snapshot`].
+    /// `painter` is only used to lay out the text.
+    #[must_use]
+    pub fn snapshot_shapes<V>(
+        &self,
+        viewer: &mut V,
+        painter: &Painter,
+        target: Rect,
+        opts: &SnapshotOptions,
+    ) -> Vec<Shape>
+    where
+        V: SnarlViewer<T>,
+    {
+        let style = &opts.style;
+        let egui_style = &opts.egui_style;
+
+        let mut shapes = Vec::new();
+
+        if opts.background {
+            shapes.push(Shape::rect_filled(
+                target,
+                CornerRadius::ZERO,
+                style.bg_frame(egui_style).fill,
+            ));
+        }
+
+        let (nodes, wires) = export_layout(self, viewer, style, egui_style, &opts.node_sizes);
+
+        let bb = export_bounding_rect(&nodes);
+        if !bb.is_positive() || !target.is_positive() {
+            return shapes;
+        }
+        let bb = bb.expand(opts.padding);
+
+        let scale = f32::min(target.width() / bb.width(), target.height() / bb.height());
+        let to_target = TSTransform::new(target.center().to_vec2(), scale)
+            * TSTransform::from_translation(-bb.center().to_vec2());
+
+        let node_frame = style.node_frame(egui_style);
+        let header_frame = style.header_frame(egui_style);
+        let pin_size = style.pin_size(egui_style) * scale;
+        let pin_stroke = style.pin_stroke(egui_style);
+        let wire_width = style.wire_width(egui_style) * scale;
+        let text_color = egui_style.visuals.text_color();
+        let font = FontId::proportional(export_font_size(egui_style) * scale);
+        let rounding = node_frame.corner_radius * scale;
+        let text_offset = egui_style.spacing.item_spacing.x * 2.0 * scale;
+
+        for wire in &wires {
+            let line = wire_line(
+                style.wire_frame_size(egui_style),
+                style.upscale_wire_frame(),
+                style.downscale_wire_frame(),
+                style.bezier_tension(),
+                wire.from,
+                wire.to,
+                style.wire_smoothness(),
+                style.wire_style(),
+                wire.vertical,
+            );
+            let mut shape = Shape::line(
+                line.into_iter().map(|p| to_target * p).collect(),
+                Stroke::new(wire_width, wire.color),
+            );
+            if let Some((from_color, to_color)) = wire.gradient {
+                gradient_wire(
+                    &mut shape,
+                    to_target * wire.from,
+                    to_target * wire.to,
+                    wire.color,
+                    from_color,
+                    to_color,
+                );
+            }
+            shapes.push(shape);
+        }
+
+        for (_, node) in &nodes {
+            let rect = to_target * node.rect;
+            let header = to_target * node.header;
+
+            shapes.push(Shape::rect_filled(rect, rounding, node_frame.fill));
+            shapes.push(Shape::rect_filled(header, rounding, header_frame.fill));
+            shapes.push(Shape::rect_stroke(
+                rect,
+                rounding,
+                node_frame.stroke,
+                StrokeKind::Inside,
+            ));
+            let galley = painter.layout_no_wrap(node.title.clone(), font.clone(), text_color);
+            let text_rect = Align2::LEFT_CENTER
+                .anchor_size(header.left_center() + vec2(text_offset, 0.0), galley.size());
+            shapes.push(Shape::galley(text_rect.min, galley, text_color));
+
+            for pin in node.inputs.iter().chain(&node.outputs) {
+                shapes.push(Shape::circle_filled(
+                    to_target * pin.pos,
+                    pin_size * 0.5,
+                    pin.fill,
+                ));
+                shapes.push(Shape::circle_stroke(
+                    to_target * pin.pos,
+                    pin_size * 0.5,
+                    pin_stroke,
+                ));
+            }
+        }
+
+        shapes
+    }
+}
@@ -6,9 +6,12 @@ use egui::{
 };
 use smallvec::{SmallVec, ToSmallVec, smallvec};
 
-use crate::{InPinId, NodeId, OutPinId, Snarl};
+use crate::{AnyPinId, InPinId, NodeId, OutPinId, Snarl};
 
-use super::{SnarlWidget, transform_matching_points};
+use super::{
+    FitOptions, SnarlViewer, SnarlWidget, look_at_transform, transform_matching_points,
+    viewport::ViewportAnimation,
+};
 
 pub type RowHeights = SmallVec<[f32; 8]>;
 
@@ -70,6 +73,14 @@ impl NodeState {
         cx.data_mut(|d| d.remove::<Self>(self.id));
     }
 
+    /// Stores initial state under `id` if there is none,
+    /// so following [`NodeState::load`] doesn't discard the frame.
+    pub fn seed(cx: &Context, id: Id, spacing: &Spacing) {
+        if cx.data(|d| d.get_temp::<NodeData>(id)).is_none() {
+            Self::initial(id, spacing).store(cx);
+        }
+    }
+
     pub fn store(self, cx: &Context) {
         if self.dirty {
             cx.data_mut(|d| {
@@ -201,12 +212,23 @@ pub enum NewWires {
     Out(SmallVec<[OutPinId; 4]>),
 }
 
+impl NewWires {
+    /// Returns first pin the wires are dragged from.
+    pub fn first_pin(&self) -> Option<AnyPinId> {
+        match self {
+            NewWires::In(x) => x.first().copied().map(AnyPinId::In),
+            NewWires::Out(x) => x.first().copied().map(AnyPinId::Out),
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 struct RectSelect {
     origin: Pos2,
     current: Pos2,
 }
 
+#[allow(clippy::struct_excessive_bools)]
 pub struct SnarlState {
     /// Snarl viewport transform to global space.
     to_global: TSTransform,
@@ -216,13 +238,32 @@ pub struct SnarlState {
     /// Flag indicating that new wires are owned by the menu now.
     new_wires_menu: bool,
 
+    /// Flag indicating that new wires were pulled off a pin by click
+    /// and are finished by the next click instead of drag release.
+    new_wires_pulled: bool,
+
+    /// Position along the rail pin where new wires were started.
+    new_wires_rail: Option<f32>,
+
+    /// Screen position and time of the wire drop
+    /// while waiting for the pointer to settle before opening the wire-drop menu.
+    new_wires_menu_pending: Option<(Pos2, f64)>,
+
     id: Id,
 
     /// Flag indicating that the graph state is dirty must be saved.
     dirty: bool,
 
+    /// Flag indicating that the graph is animating or interacted with
+    /// and must be repainted.
+    repaint: bool,
+
     /// Active rect selection.
     rect_selection: Option<RectSelect>,
+    lasso_selection: Option<Vec<Pos2>>,
+
+    /// Active wire cut line, from start to current point.
+    cut_line: Option<(Pos2, Pos2)>,
 
     /// Order of nodes to draw.
     draw_order: Vec<NodeId>,
@@ -230,8 +271,31 @@ pub struct SnarlState {
     /// List of currently selected nodes.
     selected_nodes: SmallVec<[NodeId; 8]>,
 
+    /// List of currently selected wires.
+    selected_wires: SmallVec<[(OutPinId, InPinId); 4]>,
+
     /// The center of the UI rect, used to track container movement.
     ui_rect_center: Pos2,
+
+    /// Node title being edited in place.
+    title_edit: Option<TitleEdit>,
+
+    /// Active animated transition of the viewport.
+    animation: Option<ViewportAnimation>,
+
+    /// Velocity of the view pan in screen points per second.
+    /// Tracked while panning and used to glide after release.
+    pan_velocity: Option<Vec2>,
+
+    /// Collapsed node under the pointer and time the hover started.
+    hover_expand: Option<(NodeId, f64)>,
+}
+
+/// State of in place node title editing.
+#[derive(Clone)]
+struct TitleEdit {
+    node: NodeId,
+    text: String,
 }
 
 #[derive(Clone, Default)]
@@ -256,6 +320,25 @@ impl DrawOrder {
 #[derive(Clone, Default)]
 struct SelectedNodes(SmallVec<[NodeId; 8]>);
 
+#[derive(Clone, Default)]
+struct SelectedWires(SmallVec<[(OutPinId, InPinId); 4]>);
+
+impl SelectedWires {
+    fn save(self, cx: &Context, id: Id) {
+        cx.data_mut(|d| {
+            if self.0.is_empty() {
+                d.remove_temp::<Self>(id);
+            } else {
+                d.insert_temp::<Self>(id, self);
+            }
+        });
+    }
+
+    fn load(cx: &Context, id: Id) -> Self {
+        cx.data(|d| d.get_temp::<Self>(id)).unwrap_or_default()
+    }
+}
+
 impl SelectedNodes {
     fn save(self, cx: &Context, id: Id) {
         cx.data_mut(|d| {
@@ -273,15 +356,137 @@ impl SelectedNodes {
     }
 }
 
+/// Change of the viewport requested outside of the graph UI.
+///
+/// It is applied next time the graph is shown,
+/// when node rects and viewport are known.
+#[derive(Clone, Copy, Debug)]
+pub enum ViewportRequest {
+    /// Pan the view minimally to make the node visible.
+    EnsureNodeVisible { node: NodeId, margin: f32 },
+
+    /// Pan and zoom the view to fit all nodes.
+    FitToView { opts: FitOptions },
+
+    /// Pan the view to center the node.
+    FocusNode { node: NodeId },
+
+    /// Reset the view to graph origin at the top-left corner and scale of 1.0.
+    Reset,
+}
+
+impl ViewportRequest {
+    fn save(self, cx: &Context, id: Id) {
+        cx.data_mut(|d| d.insert_temp::<Self>(id, self));
+    }
+
+    pub fn take(cx: &Context, id: Id) -> Option<Self> {
+        cx.data_mut(|d| {
+            let request = d.get_temp::<Self>(id);
+            d.remove::<Self>(id);
+            request
+        })
+    }
+}
+
+/// Wire being dragged from a pin.
+///
+/// Returned by [`SnarlWidget::in_progress_wire`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct InProgressWire {
+    /// Pins the wire is dragged from.
+    /// Usually single pin, but more can be added with modifiers
+    /// or when existing wires are detached from a pin together.
+    pub pins: Vec<AnyPinId>,
+
+    /// Position of the dragged end of the wire in graph space.
+    pub pos: Pos2,
+}
+
+impl InProgressWire {
+    pub(crate) fn new(new_wires: &NewWires, pos: Pos2) -> Self {
+        let pins = match new_wires {
+            NewWires::In(pins) => pins.iter().copied().map(AnyPinId::In).collect(),
+            NewWires::Out(pins) => pins.iter().copied().map(AnyPinId::Out).collect(),
+        };
+        InProgressWire { pins, pos }
+    }
+
+    /// Returns the first pin the wire is dragged from.
+    #[must_use]
+    pub fn source(&self) -> Option<AnyPinId> {
+        self.pins.first().copied()
+    }
+
+    pub(crate) fn save(wire: Option<Self>, cx: &Context, id: Id) {
+        let id = id.with("in-progress-wire");
+        cx.data_mut(|d| match wire {
+            None => d.remove::<Self>(id),
+            Some(wire) => d.insert_temp(id, wire),
+        });
+    }
+
+    fn load(cx: &Context, id: Id) -> Option<Self> {
+        cx.data(|d| d.get_temp::<Self>(id.with("in-progress-wire")))
+    }
+}
+
+/// Current interaction of the user with the graph.
+///
+/// Returned by [`SnarlWidget::interaction`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Interaction {
+    /// No interaction in progress.
+    #[default]
+    Idle,
+
+    /// Canvas is dragged to pan the view.
+    PanningCanvas,
+
+    /// Node is dragged, possibly with other selected nodes.
+    DraggingNode(NodeId),
+
+    /// Wire is dragged from a pin or waits for a click to connect.
+    DraggingWire,
+
+    /// Nodes are selected with a rectangle.
+    RubberBandSelect,
+
+    /// Nodes are selected with a lasso.
+    LassoSelect,
+
+    /// Line is drawn to cut wires it crosses.
+    CuttingWires,
+}
+
+impl Interaction {
+    pub(crate) fn save(self, cx: &Context, id: Id) {
+        cx.data_mut(|d| d.insert_temp(id.with("interaction"), self));
+    }
+
+    fn load(cx: &Context, id: Id) -> Self {
+        cx.data(|d| d.get_temp(id.with("interaction")).unwrap_or_default())
+    }
+}
+
 #[derive(Clone)]
 struct SnarlStateData {
     to_global: TSTransform,
     new_wires: Option<NewWires>,
     new_wires_menu: bool,
+    new_wires_pulled: bool,
+    new_wires_rail: Option<f32>,
+    new_wires_menu_pending: Option<(Pos2, f64)>,
     rect_selection: Option<RectSelect>,
+    lasso_selection: Option<Vec<Pos2>>,
+    cut_line: Option<(Pos2, Pos2)>,
     /// The center of the UI rect when the transform was last stored.
     /// Used to adjust the transform when the UI rect moves (e.g., window dragged).
     ui_rect_center: Pos2,
+    title_edit: Option<TitleEdit>,
+    animation: Option<ViewportAnimation>,
+    pan_velocity: Option<Vec2>,
+    hover_expand: Option<(NodeId, f64)>,
 }
 
 impl SnarlStateData {
@@ -302,6 +507,16 @@ fn prune_selected_nodes<T>(selected_nodes: &mut SmallVec<[NodeId; 8]>, snarl: &S
     old_size != selected_nodes.len()
 }
 
+fn prune_selected_wires<T>(
+    selected_wires: &mut SmallVec<[(OutPinId, InPinId); 4]>,
+    snarl: &Snarl<T>,
+) -> bool {
+    let old_size = selected_wires.len();
+    selected_wires
+        .retain(|&mut (out_pin, in_pin)| snarl.wires.contains(&crate::Wire { out_pin, in_pin }));
+    old_size != selected_wires.len()
+}
+
 impl SnarlState {
     pub fn load<T>(
         cx: &Context,
@@ -319,15 +534,22 @@ impl SnarlState {
         let mut selected_nodes = SelectedNodes::load(cx, id).0;
         let mut dirty = prune_selected_nodes(&mut selected_nodes, snarl);
 
+        let mut selected_wires = SelectedWires::load(cx, id).0;
+        dirty |= prune_selected_wires(&mut selected_wires, snarl);
+
         let draw_order = DrawOrder::load(cx, id).0;
 
         // Adjust transform if the UI rect center has moved (e.g., window was dragged).
         // This ensures nodes follow the container when it moves.
         let ui_rect_center = ui_rect.center();
         let mut to_global = data.to_global;
+        let mut animation = data.animation;
         let center_delta = ui_rect_center - data.ui_rect_center;
         if center_delta != Vec2::ZERO {
             to_global.translation += center_delta;
+            if let Some(animation) = &mut animation {
+                animation.translate(center_delta);
+            }
             dirty = true;
         }
 
@@ -335,12 +557,23 @@ impl SnarlState {
             to_global,
             new_wires: data.new_wires,
             new_wires_menu: data.new_wires_menu,
+            new_wires_pulled: data.new_wires_pulled,
+            new_wires_rail: data.new_wires_rail,
+            new_wires_menu_pending: data.new_wires_menu_pending,
             id,
             dirty,
+            repaint: false,
             rect_selection: data.rect_selection,
+            lasso_selection: data.lasso_selection,
+            cut_line: data.cut_line,
             draw_order,
             selected_nodes,
+            selected_wires,
             ui_rect_center,
+            title_edit: data.title_edit,
+            animation,
+            pan_velocity: data.pan_velocity,
+            hover_expand: data.hover_expand,
         }
     }
 
@@ -369,34 +602,73 @@ impl SnarlState {
             to_global,
             new_wires: None,
             new_wires_menu: false,
+            new_wires_pulled: false,
+            new_wires_rail: None,
+            new_wires_menu_pending: None,
             id,
             dirty: true,
-            draw_order: Vec::new(),
+            repaint: false,
+            // Restore z-order stored with the graph.
+            draw_order: snarl.draw_order().to_vec(),
             rect_selection: None,
+            lasso_selection: None,
+            cut_line: None,
             ui_rect_center,
             selected_nodes: SmallVec::new(),
+            selected_wires: SmallVec::new(),
+            title_edit: None,
+            animation: None,
+            pan_velocity: None,
+            hover_expand: None,
         }
     }
 
+    /// Creates state that is not loaded from nor stored into the context,
+    /// for drawing nodes outside of the graph.
+    pub fn detached<T>(id: Id, snarl: &Snarl<T>) -> Self {
+        Self::initial(id, snarl, Rect::NOTHING, 1.0, 1.0)
+    }
+
     #[inline(always)]
     pub fn store<T>(mut self, snarl: &Snarl<T>, cx: &Context) {
         self.dirty |= prune_selected_nodes(&mut self.selected_nodes, snarl);
+        self.dirty |= prune_selected_wires(&mut self.selected_wires, snarl);
 
         if self.dirty {
             let data = SnarlStateData {
                 to_global: self.to_global,
                 new_wires: self.new_wires,
                 new_wires_menu: self.new_wires_menu,
+                new_wires_pulled: self.new_wires_pulled,
+                new_wires_rail: self.new_wires_rail,
+                new_wires_menu_pending: self.new_wires_menu_pending,
                 rect_selection: self.rect_selection,
+                lasso_selection: self.lasso_selection,
+                cut_line: self.cut_line,
                 ui_rect_center: self.ui_rect_center,
+                title_edit: self.title_edit,
+                animation: self.animation,
+                pan_velocity: self.pan_velocity,
+                hover_expand: self.hover_expand,
             };
             data.save(cx, self.id);
 
             DrawOrder(self.draw_order).save(cx, self.id);
             SelectedNodes(self.selected_nodes).save(cx, self.id);
+            SelectedWires(self.selected_wires).save(cx, self.id);
+
+            self.repaint = true;
+        }
 
+        if self.repaint {
             cx.request_repaint();
         }
+        cx.data_mut(|d| d.insert_temp(self.id.with("needs-repaint"), self.repaint));
+    }
+
+    /// Requests repaint of the graph.
+    pub fn request_repaint(&mut self) {
+        self.repaint = true;
     }
 
     pub fn to_global(&self) -> TSTransform {
@@ -411,10 +683,7 @@ impl SnarlState {
     }
 
     pub fn look_at(&mut self, view: Rect, ui_rect: Rect, min_scale: f32, max_scale: f32) {
-        let scaling2 = ui_rect.size() / view.size();
-        let scaling = scaling2.min_elem().clamp(min_scale, max_scale);
-
-        let to_global = transform_matching_points(view.center(), ui_rect.center(), scaling);
+        let to_global = look_at_transform(view, ui_rect, min_scale, max_scale);
 
         if self.to_global != to_global {
             self.to_global = to_global;
@@ -422,30 +691,205 @@ impl SnarlState {
         }
     }
 
+    /// Moves the view to `to_global` transform.
+    ///
+    /// With `duration` in seconds the transition is animated starting at `time`,
+    /// otherwise the view jumps immediately.
+    pub fn animate_to(&mut self, to_global: TSTransform, duration: Option<f32>, time: f64) {
+        match duration {
+            Some(duration) if self.to_global != to_global => {
+                self.stop_pan_glide();
+                self.animation = Some(ViewportAnimation::new(
+                    self.to_global,
+                    to_global,
+                    time,
+                    duration,
+                ));
+                self.dirty = true;
+            }
+            _ => {
+                self.stop_animation();
+                self.stop_pan_glide();
+                self.set_to_global(to_global);
+            }
+        }
+    }
+
+    /// Advances active viewport animation to `time`.
+    ///
+    /// Returns `true` if animation is still running.
+    pub fn step_animation(&mut self, time: f64) -> bool {
+        let Some(animation) = self.animation else {
+            return false;
+        };
+
+        let (to_global, finished) = animation.sample(time);
+        self.set_to_global(to_global);
+
+        if finished {
+            self.animation = None;
+            self.dirty = true;
+        }
+        !finished
+    }
+
+    /// Cancels active viewport animation, keeping current view.
+    pub fn stop_animation(&mut self) {
+        if self.animation.take().is_some() {
+            self.dirty = true;
+        }
+    }
+
+    /// Updates pan velocity with view moved by `delta` during `dt` seconds of panning.
+    pub fn track_pan(&mut self, delta: Vec2, dt: f32) {
+        if dt <= 0.0 {
+            return;
+        }
+
+        // Smooth out jitter of the pointer movement.
+        let velocity = delta / dt;
+        let velocity = match self.pan_velocity {
+            None => velocity,
+            Some(prev) => (prev + velocity) * 0.5,
+        };
+
+        self.pan_velocity = Some(velocity);
+        self.dirty = true;
+    }
+
+    /// Decelerates pan velocity with `friction` over `dt` seconds.
+    ///
+    /// Returns translation of the view for this frame
+    /// or `None` if the view does not glide.
+    pub fn step_pan_glide(&mut self, friction: f32, dt: f32) -> Option<Vec2> {
+        const MIN_VELOCITY: f32 = 5.0;
+
+        let velocity = self.pan_velocity?;
+        let velocity = velocity * (-friction * dt).exp();
+
+        self.dirty = true;
+        if velocity.length_sq() < MIN_VELOCITY * MIN_VELOCITY {
+            self.pan_velocity = None;
+            return None;
+        }
+
+        self.pan_velocity = Some(velocity);
+        Some(velocity * dt)
+    }
+
+    /// Stops tracking pan velocity, cancelling the glide.
+    pub fn stop_pan_glide(&mut self) {
+        if self.pan_velocity.take().is_some() {
+            self.dirty = true;
+        }
+    }
+
+    /// Tracks collapsed `node` under the pointer at `time`.
+    ///
+    /// Returns seconds the node has been hovered for.
+    pub fn track_hover_expand(&mut self, node: Option<NodeId>, time: f64) -> f64 {
+        match (self.hover_expand, node) {
+            (Some((prev, start)), Some(node)) if prev == node => time - start,
+            (_, Some(node)) => {
+                self.hover_expand = Some((node, time));
+                self.dirty = true;
+                0.0
+            }
+            (Some(_), None) => {
+                self.hover_expand = None;
+                self.dirty = true;
+                0.0
+            }
+            (None, None) => 0.0,
+        }
+    }
+
+    /// Starts editing title of the node with initial `text`.
+    pub fn start_title_edit(&mut self, node: NodeId, text: String) {
+        self.title_edit = Some(TitleEdit { node, text });
+        self.dirty = true;
+    }
+
+    /// Returns text of the title being edited if it belongs to the `node`.
+    ///
+    /// Marks state dirty as text may be modified.
+    pub fn title_edit_mut(&mut self, node: NodeId) -> Option<&mut String> {
+        match &mut self.title_edit {
+            Some(edit) if edit.node == node => {
+                self.dirty = true;
+                Some(&mut edit.text)
+            }
+            _ => None,
+        }
+    }
+
+    /// Stops editing title and returns edited text.
+    pub fn take_title_edit(&mut self) -> Option<String> {
+        let edit = self.title_edit.take()?;
+        self.dirty = true;
+        Some(edit.text)
+    }
+
     pub fn start_new_wire_in(&mut self, pin: InPinId) {
         self.new_wires = Some(NewWires::In(smallvec![pin]));
         self.new_wires_menu = false;
+        self.new_wires_pulled = false;
+        self.new_wires_rail = None;
         self.dirty = true;
     }
 
     pub fn start_new_wire_out(&mut self, pin: OutPinId) {
         self.new_wires = Some(NewWires::Out(smallvec![pin]));
         self.new_wires_menu = false;
+        self.new_wires_pulled = false;
+        self.new_wires_rail = None;
         self.dirty = true;
     }
 
     pub fn start_new_wires_in(&mut self, pins: &[InPinId]) {
         self.new_wires = Some(NewWires::In(pins.to_smallvec()));
         self.new_wires_menu = false;
+        self.new_wires_pulled = false;
+        self.new_wires_rail = None;
         self.dirty = true;
     }
 
     pub fn start_new_wires_out(&mut self, pins: &[OutPinId]) {
         self.new_wires = Some(NewWires::Out(pins.to_smallvec()));
         self.new_wires_menu = false;
+        self.new_wires_pulled = false;
+        self.new_wires_rail = None;
         self.dirty = true;
     }
 
+    /// Starts dragging wires from `pins` that were pulled off an input pin by click.
+    pub fn pull_new_wires_out(&mut self, pins: &[OutPinId]) {
+        self.start_new_wires_out(pins);
+        self.new_wires_pulled = true;
+    }
+
+    /// Starts wire from `pin` armed by click, to be finished by next click.
+    pub fn arm_new_wire_out(&mut self, pin: OutPinId) {
+        self.start_new_wire_out(pin);
+        self.new_wires_pulled = true;
+    }
+
+    /// Sets position along the rail pin where new wires were started.
+    pub fn set_new_wires_rail(&mut self, t: f32) {
+        self.new_wires_rail = Some(t);
+        self.dirty = true;
+    }
+
+    /// Returns position along the rail pin where new wires were started.
+    pub const fn new_wires_rail(&self) -> Option<f32> {
+        self.new_wires_rail
+    }
+
+    /// Returns `true` if new wires were pulled off a pin by click.
+    pub const fn has_pulled_new_wires(&self) -> bool {
+        self.new_wires_pulled && self.has_new_wires()
+    }
+
     pub fn add_new_wire_in(&mut self, pin: InPinId) {
         debug_assert!(!self.new_wires_menu);
         let Some(NewWires::In(pins)) = &mut self.new_wires else {
@@ -548,6 +992,28 @@ impl SnarlState {
         self.new_wires_menu = true;
     }
 
+    /// Passes new wires to the wire-drop menu that opens once pointer settles.
+    pub(crate) fn set_new_wires_menu_pending(&mut self, wires: NewWires, pos: Pos2, time: f64) {
+        self.set_new_wires_menu(wires);
+        self.new_wires_menu_pending = Some((pos, time));
+        self.dirty = true;
+    }
+
+    pub(crate) const fn new_wires_menu_pending(&self) -> Option<(Pos2, f64)> {
+        self.new_wires_menu_pending
+    }
+
+    /// Stops waiting for the pointer to settle.
+    /// If `cancel` is true, new wires are dropped, otherwise the menu opens.
+    pub(crate) fn finish_new_wires_menu_pending(&mut self, cancel: bool) {
+        self.new_wires_menu_pending = None;
+        if cancel {
+            self.new_wires = None;
+            self.new_wires_menu = false;
+        }
+        self.dirty = true;
+    }
+
     pub(crate) fn update_draw_order<T>(&mut self, snarl: &Snarl<T>) -> Vec<NodeId> {
         let mut node_ids = snarl
             .nodes
@@ -570,6 +1036,10 @@ impl SnarlState {
         self.draw_order.clone()
     }
 
+    pub(crate) fn draw_order(&self) -> &[NodeId] {
+        &self.draw_order
+    }
+
     pub(crate) fn node_to_top(&mut self, node: NodeId) {
         if let Some(order) = self.draw_order.iter().position(|idx| *idx == node) {
             self.draw_order.remove(order);
@@ -630,6 +1100,33 @@ impl SnarlState {
         self.selected_nodes.clear();
     }
 
+    pub fn selected_wires(&self) -> &[(OutPinId, InPinId)] {
+        &self.selected_wires
+    }
+
+    pub fn is_wire_selected(&self, out_pin: OutPinId, in_pin: InPinId) -> bool {
+        self.selected_wires.contains(&(out_pin, in_pin))
+    }
+
+    /// Adds wire to selection or removes it if already selected.
+    pub fn toggle_wire_selection(&mut self, out_pin: OutPinId, in_pin: InPinId) {
+        if let Some(pos) = self
+            .selected_wires
+            .iter()
+            .position(|&w| w == (out_pin, in_pin))
+        {
+            self.selected_wires.remove(pos);
+        } else {
+            self.selected_wires.push((out_pin, in_pin));
+        }
+        self.dirty = true;
+    }
+
+    pub fn deselect_all_wires(&mut self) {
+        self.dirty |= !self.selected_wires.is_empty();
+        self.selected_wires.clear();
+    }
+
     pub fn start_rect_selection(&mut self, pos: Pos2) {
         self.dirty |= self.rect_selection.is_none();
         self.rect_selection = Some(RectSelect {
@@ -658,6 +1155,50 @@ impl SnarlState {
         let rect = self.rect_selection?;
         Some(Rect::from_two_pos(rect.origin, rect.current))
     }
+
+    /// Appends point to the lasso path, starting new lasso if none.
+    pub fn extend_lasso_selection(&mut self, pos: Pos2) {
+        let path = self.lasso_selection.get_or_insert_with(Vec::new);
+        if path.last().is_none_or(|last| last.distance_sq(pos) >= 1.0) {
+            path.push(pos);
+            self.dirty = true;
+        }
+    }
+
+    pub fn take_lasso_selection(&mut self) -> Option<Vec<Pos2>> {
+        self.dirty |= self.lasso_selection.is_some();
+        self.lasso_selection.take()
+    }
+
+    pub const fn is_lasso_selection(&self) -> bool {
+        self.lasso_selection.is_some()
+    }
+
+    pub fn lasso_selection(&self) -> Option<&[Pos2]> {
+        self.lasso_selection.as_deref()
+    }
+
+    /// Moves end of the wire cut line, starting new line if none.
+    pub fn extend_cut_line(&mut self, pos: Pos2) {
+        match &mut self.cut_line {
+            None => self.cut_line = Some((pos, pos)),
+            Some((_, end)) => *end = pos,
+        }
+        self.dirty = true;
+    }
+
+    pub fn take_cut_line(&mut self) -> Option<(Pos2, Pos2)> {
+        self.dirty |= self.cut_line.is_some();
+        self.cut_line.take()
+    }
+
+    pub const fn is_cut_line(&self) -> bool {
+        self.cut_line.is_some()
+    }
+
+    pub const fn cut_line(&self) -> Option<(Pos2, Pos2)> {
+        self.cut_line
+    }
 }
 
 impl SnarlWidget {
@@ -681,6 +1222,440 @@ impl SnarlWidget {
         ctx.data(|d| d.get_temp::<SelectedNodes>(snarl_id).unwrap_or_default().0)
             .into_vec()
     }
+
+    /// Adds nodes to the selection of the `SnarlWidget` with same id.
+    ///
+    /// Use same `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn select_nodes(self, ui: &Ui, nodes: impl IntoIterator<Item = NodeId>) {
+        self.select_nodes_at(ui.id(), ui.ctx(), nodes);
+    }
+
+    /// Adds nodes to the selection of the `SnarlWidget` with same id.
+    ///
+    /// `ui_id` must be the Id of the `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn select_nodes_at(
+        self,
+        ui_id: Id,
+        ctx: &Context,
+        nodes: impl IntoIterator<Item = NodeId>,
+    ) {
+        self.update_selection_at(ui_id, ctx, |selected| {
+            for node in nodes {
+                if !selected.contains(&node) {
+                    selected.push(node);
+                }
+            }
+        });
+    }
+
+    /// Removes nodes from the selection of the `SnarlWidget` with same id.
+    ///
+    /// Use same `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn deselect_nodes(self, ui: &Ui, nodes: impl IntoIterator<Item = NodeId>) {
+        self.deselect_nodes_at(ui.id(), ui.ctx(), nodes);
+    }
+
+    /// Removes nodes from the selection of the `SnarlWidget` with same id.
+    ///
+    /// `ui_id` must be the Id of the `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn deselect_nodes_at(
+        self,
+        ui_id: Id,
+        ctx: &Context,
+        nodes: impl IntoIterator<Item = NodeId>,
+    ) {
+        self.update_selection_at(ui_id, ctx, |selected| {
+            for node in nodes {
+                selected.retain(|n| *n != node);
+            }
+        });
+    }
+
+    /// Deselects all nodes of the `SnarlWidget` with same id.
+    ///
+    /// Use same `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn clear_selection(self, ui: &Ui) {
+        self.clear_selection_at(ui.id(), ui.ctx());
+    }
+
+    /// Deselects all nodes of the `SnarlWidget` with same id.
+    ///
+    /// `ui_id` must be the Id of the `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn clear_selection_at(self, ui_id: Id, ctx: &Context) {
+        self.update_selection_at(ui_id, ctx, SmallVec::clear);
+    }
+
+    /// Replaces selection of the `SnarlWidget` with same id.
+    ///
+    /// Use same `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn set_selection(self, ui: &Ui, nodes: impl IntoIterator<Item = NodeId>) {
+        self.set_selection_at(ui.id(), ui.ctx(), nodes);
+    }
+
+    /// Replaces selection of the `SnarlWidget` with same id.
+    ///
+    /// `ui_id` must be the Id of the `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn set_selection_at(
+        self,
+        ui_id: Id,
+        ctx: &Context,
+        nodes: impl IntoIterator<Item = NodeId>,
+    ) {
+        self.update_selection_at(ui_id, ctx, |selected| {
+            selected.clear();
+            for node in nodes {
+                if !selected.contains(&node) {
+                    selected.push(node);
+                }
+            }
+        });
+    }
+
+    fn update_selection_at(
+        self,
+        ui_id: Id,
+        ctx: &Context,
+        f: impl FnOnce(&mut SmallVec<[NodeId; 8]>),
+    ) {
+        let snarl_id = self.get_id(ui_id);
+
+        let mut selected = SelectedNodes::load(ctx, snarl_id);
+        let old = selected.0.clone();
+        f(&mut selected.0);
+
+        if selected.0 != old {
+            selected.save(ctx, snarl_id);
+            ctx.request_repaint();
+        }
+    }
+
+    /// Returns list of wires selected in the UI for the `SnarlWidget` with same id.
+    ///
+    /// Use same `Ui` instance that was used in [`SnarlWidget::show`].
+    #[must_use]
+    #[inline]
+    pub fn selected_wires(self, ui: &Ui) -> Vec<(OutPinId, InPinId)> {
+        self.selected_wires_at(ui.id(), ui.ctx())
+    }
+
+    /// Returns list of wires selected in the UI for the `SnarlWidget` with same id.
+    ///
+    /// `ui_id` must be the Id of the `Ui` instance that was used in [`SnarlWidget::show`].
+    #[must_use]
+    #[inline]
+    pub fn selected_wires_at(self, ui_id: Id, ctx: &Context) -> Vec<(OutPinId, InPinId)> {
+        let snarl_id = self.get_id(ui_id);
+
+        SelectedWires::load(ctx, snarl_id).0.into_vec()
+    }
+
+    /// Adds wires to the selection of the `SnarlWidget` with same id.
+    ///
+    /// Use same `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn select_wires(self, ui: &Ui, wires: impl IntoIterator<Item = (OutPinId, InPinId)>) {
+        self.select_wires_at(ui.id(), ui.ctx(), wires);
+    }
+
+    /// Adds wires to the selection of the `SnarlWidget` with same id.
+    ///
+    /// `ui_id` must be the Id of the `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn select_wires_at(
+        self,
+        ui_id: Id,
+        ctx: &Context,
+        wires: impl IntoIterator<Item = (OutPinId, InPinId)>,
+    ) {
+        let snarl_id = self.get_id(ui_id);
+
+        let mut selected = SelectedWires::load(ctx, snarl_id);
+        let old_len = selected.0.len();
+        for wire in wires {
+            if !selected.0.contains(&wire) {
+                selected.0.push(wire);
+            }
+        }
+
+        if selected.0.len() != old_len {
+            selected.save(ctx, snarl_id);
+            ctx.request_repaint();
+        }
+    }
+
+    /// Clears wire selection of the `SnarlWidget` with same id.
+    ///
+    /// Use same `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn deselect_all_wires(self, ui: &Ui) {
+        self.deselect_all_wires_at(ui.id(), ui.ctx());
+    }
+
+    /// Clears wire selection of the `SnarlWidget` with same id.
+    ///
+    /// `ui_id` must be the Id of the `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn deselect_all_wires_at(self, ui_id: Id, ctx: &Context) {
+        let snarl_id = self.get_id(ui_id);
+
+        SelectedWires::default().save(ctx, snarl_id);
+        ctx.request_repaint();
+    }
+
+    /// Returns wire being dragged from a pin in the `SnarlWidget` with same id.
+    ///
+    /// Reflects the state from the last time the widget was shown.
+    /// Returns `None` when no wire is dragged.
+    ///
+    /// Use same `Ui` instance that was used in [`SnarlWidget::show`].
+    #[must_use]
+    #[inline]
+    pub fn in_progress_wire(self, ui: &Ui) -> Option<InProgressWire> {
+        self.in_progress_wire_at(ui.id(), ui.ctx())
+    }
+
+    /// Returns wire being dragged from a pin in the `SnarlWidget` with same id.
+    ///
+    /// See [`SnarlWidget::in_progress_wire`].
+    ///
+    /// `ui_id` must be the Id of the `Ui` instance that was used in [`SnarlWidget::show`].
+    #[must_use]
+    #[inline]
+    pub fn in_progress_wire_at(self, ui_id: Id, ctx: &Context) -> Option<InProgressWire> {
+        InProgressWire::load(ctx, self.get_id(ui_id))
+    }
+
+    /// Starts dragging a wire from `pin` in the `SnarlWidget` with same id,
+    /// as if user grabbed the pin.
+    ///
+    /// The wire follows the pointer and is dropped with the next click,
+    /// same as a wire armed by clicking a pin,
+    /// so scripted pointer input can finish the connection.
+    ///
+    /// Does nothing and returns `false` if the pin does not exist
+    /// or the widget was not shown yet.
+    ///
+    /// Use same `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn begin_wire_drag<T, V>(
+        self,
+        ui: &Ui,
+        pin: AnyPinId,
+        snarl: &Snarl<T>,
+        viewer: &mut V,
+    ) -> bool
+    where
+        V: SnarlViewer<T>,
+    {
+        self.begin_wire_drag_at(ui.id(), ui.ctx(), pin, snarl, viewer)
+    }
+
+    /// Starts dragging a wire from `pin` in the `SnarlWidget` with same id.
+    ///
+    /// See [`SnarlWidget::begin_wire_drag`].
+    ///
+    /// `ui_id` must be the Id of the `Ui` instance that was used in [`SnarlWidget::show`].
+    pub fn begin_wire_drag_at<T, V>(
+        self,
+        ui_id: Id,
+        ctx: &Context,
+        pin: AnyPinId,
+        snarl: &Snarl<T>,
+        viewer: &mut V,
+    ) -> bool
+    where
+        V: SnarlViewer<T>,
+    {
+        let exists = match pin {
+            AnyPinId::In(pin) => snarl
+                .node(pin.node)
+                .is_some_and(|node| pin.input < viewer.inputs(node)),
+            AnyPinId::Out(pin) => snarl
+                .node(pin.node)
+                .is_some_and(|node| pin.output < viewer.outputs(node)),
+        };
+        if !exists {
+            return false;
+        }
+
+        let snarl_id = self.get_id(ui_id);
+        let Some(mut data) = SnarlStateData::load(ctx, snarl_id) else {
+            return false;
+        };
+
+        data.new_wires = Some(match pin {
+            AnyPinId::In(pin) => NewWires::In(smallvec![pin]),
+            AnyPinId::Out(pin) => NewWires::Out(smallvec![pin]),
+        });
+        data.new_wires_menu = false;
+        data.new_wires_pulled = true;
+        data.new_wires_rail = None;
+        data.save(ctx, snarl_id);
+
+        ctx.request_repaint();
+        true
+    }
+
+    /// Returns current interaction with the `SnarlWidget` with same id.
+    ///
+    /// Reflects the state from the last time the widget was shown,
+    /// e.g. to pick the cursor icon or show status text.
+    ///
+    /// Use same `Ui` instance that was used in [`SnarlWidget::show`].
+    #[must_use]
+    #[inline]
+    pub fn interaction(self, ui: &Ui) -> Interaction {
+        self.interaction_at(ui.id(), ui.ctx())
+    }
+
+    /// Returns current interaction with the `SnarlWidget` with same id.
+    ///
+    /// See [`SnarlWidget::interaction`].
+    ///
+    /// `ui_id` must be the Id of the `Ui` instance that was used in [`SnarlWidget::show`].
+    #[must_use]
+    #[inline]
+    pub fn interaction_at(self, ui_id: Id, ctx: &Context) -> Interaction {
+        Interaction::load(ctx, self.get_id(ui_id))
+    }
+
+    /// Returns `true` if the `SnarlWidget` with same id requested repaint
+    /// the last time it was shown.
+    ///
+    /// Graph requests repaint only while something is animating or interacted with,
+    /// so static graph lets egui idle.
+    ///
+    /// Use same `Ui` instance that was used in [`SnarlWidget::show`].
+    #[must_use]
+    #[inline]
+    pub fn needs_repaint(self, ui: &Ui) -> bool {
+        self.needs_repaint_at(ui.id(), ui.ctx())
+    }
+
+    /// Returns `true` if the `SnarlWidget` with same id requested repaint
+    /// the last time it was shown.
+    ///
+    /// See [`SnarlWidget::needs_repaint`].
+    ///
+    /// `ui_id` must be the Id of the `Ui` instance that was used in [`SnarlWidget::show`].
+    #[must_use]
+    #[inline]
+    pub fn needs_repaint_at(self, ui_id: Id, ctx: &Context) -> bool {
+        let snarl_id = self.get_id(ui_id);
+        ctx.data(|d| d.get_temp(snarl_id.with("needs-repaint")))
+            .unwrap_or(false)
+    }
+
+    /// Resets the view of the `SnarlWidget` with same id,
+    /// placing graph origin at the top-left corner of the widget with scale of 1.0.
+    ///
+    /// The view is adjusted next time the widget is shown.
+    /// Transition is animated if [`SnarlStyle::viewport_animation`](crate::ui::SnarlStyle::viewport_animation) is set.
+    ///
+    /// Use same `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn reset_view(self, ui: &Ui) {
+        self.reset_view_at(ui.id(), ui.ctx());
+    }
+
+    /// Resets the view of the `SnarlWidget` with same id,
+    /// placing graph origin at the top-left corner of the widget with scale of 1.0.
+    ///
+    /// See [`SnarlWidget::reset_view`].
+    ///
+    /// `ui_id` must be the Id of the `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn reset_view_at(self, ui_id: Id, ctx: &Context) {
+        let snarl_id = self.get_id(ui_id);
+        ViewportRequest::Reset.save(ctx, snarl_id);
+        ctx.request_repaint();
+    }
+
+    /// Pans and zooms the view of the `SnarlWidget` with same id
+    /// to frame all nodes as set by `opts`.
+    ///
+    /// Pass `f32` to only set padding around the nodes.
+    /// The view is adjusted next time the widget is shown.
+    /// Transition is animated if [`SnarlStyle::viewport_animation`](crate::ui::SnarlStyle::viewport_animation) is set.
+    ///
+    /// Use same `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn fit_to_view(self, ui: &Ui, opts: impl Into<FitOptions>) {
+        self.fit_to_view_at(ui.id(), ui.ctx(), opts);
+    }
+
+    /// Pans and zooms the view of the `SnarlWidget` with same id
+    /// to frame all nodes as set by `opts`.
+    ///
+    /// See [`SnarlWidget::fit_to_view`].
+    ///
+    /// `ui_id` must be the Id of the `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn fit_to_view_at(self, ui_id: Id, ctx: &Context, opts: impl Into<FitOptions>) {
+        let snarl_id = self.get_id(ui_id);
+        ViewportRequest::FitToView { opts: opts.into() }.save(ctx, snarl_id);
+        ctx.request_repaint();
+    }
+
+    /// Pans the view of the `SnarlWidget` with same id,
+    /// so that the node is in the center of the view.
+    ///
+    /// Zoom is not changed.
+    /// The view is adjusted next time the widget is shown.
+    /// Transition is animated if [`SnarlStyle::viewport_animation`](crate::ui::SnarlStyle::viewport_animation) is set.
+    ///
+    /// Use same `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn focus_node(self, ui: &Ui, node: NodeId) {
+        self.focus_node_at(ui.id(), ui.ctx(), node);
+    }
+
+    /// Pans the view of the `SnarlWidget` with same id,
+    /// so that the node is in the center of the view.
+    ///
+    /// See [`SnarlWidget::focus_node`].
+    ///
+    /// `ui_id` must be the Id of the `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn focus_node_at(self, ui_id: Id, ctx: &Context, node: NodeId) {
+        let snarl_id = self.get_id(ui_id);
+        ViewportRequest::FocusNode { node }.save(ctx, snarl_id);
+        ctx.request_repaint();
+    }
+
+    /// Pans the view of the `SnarlWidget` with same id minimally,
+    /// so that the node's rect expanded by `margin` becomes visible.
+    ///
+    /// Zoom is not changed, and nothing happens if node is already visible.
+    /// The view is adjusted next time the widget is shown.
+    /// Transition is animated if [`SnarlStyle::viewport_animation`](crate::ui::SnarlStyle::viewport_animation) is set.
+    ///
+    /// Use same `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn ensure_node_visible(self, ui: &Ui, node: NodeId, margin: f32) {
+        self.ensure_node_visible_at(ui.id(), ui.ctx(), node, margin);
+    }
+
+    /// Pans the view of the `SnarlWidget` with same id minimally,
+    /// so that the node's rect expanded by `margin` becomes visible.
+    ///
+    /// See [`SnarlWidget::ensure_node_visible`].
+    ///
+    /// `ui_id` must be the Id of the `Ui` instance that was used in [`SnarlWidget::show`].
+    #[inline]
+    pub fn ensure_node_visible_at(self, ui_id: Id, ctx: &Context, node: NodeId, margin: f32) {
+        let snarl_id = self.get_id(ui_id);
+        ViewportRequest::EnsureNodeVisible { node, margin }.save(ctx, snarl_id);
+        ctx.request_repaint();
+    }
 }
 
 /// Returns nodes selected in the UI for the `SnarlWidget` with same ID.
@@ -693,3 +1668,108 @@ pub fn selected_nodes(id: Id, ctx: &Context) -> Vec<NodeId> {
     ctx.data(|d| d.get_temp::<SelectedNodes>(id).unwrap_or_default().0)
         .into_vec()
 }
+
+#[test]
+fn refused_deletion_keeps_selected_wires() {
+    use egui::{Event, Key, KeyboardShortcut, Modifiers, RawInput, pos2, vec2};
+
+    use crate::ui::{InPin, OutPin, PinContext, PinInfo, SnarlConfig, SnarlPin, SnarlStyle};
+
+    struct Viewer {
+        confirm: bool,
+    }
+
+    impl SnarlViewer<()> for Viewer {
+        fn title(&mut self, (): &()) -> String {
+            "Node".to_owned()
+        }
+        fn inputs(&mut self, (): &()) -> usize {
+            1
+        }
+        fn outputs(&mut self, (): &()) -> usize {
+            1
+        }
+        fn show_input(
+            &mut self,
+            _: &InPin,
+            _: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<()>,
+        ) -> impl SnarlPin + 'static {
+            PinInfo::circle()
+        }
+        fn show_output(
+            &mut self,
+            _: &OutPin,
+            _: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<()>,
+        ) -> impl SnarlPin + 'static {
+            PinInfo::circle()
+        }
+        fn confirm_delete_nodes(&mut self, _: &[NodeId], _: &mut Snarl<()>) -> bool {
+            self.confirm
+        }
+    }
+
+    let ctx = Context::default();
+    let screen = Rect::from_min_size(Pos2::ZERO, vec2(800.0, 600.0));
+    let style = SnarlStyle::new();
+    let config = SnarlConfig {
+        delete_selection: Some(KeyboardShortcut::new(Modifiers::NONE, Key::Delete)),
+        ..SnarlConfig::new()
+    };
+    let widget = SnarlWidget::new()
+        .id_salt("snarl")
+        .style(style)
+        .config(config);
+    let mut snarl = Snarl::new();
+    let node = snarl.insert_node(pos2(0.0, 0.0), ());
+    let a = snarl.insert_node(pos2(0.0, 200.0), ());
+    let b = snarl.insert_node(pos2(200.0, 200.0), ());
+    let out_pin = OutPinId { node: a, output: 0 };
+    let in_pin = InPinId { node: b, input: 0 };
+    snarl.connect(out_pin, in_pin);
+
+    let mut viewer = Viewer { confirm: false };
+    let frame = |viewer: &mut Viewer, snarl: &mut Snarl<()>, delete: bool| {
+        let mut events = vec![Event::PointerMoved(pos2(790.0, 590.0))];
+        if delete {
+            events.push(Event::Key {
+                key: Key::Delete,
+                physical_key: None,
+                pressed: true,
+                repeat: false,
+                modifiers: Modifiers::NONE,
+            });
+        }
+        let input = RawInput {
+            screen_rect: Some(screen),
+            events,
+            ..Default::default()
+        };
+        let _ = ctx.run(input, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                widget.show(snarl, viewer, ui);
+                if !delete {
+                    let id = widget.get_id(ui.id());
+                    SelectedNodes(smallvec![node]).save(ctx, id);
+                    SelectedWires(smallvec![(out_pin, in_pin)]).save(ctx, id);
+                }
+            });
+        });
+    };
+
+    for _ in 0..3 {
+        frame(&mut viewer, &mut snarl, false);
+    }
+    frame(&mut viewer, &mut snarl, true);
+    assert!(snarl.node(node).is_some());
+    assert!(snarl.wires().any(|w| w == (out_pin, in_pin)));
+
+    viewer.confirm = true;
+    frame(&mut viewer, &mut snarl, false);
+    frame(&mut viewer, &mut snarl, true);
+    assert!(snarl.node(node).is_none());
+    assert!(!snarl.wires().any(|w| w == (out_pin, in_pin)));
+}
@@ -0,0 +1,345 @@
+//! Export of [`Snarl`] graph into SVG document.
+
+use std::{collections::HashMap, fmt::Write};
+
+use egui::{Color32, Pos2, Rect, Style, Vec2};
+
+use crate::{NodeId, Snarl};
+
+use super::{
+    SnarlStyle, SnarlViewer, WireStyle,
+    export::{export_bounding_rect, export_font_size, export_layout},
+    wire::{wire_bezier_3_points, wire_line},
+};
+
+/// Options for [`Snarl::to_svg`].
+#[derive(Clone, Debug)]
+pub struct SvgOptions {
+    /// Style of the graph.
+    /// Colors, pin sizes, wire style and node layout are taken from here.
+    pub style: SnarlStyle,
+
+    /// egui style used to resolve values missing in [`SvgOptions::style`],
+    /// same as [`Ui::style`](egui::Ui::style) does when graph is shown.
+    pub egui_style: Style,
+
+    /// Padding around the graph.
+    pub padding: f32,
+
+    /// Sizes of nodes in graph space, e.g. measured with [`Snarl::measure_node`].
+    ///
+    /// Nodes don't render their bodies in exported graph,
+    /// so sizes of nodes missing here are estimated from titles and number of pins.
+    pub node_sizes: HashMap<NodeId, Vec2>,
+
+    /// Whether to fill the background of the document.
+    pub background: bool,
+
+    #[doc(hidden)]
+    /// Do not access other than with .., here to emulate `#[non_exhaustive(pub)]`
+    pub _non_exhaustive: (),
+}
+
+impl SvgOptions {
+    /// Creates new [`SvgOptions`] with default style.
+    #[must_use]
+    pub fn new() -> Self {
+        SvgOptions {
+            style: SnarlStyle::new(),
+            egui_style: Style::default(),
+            padding: 16.0,
+            node_sizes: HashMap::new(),
+            background: true,
+            _non_exhaustive: (),
+        }
+    }
+}
+
+impl Default for SvgOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Snarl<T> {
+    /// Exports the graph into SVG document.
+    ///
+    /// Nodes are rendered as rounded rectangles with titles and pins,
+    /// wires are rendered as paths matching the [`WireStyle`] from `opts`.
+    /// Node bodies, headers and footers with custom widgets are not rendered.
+    ///
+    /// Colors are taken from [`SnarlStyle`] in `opts`,
+    /// [`SnarlViewer::pin_color`] and wire colors set in [`WireData`](crate::WireData),
+    /// and wires are blended between pin colors with [`SnarlStyle::gradient_wires`], same as in the UI.
+    /// Pin colors and wire styles set in [`PinInfo`](crate::ui::PinInfo)
+    /// are not known without showing the pins, so they are not used.
+    #[must_use]
+    #[allow(clippy::too_many_lines, clippy::needless_pass_by_value)]
+    pub fn to_svg<V>(&self, viewer: &mut V, opts: SvgOptions) -> String
+    where
+        V: SnarlViewer<T>,
+    {
+        let style = &opts.style;
+        let egui_style = &opts.egui_style;
+
+        let (nodes, wires) = export_layout(self, viewer, style, egui_style, &opts.node_sizes);
+
+        let mut bb = export_bounding_rect(&nodes);
+        if !bb.is_positive() {
+            bb = Rect::from_min_size(Pos2::ZERO, egui::Vec2::ZERO);
+        }
+        let bb = bb.expand(opts.padding);
+
+        let node_frame = style.node_frame(egui_style);
+        let header_frame = style.header_frame(egui_style);
+        let pin_size = style.pin_size(egui_style);
+        let pin_stroke = style.pin_stroke(egui_style);
+        let wire_style = style.wire_style();
+        let wire_width = style.wire_width(egui_style);
+        let wire_frame_size = style.wire_frame_size(egui_style);
+        let text_color = egui_style.visuals.text_color();
+        let font_size = export_font_size(egui_style);
+        let rounding = f32::from(node_frame.corner_radius.nw);
+
+        let mut svg = String::new();
+
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}" width="{}" height="{}">"#,
+            bb.min.x,
+            bb.min.y,
+            bb.width(),
+            bb.height(),
+            bb.width(),
+            bb.height()
+        );
+
+        if opts.background {
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" {}/>"#,
+                bb.min.x,
+                bb.min.y,
+                bb.width(),
+                bb.height(),
+                svg_paint("fill", style.bg_frame(egui_style).fill),
+            );
+        }
+
+        let _ = writeln!(svg, r#"<g fill="none">"#);
+        for (idx, wire) in wires.iter().enumerate() {
+            let d = if wire_style == WireStyle::Bezier3 {
+                let [a, b, c, d] = wire_bezier_3_points(
+                    wire_frame_size,
+                    style.upscale_wire_frame(),
+                    style.downscale_wire_frame(),
+                    style.bezier_tension(),
+                    wire.from,
+                    wire.to,
+                    wire.vertical,
+                );
+                format!(
+                    "M {} {} C {} {} {} {} {} {}",
+                    a.x, a.y, b.x, b.y, c.x, c.y, d.x, d.y
+                )
+            } else {
+                let line = wire_line(
+                    wire_frame_size,
+                    style.upscale_wire_frame(),
+                    style.downscale_wire_frame(),
+                    style.bezier_tension(),
+                    wire.from,
+                    wire.to,
+                    style.wire_smoothness(),
+                    wire_style,
+                    wire.vertical,
+                );
+                svg_polyline(&line)
+            };
+
+            let stroke = if let Some((from_color, to_color)) = wire.gradient {
+                let _ = writeln!(
+                    svg,
+                    r#"<linearGradient id="wire-{idx}" gradientUnits="userSpaceOnUse" x1="{}" y1="{}" x2="{}" y2="{}"><stop offset="0" {}/><stop offset="1" {}/></linearGradient>"#,
+                    wire.from.x,
+                    wire.from.y,
+                    wire.to.x,
+                    wire.to.y,
+                    svg_paint("stop-color", from_color),
+                    svg_paint("stop-color", to_color),
+                );
+                format!(r#"stroke="url(#wire-{idx})""#)
+            } else {
+                svg_paint("stroke", wire.color)
+            };
+
+            let _ = writeln!(
+                svg,
+                r#"<path d="{d}" {stroke} stroke-width="{wire_width}"/>"#,
+            );
+        }
+        let _ = writeln!(svg, "</g>");
+
+        for (_, node) in &nodes {
+            let _ = writeln!(svg, "<g>");
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{rounding}" {} {} stroke-width="{}"/>"#,
+                node.rect.min.x,
+                node.rect.min.y,
+                node.rect.width(),
+                node.rect.height(),
+                svg_paint("fill", node_frame.fill),
+                svg_paint("stroke", node_frame.stroke.color),
+                node_frame.stroke.width,
+            );
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{rounding}" {}/>"#,
+                node.header.min.x,
+                node.header.min.y,
+                node.header.width(),
+                node.header.height(),
+                svg_paint("fill", header_frame.fill),
+            );
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{}" font-family="sans-serif" font-size="{font_size}" dominant-baseline="central" {}>{}</text>"#,
+                node.header.min.x + egui_style.spacing.item_spacing.x * 2.0,
+                node.header.center().y,
+                svg_paint("fill", text_color),
+                escape_xml(&node.title),
+            );
+            for pin in node.inputs.iter().chain(&node.outputs) {
+                let _ = writeln!(
+                    svg,
+                    r#"<circle cx="{}" cy="{}" r="{}" {} {} stroke-width="{}"/>"#,
+                    pin.pos.x,
+                    pin.pos.y,
+                    pin_size * 0.5,
+                    svg_paint("fill", pin.fill),
+                    svg_paint("stroke", pin_stroke.color),
+                    pin_stroke.width,
+                );
+            }
+            let _ = writeln!(svg, "</g>");
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+fn svg_polyline(points: &[Pos2]) -> String {
+    let mut d = String::new();
+    for (idx, p) in points.iter().enumerate() {
+        let cmd = if idx == 0 { 'M' } else { 'L' };
+        let _ = write!(d, "{cmd} {} {} ", p.x, p.y);
+    }
+    d.truncate(d.trim_end().len());
+    d
+}
+
+/// Returns `attr` set to hex `color` with separate opacity attribute,
+/// e.g. `fill="#ff0000" fill-opacity="0.500"`,
+/// since not all SVG renderers support `rgba()` colors.
+fn svg_paint(attr: &str, color: Color32) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    let paint = format!(r##"{attr}="#{r:02x}{g:02x}{b:02x}""##);
+    if a == u8::MAX {
+        return paint;
+    }
+    let opacity = attr.strip_suffix("-color").unwrap_or(attr);
+    format!(r#"{paint} {opacity}-opacity="{:.3}""#, f32::from(a) / 255.0)
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[test]
+fn wires_use_pin_and_custom_colors() {
+    use egui::Ui;
+
+    use crate::{AnyPinId, InPin, InPinId, OutPin, OutPinId, WireData};
+
+    use super::{PinContext, PinInfo, SnarlPin};
+
+    struct Viewer;
+
+    impl SnarlViewer<()> for Viewer {
+        fn title(&mut self, (): &()) -> String {
+            "Node".to_owned()
+        }
+        fn inputs(&mut self, (): &()) -> usize {
+            2
+        }
+        fn outputs(&mut self, (): &()) -> usize {
+            1
+        }
+        fn show_input(
+            &mut self,
+            _: &InPin,
+            _: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<()>,
+        ) -> impl SnarlPin + 'static {
+            PinInfo::circle()
+        }
+        fn show_output(
+            &mut self,
+            _: &OutPin,
+            _: &mut Ui,
+            _: PinContext,
+            _: &mut Snarl<()>,
+        ) -> impl SnarlPin + 'static {
+            PinInfo::circle()
+        }
+        fn pin_color(&mut self, pin: AnyPinId, _: &Snarl<()>) -> Option<Color32> {
+            match pin {
+                AnyPinId::Out(_) => Some(Color32::RED),
+                AnyPinId::In(_) => Some(Color32::from_rgba_unmultiplied(0, 0, 255, 128)),
+            }
+        }
+    }
+
+    let mut snarl = Snarl::new();
+    let a = snarl.insert_node(Pos2::ZERO, ());
+    let b = snarl.insert_node(egui::pos2(200.0, 0.0), ());
+    let out_pin = OutPinId { node: a, output: 0 };
+    snarl.connect(out_pin, InPinId { node: b, input: 0 });
+    snarl.connect_with_data(
+        out_pin,
+        InPinId { node: b, input: 1 },
+        WireData {
+            color: Some(Color32::GREEN),
+            ..WireData::default()
+        },
+    );
+
+    let svg = snarl.to_svg(&mut Viewer, SvgOptions::new());
+    assert!(!svg.contains("rgba("));
+    assert!(svg.contains(r##"fill="#ff0000""##));
+    assert!(svg.contains(r##"fill="#0000ff" fill-opacity="0.502""##));
+    assert!(svg.contains(r##"stroke="#00ff00""##));
+    assert!(!svg.contains("linearGradient"));
+
+    let mut opts = SvgOptions::new();
+    opts.style.gradient_wires = Some(true);
+    let svg = snarl.to_svg(&mut Viewer, opts);
+    assert_eq!(svg.matches("<linearGradient").count(), 1);
+    assert!(svg.contains(r##"stop-color="#0000ff" stop-opacity="0.502""##));
+    assert!(svg.contains(r#"stroke="url(#wire-"#));
+}
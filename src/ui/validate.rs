@@ -0,0 +1,336 @@
+//! Validation of the [`Snarl`] graph with collected diagnostics.
+
+use egui::ahash::{HashMap, HashSet};
+
+use crate::{InPin, InPinId, NodeId, OutPin, OutPinId, Snarl, Wire};
+
+use super::SnarlViewer;
+
+/// Severity of the [`Diagnostic`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Informational note.
+    Info,
+
+    /// Problem that does not prevent evaluation.
+    Warning,
+
+    /// Problem that prevents evaluation.
+    Error,
+}
+
+/// Element of the graph the [`Diagnostic`] refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DiagnosticTarget {
+    /// Node.
+    Node(NodeId),
+
+    /// Input pin.
+    InPin(InPinId),
+
+    /// Output pin.
+    OutPin(OutPinId),
+
+    /// Wire between output and input pins.
+    Wire(OutPinId, InPinId),
+}
+
+/// Problem found by [`Snarl::validate`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Element of the graph with the problem.
+    pub target: DiagnosticTarget,
+
+    /// Severity of the problem.
+    pub severity: Severity,
+
+    /// Description of the problem.
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Creates new [`Diagnostic`].
+    #[must_use]
+    pub fn new(target: DiagnosticTarget, severity: Severity, message: impl Into<String>) -> Self {
+        Diagnostic {
+            target,
+            severity,
+            message: message.into(),
+        }
+    }
+
+    /// Creates new [`Diagnostic`] with [`Severity::Error`].
+    #[must_use]
+    pub fn error(target: DiagnosticTarget, message: impl Into<String>) -> Self {
+        Self::new(target, Severity::Error, message)
+    }
+
+    /// Creates new [`Diagnostic`] with [`Severity::Warning`].
+    #[must_use]
+    pub fn warning(target: DiagnosticTarget, message: impl Into<String>) -> Self {
+        Self::new(target, Severity::Warning, message)
+    }
+}
+
+impl<T> Snarl<T> {
+    /// Validates the graph and returns all problems found.
+    ///
+    /// Built-in checks report wires attached to missing nodes or pins out of node's range,
+    /// unconnected inputs required by [`SnarlViewer::input_required`]
+    /// and wires closing cycles unless [`SnarlViewer::cycles_allowed`].
+    /// [`SnarlViewer::validate_node`] and [`SnarlViewer::validate_wire`]
+    /// contribute their own diagnostics.
+    ///
+    /// Node diagnostics come first, then wire diagnostics, then cycles.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::{InPin, InPinId, OutPin, OutPinId, Snarl};
+    /// # use egui_snarl::ui::{DiagnosticTarget, PinContext, PinInfo, Severity, SnarlViewer};
+    /// struct Viewer;
+    ///
+    /// impl SnarlViewer<()> for Viewer {
+    ///     fn title(&mut self, _: &()) -> String { String::new() }
+    ///     fn inputs(&mut self, _: &()) -> usize { 1 }
+    ///     fn outputs(&mut self, _: &()) -> usize { 1 }
+    ///     fn show_input(&mut self, _: &InPin, _: &mut egui::Ui, _: PinContext, _: &mut Snarl<()>) -> PinInfo {
+    ///         PinInfo::circle()
+    ///     }
+    ///     fn show_output(&mut self, _: &OutPin, _: &mut egui::Ui, _: PinContext, _: &mut Snarl<()>) -> PinInfo {
+    ///         PinInfo::circle()
+    ///     }
+    ///     fn cycles_allowed(&mut self) -> bool { false }
+    /// }
+    ///
+    /// let mut snarl = Snarl::new();
+    /// let a = snarl.insert_node(egui::pos2(0.0, 0.0), ());
+    /// let b = snarl.insert_node(egui::pos2(100.0, 0.0), ());
+    /// snarl.connect(OutPinId { node: a, output: 0 }, InPinId { node: b, input: 0 });
+    /// assert!(snarl.validate(&mut Viewer).is_empty());
+    ///
+    /// snarl.connect(OutPinId { node: b, output: 0 }, InPinId { node: a, input: 0 });
+    /// let diagnostics = snarl.validate(&mut Viewer);
+    /// assert_eq!(diagnostics.len(), 1);
+    /// assert_eq!(diagnostics[0].severity, Severity::Error);
+    /// assert!(matches!(diagnostics[0].target, DiagnosticTarget::Wire(..)));
+    /// ```
+    #[must_use]
+    pub fn validate<V>(&self, viewer: &mut V) -> Vec<Diagnostic>
+    where
+        V: SnarlViewer<T>,
+    {
+        let mut diagnostics = Vec::new();
+
+        let mut pins = HashMap::default();
+        for (idx, node) in &self.nodes {
+            let id = NodeId(idx);
+            let inputs = viewer.inputs(&node.value);
+            let outputs = viewer.outputs(&node.value);
+            pins.insert(id, (inputs, outputs));
+
+            diagnostics.extend(viewer.validate_node(id, self));
+
+            for input in 0..inputs {
+                let pin = InPin::new(self, InPinId { node: id, input });
+                if pin.remotes.is_empty() && viewer.input_required(&pin, self) {
+                    diagnostics.push(Diagnostic::error(
+                        DiagnosticTarget::InPin(pin.id),
+                        "required input is not connected",
+                    ));
+                }
+            }
+        }
+
+        let mut wires = self.wires.iter().collect::<Vec<_>>();
+        wires.sort_unstable_by_key(|wire| (wire.out_pin, wire.in_pin));
+
+        for wire in &wires {
+            let target = DiagnosticTarget::Wire(wire.out_pin, wire.in_pin);
+            let outputs = pins
+                .get(&wire.out_pin.node)
+                .map_or(0, |&(_, outputs)| outputs);
+            let inputs = pins.get(&wire.in_pin.node).map_or(0, |&(inputs, _)| inputs);
+
+            if !self.nodes.contains(wire.out_pin.node.0) {
+                diagnostics.push(Diagnostic::error(target, "wire starts at missing node"));
+            } else if wire.out_pin.output >= outputs {
+                diagnostics.push(Diagnostic::error(target, "wire starts at missing output"));
+            }
+            if !self.nodes.contains(wire.in_pin.node.0) {
+                diagnostics.push(Diagnostic::error(target, "wire ends at missing node"));
+            } else if wire.in_pin.input >= inputs {
+                diagnostics.push(Diagnostic::error(target, "wire ends at missing input"));
+            }
+
+            let from = OutPin::new(self, wire.out_pin);
+            let to = InPin::new(self, wire.in_pin);
+            diagnostics.extend(viewer.validate_wire(&from, &to, self));
+        }
+
+        if !viewer.cycles_allowed() {
+            for wire in cycle_wires(&wires) {
+                diagnostics.push(Diagnostic::error(
+                    DiagnosticTarget::Wire(wire.out_pin, wire.in_pin),
+                    "wire closes a cycle",
+                ));
+            }
+        }
+
+        diagnostics
+    }
+}
+
+/// Returns wires that close cycles, one per back edge of depth-first search.
+fn cycle_wires(wires: &[Wire]) -> Vec<Wire> {
+    let mut edges = HashMap::<NodeId, Vec<Wire>>::default();
+    for &wire in wires {
+        edges.entry(wire.out_pin.node).or_default().push(wire);
+    }
+
+    let mut roots = wires
+        .iter()
+        .map(|wire| wire.out_pin.node)
+        .collect::<Vec<_>>();
+    roots.dedup();
+
+    let mut done = HashSet::default();
+    let mut on_path = HashSet::default();
+    let mut closing = Vec::new();
+
+    for root in roots {
+        if done.contains(&root) {
+            continue;
+        }
+
+        // Stack of nodes on the current path with index of the next wire to follow.
+        let mut stack = vec![(root, 0)];
+        on_path.insert(root);
+
+        while let Some(&mut (node, ref mut next)) = stack.last_mut() {
+            let Some(&wire) = edges.get(&node).and_then(|edges| edges.get(*next)) else {
+                stack.pop();
+                on_path.remove(&node);
+                done.insert(node);
+                continue;
+            };
+            *next += 1;
+
+            let to = wire.in_pin.node;
+            if on_path.contains(&to) {
+                closing.push(wire);
+            } else if !done.contains(&to) {
+                on_path.insert(to);
+                stack.push((to, 0));
+            }
+        }
+    }
+
+    closing
+}
+
+#[test]
+fn validate_reports_broken_wires_and_cycles() {
+    struct Viewer {
+        cycles_allowed: bool,
+    }
+
+    impl SnarlViewer<usize> for Viewer {
+        fn title(&mut self, _: &usize) -> String {
+            String::new()
+        }
+
+        fn inputs(&mut self, _: &usize) -> usize {
+            1
+        }
+
+        fn outputs(&mut self, outputs: &usize) -> usize {
+            *outputs
+        }
+
+        fn show_input(
+            &mut self,
+            _: &InPin,
+            _: &mut egui::Ui,
+            _: super::PinContext,
+            _: &mut Snarl<usize>,
+        ) -> impl super::SnarlPin + 'static {
+            super::PinInfo::circle()
+        }
+
+        fn show_output(
+            &mut self,
+            _: &OutPin,
+            _: &mut egui::Ui,
+            _: super::PinContext,
+            _: &mut Snarl<usize>,
+        ) -> impl super::SnarlPin + 'static {
+            super::PinInfo::circle()
+        }
+
+        fn cycles_allowed(&mut self) -> bool {
+            self.cycles_allowed
+        }
+    }
+
+    // Wires to missing node and output out of range.
+    let mut snarl = Snarl::new();
+    let a = snarl.insert_node(egui::Pos2::ZERO, 1);
+    let b = snarl.insert_node(egui::Pos2::ZERO, 1);
+    snarl.connect(
+        OutPinId { node: a, output: 2 },
+        InPinId { node: b, input: 0 },
+    );
+    snarl.wires.insert(Wire {
+        out_pin: OutPinId { node: b, output: 0 },
+        in_pin: InPinId {
+            node: NodeId(9),
+            input: 0,
+        },
+    });
+
+    let mut viewer = Viewer {
+        cycles_allowed: true,
+    };
+    let messages = snarl
+        .validate(&mut viewer)
+        .into_iter()
+        .map(|diagnostic| diagnostic.message)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        ["wire starts at missing output", "wire ends at missing node"]
+    );
+
+    let mut snarl = Snarl::new();
+    let nodes = (0..4)
+        .map(|_| snarl.insert_node(egui::Pos2::ZERO, 1))
+        .collect::<Vec<_>>();
+
+    // Two cycles sharing the first node: 0 -> 1 -> 0 and 0 -> 2 -> 3 -> 0.
+    for (from, to) in [(0, 1), (1, 0), (0, 2), (2, 3), (3, 0)] {
+        snarl.connect(
+            OutPinId {
+                node: nodes[from],
+                output: 0,
+            },
+            InPinId {
+                node: nodes[to],
+                input: 0,
+            },
+        );
+    }
+
+    let mut viewer = Viewer {
+        cycles_allowed: false,
+    };
+    let diagnostics = snarl.validate(&mut viewer);
+    assert_eq!(diagnostics.len(), 2);
+    assert!(diagnostics.iter().all(|diagnostic| {
+        diagnostic.severity == Severity::Error
+            && matches!(diagnostic.target, DiagnosticTarget::Wire(..))
+    }));
+
+    viewer.cycles_allowed = true;
+    assert!(snarl.validate(&mut viewer).is_empty());
+}
@@ -1,10 +1,14 @@
-use egui::{Painter, Pos2, Rect, Style, Ui, emath::TSTransform};
+use std::{collections::HashSet, ops::Range};
 
-use crate::{InPin, InPinId, NodeId, OutPin, OutPinId, Snarl};
+use egui::{Color32, Id, Painter, Pos2, Rect, Style, Ui, WidgetText, emath::TSTransform};
+
+use crate::{AnyPinId, InPin, InPinId, NodeId, OutPin, OutPinId, Snarl, WireAttach};
 
 use super::{
-    BackgroundPattern, NodeLayout, SnarlStyle,
-    pin::{AnyPins, PinContext, SnarlPin},
+    BackgroundPattern, CapacityLimit, DragHandle, EditSource, NodeAnchor, NodeHitShape, NodeLayout,
+    PinSides, SnarlStyle,
+    pin::{AnyPins, PinContext, PinLayout, SnarlPin},
+    validate::Diagnostic,
 };
 
 /// `SnarlViewer` is a trait for viewing a Snarl.
@@ -101,6 +105,32 @@ pub trait SnarlViewer<T> {
         default
     }
 
+    /// Returns on which sides of the node pins are placed.
+    ///
+    /// With [`PinSides::TopBottom`] the default layout passed to [`SnarlViewer::node_layout`]
+    /// is switched to [`NodeLayoutKind::VerticalSandwichDown`](crate::ui::NodeLayoutKind::VerticalSandwichDown),
+    /// placing inputs on top and outputs on bottom, and wires attached to them use vertical tangents.
+    ///
+    /// Returns [`PinSides::Sides`] by default.
+    #[inline]
+    fn pin_sides(&mut self, node: NodeId, snarl: &Snarl<T>) -> PinSides {
+        let _ = (node, snarl);
+        PinSides::Sides
+    }
+
+    /// Returns opacity of the node in `0.0..=1.0` range.
+    ///
+    /// Node frame, its contents and pins are drawn with this opacity multiplied in,
+    /// and wires use the smaller opacity of their two nodes.
+    /// Use it to dim nodes that are out of focus, e.g. not selected or not matching a search.
+    ///
+    /// Returns `1.0` by default.
+    #[inline]
+    fn node_opacity(&mut self, node: NodeId, snarl: &Snarl<T>) -> f32 {
+        let _ = (node, snarl);
+        1.0
+    }
+
     /// Renders elements inside the node's header frame.
     ///
     /// This is the good place to show the node's title and controls related to the whole node.
@@ -124,6 +154,41 @@ pub trait SnarlViewer<T> {
         });
     }
 
+    /// Returns region of the node that starts dragging it.
+    ///
+    /// Restrict it to the header or a custom grip
+    /// for nodes with scrollable or draggable body widgets.
+    /// Dragging outside of the region still selects the node.
+    ///
+    /// Returns [`DragHandle::WholeNode`] by default.
+    #[inline]
+    fn drag_handle(&self, node: NodeId) -> DragHandle {
+        let _ = node;
+        DragHandle::WholeNode
+    }
+
+    /// Checks if the node's title can be edited in place.
+    ///
+    /// If `true`, double-clicking the node's header replaces it with a text field.
+    /// Edited title is passed to [`SnarlViewer::rename_node`]
+    /// when `Enter` is pressed or text field loses focus.
+    /// Editing is cancelled with `Escape`.
+    ///
+    /// Returns `false` by default.
+    #[inline]
+    fn title_editable(&self, node: NodeId) -> bool {
+        let _ = node;
+        false
+    }
+
+    /// Renames the node after its title was edited in place.
+    ///
+    /// See [`SnarlViewer::title_editable`].
+    #[inline]
+    fn rename_node(&mut self, node: NodeId, new_title: String, snarl: &mut Snarl<T>) {
+        let _ = (node, new_title, snarl);
+    }
+
     /// Renders buttons/icons in the node's header (right side by default).
     ///
     /// Override this method to add custom buttons, flags, or status icons to nodes.
@@ -142,6 +207,158 @@ pub trait SnarlViewer<T> {
         let _ = (node, inputs, outputs, ui, snarl);
     }
 
+    /// Returns shape of the node used to hit-test pointer input.
+    ///
+    /// Pointer over the node's frame outside of the shape,
+    /// like in the corners of round or diamond shaped nodes,
+    /// interacts with what is beneath the node.
+    /// Drawing the shape is up to the viewer.
+    ///
+    /// Returns [`NodeHitShape::Rect`] by default.
+    #[inline]
+    fn node_hit_shape(&self, node: NodeId) -> NodeHitShape {
+        let _ = node;
+        NodeHitShape::Rect
+    }
+
+    /// Returns space the node is positioned in.
+    ///
+    /// Nodes anchored to the screen are drawn above other nodes,
+    /// can't be dragged and their wires bridge to graph space.
+    ///
+    /// Returns [`NodeAnchor::World`] by default.
+    #[inline]
+    fn node_anchor(&self, node: NodeId) -> NodeAnchor {
+        let _ = node;
+        NodeAnchor::World
+    }
+
+    /// Returns labeled groups of the node's input pins.
+    ///
+    /// Each group is a label and range of input indices.
+    /// Header with the label and a toggle to collapse the group
+    /// is shown above the first pin of the group.
+    /// Wires of pins in collapsed group attach to the header.
+    ///
+    /// Groups are shown for pins on the left and right sides of the node.
+    ///
+    /// Returns no groups by default.
+    #[inline]
+    fn input_groups(&mut self, node: NodeId, snarl: &Snarl<T>) -> Vec<(String, Range<usize>)> {
+        let _ = (node, snarl);
+        Vec::new()
+    }
+
+    /// Returns labeled groups of the node's output pins.
+    ///
+    /// See [`SnarlViewer::input_groups`].
+    ///
+    /// Returns no groups by default.
+    #[inline]
+    fn output_groups(&mut self, node: NodeId, snarl: &Snarl<T>) -> Vec<(String, Range<usize>)> {
+        let _ = (node, snarl);
+        Vec::new()
+    }
+
+    /// Checks if input pin can be added to the node.
+    ///
+    /// If true, button to add input is shown after the node's input pins.
+    /// Use this with [`SnarlViewer::can_remove_input`] to bound number of inputs of variadic nodes.
+    ///
+    /// Returns false by default.
+    #[inline]
+    fn can_add_input(&mut self, node: NodeId, snarl: &Snarl<T>) -> bool {
+        let _ = (node, snarl);
+        false
+    }
+
+    /// Adds input pin to the node.
+    ///
+    /// Called when button shown by [`SnarlViewer::can_add_input`] is clicked.
+    /// Node is expected to report one more input in [`SnarlViewer::inputs`] afterwards.
+    ///
+    /// By default it does nothing.
+    #[inline]
+    fn add_input(&mut self, node: NodeId, snarl: &mut Snarl<T>) {
+        let _ = (node, snarl);
+    }
+
+    /// Checks if last input pin can be removed from the node.
+    ///
+    /// If true, button to remove input is shown after the node's input pins.
+    ///
+    /// Returns false by default.
+    #[inline]
+    fn can_remove_input(&mut self, node: NodeId, snarl: &Snarl<T>) -> bool {
+        let _ = (node, snarl);
+        false
+    }
+
+    /// Removes last input pin from the node.
+    ///
+    /// Called when button shown by [`SnarlViewer::can_remove_input`] is clicked.
+    /// Wires of the pin are already removed when this is called.
+    ///
+    /// By default it does nothing.
+    #[inline]
+    fn remove_input(&mut self, node: NodeId, snarl: &mut Snarl<T>) {
+        let _ = (node, snarl);
+    }
+
+    /// Checks if output pin can be added to the node.
+    ///
+    /// See [`SnarlViewer::can_add_input`].
+    ///
+    /// Returns false by default.
+    #[inline]
+    fn can_add_output(&mut self, node: NodeId, snarl: &Snarl<T>) -> bool {
+        let _ = (node, snarl);
+        false
+    }
+
+    /// Adds output pin to the node.
+    ///
+    /// See [`SnarlViewer::add_input`].
+    ///
+    /// By default it does nothing.
+    #[inline]
+    fn add_output(&mut self, node: NodeId, snarl: &mut Snarl<T>) {
+        let _ = (node, snarl);
+    }
+
+    /// Checks if last output pin can be removed from the node.
+    ///
+    /// See [`SnarlViewer::can_remove_input`].
+    ///
+    /// Returns false by default.
+    #[inline]
+    fn can_remove_output(&mut self, node: NodeId, snarl: &Snarl<T>) -> bool {
+        let _ = (node, snarl);
+        false
+    }
+
+    /// Removes last output pin from the node.
+    ///
+    /// See [`SnarlViewer::remove_input`].
+    ///
+    /// By default it does nothing.
+    #[inline]
+    fn remove_output(&mut self, node: NodeId, snarl: &mut Snarl<T>) {
+        let _ = (node, snarl);
+    }
+
+    /// Returns arrangement of the node's pin contents.
+    ///
+    /// Use [`PinLayout::Inline`] for dense form-like nodes.
+    /// Node size follows the laid out contents.
+    ///
+    /// Returns [`PinLayout::Stacked`] by default.
+    #[inline]
+    fn pin_layout(&self, node: NodeId) -> PinLayout {
+        let _ = node;
+        PinLayout::Stacked
+    }
+
     /// Returns number of input pins of the node.
     ///
     /// [`SnarlViewer::show_input`] will be called for each input in range `0..inputs()`.
@@ -152,6 +369,7 @@ pub trait SnarlViewer<T> {
     /// The `context` parameter provides information about the current UI state,
     /// including whether the label should be visible based on [`SnarlStyle::pin_label_visibility`].
     /// Use `context.label_visible` to conditionally show or hide the pin's label.
+    /// Use [`InPin::is_connected`] to show editor for default value only when the pin is unconnected.
     fn show_input(
         &mut self,
         pin: &InPin,
@@ -170,6 +388,7 @@ pub trait SnarlViewer<T> {
     /// The `context` parameter provides information about the current UI state,
     /// including whether the label should be visible based on [`SnarlStyle::pin_label_visibility`].
     /// Use `context.label_visible` to conditionally show or hide the pin's label.
+    /// Use [`OutPin::is_connected`] to check whether the pin has wires.
     fn show_output(
         &mut self,
         pin: &OutPin,
@@ -178,6 +397,34 @@ pub trait SnarlViewer<T> {
         snarl: &mut Snarl<T>,
     ) -> impl SnarlPin + 'static;
 
+    /// Returns color of the pin by its data type.
+    ///
+    /// Used as fill of the pin and color of its wires
+    /// when [`PinInfo`](crate::ui::PinInfo) returned for the pin does not set them,
+    /// so color of each type is defined in one place.
+    ///
+    /// Returns `None` by default, which falls back to [`SnarlStyle::pin_fill`].
+    #[inline]
+    fn pin_color(&mut self, pin: AnyPinId, snarl: &Snarl<T>) -> Option<Color32> {
+        let _ = (pin, snarl);
+        None
+    }
+
+    /// Returns source of the id of the `Ui` passed to [`SnarlViewer::show_body`].
+    ///
+    /// State of widgets in the body is kept in egui memory under ids derived from it,
+    /// independent of node's position, draw order and pan or zoom of the view.
+    /// Override this to keep widget state across changes of [`NodeId`],
+    /// e.g. when graph is reloaded, by returning id from the node's own stable key.
+    /// Ids must be unique among nodes of the graph.
+    ///
+    /// Returns id made from `node` by default.
+    #[inline]
+    fn node_id_source(&mut self, node: NodeId, snarl: &Snarl<T>) -> Id {
+        let _ = snarl;
+        Id::new(node)
+    }
+
     /// Checks if node has something to show in body - between input and output pins.
     #[inline]
     fn has_body(&mut self, node: &T) -> bool {
@@ -247,6 +494,18 @@ pub trait SnarlViewer<T> {
         let _ = (node, inputs, outputs, ui, snarl);
     }
 
+    /// Returns tooltip shown when hovering the node.
+    ///
+    /// Tooltip is not shown while node or wire is dragged
+    /// and while pointer is over node's pin.
+    ///
+    /// Returns `None` by default.
+    #[inline]
+    fn node_tooltip(&mut self, node: NodeId, snarl: &Snarl<T>) -> Option<WidgetText> {
+        let _ = (node, snarl);
+        None
+    }
+
     /// Returns optional waypoints for a wire to route through.
     ///
     /// Override this method to specify intermediate waypoints for wires,
@@ -256,7 +515,8 @@ pub trait SnarlViewer<T> {
     /// for complex wire routing, you can hide the default wire using `wire_style`
     /// and draw custom wire segments in [`draw_foreground`].
     ///
-    /// By default returns `None` (direct wire using default rendering).
+    /// By default returns [`WireData::waypoints`](crate::WireData::waypoints) stored with the wire,
+    /// or `None` if there are none (direct wire using default rendering).
     #[inline]
     fn wire_waypoints(
         &mut self,
@@ -264,8 +524,10 @@ pub trait SnarlViewer<T> {
         to: &InPinId,
         snarl: &Snarl<T>,
     ) -> Option<Vec<Pos2>> {
-        let _ = (from, to, snarl);
-        None
+        snarl
+            .wire_data(*from, *to)
+            .filter(|data| !data.waypoints.is_empty())
+            .map(|data| data.waypoints.clone())
     }
 
     /// Checks if wire has something to show in widget.
@@ -284,6 +546,8 @@ pub trait SnarlViewer<T> {
     }
 
     /// Checks if the snarl has something to show in context menu if right-clicked or long-touched on empty space at `pos`.
+    ///
+    /// `pos` is in graph space.
     #[inline]
     fn has_graph_menu(&mut self, pos: Pos2, snarl: &mut Snarl<T>) -> bool {
         let _ = (pos, snarl);
@@ -293,11 +557,54 @@ pub trait SnarlViewer<T> {
     /// Show context menu for the snarl.
     ///
     /// This can be used to implement menu for adding new nodes.
+    /// `pos` is the graph space point where the menu was opened by click,
+    /// so nodes inserted at `pos` appear exactly under the click regardless of zoom.
     #[inline]
     fn show_graph_menu(&mut self, pos: Pos2, ui: &mut Ui, snarl: &mut Snarl<T>) {
         let _ = (pos, ui, snarl);
     }
 
+    /// Checks if the snarl has something to show in graph context menu opened at `pos`.
+    ///
+    /// `source` is `None` when the menu is opened by right-click or long-touch on empty space.
+    /// It is the dragged pin when wire is dropped to empty space
+    /// and [`SnarlViewer::has_dropped_wire_menu`] returns false.
+    /// If multiple wires were dragged, `source` is the first of them.
+    ///
+    /// By default it calls [`SnarlViewer::has_graph_menu`] when `source` is `None`
+    /// and returns false otherwise.
+    #[inline]
+    fn has_graph_menu_with_source(
+        &mut self,
+        pos: Pos2,
+        source: Option<AnyPinId>,
+        snarl: &mut Snarl<T>,
+    ) -> bool {
+        match source {
+            None => self.has_graph_menu(pos, snarl),
+            Some(_) => false,
+        }
+    }
+
+    /// Show graph context menu opened at `pos`.
+    ///
+    /// `source` is the dragged pin if the menu was opened by wire drop,
+    /// see [`SnarlViewer::has_graph_menu_with_source`].
+    /// Use it to connect created node to the pin right away.
+    ///
+    /// By default it ignores `source` and calls [`SnarlViewer::show_graph_menu`].
+    #[inline]
+    fn show_graph_menu_with_source(
+        &mut self,
+        pos: Pos2,
+        ui: &mut Ui,
+        source: Option<AnyPinId>,
+        snarl: &mut Snarl<T>,
+    ) {
+        let _ = source;
+        self.show_graph_menu(pos, ui, snarl);
+    }
+
     /// Checks if the snarl has something to show in context menu if wire drag is stopped at `pos`.
     #[inline]
     fn has_dropped_wire_menu(&mut self, src_pins: AnyPins, snarl: &mut Snarl<T>) -> bool {
@@ -319,6 +626,20 @@ pub trait SnarlViewer<T> {
         let _ = (pos, ui, src_pins, snarl);
     }
 
+    /// Creates new node when wire is dropped on empty space at `pos`.
+    ///
+    /// Called instead of opening dropped wire menu when
+    /// [`SnarlConfig::wire_drop_on_empty`](crate::ui::SnarlConfig::wire_drop_on_empty)
+    /// is [`WireDropAction::CreateNode`](crate::ui::WireDropAction::CreateNode).
+    /// `source` is the pin the wire was dragged from.
+    /// If multiple wires were dragged, `source` is the first of them.
+    ///
+    /// Use this to instantiate node and connect it to the `source` pin.
+    #[inline]
+    fn create_node_from_pin(&mut self, source: AnyPinId, pos: Pos2, snarl: &mut Snarl<T>) {
+        let _ = (source, pos, snarl);
+    }
+
     /// Checks if the node has something to show in context menu if right-clicked or long-touched on the node.
     #[inline]
     fn has_node_menu(&mut self, node: &T) -> bool {
@@ -326,6 +647,18 @@ pub trait SnarlViewer<T> {
         false
     }
 
+    /// Checks if the `node` has something to show in context menu.
+    ///
+    /// The graph UI calls this method instead of [`SnarlViewer::has_node_menu`],
+    /// so it can be overridden to gate the menu per node, e.g. for locked nodes.
+    /// Right-click on node without menu opens the graph menu instead.
+    ///
+    /// By default this method calls [`SnarlViewer::has_node_menu`] with node's value.
+    #[inline]
+    fn has_node_menu_by_id(&mut self, node: NodeId, snarl: &Snarl<T>) -> bool {
+        self.has_node_menu(&snarl[node])
+    }
+
     /// Show context menu for the snarl.
     ///
     /// This can be used to implement menu for adding new nodes.
@@ -341,13 +674,205 @@ pub trait SnarlViewer<T> {
         let _ = (node, inputs, outputs, ui, snarl);
     }
 
+    /// Checks if wire from output pin `from` to input pin `to` can be created.
+    ///
+    /// Called every frame while wire is dragged over a pin to preview whether
+    /// connection would be accepted, so it must be cheap.
+    /// Wires rejected by this method are not passed to [`SnarlViewer::connect`] when dropped.
+    ///
+    /// Returns `true` by default.
+    #[inline]
+    fn validate_connection(&mut self, from: &OutPinId, to: &InPinId, snarl: &Snarl<T>) -> bool {
+        let _ = (from, to, snarl);
+        true
+    }
+
+    /// Checks if wire dropped from `from` pin onto `to` pin of the same kind,
+    /// either both inputs or both outputs, can be handled by the viewer.
+    ///
+    /// Called only if [`SnarlConfig::allow_same_kind_connections`](crate::ui::SnarlConfig::allow_same_kind_connections) is enabled,
+    /// every frame while wire is dragged over such pin, so it must be cheap.
+    ///
+    /// Returns `false` by default.
+    #[inline]
+    fn validate_same_kind_connection(
+        &mut self,
+        from: AnyPinId,
+        to: AnyPinId,
+        snarl: &Snarl<T>,
+    ) -> bool {
+        let _ = (from, to, snarl);
+        false
+    }
+
+    /// Asks the viewer to link two pins of the same kind,
+    /// either both inputs or both outputs.
+    ///
+    /// Graph wires always go from output to input,
+    /// so the viewer decides what such link means, e.g. inserting a reroute node.
+    /// Called only for pins accepted by [`SnarlViewer::validate_same_kind_connection`].
+    ///
+    /// By default it does nothing.
+    #[inline]
+    fn connect_same_kind(&mut self, from: AnyPinId, to: AnyPinId, snarl: &mut Snarl<T>) {
+        let _ = (from, to, snarl);
+    }
+
+    /// Checks whether wire dragged from `source` pin can be connected to `candidate` pin.
+    ///
+    /// Used to dim incompatible pins during wire drag
+    /// when [`SnarlStyle::highlight_compatible_pins`] is enabled.
+    /// Called only for pins of visible nodes.
+    ///
+    /// Returns `false` for pins of the same direction by default,
+    /// otherwise delegates to [`SnarlViewer::validate_connection`].
+    #[inline]
+    fn pin_compatible(&mut self, source: AnyPinId, candidate: AnyPinId, snarl: &Snarl<T>) -> bool {
+        match (source, candidate) {
+            (AnyPinId::Out(from), AnyPinId::In(to)) | (AnyPinId::In(to), AnyPinId::Out(from)) => {
+                self.validate_connection(&from, &to, snarl)
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns position where the wire being dragged from `source` pin should end.
+    ///
+    /// Called every frame during wire drag.
+    /// `cursor` is the pointer position in graph space
+    /// and `hovered` is the pin under the pointer, if any.
+    /// If multiple wires are dragged, `source` is the first of them.
+    ///
+    /// Return `Some` to snap the wire end to custom anchor instead of the pointer.
+    /// Connecting is still decided by the hovered pin.
+    ///
+    /// Returns `None` by default.
+    #[inline]
+    fn wire_drag_target(
+        &mut self,
+        source: AnyPinId,
+        cursor: Pos2,
+        hovered: Option<AnyPinId>,
+        snarl: &Snarl<T>,
+    ) -> Option<Pos2> {
+        let _ = (source, cursor, hovered, snarl);
+        None
+    }
+
     /// Asks the viewer to connect two pins.
     ///
     /// This is usually happens when user drags a wire from one node's output pin to another node's input pin or vice versa.
     /// By default this method connects the pins and returns `Ok(())`.
+    ///
+    /// When either pin is a rail, [`OutPin::rail_pos`] and [`InPin::rail_pos`]
+    /// hold position along the rail where the wire attaches.
+    /// Default implementation stores them with [`Snarl::connect_at`].
     #[inline]
     fn connect(&mut self, from: &OutPin, to: &InPin, snarl: &mut Snarl<T>) {
-        snarl.connect(from.id, to.id);
+        snarl.connect_at(
+            from.id,
+            to.id,
+            WireAttach {
+                out_pin: from.rail_pos,
+                in_pin: to.rail_pos,
+            },
+        );
+    }
+
+    /// Asks the viewer to connect two pins, with the `source` of the change.
+    ///
+    /// The graph UI calls this method instead of [`SnarlViewer::connect`],
+    /// so it can be overridden to tell user edits apart, e.g. for undo grouping.
+    /// By default this method ignores the source and calls [`SnarlViewer::connect`].
+    #[inline]
+    fn connect_with_source(
+        &mut self,
+        from: &OutPin,
+        to: &InPin,
+        source: EditSource,
+        snarl: &mut Snarl<T>,
+    ) {
+        let _ = source;
+        self.connect(from, to, snarl);
+    }
+
+    /// Returns maximum number of wires connected to the pin.
+    ///
+    /// Wires dragged onto a pin that has this many wires are not connected,
+    /// and the pin is dimmed while wire is dragged.
+    /// Pins at capacity are drawn with a ring around them.
+    ///
+    /// Returns `None` by default, which means unlimited.
+    #[inline]
+    fn max_connections(&self, pin: AnyPinId) -> Option<usize> {
+        let _ = pin;
+        None
+    }
+
+    /// Returns number of ports of the input pin.
+    ///
+    /// Pins with more than one port accept wires into one of several slots,
+    /// for example when pin can accept values of different types.
+    /// When wire is dropped on such pin, [`SnarlViewer::choose_port`] is shown
+    /// and connection completes with [`SnarlViewer::connect_port`] once port is chosen.
+    ///
+    /// Returns `1` by default.
+    #[inline]
+    fn input_ports(&mut self, pin: InPinId, snarl: &Snarl<T>) -> usize {
+        let _ = (pin, snarl);
+        1
+    }
+
+    /// Renders port choices for the wire dropped from `source` on the `pin` with multiple ports.
+    ///
+    /// Shown in a popup at the drop position until port is chosen
+    /// or user clicks elsewhere or presses `Escape` to cancel the connection.
+    /// Returns chosen port index.
+    ///
+    /// Returns `None` by default.
+    #[inline]
+    fn choose_port(
+        &mut self,
+        pin: InPinId,
+        source: OutPinId,
+        ui: &mut Ui,
+        snarl: &mut Snarl<T>,
+    ) -> Option<usize> {
+        let _ = (pin, source, ui, snarl);
+        None
+    }
+
+    /// Asks the viewer to connect two pins using chosen `port` of the input pin.
+    ///
+    /// See [`SnarlViewer::input_ports`].
+    /// By default this method ignores the port and calls [`SnarlViewer::connect_with_source`]
+    /// with [`EditSource::UserDrag`].
+    #[inline]
+    fn connect_port(&mut self, from: &OutPin, to: &InPin, port: usize, snarl: &mut Snarl<T>) {
+        let _ = port;
+        self.connect_with_source(from, to, EditSource::UserDrag, snarl);
+    }
+
+    /// Called when node is dropped onto the wire
+    /// with [`SnarlConfig::drop_node_on_wire`](crate::ui::SnarlConfig::drop_node_on_wire) enabled.
+    ///
+    /// Return input and output of the `node` to splice it into the wire through.
+    /// The wire is then disconnected, and its output is connected to the returned input
+    /// and the returned output to its input,
+    /// using [`SnarlViewer::disconnect_with_source`] and [`SnarlViewer::connect_with_source`]
+    /// with [`EditSource::Splice`].
+    /// Return `None` to leave the wire as is.
+    ///
+    /// Returns `None` by default.
+    #[inline]
+    fn splice_node(
+        &mut self,
+        node: NodeId,
+        wire: (OutPinId, InPinId),
+        snarl: &mut Snarl<T>,
+    ) -> Option<(usize, usize)> {
+        let _ = (node, wire, snarl);
+        None
     }
 
     /// Asks the viewer to disconnect two pins.
@@ -356,6 +881,22 @@ pub trait SnarlViewer<T> {
         snarl.disconnect(from.id, to.id);
     }
 
+    /// Asks the viewer to disconnect two pins, with the `source` of the change.
+    ///
+    /// The graph UI calls this method instead of [`SnarlViewer::disconnect`].
+    /// By default this method ignores the source and calls [`SnarlViewer::disconnect`].
+    #[inline]
+    fn disconnect_with_source(
+        &mut self,
+        from: &OutPin,
+        to: &InPin,
+        source: EditSource,
+        snarl: &mut Snarl<T>,
+    ) {
+        let _ = source;
+        self.disconnect(from, to, snarl);
+    }
+
     /// Asks the viewer to disconnect all wires from the output pin.
     ///
     /// This is usually happens when right-clicking on an output pin.
@@ -426,6 +967,31 @@ pub trait SnarlViewer<T> {
         let _ = (viewport, snarl_style, style, painter, snarl);
     }
 
+    /// Draws app-specific decorations over the canvas, e.g. rulers or a playhead.
+    ///
+    /// Called after all built-in drawing, including [`SnarlViewer::draw_foreground`].
+    /// `painter` works in screen space and is clipped to the canvas.
+    /// `to_global` maps graph space to screen space,
+    /// use its inverse to map screen positions back to the graph.
+    ///
+    /// By default it does nothing.
+    #[inline]
+    fn draw_overlay(&mut self, painter: &Painter, to_global: TSTransform, snarl: &Snarl<T>) {
+        let _ = (painter, to_global, snarl);
+    }
+
+    /// Renders content over the canvas when the graph has no nodes,
+    /// e.g. hint on how to add the first node.
+    ///
+    /// `ui` covers the canvas in screen space, unaffected by pan and zoom,
+    /// and centers its content.
+    ///
+    /// By default it does nothing.
+    #[inline]
+    fn show_empty(&mut self, ui: &mut Ui, snarl: &Snarl<T>) {
+        let _ = (ui, snarl);
+    }
+
     /// Called to compute automatic layout positions for nodes.
     ///
     /// Override this method to implement automatic layout algorithms
@@ -476,4 +1042,150 @@ pub trait SnarlViewer<T> {
         let _ = selected_nodes;
         None
     }
+
+    /// Called when the set of selected nodes has changed.
+    ///
+    /// This method is called at most once per frame, at the end of graph rendering,
+    /// after clicks and rect selection are resolved,
+    /// and only if the final set differs from the set at the beginning of the frame.
+    ///
+    /// By default it does nothing.
+    #[inline]
+    fn on_selection_changed(&mut self, selected: &HashSet<NodeId>, snarl: &mut Snarl<T>) {
+        let _ = (selected, snarl);
+    }
+
+    /// Called when pan or zoom of the graph view has changed.
+    ///
+    /// `to_global` is the new transform from graph space to screen space,
+    /// same as passed to [`SnarlViewer::current_transform`].
+    /// Mirror it into another view from that method to keep views in sync.
+    ///
+    /// This method is called at most once per frame, at the end of graph rendering,
+    /// and only if the transform differs from the one at the beginning of the frame.
+    ///
+    /// By default it does nothing.
+    #[inline]
+    fn on_viewport_changed(&mut self, to_global: TSTransform, snarl: &mut Snarl<T>) {
+        let _ = (to_global, snarl);
+    }
+
+    /// Called when empty area of the graph is clicked,
+    /// with the click position in graph space.
+    ///
+    /// Called after the click deselects nodes and wires,
+    /// so built-in behavior can be extended, e.g. to place a cursor.
+    ///
+    /// By default it does nothing.
+    #[inline]
+    fn on_background_click(&mut self, pos: Pos2, snarl: &mut Snarl<T>) {
+        let _ = (pos, snarl);
+    }
+
+    /// Called when wire is hovered, once per frame.
+    ///
+    /// Wire is hovered with the same hit tolerance that is used to select and remove wires.
+    /// Only topmost wire is reported when several wires overlap.
+    ///
+    /// By default it does nothing.
+    #[inline]
+    fn on_wire_hovered(&mut self, from: &OutPin, to: &InPin, snarl: &Snarl<T>) {
+        let _ = (from, to, snarl);
+    }
+
+    /// Returns diagnostics of the node for [`Snarl::validate`].
+    ///
+    /// Returns no diagnostics by default.
+    #[inline]
+    fn validate_node(&mut self, node: NodeId, snarl: &Snarl<T>) -> Vec<Diagnostic> {
+        let _ = (node, snarl);
+        Vec::new()
+    }
+
+    /// Returns diagnostics of the wire for [`Snarl::validate`].
+    ///
+    /// Returns no diagnostics by default.
+    #[inline]
+    fn validate_wire(&mut self, from: &OutPin, to: &InPin, snarl: &Snarl<T>) -> Vec<Diagnostic> {
+        let _ = (from, to, snarl);
+        Vec::new()
+    }
+
+    /// Checks if input pin must be connected.
+    /// [`Snarl::validate`] reports required inputs without wires.
+    ///
+    /// Returns `false` by default.
+    #[inline]
+    fn input_required(&mut self, pin: &InPin, snarl: &Snarl<T>) -> bool {
+        let _ = (pin, snarl);
+        false
+    }
+
+    /// Checks if graph may contain cycles.
+    /// Otherwise [`Snarl::validate`] reports wires closing cycles.
+    ///
+    /// Returns `true` by default.
+    #[inline]
+    fn cycles_allowed(&mut self) -> bool {
+        true
+    }
+
+    /// Called before graph fragment is merged with [`Snarl::merge_deserialized_with`].
+    ///
+    /// `nodes` hold decoded nodes with their new ids
+    /// and `wires` hold wires between them.
+    /// Modify them to transform pasted data, e.g. regenerate ids stored in nodes
+    /// or strip secrets, remove entries to skip them, or add wires to existing nodes.
+    /// Node ids must not be changed, the merge fails if a node has id
+    /// that is not in the fragment.
+    /// Wires to nodes that are not in the graph after the merge are ignored.
+    ///
+    /// Return `false` to cancel the merge.
+    ///
+    /// By default it returns `true`.
+    #[inline]
+    fn on_paste(
+        &mut self,
+        nodes: &mut Vec<(NodeId, T)>,
+        wires: &mut Vec<(OutPinId, InPinId)>,
+    ) -> bool {
+        let _ = (nodes, wires);
+        true
+    }
+
+    /// Called before selected nodes are removed with
+    /// [`SnarlConfig::delete_selection`](crate::ui::SnarlConfig::delete_selection) key.
+    ///
+    /// Return `false` to keep the nodes and selected wires,
+    /// e.g. to show a confirmation dialog and remove them later
+    /// with [`Snarl::remove_nodes`].
+    /// Nothing is changed before this method is called.
+    ///
+    /// By default it returns `true`.
+    #[inline]
+    fn confirm_delete_nodes(&mut self, nodes: &[NodeId], snarl: &mut Snarl<T>) -> bool {
+        let _ = (nodes, snarl);
+        true
+    }
+
+    /// Called after nodes are removed by the UI.
+    /// Receives removed nodes with their ids.
+    ///
+    /// By default it drops the nodes.
+    #[inline]
+    fn on_nodes_removed(&mut self, removed: Vec<(NodeId, T)>, snarl: &mut Snarl<T>) {
+        let _ = (removed, snarl);
+    }
+
+    /// Called when the UI refuses to create a node or a wire
+    /// because [`SnarlConfig::max_nodes`](crate::ui::SnarlConfig::max_nodes)
+    /// or [`SnarlConfig::max_wires`](crate::ui::SnarlConfig::max_wires) is reached.
+    ///
+    /// Use it to show a toast or other notification.
+    ///
+    /// By default it does nothing.
+    #[inline]
+    fn on_capacity_reached(&mut self, limit: CapacityLimit, snarl: &mut Snarl<T>) {
+        let _ = (limit, snarl);
+    }
 }
@@ -0,0 +1,241 @@
+//! Viewport changes requested by the application or key bindings.
+
+use egui::{Id, KeyboardShortcut, Rect, Response, Ui, Vec2, emath::TSTransform, vec2};
+
+use super::{
+    FitMode, FitOptions, SnarlConfig, SnarlStyle,
+    state::{SnarlState, ViewportRequest},
+    transform_matching_points,
+};
+
+/// Margin around nodes when view is fit with [`SnarlConfig::fit_view`] key binding.
+const KEY_FIT_VIEW_MARGIN: f32 = 32.0;
+
+/// Takes viewport change requested for the widget in this frame.
+///
+/// Key bindings pressed over the widget take precedence over request made with
+/// [`SnarlWidget`](super::SnarlWidget) methods.
+pub(super) fn take_viewport_request(
+    ui: &Ui,
+    snarl_id: Id,
+    config: &SnarlConfig,
+    snarl_resp: &Response,
+) -> Option<ViewportRequest> {
+    let mut request = ViewportRequest::take(ui.ctx(), snarl_id);
+
+    if !config.viewport_locked && snarl_resp.contains_pointer() && !ui.ctx().wants_keyboard_input()
+    {
+        let consume_shortcut = |shortcut: Option<KeyboardShortcut>| {
+            shortcut.is_some_and(|shortcut| ui.input_mut(|i| i.consume_shortcut(&shortcut)))
+        };
+        if consume_shortcut(config.reset_view) {
+            request = Some(ViewportRequest::Reset);
+        } else if consume_shortcut(config.fit_view) {
+            request = Some(ViewportRequest::FitToView {
+                opts: FitOptions::from(KEY_FIT_VIEW_MARGIN),
+            });
+        }
+    }
+
+    request
+}
+
+/// Animates the viewport towards the transform that satisfies `request`.
+///
+/// `node_rect` is the graph space rect of the node named in the request, if it was drawn,
+/// and `nodes_bb` is the bounding box of all nodes.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn apply_viewport_request(
+    request: ViewportRequest,
+    node_rect: Option<Rect>,
+    nodes_bb: Rect,
+    ui_rect: Rect,
+    mut to_global: TSTransform,
+    (min_scale, max_scale): (f32, f32),
+    style: &SnarlStyle,
+    time: f64,
+    snarl_state: &mut SnarlState,
+) {
+    match request {
+        ViewportRequest::EnsureNodeVisible { margin, .. } => {
+            let Some(node_rect) = node_rect else {
+                return;
+            };
+
+            let node_rect = (to_global * node_rect).expand(margin);
+            let delta = pan_to_contain(ui_rect, node_rect);
+            if delta == Vec2::ZERO {
+                return;
+            }
+
+            to_global.translation += delta;
+        }
+        ViewportRequest::FocusNode { .. } => {
+            let Some(node_rect) = node_rect else {
+                return;
+            };
+
+            to_global.translation += ui_rect.center() - to_global * node_rect.center();
+        }
+        ViewportRequest::Reset => {
+            to_global = TSTransform::from_translation(ui_rect.min.to_vec2());
+        }
+        ViewportRequest::FitToView { opts } => {
+            if !nodes_bb.is_finite() {
+                return;
+            }
+
+            to_global = fit_transform(nodes_bb, ui_rect, &opts, min_scale, max_scale);
+        }
+    }
+
+    snarl_state.animate_to(to_global, style.viewport_animation(), time);
+}
+
+/// Animated transition of the viewport transform.
+#[derive(Clone, Copy)]
+pub(super) struct ViewportAnimation {
+    from: TSTransform,
+    to: TSTransform,
+    start: f64,
+    duration: f32,
+}
+
+impl ViewportAnimation {
+    /// Starts transition from `from` to `to` at `start` lasting `duration` seconds.
+    pub(super) const fn new(from: TSTransform, to: TSTransform, start: f64, duration: f32) -> Self {
+        ViewportAnimation {
+            from,
+            to,
+            start,
+            duration,
+        }
+    }
+
+    /// Moves both ends of the transition by `delta`.
+    pub(super) fn translate(&mut self, delta: Vec2) {
+        self.from.translation += delta;
+        self.to.translation += delta;
+    }
+
+    /// Returns the transform at `time` and `true` if the transition is finished.
+    pub(super) fn sample(&self, time: f64) -> (TSTransform, bool) {
+        #[allow(clippy::cast_possible_truncation)]
+        let t = ((time - self.start) as f32 / self.duration).clamp(0.0, 1.0);
+
+        if t >= 1.0 {
+            return (self.to, true);
+        }
+
+        // Ease out cubic.
+        let t = 1.0 - (1.0 - t).powi(3);
+
+        let to_global = TSTransform {
+            scaling: egui::lerp(self.from.scaling..=self.to.scaling, t),
+            translation: self.from.translation + (self.to.translation - self.from.translation) * t,
+        };
+        (to_global, false)
+    }
+}
+
+/// Returns minimal translation that moves `rect` into `viewport`.
+///
+/// If `rect` doesn't fit, it is aligned to the top left corner of the `viewport`.
+pub(super) fn pan_to_contain(viewport: Rect, rect: Rect) -> Vec2 {
+    let axis = |min: f32, max: f32, view_min: f32, view_max: f32| {
+        if min < view_min || max - min > view_max - view_min {
+            view_min - min
+        } else if max > view_max {
+            view_max - max
+        } else {
+            0.0
+        }
+    };
+
+    vec2(
+        axis(rect.min.x, rect.max.x, viewport.min.x, viewport.max.x),
+        axis(rect.min.y, rect.max.y, viewport.min.y, viewport.max.y),
+    )
+}
+
+/// Returns transform that frames `nodes_bb` in graph space into `ui_rect` as set by `opts`.
+#[must_use]
+fn fit_transform(
+    nodes_bb: Rect,
+    ui_rect: Rect,
+    opts: &FitOptions,
+    min_scale: f32,
+    max_scale: f32,
+) -> TSTransform {
+    let view = nodes_bb + opts.padding;
+    let scaling2 = ui_rect.size() / view.size();
+    let scaling = match opts.mode {
+        FitMode::Contain => scaling2.min_elem(),
+        FitMode::Cover => scaling2.max_elem(),
+    };
+    let scaling = scaling.min(opts.max_zoom).clamp(min_scale, max_scale);
+
+    transform_matching_points(view.center(), ui_rect.center(), scaling)
+}
+
+#[test]
+fn fit_modes_frame_graph() {
+    use egui::{Margin, Pos2, pos2};
+
+    let ui_rect = Rect::from_min_size(Pos2::ZERO, vec2(400.0, 200.0));
+    let nodes_bb = Rect::from_min_size(pos2(100.0, 100.0), vec2(100.0, 100.0));
+    let fit = |mode, max_zoom| {
+        let opts = FitOptions {
+            padding: Margin::ZERO,
+            max_zoom,
+            mode,
+            ..FitOptions::new()
+        };
+        fit_transform(nodes_bb, ui_rect, &opts, 0.1, 10.0)
+    };
+
+    let contain = fit(FitMode::Contain, 10.0);
+    assert!((contain.scaling - 2.0).abs() < 1e-4);
+    assert!((contain * nodes_bb.center() - ui_rect.center()).length() < 1e-3);
+
+    assert!((fit(FitMode::Cover, 10.0).scaling - 4.0).abs() < 1e-4);
+    assert!((fit(FitMode::Cover, 3.0).scaling - 3.0).abs() < 1e-4);
+}
+
+#[test]
+fn pan_to_contain_moves_minimally() {
+    use egui::pos2;
+
+    let viewport = Rect::from_min_size(pos2(0.0, 0.0), vec2(100.0, 100.0));
+    let rect = |x, y, size| Rect::from_min_size(pos2(x, y), vec2(size, size));
+
+    assert_eq!(pan_to_contain(viewport, rect(10.0, 10.0, 20.0)), Vec2::ZERO);
+    assert_eq!(
+        pan_to_contain(viewport, rect(90.0, -5.0, 20.0)),
+        vec2(-10.0, 5.0)
+    );
+
+    // Rect larger than viewport is aligned to top left corner.
+    assert_eq!(
+        pan_to_contain(viewport, rect(30.0, 30.0, 200.0)),
+        vec2(-30.0, -30.0)
+    );
+}
+
+#[test]
+fn viewport_animation_eases_out_to_target() {
+    let from = TSTransform::IDENTITY;
+    let to = TSTransform::new(vec2(100.0, 0.0), 2.0);
+    let animation = ViewportAnimation::new(from, to, 1.0, 0.5);
+
+    assert_eq!(animation.sample(1.0), (from, false));
+
+    // Ease out covers more than half of the distance by half of the time.
+    let (half, finished) = animation.sample(1.25);
+    assert!(!finished);
+    assert!(half.translation.x > 50.0 && half.translation.x < 100.0);
+    assert!(half.scaling > 1.5 && half.scaling < 2.0);
+
+    assert_eq!(animation.sample(1.5), (to, true));
+    assert_eq!(animation.sample(10.0), (to, true));
+}
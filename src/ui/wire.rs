@@ -1,11 +1,72 @@
 use core::f32;
+use std::sync::Arc;
 
-use egui::{Context, Id, Pos2, Rect, Shape, Stroke, Ui, ahash::HashMap, cache::CacheTrait, pos2};
+use egui::{
+    Color32, Context, Id, Pos2, Rect, Shape, Stroke, Ui, Vec2, ahash::HashMap, cache::CacheTrait,
+    epaint::ColorMode, pos2,
+};
 
 use crate::{InPinId, OutPinId};
 
 const MAX_CURVE_SAMPLES: usize = 100;
 
+/// Opacity of the middle of wires faded with [`SnarlStyle::long_wire_fade`](crate::ui::SnarlStyle::long_wire_fade).
+const LONG_WIRE_MIN_OPACITY: f32 = 0.2;
+
+/// Number of distinct opacity levels along the faded wire.
+const LONG_WIRE_FADE_STEPS: f32 = 8.0;
+
+/// Path of the wire as it was laid out last time the graph was shown.
+///
+/// Returned by [`SnarlWidget::wire_path`](crate::ui::SnarlWidget::wire_path).
+#[derive(Clone, Debug, PartialEq)]
+pub struct WirePath {
+    points: Vec<Pos2>,
+}
+
+impl WirePath {
+    pub(crate) const fn new(points: Vec<Pos2>) -> Self {
+        WirePath { points }
+    }
+
+    /// Returns polyline approximating the wire in screen space,
+    /// from output pin to input pin.
+    #[must_use]
+    pub fn points(&self) -> &[Pos2] {
+        &self.points
+    }
+
+    /// Returns length of the wire in screen space.
+    #[must_use]
+    pub fn length(&self) -> f32 {
+        self.points
+            .windows(2)
+            .map(|pair| pair[0].distance(pair[1]))
+            .sum()
+    }
+
+    /// Returns point on the wire at `t` fraction of its length.
+    /// `t` is clamped to `0.0..=1.0`, where `0.0` is at the output pin
+    /// and `1.0` is at the input pin.
+    #[must_use]
+    pub fn point_at(&self, t: f32) -> Pos2 {
+        let Some(&first) = self.points.first() else {
+            return Pos2::ZERO;
+        };
+
+        let mut remaining = self.length() * t.clamp(0.0, 1.0);
+        for pair in self.points.windows(2) {
+            let segment = pair[0].distance(pair[1]);
+            if remaining <= segment && segment > 0.0 {
+                return pair[0].lerp(pair[1], remaining / segment);
+            }
+            remaining -= segment;
+        }
+
+        self.points.last().copied().unwrap_or(first)
+    }
+}
+
 /// Layer where wires are rendered.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -356,6 +417,17 @@ fn wire_bezier_5_vertical(frame_size: f32, from: Pos2, to: Pos2) -> [Pos2; 6] {
     }
 }
 
+/// Returns multiplier of the frame size for the wire style.
+///
+/// Tension only changes the curvature of bezier wires.
+#[inline]
+const fn bezier_tension(style: WireStyle, tension: f32) -> f32 {
+    match style {
+        WireStyle::Bezier3 | WireStyle::Bezier5 => tension,
+        WireStyle::Line | WireStyle::AxisAligned { .. } => 1.0,
+    }
+}
+
 /// Returns 3rd degree bezier curve control points for the wire
 fn wire_bezier_3(frame_size: f32, from: Pos2, to: Pos2) -> [Pos2; 4] {
     let [a, b, _, _, c, d] = wire_bezier_5_horizontal(frame_size, from, to);
@@ -376,12 +448,14 @@ pub fn draw_wire(
     frame_size: f32,
     upscale: bool,
     downscale: bool,
+    tension: f32,
     from: Pos2,
     to: Pos2,
     mut stroke: Stroke,
     threshold: f32,
     style: WireStyle,
     vertical: bool,
+    fade: Option<f32>,
 ) {
     if !ui.is_visible() {
         return;
@@ -392,7 +466,8 @@ pub fn draw_wire(
         stroke.width = 1.0;
     }
 
-    let frame_size = adjust_frame_size(frame_size, upscale, downscale, from, to);
+    let frame_size = adjust_frame_size(frame_size, upscale, downscale, from, to)
+        * bezier_tension(style, tension);
 
     let args = WireArgs {
         frame_size,
@@ -402,6 +477,8 @@ pub fn draw_wire(
         vertical,
     };
 
+    let first_shape = shapes.len();
+
     match style {
         WireStyle::Line => {
             let bb = Rect::from_two_pos(from, to);
@@ -425,6 +502,217 @@ pub fn draw_wire(
             draw_axis_aligned(ui, wire, args, stroke, threshold, shapes);
         }
     }
+
+    if let Some(length) = fade {
+        for shape in &mut shapes[first_shape..] {
+            fade_long_wire(shape, length);
+        }
+    }
+}
+
+/// Splits wire line longer than `length` into parts fading towards the middle.
+fn fade_long_wire(shape: &mut Shape, length: f32) {
+    let (points, width, color) = match shape {
+        Shape::LineSegment { points, stroke } => (points.to_vec(), stroke.width, stroke.color),
+        Shape::Path(path) => match path.stroke.color {
+            ColorMode::Solid(color) => (path.points.clone(), path.stroke.width, color),
+            ColorMode::UV(_) => return,
+        },
+        _ => return,
+    };
+
+    let mut distances = Vec::with_capacity(points.len());
+    let mut total = 0.0;
+    distances.push(total);
+    for pair in points.windows(2) {
+        total += pair[0].distance(pair[1]);
+        distances.push(total);
+    }
+
+    if total <= length || length <= 0.0 {
+        return;
+    }
+
+    // Opacity level of the point at `distance` along the wire.
+    let half = length * 0.5;
+    let level = |distance: f32| {
+        let from_end = distance.min(total - distance);
+        let t = ((from_end - half) / half).clamp(0.0, 1.0);
+        (t * LONG_WIRE_FADE_STEPS).round()
+    };
+
+    // Group segments with the same opacity into lines.
+    let mut parts = Vec::new();
+    let mut start = 0;
+    while start + 1 < points.len() {
+        let part_level = level(f32::midpoint(distances[start], distances[start + 1]));
+        let mut end = start + 1;
+        while end + 1 < points.len()
+            && (level(f32::midpoint(distances[end], distances[end + 1])) - part_level).abs()
+                < f32::EPSILON
+        {
+            end += 1;
+        }
+
+        let opacity = 1.0 - part_level / LONG_WIRE_FADE_STEPS * (1.0 - LONG_WIRE_MIN_OPACITY);
+        parts.push(Shape::line(
+            points[start..=end].to_vec(),
+            Stroke::new(width, color.gamma_multiply(opacity)),
+        ));
+        start = end;
+    }
+
+    *shape = Shape::Vec(parts);
+}
+
+/// Replaces solid color of the wire shape with gradient
+/// from `from_color` at `from` to `to_color` at `to`.
+///
+/// Opacity of the shape relative to `base` color is kept, so faded parts stay faded.
+pub(crate) fn gradient_wire(
+    shape: &mut Shape,
+    from: Pos2,
+    to: Pos2,
+    base: Color32,
+    from_color: Color32,
+    to_color: Color32,
+) {
+    match shape {
+        Shape::Vec(shapes) => {
+            for shape in shapes {
+                gradient_wire(shape, from, to, base, from_color, to_color);
+            }
+        }
+        Shape::LineSegment { points, stroke } => {
+            *shape = Shape::line(points.to_vec(), *stroke);
+            gradient_wire(shape, from, to, base, from_color, to_color);
+        }
+        Shape::Path(path) => {
+            let ColorMode::Solid(color) = path.stroke.color else {
+                return;
+            };
+            let opacity = if base.a() == 0 {
+                1.0
+            } else {
+                f32::from(color.a()) / f32::from(base.a())
+            };
+
+            // Tessellator passes positions with bounding box of the path
+            // after the layer transform is applied,
+            // so endpoints are kept relative to the bounding box.
+            let bb = Rect::from_points(&path.points);
+            let relative =
+                |rect: Rect, pos: Pos2| (pos - rect.min) / rect.size().max(Vec2::splat(1.0));
+            let start = relative(bb, from);
+            let mut dir = relative(bb, to) - start;
+            if bb.width() < 1.0 {
+                dir.x = 0.0;
+            }
+            if bb.height() < 1.0 {
+                dir.y = 0.0;
+            }
+            let length_sq = dir.length_sq();
+
+            path.stroke.color = ColorMode::UV(Arc::new(move |rect, pos| {
+                let t = if length_sq > 0.0 {
+                    ((relative(rect, pos) - start).dot(dir) / length_sq).clamp(0.0, 1.0)
+                } else {
+                    0.5
+                };
+                from_color
+                    .lerp_to_gamma(to_color, t)
+                    .gamma_multiply(opacity)
+            }));
+        }
+        _ => {}
+    }
+}
+
+/// Returns control points of the 3rd degree bezier curve of the wire.
+pub(crate) fn wire_bezier_3_points(
+    frame_size: f32,
+    upscale: bool,
+    downscale: bool,
+    tension: f32,
+    from: Pos2,
+    to: Pos2,
+    vertical: bool,
+) -> [Pos2; 4] {
+    let frame_size = adjust_frame_size(frame_size, upscale, downscale, from, to) * tension;
+    if vertical {
+        wire_bezier_3_vertical(frame_size, from, to)
+    } else {
+        wire_bezier_3(frame_size, from, to)
+    }
+}
+
+/// Returns polyline approximating the wire.
+///
+/// Same as what [`draw_wire`] renders, but computed without touching caches,
+/// so it can be used outside of the UI.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn wire_line(
+    frame_size: f32,
+    upscale: bool,
+    downscale: bool,
+    tension: f32,
+    from: Pos2,
+    to: Pos2,
+    threshold: f32,
+    style: WireStyle,
+    vertical: bool,
+) -> Vec<Pos2> {
+    let frame_size = adjust_frame_size(frame_size, upscale, downscale, from, to)
+        * bezier_tension(style, tension);
+
+    match style {
+        WireStyle::Line => vec![from, to],
+        WireStyle::Bezier3 => {
+            let points = if vertical {
+                wire_bezier_3_vertical(frame_size, from, to)
+            } else {
+                wire_bezier_3(frame_size, from, to)
+            };
+            WireCache3 {
+                points,
+                ..WireCache3::default()
+            }
+            .line(threshold)
+        }
+        WireStyle::Bezier5 => {
+            let points = if vertical {
+                wire_bezier_5_vertical(frame_size, from, to)
+            } else {
+                wire_bezier_5_horizontal(frame_size, from, to)
+            };
+            WireCache5 {
+                points,
+                ..WireCache5::default()
+            }
+            .line(threshold)
+        }
+        WireStyle::AxisAligned { corner_radius } => WireCacheAA {
+            aawire: wire_axis_aligned(corner_radius, frame_size, from, to),
+            threshold,
+            ..WireCacheAA::default()
+        }
+        .line(threshold),
+    }
+}
+
+/// Returns true if segment from `a` to `b` crosses polyline of the wire.
+/// Touching and collinear segments do not count as crossing.
+pub(crate) fn line_crosses_wire(points: &[Pos2], a: Pos2, b: Pos2) -> bool {
+    let cross = |o: Pos2, p: Pos2, q: Pos2| (p - o).x * (q - o).y - (p - o).y * (q - o).x;
+
+    points.windows(2).any(|pair| {
+        let (c, d) = (pair[0], pair[1]);
+        let d1 = cross(c, d, a);
+        let d2 = cross(c, d, b);
+        let d3 = cross(a, b, c);
+        let d4 = cross(a, b, d);
+        d1 * d2 < 0.0 && d3 * d4 < 0.0
+    })
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -434,6 +722,7 @@ pub fn hit_wire(
     frame_size: f32,
     upscale: bool,
     downscale: bool,
+    tension: f32,
     from: Pos2,
     to: Pos2,
     pos: Pos2,
@@ -441,7 +730,8 @@ pub fn hit_wire(
     style: WireStyle,
     vertical: bool,
 ) -> bool {
-    let frame_size = adjust_frame_size(frame_size, upscale, downscale, from, to);
+    let frame_size = adjust_frame_size(frame_size, upscale, downscale, from, to)
+        * bezier_tension(style, tension);
 
     let args = WireArgs {
         frame_size,